@@ -0,0 +1,125 @@
+//! Patterns as rkyv archives, which can be matched against right where they're mapped in, without deserializing
+
+use std::borrow::Cow;
+use rkyv::util::AlignedVec;
+use rkyv::{Archive, Deserialize, Serialize};
+use crate::{DecodeError, DomainPattern, DomainPatternPart, DomainPatternWildcard, StepVec};
+#[cfg(feature = "regex")]
+use crate::LabelRegex;
+
+/// A part of a [`PatternArchive`], like [`DomainPatternPart`] with owned labels
+#[derive(Archive, Serialize, Deserialize, Clone, Eq, PartialEq, Debug)]
+pub enum PartArchive {
+    Static(String),
+    Wildcard { multi: bool, optional: bool },
+    Named(String),
+    Glob(String),
+    OneOf(Vec<String>),
+    Numeric,
+    /// Compiled again every time the archived part is matched, so archives are best kept free of them
+    Regex(String),
+}
+
+impl From<&DomainPatternPart<'_>> for PartArchive {
+    fn from(part: &DomainPatternPart<'_>) -> Self {
+        match part {
+            DomainPatternPart::Static(label) => PartArchive::Static(label.to_string()),
+            DomainPatternPart::Wildcard(DomainPatternWildcard { multi, optional }) => PartArchive::Wildcard { multi: *multi, optional: *optional },
+            DomainPatternPart::Named(name) => PartArchive::Named(name.to_string()),
+            DomainPatternPart::Glob(glob) => PartArchive::Glob(glob.to_string()),
+            DomainPatternPart::OneOf(labels) => PartArchive::OneOf(labels.iter().map(|label| label.to_string()).collect()),
+            DomainPatternPart::Numeric => PartArchive::Numeric,
+            #[cfg(feature = "regex")]
+            DomainPatternPart::Regex(regex) => PartArchive::Regex(regex.as_str().to_string()),
+        }
+    }
+}
+
+impl ArchivedPartArchive {
+    /// The part, borrowing its labels from the archive, None for a regex that doesn't compile or without the `regex` feature
+    pub fn to_part(&self) -> Option<DomainPatternPart<'_>> {
+        Some(match self {
+            ArchivedPartArchive::Static(label) => DomainPatternPart::Static(Cow::Borrowed(label.as_str())),
+            ArchivedPartArchive::Wildcard { multi, optional } => DomainPatternPart::Wildcard(DomainPatternWildcard { multi: *multi, optional: *optional }),
+            ArchivedPartArchive::Named(name) => DomainPatternPart::Named(Cow::Borrowed(name.as_str())),
+            ArchivedPartArchive::Glob(glob) => DomainPatternPart::Glob(Cow::Borrowed(glob.as_str())),
+            ArchivedPartArchive::OneOf(labels) => DomainPatternPart::OneOf(labels.iter().map(|label| Cow::Borrowed(label.as_str())).collect()),
+            ArchivedPartArchive::Numeric => DomainPatternPart::Numeric,
+            #[cfg(feature = "regex")]
+            ArchivedPartArchive::Regex(regex) => DomainPatternPart::Regex(LabelRegex::new(regex.as_str()).ok()?),
+            #[cfg(not(feature = "regex"))]
+            ArchivedPartArchive::Regex(_) => return None,
+        })
+    }
+}
+
+/// A pattern that can be archived with rkyv, see [`DomainPattern::to_archive`]
+#[derive(Archive, Serialize, Deserialize, Clone, Eq, PartialEq, Debug)]
+pub struct PatternArchive {
+    pub negated: bool,
+    pub parts: Vec<PartArchive>,
+}
+
+impl<const SPLITTER: char> DomainPattern<'_, SPLITTER> {
+    /// The pattern as something rkyv can archive, matching its archive doesn't need deserializing it
+    pub fn to_archive(&self) -> PatternArchive {
+        PatternArchive {
+            negated: self.negated,
+            parts: self.steps.iter().map(PartArchive::from).collect(),
+        }
+    }
+}
+
+impl PatternArchive {
+    pub fn to_bytes(&self) -> AlignedVec {
+        rkyv::to_bytes::<rkyv::rancor::Error>(self).expect("serializing into memory doesn't fail")
+    }
+
+    /// Checks the bytes are a valid archive, and returns it without copying anything
+    ///
+    /// The bytes have to be aligned like the [`AlignedVec`] they were written to, a memory map always is.
+    pub fn access(bytes: &[u8]) -> Result<&ArchivedPatternArchive, DecodeError> {
+        rkyv::access::<ArchivedPatternArchive, rkyv::rancor::Error>(bytes).map_err(|_| DecodeError::Invalid)
+    }
+}
+
+impl ArchivedPatternArchive {
+    /// The pattern, borrowing its labels from the archive, None if it has a regex that can't be compiled
+    pub fn to_pattern<const SPLITTER: char>(&self) -> Option<DomainPattern<'_, SPLITTER>> {
+        let mut steps: StepVec = Default::default();
+        for part in self.parts.iter() {
+            steps.push(part.to_part()?);
+        }
+
+        Some(DomainPattern {
+            steps,
+            negated: self.negated,
+        })
+    }
+
+    /// Matches like [`DomainPattern::matches`], borrowing the labels of the archive, a pattern that can't be
+    /// restored matches nothing
+    pub fn matches(&self, domain: &str) -> bool {
+        self.to_pattern::<'.'>().is_some_and(|pattern| pattern.matches(domain))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{DecodeError, DomainPattern, PatternArchive};
+
+    #[test]
+    pub fn test_pattern_archive() {
+        let pattern: DomainPattern = "!*.{tenant}.{a,b}.#.api-*.com".try_into().expect("failed to parse");
+        let bytes = pattern.to_archive().to_bytes();
+        let archived = PatternArchive::access(&bytes).expect("valid archive");
+
+        assert!(archived.negated);
+        assert_eq!(archived.to_pattern::<'.'>().map(|restored| restored.to_string()), Some(pattern.to_string()));
+        for domain in ["x.a.1.api-v1.com", "t.b.22.api-.com", "x.c.1.api-v1.com", "a.1.api-v1.com", "x.y.a.1.api-v1.com"] {
+            assert_eq!(archived.matches(domain), pattern.matches(domain), "{:?} matches differently", domain);
+        }
+
+        assert_eq!(PatternArchive::access(&bytes[..bytes.len() - 1]).err(), Some(DecodeError::Invalid));
+    }
+}
@@ -0,0 +1,373 @@
+//! Little endian encoding shared by the binary formats, with a checksum over everything written
+
+use std::borrow::Cow;
+use std::fmt::{Display, Formatter};
+use crate::{Direction, DomainPattern, DomainPatternPart, DomainPatternWildcard, MatchOptions, StepVec, TrailingDot};
+#[cfg(feature = "regex")]
+use crate::LabelRegex;
+
+/// Why bytes couldn't be loaded, see [`DomainPatternSet::deserialize_compiled`](crate::DomainPatternSet::deserialize_compiled)
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum DecodeError {
+    /// The bytes don't start with the expected magic, they aren't what they're loaded as
+    Magic,
+    /// The bytes were written in a format version this version of the crate can't read
+    Version(u16),
+    /// The checksum doesn't match, the bytes were corrupted
+    Checksum,
+    /// The bytes end too early
+    Truncated,
+    /// The bytes decode to something invalid, like a different splitter, invalid UTF-8, or a regex label without the
+    /// `regex` feature
+    Invalid,
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DecodeError::Magic => write!(f, "Not in the expected format"),
+            DecodeError::Version(version) => write!(f, "Unsupported format version {}", version),
+            DecodeError::Checksum => write!(f, "Checksum mismatch"),
+            DecodeError::Truncated => write!(f, "Unexpected end of input"),
+            DecodeError::Invalid => write!(f, "Invalid contents"),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+// FNV-1a, not for security, only to catch corruption
+pub(crate) fn checksum(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3))
+}
+
+// the options as three bytes, flags, trailing dot and direction
+pub(crate) fn encode_options(options: &MatchOptions) -> [u8; 3] {
+    #[cfg(feature = "idna")]
+    let idna = options.idna;
+    #[cfg(not(feature = "idna"))]
+    let idna = false;
+    #[cfg(feature = "unicode-normalization")]
+    let nfc = options.nfc;
+    #[cfg(not(feature = "unicode-normalization"))]
+    let nfc = false;
+
+    let flags = [options.case_insensitive, idna, nfc, options.validate_input, options.root_matches, options.strip_port];
+    let trailing_dot = match options.trailing_dot {
+        TrailingDot::Ignore => 0,
+        TrailingDot::Require => 1,
+        TrailingDot::Forbid => 2,
+    };
+
+    let direction = match options.direction {
+        Direction::Auto => 0,
+        Direction::LeftToRight => 1,
+        Direction::RightToLeft => 2,
+    };
+
+    [flags.iter().enumerate().fold(0, |bits, (bit, flag)| bits | (*flag as u8) << bit), trailing_dot, direction]
+}
+
+pub(crate) fn decode_options([flags, trailing_dot, direction]: [u8; 3]) -> Result<MatchOptions, DecodeError> {
+    let flag = |bit: u8| flags & (1 << bit) != 0;
+    let mut options = MatchOptions::new()
+        .case_insensitive(flag(0))
+        .validate_input(flag(3))
+        .root_matches(flag(4))
+        .strip_port(flag(5));
+
+    #[cfg(feature = "idna")]
+    {
+        options.idna = flag(1);
+    }
+
+    #[cfg(feature = "unicode-normalization")]
+    {
+        options.nfc = flag(2);
+    }
+
+    // normalizing labels in a way this build can't would match differently
+    #[cfg(not(feature = "idna"))]
+    if flag(1) {
+        return Err(DecodeError::Invalid);
+    }
+
+    #[cfg(not(feature = "unicode-normalization"))]
+    if flag(2) {
+        return Err(DecodeError::Invalid);
+    }
+
+    if flags >> 6 != 0 {
+        return Err(DecodeError::Invalid);
+    }
+
+    options.trailing_dot = match trailing_dot {
+        0 => TrailingDot::Ignore,
+        1 => TrailingDot::Require,
+        2 => TrailingDot::Forbid,
+        _ => return Err(DecodeError::Invalid),
+    };
+
+    options.direction = match direction {
+        0 => Direction::Auto,
+        1 => Direction::LeftToRight,
+        2 => Direction::RightToLeft,
+        _ => return Err(DecodeError::Invalid),
+    };
+
+    Ok(options)
+}
+
+pub(crate) struct Writer {
+    bytes: Vec<u8>,
+}
+
+impl Writer {
+    pub(crate) fn new(magic: &[u8; 4], version: u16) -> Self {
+        let mut writer = Writer { bytes: vec![] };
+        writer.bytes.extend_from_slice(magic);
+        writer.u16(version);
+        writer
+    }
+
+    pub(crate) fn u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    pub(crate) fn u16(&mut self, value: u16) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub(crate) fn u32(&mut self, value: u32) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub(crate) fn u64(&mut self, value: u64) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub(crate) fn usize(&mut self, value: usize) {
+        self.u64(value as u64);
+    }
+
+    pub(crate) fn bool(&mut self, value: bool) {
+        self.u8(value as u8);
+    }
+
+    pub(crate) fn str(&mut self, value: &str) {
+        self.usize(value.len());
+        self.bytes.extend_from_slice(value.as_bytes());
+    }
+
+    pub(crate) fn part(&mut self, part: &DomainPatternPart<'_>) {
+        match part {
+            DomainPatternPart::Static(label) => {
+                self.u8(0);
+                self.str(label);
+            }
+
+            DomainPatternPart::Wildcard(DomainPatternWildcard { multi, optional }) => {
+                self.u8(1);
+                self.u8((*multi as u8) << 1 | *optional as u8);
+            }
+
+            DomainPatternPart::Named(name) => {
+                self.u8(2);
+                self.str(name);
+            }
+
+            DomainPatternPart::Glob(glob) => {
+                self.u8(3);
+                self.str(glob);
+            }
+
+            DomainPatternPart::OneOf(labels) => {
+                self.u8(4);
+                self.usize(labels.len());
+                labels.iter().for_each(|label| self.str(label));
+            }
+
+            DomainPatternPart::Numeric => self.u8(5),
+            #[cfg(feature = "regex")]
+            DomainPatternPart::Regex(regex) => {
+                self.u8(6);
+                self.str(regex.as_str());
+            }
+        }
+    }
+
+    pub(crate) fn pattern<const SPLITTER: char>(&mut self, pattern: &DomainPattern<'_, SPLITTER>) {
+        self.bool(pattern.negated);
+        self.usize(pattern.steps.len());
+        pattern.steps.iter().for_each(|part| self.part(part));
+    }
+
+    /// The bytes, with the checksum appended
+    pub(crate) fn finish(mut self) -> Vec<u8> {
+        let checksum = checksum(&self.bytes);
+        self.u64(checksum);
+        self.bytes
+    }
+}
+
+pub(crate) struct Reader<'b> {
+    bytes: &'b [u8],
+    pos: usize,
+}
+
+impl<'b> Reader<'b> {
+    /// Checks the magic, version and checksum, and returns a reader over everything in between
+    pub(crate) fn new(bytes: &'b [u8], magic: &[u8; 4], version: u16) -> Result<Self, DecodeError> {
+        if !bytes.starts_with(magic) {
+            return Err(DecodeError::Magic);
+        }
+
+        let mut reader = Reader { bytes, pos: magic.len() };
+        let found = reader.u16()?;
+        if found != version {
+            return Err(DecodeError::Version(found));
+        }
+
+        let Some(end) = bytes.len().checked_sub(8).filter(|end| *end >= reader.pos) else {
+            return Err(DecodeError::Truncated);
+        };
+
+        let expected = u64::from_le_bytes(bytes[end..].try_into().expect("checksum is 8 bytes"));
+        if checksum(&bytes[..end]) != expected {
+            return Err(DecodeError::Checksum);
+        }
+
+        reader.bytes = &bytes[..end];
+        Ok(reader)
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'b [u8], DecodeError> {
+        let end = self.pos.checked_add(len).filter(|end| *end <= self.bytes.len()).ok_or(DecodeError::Truncated)?;
+        let taken = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(taken)
+    }
+
+    pub(crate) fn u8(&mut self) -> Result<u8, DecodeError> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub(crate) fn u16(&mut self) -> Result<u16, DecodeError> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().expect("took 2 bytes")))
+    }
+
+    pub(crate) fn u32(&mut self) -> Result<u32, DecodeError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().expect("took 4 bytes")))
+    }
+
+    pub(crate) fn u64(&mut self) -> Result<u64, DecodeError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().expect("took 8 bytes")))
+    }
+
+    pub(crate) fn usize(&mut self) -> Result<usize, DecodeError> {
+        usize::try_from(self.u64()?).map_err(|_| DecodeError::Invalid)
+    }
+
+    /// A length of things that take at least `min` bytes each, so a corrupted length can't allocate too much
+    pub(crate) fn len(&mut self, min: usize) -> Result<usize, DecodeError> {
+        let len = self.usize()?;
+        match len.checked_mul(min.max(1)).is_some_and(|bytes| bytes <= self.bytes.len() - self.pos) {
+            true => Ok(len),
+            false => Err(DecodeError::Truncated),
+        }
+    }
+
+    /// An index below `bound`
+    pub(crate) fn index(&mut self, bound: usize) -> Result<usize, DecodeError> {
+        match self.usize()? {
+            idx if idx < bound => Ok(idx),
+            _ => Err(DecodeError::Invalid),
+        }
+    }
+
+    pub(crate) fn bool(&mut self) -> Result<bool, DecodeError> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(DecodeError::Invalid),
+        }
+    }
+
+    pub(crate) fn str(&mut self) -> Result<&'b str, DecodeError> {
+        let len = self.usize()?;
+        std::str::from_utf8(self.take(len)?).map_err(|_| DecodeError::Invalid)
+    }
+
+    pub(crate) fn part(&mut self) -> Result<DomainPatternPart<'static>, DecodeError> {
+        let owned = |label: &str| Cow::Owned(label.to_string());
+        Ok(match self.u8()? {
+            0 => DomainPatternPart::Static(owned(self.str()?)),
+            1 => match self.u8()? {
+                bits @ 0..=3 => DomainPatternPart::Wildcard(DomainPatternWildcard { multi: bits & 2 != 0, optional: bits & 1 != 0 }),
+                _ => return Err(DecodeError::Invalid),
+            },
+            2 => DomainPatternPart::Named(owned(self.str()?)),
+            3 => DomainPatternPart::Glob(owned(self.str()?)),
+            4 => {
+                let len = self.len(8)?;
+                DomainPatternPart::OneOf((0..len).map(|_| self.str().map(owned)).collect::<Result<_, _>>()?)
+            }
+            5 => DomainPatternPart::Numeric,
+            #[cfg(feature = "regex")]
+            6 => DomainPatternPart::Regex(LabelRegex::new(self.str()?).map_err(|_| DecodeError::Invalid)?),
+            _ => return Err(DecodeError::Invalid),
+        })
+    }
+
+    pub(crate) fn pattern<const SPLITTER: char>(&mut self) -> Result<DomainPattern<'static, SPLITTER>, DecodeError> {
+        let negated = self.bool()?;
+        let len = self.len(1)?;
+        let mut steps: StepVec<'static> = Default::default();
+        for _ in 0..len {
+            steps.push(self.part()?);
+        }
+
+        Ok(DomainPattern { steps, negated })
+    }
+
+    /// Fails if anything is left, the bytes were written by something else
+    pub(crate) fn finish(self) -> Result<(), DecodeError> {
+        match self.pos == self.bytes.len() {
+            true => Ok(()),
+            false => Err(DecodeError::Invalid),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::binary::{decode_options, encode_options, Reader, Writer};
+    use crate::{DecodeError, Direction, DomainPattern, MatchOptions, TrailingDot};
+
+    #[test]
+    pub fn test_binary() {
+        let pattern: DomainPattern = "!*.{tenant}.{a,b}.#.api-*.com".try_into().expect("failed to parse");
+        let mut writer = Writer::new(b"TEST", 3);
+        writer.pattern(&pattern);
+        writer.str("done");
+        let bytes = writer.finish();
+
+        let mut reader = Reader::new(&bytes, b"TEST", 3).expect("valid bytes");
+        assert_eq!(reader.pattern::<'.'>(), Ok(pattern.to_owned()));
+        assert_eq!(reader.str(), Ok("done"));
+        assert_eq!(reader.finish(), Ok(()));
+
+        assert_eq!(Reader::new(&bytes, b"NOPE", 3).err(), Some(DecodeError::Magic));
+        assert_eq!(Reader::new(&bytes, b"TEST", 4).err(), Some(DecodeError::Version(3)));
+        assert_eq!(Reader::new(&bytes[..bytes.len() - 1], b"TEST", 3).err(), Some(DecodeError::Checksum));
+        assert_eq!(Reader::new(&bytes[..7], b"TEST", 3).err(), Some(DecodeError::Truncated));
+
+        let options = MatchOptions::new().case_insensitive(true).root_matches(false).trailing_dot(TrailingDot::Forbid).direction(Direction::RightToLeft);
+        assert_eq!(decode_options(encode_options(&options)), Ok(options));
+        assert_eq!(decode_options([0x80, 0, 0]), Err(DecodeError::Invalid));
+
+        let mut corrupted = bytes.clone();
+        corrupted[10] ^= 1;
+        assert_eq!(Reader::new(&corrupted, b"TEST", 3).err(), Some(DecodeError::Checksum));
+    }
+}
@@ -0,0 +1,125 @@
+//! Matching with a bit per part of the pattern instead of a stack of paths, for patterns that fit, from either end
+//! of the domain
+
+use crate::{Direction, DomainPattern, DomainPatternPart, DomainPatternWildcard, MatchOptions};
+
+// one bit for every part, and one for having taken every part
+pub(crate) const MAX_STEPS: usize = 127;
+
+impl<const SPLITTER: char> DomainPattern<'_, SPLITTER> {
+    // the same as `run`, without telling anyone about the decisions, the pattern can't have more than MAX_STEPS parts
+    pub(crate) fn run_bitset<'l>(&self, labels: impl DoubleEndedIterator<Item=&'l str>, options: &MatchOptions) -> bool {
+        let end = self.steps.len();
+        debug_assert!(end <= MAX_STEPS);
+
+        // parts that can be skipped, that stay around after taking a label, and that take any label
+        let (mut optional, mut multi, mut any) = (0u128, 0u128, 0u128);
+        for (idx, part) in self.steps.iter().enumerate() {
+            match part {
+                DomainPatternPart::Wildcard(DomainPatternWildcard { multi: is_multi, optional: is_optional }) => {
+                    optional |= (*is_optional as u128) << idx;
+                    multi |= (*is_multi as u128) << idx;
+                    any |= 1 << idx;
+                }
+                DomainPatternPart::Named(_) => any |= 1 << idx,
+                _ => {}
+            }
+        }
+
+        // the parts of paths that take the label
+        let take = |paths: u128, label: &str| {
+            let mut taken = paths & any;
+            let mut others = paths & !any;
+            while others != 0 {
+                let idx = others.trailing_zeros() as usize;
+                others &= others - 1;
+                if self.steps[idx].matches_label(label, options) {
+                    taken |= 1 << idx;
+                }
+            }
+
+            taken
+        };
+
+        let backwards = match options.direction {
+            Direction::Auto => any & (1 << (end - 1)) == 0 && any & 1 != 0,
+            Direction::LeftToRight => false,
+            Direction::RightToLeft => true,
+        };
+
+        if backwards {
+            // bit i is a path that took every part from i on, skipping optional wildcards goes down
+            let skip = |mut paths: u128| loop {
+                let skipped = (paths >> 1) & optional;
+                if skipped & !paths == 0 {
+                    return paths;
+                }
+
+                paths |= skipped;
+            };
+
+            let mut paths = skip(1 << end);
+            for label in labels.rev() {
+                paths = skip((paths & (multi << 1)) | take(paths >> 1, label));
+                if paths == 0 {
+                    return false;
+                }
+            }
+
+            return paths & 1 != 0;
+        }
+
+        // bit i is a path that took every part before i, skipping optional wildcards goes up
+        let skip = |mut paths: u128| loop {
+            let skipped = (paths & optional) << 1;
+            if skipped & !paths == 0 {
+                return paths;
+            }
+
+            paths |= skipped;
+        };
+
+        let mut paths = skip(1);
+        for label in labels {
+            paths = skip((paths & multi) | (take(paths & !(1 << end), label) << 1));
+            if paths == 0 {
+                return false;
+            }
+        }
+
+        paths & (1 << end) != 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Direction, DomainPattern, MatchOptions};
+
+    #[test]
+    pub fn test_bitset() {
+        let patterns = ["example.com", "*.example.com", "+.example.com", "**.example.com", "**+.example.com", "*.*.*.*.*.*", "**.a.**.b",
+            "{api,www}.#.com", "api-*.*-v1.com", "{tenant}.com", "a..b", "**", "*", "x.**.x.**", "*{100}.x"];
+        let domains = ["", "com", "example.com", "api.example.com", "a.b.example.com", "api.example.org", "a.b", "a.b.c.d.e.f",
+            "a.b.c.d.e.f.g", "a.x.y.b", "b.a", "api.42.com", "www.x.com", "api-x.y-v1.com", "x.x", "x.y.x.z", "example.com.", "..example..com"];
+
+        // explaining walks the stack of paths, matching uses the bitset
+        for pattern in patterns {
+            let pattern: DomainPattern = pattern.try_into().expect("failed to parse");
+            for domain in domains {
+                let expected = pattern.explain(domain).matched();
+                for direction in [Direction::Auto, Direction::LeftToRight, Direction::RightToLeft] {
+                    let options = MatchOptions::new().direction(direction);
+                    assert_eq!(pattern.matches_with(domain, &options), expected, "{} disagrees with itself on {:?} going {:?}", pattern, domain, direction);
+                }
+            }
+        }
+
+        let pattern: DomainPattern = "*{100}.x".try_into().expect("failed to parse");
+        assert!(pattern.matches(&format!("{}.x", ["a"; 100].join("."))));
+        assert!(!pattern.matches(&format!("{}.x", ["a"; 101].join("."))));
+
+        let pattern: DomainPattern = "+{127}.x".try_into().expect("failed to parse");
+        assert_eq!(pattern.len(), 128);
+        assert!(pattern.matches(&format!("{}.x", ["a"; 127].join("."))));
+    }
+}
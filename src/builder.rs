@@ -0,0 +1,289 @@
+//! Building patterns from code instead of parsing them
+
+use std::borrow::Cow;
+use std::fmt::{Display, Formatter};
+use crate::{is_glob, is_plain, push_part, push_wildcard, DomainPattern, DomainPatternPart, StepVec};
+
+/// Why a part can't be in a pattern, with the index of the part
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum InvalidPart {
+    /// The label is empty
+    EmptyLabel(usize),
+    /// The label contains the splitter
+    Splitter(usize),
+    /// A static label contains `*`, `+`, `?`, `[` or `]`, which would make it look like a wildcard
+    Wildcard(usize),
+    /// A name or one of the labels of a `{a,b}` group contains one of `{},*+?[]\`
+    InvalidCharacter(usize),
+    /// A glob with a malformed character class, or without anything that makes it a glob
+    InvalidGlob(usize),
+}
+
+impl Display for InvalidPart {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InvalidPart::EmptyLabel(part) => write!(f, "Label of part {} is empty", part),
+            InvalidPart::Splitter(part) => write!(f, "Label of part {} contains the splitter", part),
+            InvalidPart::Wildcard(part) => write!(f, "Label of part {} contains a wildcard character", part),
+            InvalidPart::InvalidCharacter(part) => write!(f, "Part {} contains an invalid character", part),
+            InvalidPart::InvalidGlob(part) => write!(f, "Glob of part {} is invalid", part),
+        }
+    }
+}
+
+impl std::error::Error for InvalidPart {}
+
+/// Builds a pattern part by part, from left to right, `DomainPatternBuilder::new().one().static_label("example").static_label("com")`
+/// builds the same pattern as parsing `+.example.com`
+///
+/// Wildcards are folded the same way the parser folds them.
+#[derive(Clone, Debug)]
+pub struct DomainPatternBuilder<'a, const SPLITTER: char = '.'> {
+    steps: StepVec<'a>,
+    negated: bool,
+}
+
+impl<const SPLITTER: char> Default for DomainPatternBuilder<'_, SPLITTER> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, const SPLITTER: char> DomainPatternBuilder<'a, SPLITTER> {
+    pub fn new() -> Self {
+        DomainPatternBuilder {
+            steps: Default::default(),
+            negated: false,
+        }
+    }
+
+    /// A label matched as is
+    pub fn static_label(mut self, label: impl Into<Cow<'a, str>>) -> Self {
+        self.steps.push(DomainPatternPart::Static(label.into()));
+        self
+    }
+
+    /// `*`, zero or one label
+    pub fn any(self) -> Self {
+        self.wildcard(true, false)
+    }
+
+    /// `+`, exactly one label
+    pub fn one(self) -> Self {
+        self.wildcard(false, false)
+    }
+
+    /// `**`, any amount of labels, including none
+    pub fn many(self) -> Self {
+        self.wildcard(true, true)
+    }
+
+    /// `**+`, one or more labels
+    pub fn at_least_one(self) -> Self {
+        self.wildcard(false, true)
+    }
+
+    /// Whether the pattern is an exception, like a pattern starting with `!`, see [`DomainPattern::is_negated`]
+    pub fn negated(mut self, negated: bool) -> Self {
+        self.negated = negated;
+        self
+    }
+
+    fn wildcard(mut self, optional: bool, multi: bool) -> Self {
+        push_wildcard(&mut self.steps, optional, multi);
+        self
+    }
+
+    /// Checks the labels and returns the pattern
+    pub fn build(self) -> Result<DomainPattern<'a, SPLITTER>, InvalidPart> {
+        for (idx, part) in self.steps.iter().enumerate() {
+            validate::<SPLITTER>(idx, part)?;
+        }
+
+        Ok(DomainPattern {
+            steps: self.steps,
+            negated: self.negated,
+        })
+    }
+}
+
+/// Builds a pattern from its parts like the parser does, folding wildcards and checking the labels
+impl<'a, const SPLITTER: char> TryFrom<Vec<DomainPatternPart<'a>>> for DomainPattern<'a, SPLITTER> {
+    type Error = InvalidPart;
+
+    fn try_from(parts: Vec<DomainPatternPart<'a>>) -> Result<Self, Self::Error> {
+        let mut steps: StepVec = Default::default();
+        for (idx, part) in parts.into_iter().enumerate() {
+            validate::<SPLITTER>(idx, &part)?;
+            push_part(&mut steps, part);
+        }
+
+        Ok(DomainPattern {
+            steps,
+            negated: false,
+        })
+    }
+}
+
+impl<'a, const SPLITTER: char> DomainPattern<'a, SPLITTER> {
+    /// Adds a part in front of the pattern
+    pub fn push_front(&mut self, part: DomainPatternPart<'a>) -> Result<(), InvalidPart> {
+        validate::<SPLITTER>(0, &part)?;
+        self.steps.insert(0, part);
+        self.optimize();
+        Ok(())
+    }
+
+    /// Removes the first part of the pattern
+    pub fn pop_front(&mut self) -> Option<DomainPatternPart<'a>> {
+        if self.steps.is_empty() {
+            return None;
+        }
+
+        let part = self.steps.remove(0);
+        self.optimize();
+        Some(part)
+    }
+
+    /// Replaces the part at the index, and returns the part that was there
+    ///
+    /// Wildcards are folded again afterwards, so the indices of the parts after it may change.
+    /// Panics if the index is out of bounds.
+    pub fn replace(&mut self, idx: usize, part: DomainPatternPart<'a>) -> Result<DomainPatternPart<'a>, InvalidPart> {
+        validate::<SPLITTER>(idx, &part)?;
+        let replaced = std::mem::replace(&mut self.steps[idx], part);
+        self.optimize();
+        Ok(replaced)
+    }
+
+    // folds the wildcards again, after parts were changed
+    fn optimize(&mut self) {
+        for part in std::mem::take(&mut self.steps) {
+            push_part(&mut self.steps, part);
+        }
+    }
+}
+
+// checks a part could have come out of the parser
+pub(crate) fn validate<const SPLITTER: char>(idx: usize, part: &DomainPatternPart<'_>) -> Result<(), InvalidPart> {
+    let label = |label: &str| match label {
+        "" => Err(InvalidPart::EmptyLabel(idx)),
+        label if label.contains(SPLITTER) => Err(InvalidPart::Splitter(idx)),
+        _ => Ok(()),
+    };
+
+    match part {
+        DomainPatternPart::Static(static_label) => {
+            label(static_label)?;
+            match static_label.contains(['*', '+', '?', '[', ']']) {
+                true => Err(InvalidPart::Wildcard(idx)),
+                false => Ok(()),
+            }
+        }
+
+        DomainPatternPart::Named(name) => {
+            label(name)?;
+            match is_plain(name) {
+                true => Ok(()),
+                false => Err(InvalidPart::InvalidCharacter(idx)),
+            }
+        }
+
+        DomainPatternPart::OneOf(labels) if labels.is_empty() => Err(InvalidPart::EmptyLabel(idx)),
+        DomainPatternPart::OneOf(labels) => labels.iter().try_for_each(|option| {
+            label(option)?;
+            match is_plain(option) {
+                true => Ok(()),
+                false => Err(InvalidPart::InvalidCharacter(idx)),
+            }
+        }),
+
+        DomainPatternPart::Glob(glob) => {
+            label(glob)?;
+            match is_glob(glob) {
+                true => Ok(()),
+                false => Err(InvalidPart::InvalidGlob(idx)),
+            }
+        }
+
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{DomainPattern, DomainPatternBuilder, DomainPatternPart, DomainPatternWildcard, InvalidPart};
+
+    #[test]
+    pub fn test_builder() {
+        let built: DomainPattern = DomainPatternBuilder::new().one().static_label("example").static_label("com").build().expect("valid pattern");
+        assert_eq!(built, "+.example.com".try_into().expect("failed to parse"));
+        assert!(built.matches("www.example.com"));
+
+        let built: DomainPattern = DomainPatternBuilder::new().many().many().any().at_least_one().static_label(String::from("x")).build().expect("valid pattern");
+        assert_eq!(built, "**.**.*.**+.x".try_into().expect("failed to parse"));
+
+        let built: DomainPattern = DomainPatternBuilder::new().negated(true).static_label("internal").build().expect("valid pattern");
+        assert_eq!(built.to_string(), "!internal");
+
+        let invalid = DomainPatternBuilder::<'_, '.'>::new().static_label("a").static_label("b.c").build();
+        assert_eq!(invalid.err(), Some(InvalidPart::Splitter(1)));
+        let invalid = DomainPatternBuilder::<'_, '.'>::new().static_label("").build();
+        assert_eq!(invalid.err(), Some(InvalidPart::EmptyLabel(0)));
+        let invalid = DomainPatternBuilder::<'_, '.'>::new().one().static_label("a*").build();
+        assert_eq!(invalid.err(), Some(InvalidPart::Wildcard(1)));
+    }
+
+    #[test]
+    pub fn test_from_parts() {
+        let any = || DomainPatternPart::Wildcard(DomainPatternWildcard::new(true, true));
+
+        let parts = vec![any(), any(), DomainPatternPart::OneOf(vec!["a".into(), "b".into()]), DomainPatternPart::Glob("api-*".into()), DomainPatternPart::Static("com".into())];
+        let pattern: DomainPattern = parts.try_into().expect("valid parts");
+        assert_eq!(pattern, "**.{a,b}.api-*.com".try_into().expect("failed to parse"));
+
+        let invalid = |part: DomainPatternPart<'static>| DomainPattern::<'_, '.'>::try_from(vec![DomainPatternPart::Static("x".into()), part]).err();
+        assert_eq!(invalid(DomainPatternPart::Static("a.b".into())), Some(InvalidPart::Splitter(1)));
+        assert_eq!(invalid(DomainPatternPart::Static("a+".into())), Some(InvalidPart::Wildcard(1)));
+        assert_eq!(invalid(DomainPatternPart::Named("a,b".into())), Some(InvalidPart::InvalidCharacter(1)));
+        assert_eq!(invalid(DomainPatternPart::OneOf(vec![])), Some(InvalidPart::EmptyLabel(1)));
+        assert_eq!(invalid(DomainPatternPart::OneOf(vec!["a".into(), "".into()])), Some(InvalidPart::EmptyLabel(1)));
+        assert_eq!(invalid(DomainPatternPart::Glob("[a".into())), Some(InvalidPart::InvalidGlob(1)));
+        assert_eq!(invalid(DomainPatternPart::Glob("abc".into())), Some(InvalidPart::InvalidGlob(1)));
+        // globs the parser would read as something else
+        for glob in ["*", "**", "a+*", "a{b}*", "{a*"] {
+            assert_eq!(invalid(DomainPatternPart::Glob(glob.into())), Some(InvalidPart::InvalidGlob(1)), "{:?}", glob);
+        }
+        assert_eq!(invalid(DomainPatternPart::Glob("a\\+*".into())), None);
+        assert_eq!(invalid(DomainPatternPart::Numeric), None);
+    }
+
+    #[test]
+    pub fn test_edit() {
+        let mut pattern: DomainPattern = "+.example.com".try_into().expect("failed to parse");
+        pattern.push_front(DomainPatternPart::Static("api".into())).expect("valid part");
+        assert_eq!(pattern.to_string(), "api.+.example.com");
+
+        assert_eq!(pattern.pop_front(), Some(DomainPatternPart::Static("api".into())));
+        assert_eq!(pattern.to_string(), "+.example.com");
+
+        pattern.push_front(DomainPatternPart::Wildcard(DomainPatternWildcard::new(true, true))).expect("valid part");
+        assert_eq!(pattern.to_string(), "**+.example.com");
+        assert_eq!(pattern.len(), 3);
+
+        let replaced = pattern.replace(1, DomainPatternPart::Static("test".into())).expect("valid part");
+        assert_eq!(replaced, DomainPatternPart::Static("example".into()));
+        assert_eq!(pattern.to_string(), "**+.test.com");
+
+        let replaced = pattern.replace(1, DomainPatternPart::Wildcard(DomainPatternWildcard::new(true, true))).expect("valid part");
+        assert_eq!(replaced, DomainPatternPart::Static("test".into()));
+        assert_eq!(pattern.to_string(), "**+.com");
+
+        assert_eq!(pattern.replace(0, DomainPatternPart::Static("a.b".into())), Err(InvalidPart::Splitter(0)));
+        assert_eq!(pattern.push_front(DomainPatternPart::Static("".into())), Err(InvalidPart::EmptyLabel(0)));
+        assert_eq!(pattern.to_string(), "**+.com");
+
+        let mut empty: DomainPattern = DomainPatternBuilder::new().build().expect("valid pattern");
+        assert_eq!(empty.pop_front(), None);
+    }
+}
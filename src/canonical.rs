@@ -0,0 +1,114 @@
+//! Rewriting patterns into a single form for patterns that match the same way
+
+use crate::{DomainPattern, DomainPatternPart, DomainPatternWildcard, StepVec};
+
+// a run of wildcards, as the amount of labels it must take, and may take on top of that, None for no limit
+struct Run {
+    required: usize,
+    optional: Option<usize>,
+}
+
+impl Run {
+    fn take(&mut self, wildcard: &DomainPatternWildcard) {
+        if !wildcard.optional {
+            self.required += 1;
+        }
+
+        self.optional = match wildcard.multi {
+            true => None,
+            false => self.optional.map(|optional| optional + wildcard.optional as usize),
+        };
+    }
+
+    // the labels it must take as `+`, followed by the labels it may take as `*`, or `**+` when there's no limit
+    fn flush(&mut self, steps: &mut StepVec) {
+        let wildcard = |multi, optional| DomainPatternPart::Wildcard(DomainPatternWildcard { multi, optional });
+        match self.optional {
+            Some(optional) => {
+                steps.extend((0..self.required).map(|_| wildcard(false, false)));
+                steps.extend((0..optional).map(|_| wildcard(false, true)));
+            }
+
+            None if self.required == 0 => steps.push(wildcard(true, true)),
+            None => {
+                steps.extend((1..self.required).map(|_| wildcard(false, false)));
+                steps.push(wildcard(true, false));
+            }
+        }
+
+        *self = Run { required: 0, optional: Some(0) };
+    }
+}
+
+impl<'a, const SPLITTER: char> DomainPattern<'a, SPLITTER> {
+    /// The pattern in a canonical form, patterns written differently that match the same way, like `*.+.*`, `+.*.*` and
+    /// `*{1,3}`, have the same canonical form, so it can be used to find duplicates
+    ///
+    /// A run of wildcards becomes the labels it must take as `+`, followed by the labels it may take as `*`, or a
+    /// trailing `**+` if there's no limit. `{a,b}` groups are sorted without duplicates, and a group of one label
+    /// becomes that label.
+    pub fn canonicalize(&self) -> DomainPattern<'a, SPLITTER> {
+        let mut steps: StepVec = Default::default();
+        let mut run = Run { required: 0, optional: Some(0) };
+        for part in self.steps.iter() {
+            if let DomainPatternPart::Wildcard(wildcard) = part {
+                run.take(wildcard);
+                continue;
+            }
+
+            run.flush(&mut steps);
+            steps.push(match part {
+                DomainPatternPart::OneOf(labels) => {
+                    let mut labels = labels.clone();
+                    labels.sort();
+                    labels.dedup();
+                    match labels.len() {
+                        1 => DomainPatternPart::Static(labels.remove(0)),
+                        _ => DomainPatternPart::OneOf(labels),
+                    }
+                }
+
+                part => part.clone(),
+            });
+        }
+
+        run.flush(&mut steps);
+        DomainPattern {
+            steps,
+            negated: self.negated,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::DomainPattern;
+
+    fn canonical(pattern: &str) -> String {
+        let pattern: DomainPattern = pattern.try_into().expect("failed to parse");
+        let canonical = pattern.canonicalize();
+        assert!(canonical.equivalent(&pattern), "{} isn't equivalent to {}", canonical, pattern);
+        canonical.to_string()
+    }
+
+    #[test]
+    pub fn test_canonicalize() {
+        assert_eq!(canonical("*.+.*.example.com"), "+.*.*.example.com");
+        assert_eq!(canonical("*{1,3}.example.com"), "+.*.*.example.com");
+        assert_eq!(canonical("*.**"), "**");
+        assert_eq!(canonical("*.**.+"), "**+");
+        assert_eq!(canonical("+.*.**+.+"), "+.+.**+");
+        assert_eq!(canonical("+{2,}"), "+.**+");
+        assert_eq!(canonical("*.a.*.*"), "*.a.*.*");
+        assert_eq!(canonical("{www,api,www}.{tenant}.*.com"), "{api,www}.{tenant}.*.com");
+        assert_eq!(canonical("{api,api}.com"), "api.com");
+        assert_eq!(canonical("!*.+.internal"), "!+.*.internal");
+        assert_eq!(canonical(""), "");
+
+        let pattern: DomainPattern = "*.+.com".try_into().expect("failed to parse");
+        let other: DomainPattern = "+.*.com".try_into().expect("failed to parse");
+        assert_ne!(pattern, other);
+        assert_eq!(pattern.canonicalize(), other.canonicalize());
+        assert_eq!(other.canonicalize(), other);
+    }
+}
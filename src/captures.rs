@@ -0,0 +1,305 @@
+//! Finding out which labels every part of a pattern matched
+
+use std::ops::Range;
+use crate::{DomainPattern, DomainPatternPart, DomainPatternWildcard, MatchOptions};
+
+/// The labels every part of a pattern matched, see [`DomainPattern::captures`]
+///
+/// Parts are indexed in the same order as the pattern, after the pattern has been optimized,
+/// so `**.**.domain.tld` only has 3 parts.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Captures<'d> {
+    domain: &'d str,
+    // byte ranges of the non empty labels in the domain
+    labels: Vec<Range<usize>>,
+    // for every part, the range of labels it matched
+    parts: Vec<Range<usize>>,
+    // name and index of every named part
+    names: Vec<(String, usize)>,
+}
+
+impl<'d> Captures<'d> {
+    /// The domain that was matched
+    pub fn domain(&self) -> &'d str {
+        self.domain
+    }
+
+    /// Amount of parts in the pattern
+    pub fn len(&self) -> usize {
+        self.parts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parts.is_empty()
+    }
+
+    /// Returns the part of the domain the part at the given index matched, including the splitters between labels
+    ///
+    /// Returns None if the part didn't match any label, e.g. a `*` or `**` that got skipped.
+    pub fn get(&self, part: usize) -> Option<&'d str> {
+        Some(&self.domain[self.span(part)?])
+    }
+
+    /// Returns the byte range in the domain the part at the given index matched, see [`Captures::get`]
+    pub fn span(&self, part: usize) -> Option<Range<usize>> {
+        let labels = self.parts.get(part)?;
+        if labels.is_empty() {
+            return None;
+        }
+
+        Some(self.labels[labels.start].start..self.labels[labels.end - 1].end)
+    }
+
+    /// Iterates over the byte ranges of every part that matched at least one label, e.g. for highlighting
+    pub fn spans(&self) -> impl Iterator<Item=MatchSpan> + '_ {
+        (0..self.parts.len()).filter_map(|part_index| self.span(part_index).map(|range| MatchSpan { part_index, range }))
+    }
+
+    /// Iterates over the separate labels the part at the given index matched, borrowed from the domain
+    ///
+    /// Empty labels are skipped, like when matching, so `**` matching `a..b` yields `a` and `b`.
+    pub fn labels(&self, part: usize) -> impl DoubleEndedIterator<Item=&'d str> + '_ {
+        let labels = self.parts.get(part).cloned().unwrap_or_default();
+        let domain = self.domain;
+        self.labels[labels].iter().map(move |range| &domain[range.clone()])
+    }
+
+    /// Returns the amount of labels the part at the given index matched, e.g. how deep the subdomain a `**` matched is
+    ///
+    /// Static labels and single wildcards always give 1, except for a skipped `*` which gives 0.
+    pub fn consumed_count(&self, part: usize) -> Option<usize> {
+        self.parts.get(part).map(|labels| labels.len())
+    }
+
+    /// Returns the label the named part (`{name}`) matched
+    pub fn name(&self, name: &str) -> Option<&'d str> {
+        let (_, part) = self.names.iter().find(|(part_name, _)| part_name == name)?;
+        self.get(*part)
+    }
+
+    /// Iterates over what every part matched, see [`Captures::get`]
+    pub fn iter(&self) -> impl Iterator<Item=Option<&'d str>> + '_ {
+        (0..self.parts.len()).map(|part| self.get(part))
+    }
+}
+
+/// How a pattern matched a domain, see [`DomainPattern::match_kind`]
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum MatchKind {
+    /// Every label was matched by a static label or a `{a,b}` group, wildcards only got skipped
+    Exact,
+    /// Every label was matched by a wildcard
+    Wildcard,
+    /// Some labels were matched by static labels, some by wildcards
+    MixedWildcard,
+}
+
+/// The byte range in the domain a single part of the pattern matched, see [`Captures::spans`]
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct MatchSpan {
+    pub part_index: usize,
+    pub range: Range<usize>,
+}
+
+impl<'a, const SPLITTER: char> DomainPattern<'a, SPLITTER> {
+    /// Matches the domain and returns which labels every part matched, or None if the pattern doesn't match
+    ///
+    /// When there are multiple ways to match, parts further to the left take as many labels as they can,
+    /// so `**.x.**` gives `x.x` to the first part when matching `x.x.x`.
+    pub fn captures<'d>(&self, domain: &'d str) -> Option<Captures<'d>> {
+        let mut labels = vec![];
+        let mut offset = 0;
+        for label in domain.split(SPLITTER) {
+            if !label.is_empty() {
+                labels.push(offset..offset + label.len());
+            }
+
+            offset += label.len() + SPLITTER.len_utf8();
+        }
+
+        let steps = self.steps.len();
+        let width = labels.len() + 1;
+
+        // tail[step * width + label] is true when the steps from step onwards match the labels from label onwards
+        let mut tail = vec![false; (steps + 1) * width];
+        tail[steps * width + labels.len()] = true;
+
+        for step in (0..steps).rev() {
+            for label in (0..=labels.len()).rev() {
+                let has_label = label < labels.len();
+                let matched = match &self.steps[step] {
+                    DomainPatternPart::Wildcard(DomainPatternWildcard { multi, optional }) => {
+                        (*optional && tail[(step + 1) * width + label])
+                            || (has_label && (tail[(step + 1) * width + label + 1] || (*multi && tail[step * width + label + 1])))
+                    }
+
+                    part => has_label && part.matches_label(&domain[labels[label].clone()], &MatchOptions::new()) && tail[(step + 1) * width + label + 1],
+                };
+
+                tail[step * width + label] = matched;
+            }
+        }
+
+        if !tail[0] {
+            return None;
+        }
+
+        let mut parts = Vec::with_capacity(steps);
+        let mut label = 0;
+        for step in 0..steps {
+            let consumed = match &self.steps[step] {
+                DomainPatternPart::Wildcard(DomainPatternWildcard { multi, optional }) => {
+                    let max = if *multi { labels.len() - label } else { 1.min(labels.len() - label) };
+                    let min = if *optional { 0 } else { 1 };
+                    (min..=max).rev()
+                        .find(|consumed| tail[(step + 1) * width + label + consumed])
+                        .expect("tail says the rest matches")
+                }
+
+                _ => 1,
+            };
+
+            parts.push(label..label + consumed);
+            label += consumed;
+        }
+
+        let names = self.steps.iter()
+            .enumerate()
+            .filter_map(|(idx, part)| match part {
+                DomainPatternPart::Named(name) => Some((name.to_string(), idx)),
+                _ => None,
+            })
+            .collect();
+
+        Some(Captures {
+            domain,
+            labels,
+            parts,
+            names,
+        })
+    }
+}
+
+impl<const SPLITTER: char> DomainPattern<'_, SPLITTER> {
+    /// Returns whether the domain was matched literally or through wildcards, or None if the pattern doesn't match
+    pub fn match_kind(&self, domain: &str) -> Option<MatchKind> {
+        let captures = self.captures(domain)?;
+        let (mut literal, mut wildcard) = (false, false);
+        for (part, labels) in self.steps.iter().zip(&captures.parts) {
+            if labels.is_empty() {
+                continue;
+            }
+
+            match part {
+                DomainPatternPart::Static(_) | DomainPatternPart::OneOf(_) => literal = true,
+                _ => wildcard = true,
+            }
+        }
+
+        Some(match (literal, wildcard) {
+            (_, false) => MatchKind::Exact,
+            (false, true) => MatchKind::Wildcard,
+            (true, true) => MatchKind::MixedWildcard,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{DomainPattern, MatchKind, MatchSpan};
+
+    #[test]
+    pub fn test_captures() {
+        let pattern: DomainPattern = "+.app.example.com".try_into().expect("failed to parse");
+        let captures = pattern.captures("tenant.app.example.com").expect("should match");
+        assert_eq!(captures.len(), 4);
+        assert_eq!(captures.get(0), Some("tenant"));
+        assert_eq!(captures.get(1), Some("app"));
+        assert_eq!(captures.get(4), None);
+        assert!(pattern.captures("app.example.com").is_none());
+
+        let pattern: DomainPattern = "**.example.com".try_into().expect("failed to parse");
+        assert_eq!(pattern.captures("a.b.example.com").expect("should match").get(0), Some("a.b"));
+        assert_eq!(pattern.captures("example.com").expect("should match").get(0), None);
+        assert_eq!(pattern.captures(".a..b.example.com.").expect("should match").get(0), Some("a..b"));
+        let captures = pattern.captures(".a..b.example.com.").expect("should match");
+        assert_eq!(captures.labels(0).collect::<Vec<_>>(), vec!["a", "b"]);
+        assert_eq!(captures.labels(0).next_back(), Some("b"));
+        assert_eq!(captures.labels(7).count(), 0);
+
+        let pattern: DomainPattern = "*.*.x".try_into().expect("failed to parse");
+        let captures = pattern.captures("a.x").expect("should match");
+        assert_eq!(captures.iter().collect::<Vec<_>>(), vec![Some("a"), None, Some("x")]);
+
+        let pattern: DomainPattern = "**.x.**".try_into().expect("failed to parse");
+        let captures = pattern.captures("x.x.x").expect("should match");
+        assert_eq!(captures.iter().collect::<Vec<_>>(), vec![Some("x.x"), Some("x"), None]);
+        assert_eq!(captures.consumed_count(0), Some(2));
+        assert_eq!(captures.consumed_count(1), Some(1));
+        assert_eq!(captures.consumed_count(2), Some(0));
+        assert_eq!(captures.consumed_count(3), None);
+
+        let pattern: DomainPattern<'/'> = "+/nice/**".try_into().expect("failed to parse");
+        let captures = pattern.captures("wow/nice/very/nice").expect("should match");
+        assert_eq!(captures.iter().collect::<Vec<_>>(), vec![Some("wow"), Some("nice"), Some("very/nice")]);
+    }
+
+    #[test]
+    pub fn test_spans() {
+        let pattern: DomainPattern = "**.www.+.com".try_into().expect("failed to parse");
+        let domain = "a..b.www.example.com.";
+        let captures = pattern.captures(domain).expect("should match");
+        assert_eq!(captures.span(0), Some(0..4));
+        assert_eq!(captures.span(1), Some(5..8));
+
+        let spans: Vec<_> = captures.spans().collect();
+        assert_eq!(spans, vec![
+            MatchSpan { part_index: 0, range: 0..4 },
+            MatchSpan { part_index: 1, range: 5..8 },
+            MatchSpan { part_index: 2, range: 9..16 },
+            MatchSpan { part_index: 3, range: 17..20 },
+        ]);
+
+        // skipped parts don't get a span
+        let captures = pattern.captures("www.example.com").expect("should match");
+        assert_eq!(captures.span(0), None);
+        assert_eq!(captures.spans().map(|span| span.part_index).collect::<Vec<_>>(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    pub fn test_named() {
+        let pattern: DomainPattern = "{tenant}.app.{region}.example.com".try_into().expect("failed to parse");
+        let captures = pattern.captures("cijber.app.eu.example.com").expect("should match");
+        assert_eq!(captures.name("tenant"), Some("cijber"));
+        assert_eq!(captures.name("region"), Some("eu"));
+        assert_eq!(captures.name("nope"), None);
+        assert_eq!(captures.get(0), Some("cijber"));
+
+        assert!(pattern.matches("cijber.app.eu.example.com"));
+        assert!(!pattern.matches("app.eu.example.com"));
+    }
+
+    #[test]
+    pub fn test_agrees_with_matches() {
+        let patterns = ["domain.tld", "*.domain.tld", "+.domain.tld", "**.domain.tld", "**+.domain.tld", "nice.**.nice", "nice.**+.nice", "+.nice.**", "x.*.*", "*.+", "**", "*"];
+        let domains = ["", "tld", "domain.tld", "sub.domain.tld", "sub.sub.domain.tld", "nice", "nice.nice", "nice.nice.nice", "nice.wow", "x", "x.x.x", "x.x.x.x"];
+
+        for pattern in patterns {
+            let pattern: DomainPattern = pattern.try_into().expect("failed to parse");
+            for domain in domains {
+                assert_eq!(pattern.matches(domain), pattern.captures(domain).is_some(), "{:?} against {:?}", pattern, domain);
+            }
+        }
+    }
+
+    #[test]
+    pub fn test_match_kind() {
+        let pattern: DomainPattern = "*.example.com".try_into().expect("failed to parse");
+        assert_eq!(pattern.match_kind("example.com"), Some(MatchKind::Exact));
+        assert_eq!(pattern.match_kind("www.example.com"), Some(MatchKind::MixedWildcard));
+        assert_eq!(pattern.match_kind("example.org"), None);
+
+        let pattern: DomainPattern = "+.+".try_into().expect("failed to parse");
+        assert_eq!(pattern.match_kind("example.com"), Some(MatchKind::Wildcard));
+    }
+}
@@ -0,0 +1,132 @@
+//! Matching hostnames against the DNS names of TLS certificates, following RFC 6125
+
+use std::borrow::Cow;
+use crate::hostname::validate;
+use crate::{DomainPattern, DomainPatternPart, DomainPatternWildcard, InvalidToken, MatchOptions, ParseErrorKind, StepVec};
+
+// the only characters a DNS name of a certificate can have, besides the wildcard
+fn is_ldh(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'-' || b == b'_'
+}
+
+impl<'a> DomainPattern<'a> {
+    /// Parses a DNS name of a certificate, like a subject alternative name, the way RFC 6125 asks for
+    ///
+    /// Only the leftmost label can have a wildcard, and only one, so `*.example.com` takes exactly one label, and
+    /// `api-*.example.com` becomes a glob taking a single label starting with `api-`. A wildcard right in front of the
+    /// last label, like `*.com`, is rejected, and so are wildcards within A-labels like `xn--*.example.com`. Labels are
+    /// kept as they are, certificate names are matched ignoring case, see [`CertHostMatcher`].
+    pub fn parse_rfc6125(name: &'a str) -> Result<Self, InvalidToken<'a>> {
+        if name.is_empty() {
+            return Err(InvalidToken::new(ParseErrorKind::EmptyPattern, 0, name, name));
+        }
+
+        let labels: Vec<&str> = name.split('.').collect();
+        let mut steps: StepVec<'a> = Default::default();
+        let mut offset = 0;
+        for (idx, label) in labels.iter().copied().enumerate() {
+            if label.is_empty() {
+                let kind = match idx {
+                    0 => ParseErrorKind::LeadingSplitter,
+                    idx if idx == labels.len() - 1 => ParseErrorKind::TrailingSplitter,
+                    _ => ParseErrorKind::EmptyLabel,
+                };
+
+                return Err(InvalidToken::new(kind, offset, label, name));
+            }
+
+            let invalid = |position: usize| InvalidToken::new(ParseErrorKind::InvalidToken, offset + position, label, name);
+            // only the first wildcard of the leftmost label is allowed
+            let wildcard = label.find('*').filter(|_| idx == 0);
+            if let Some(position) = label.bytes().enumerate().position(|(position, b)| !is_ldh(b) && Some(position) != wildcard) {
+                return Err(invalid(position));
+            }
+
+            steps.push(match label {
+                "*" if labels.len() < 3 => return Err(invalid(0)),
+                "*" => DomainPatternPart::Wildcard(DomainPatternWildcard::new(false, false)),
+                label if wildcard.is_none() => DomainPatternPart::Static(Cow::Borrowed(label)),
+                label if labels.len() < 3 || label.get(..4).is_some_and(|prefix| prefix.eq_ignore_ascii_case("xn--")) => return Err(invalid(0)),
+                label => DomainPatternPart::Glob(Cow::Borrowed(label)),
+            });
+
+            offset += label.len() + 1;
+        }
+
+        Ok(DomainPattern {
+            steps,
+            negated: false,
+        })
+    }
+}
+
+/// Checks a hostname against the DNS names of a certificate, like a TLS client verifying the server it connected to
+///
+/// Names are parsed with [`DomainPattern::parse_rfc6125`] and matched ignoring ASCII case. The hostname has to be a
+/// valid hostname in its A-labels, a trailing dot is ignored, and partial wildcards like `api-*` never match an A-label.
+#[derive(Clone, Debug, Default)]
+pub struct CertHostMatcher<'a> {
+    names: Vec<DomainPattern<'a>>,
+}
+
+impl<'a> CertHostMatcher<'a> {
+    pub fn new(names: impl IntoIterator<Item=&'a str>) -> Result<Self, InvalidToken<'a>> {
+        let names = names.into_iter().map(DomainPattern::parse_rfc6125).collect::<Result<_, _>>()?;
+        Ok(CertHostMatcher { names })
+    }
+
+    pub fn names(&self) -> &[DomainPattern<'a>] {
+        &self.names
+    }
+
+    pub fn matches(&self, hostname: &str) -> bool {
+        if validate::<'.'>(hostname).is_err() {
+            return false;
+        }
+
+        let hostname = hostname.strip_suffix('.').unwrap_or(hostname);
+        let a_label = hostname.get(..4).is_some_and(|prefix| prefix.eq_ignore_ascii_case("xn--"));
+        let options = MatchOptions::new().case_insensitive(true);
+        self.names.iter()
+            .filter(|name| !a_label || !matches!(name.steps.first(), Some(DomainPatternPart::Glob(_))))
+            .any(|name| name.matches_with(hostname, &options))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CertHostMatcher, DomainPattern, ParseErrorKind};
+
+    #[test]
+    pub fn test_parse_rfc6125() {
+        assert_eq!(DomainPattern::parse_rfc6125("*.example.com").unwrap(), DomainPattern::parse("+.example.com").unwrap());
+        assert_eq!(DomainPattern::parse_rfc6125("api-*.example.com").unwrap(), DomainPattern::parse("api-*.example.com").unwrap());
+        assert_eq!(DomainPattern::parse_rfc6125("Example.com").unwrap().to_string(), "Example.com");
+
+        for (name, position, kind) in [("www.*.example.com", 4, ParseErrorKind::InvalidToken), ("**.example.com", 1, ParseErrorKind::InvalidToken),
+            ("a*b*.example.com", 3, ParseErrorKind::InvalidToken), ("*.com", 0, ParseErrorKind::InvalidToken), ("a*.com", 0, ParseErrorKind::InvalidToken),
+            ("xn--*.example.com", 0, ParseErrorKind::InvalidToken), ("{a,b}.com", 0, ParseErrorKind::InvalidToken),
+            ("", 0, ParseErrorKind::EmptyPattern), (".example.com", 0, ParseErrorKind::LeadingSplitter),
+            ("example.com.", 12, ParseErrorKind::TrailingSplitter), ("a..com", 2, ParseErrorKind::EmptyLabel)] {
+            let err = DomainPattern::parse_rfc6125(name).expect_err(name);
+            assert_eq!((err.position(), err.kind()), (position, kind), "{}", name);
+        }
+    }
+
+    #[test]
+    pub fn test_cert_host_matcher() {
+        let matcher = CertHostMatcher::new(["*.example.com", "example.com", "api-*.example.net"]).expect("valid names");
+        for (hostname, matches) in [("www.example.com", true), ("WWW.Example.COM", true), ("www.example.com.", true), ("example.com", true),
+            ("a.b.example.com", false), (".example.com", false), ("api-v1.example.net", true), ("api.example.net", false),
+            ("xn--bcher-kva.example.com", true), ("*.example.com", false), ("example.org", false)] {
+            assert_eq!(matcher.matches(hostname), matches, "{:?}", hostname);
+        }
+
+        let matcher = CertHostMatcher::new(["x*.example.com"]).expect("valid names");
+        assert!(matcher.matches("xyz.example.com"));
+        assert!(!matcher.matches("xn--bcher-kva.example.com"));
+        assert_eq!(matcher.names().len(), 1);
+
+        assert!(CertHostMatcher::new(["example.com", "*.*.example.com"]).is_err());
+    }
+}
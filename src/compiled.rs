@@ -0,0 +1,254 @@
+//! Patterns compiled into a DFA over labels, so matching takes a single table lookup per label
+
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use crate::{split, DomainPattern, DomainPatternPart, DomainPatternWildcard, MatchOptions};
+
+mod binary;
+
+// the state without any paths left, nothing matches from here
+pub(crate) const DEAD: u32 = 0;
+// most states the DFA may have, patterns like `**.a.+.+.+` need a state for every combination of labels they saw
+pub(crate) const MAX_STATES: usize = 4096;
+// most parts like globs that have to be tried against every label, every combination of them is a class of labels
+pub(crate) const MAX_PREDICATES: usize = 8;
+
+/// Why a pattern can't be compiled, see [`DomainPattern::compile`]
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum CompileError {
+    /// The DFA would have more than 4096 states, like for `**.a.+.+.+.+.+.+.+.+.+.+.+.+`
+    TooManyStates,
+    /// The pattern has more than 8 different globs, `#` or regexes
+    TooManyPredicates,
+}
+
+impl Display for CompileError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CompileError::TooManyStates => write!(f, "Pattern needs more than {} states", MAX_STATES),
+            CompileError::TooManyPredicates => write!(f, "Pattern has more than {} globs, numeric labels or regexes", MAX_PREDICATES),
+        }
+    }
+}
+
+impl std::error::Error for CompileError {}
+
+/// A pattern compiled into a DFA, matching like [`DomainPattern::matches`] in a single pass over the labels,
+/// without allocating
+///
+/// Labels are sorted into classes first, every static label of the pattern is a class of its own, and other labels
+/// are classed by which globs, `#` and regexes of the pattern they match.
+#[derive(Clone, Debug)]
+pub struct CompiledPattern<const SPLITTER: char = '.'> {
+    // class of every static label in the pattern
+    literals: HashMap<Box<str>, u32>,
+    // parts tried against labels that aren't static labels of the pattern
+    predicates: Vec<DomainPatternPart<'static>>,
+    classes: u32,
+    // the next state for every state and class of label
+    transitions: Vec<u32>,
+    accepting: Vec<bool>,
+}
+
+// the states reachable from the state by skipping optional wildcards, including the state itself
+fn closure(steps: &[DomainPatternPart], mut state: usize, states: &mut Vec<usize>) {
+    states.push(state);
+    while let Some(DomainPatternPart::Wildcard(DomainPatternWildcard { optional: true, .. })) = steps.get(state) {
+        state += 1;
+        states.push(state);
+    }
+}
+
+impl<const SPLITTER: char> DomainPattern<'_, SPLITTER> {
+    /// Compiles the pattern into a DFA, so matching a domain takes time linear in its labels, even for patterns like
+    /// `*.*.*.*.*.*`
+    ///
+    /// Compiling takes time and memory, and may fail for patterns that would need too big a DFA, so it's worth it for
+    /// patterns that match a lot of domains. Whether the pattern is negated is ignored.
+    pub fn compile(&self) -> Result<CompiledPattern<SPLITTER>, CompileError> {
+        let steps = &self.steps[..];
+        let options = MatchOptions::new();
+
+        let mut literal_labels: Vec<&str> = vec![];
+        let mut predicates: Vec<DomainPatternPart<'static>> = vec![];
+        for part in steps {
+            match part {
+                DomainPatternPart::Static(label) => literal_labels.push(label),
+                DomainPatternPart::OneOf(labels) => literal_labels.extend(labels.iter().map(|label| label.as_ref())),
+                DomainPatternPart::Wildcard(_) | DomainPatternPart::Named(_) => {}
+                part => {
+                    if !predicates.iter().any(|predicate| predicate == part) {
+                        predicates.push(part.clone().into_owned());
+                    }
+                }
+            }
+        }
+
+        if predicates.len() > MAX_PREDICATES {
+            return Err(CompileError::TooManyPredicates);
+        }
+
+        // empty labels are skipped, so they never get a class
+        literal_labels.retain(|label| !label.is_empty());
+        literal_labels.sort_unstable();
+        literal_labels.dedup();
+
+        // literals come first, then a class for every combination of predicates
+        let masks = 1u32 << predicates.len();
+        let classes = literal_labels.len() as u32 + masks;
+        let accepts = |part: &DomainPatternPart, class: u32| -> bool {
+            match (part, literal_labels.get(class as usize)) {
+                (DomainPatternPart::Wildcard(_) | DomainPatternPart::Named(_), _) => true,
+                (part, Some(label)) => part.matches_label(label, &options),
+                (DomainPatternPart::Static(_) | DomainPatternPart::OneOf(_), None) => false,
+                (part, None) => {
+                    let mask = class - literal_labels.len() as u32;
+                    let bit = predicates.iter().position(|predicate| predicate == part).expect("every predicate is known");
+                    mask & (1 << bit) != 0
+                }
+            }
+        };
+
+        let mut ids: HashMap<Vec<usize>, u32> = HashMap::new();
+        let mut sets: Vec<Vec<usize>> = vec![];
+        let mut intern = |set: Vec<usize>, sets: &mut Vec<Vec<usize>>| -> Result<u32, CompileError> {
+            if let Some(id) = ids.get(&set) {
+                return Ok(*id);
+            }
+
+            if sets.len() >= MAX_STATES {
+                return Err(CompileError::TooManyStates);
+            }
+
+            let id = sets.len() as u32;
+            ids.insert(set.clone(), id);
+            sets.push(set);
+            Ok(id)
+        };
+
+        intern(vec![], &mut sets)?;
+        let mut start = vec![];
+        closure(steps, 0, &mut start);
+        let start = intern(start, &mut sets)?;
+
+        let mut transitions: Vec<u32> = vec![];
+        let mut state = 0;
+        while state < sets.len() {
+            for class in 0..classes {
+                let mut next = vec![];
+                for path in &sets[state] {
+                    let Some(part) = steps.get(*path) else {
+                        continue;
+                    };
+
+                    if let DomainPatternPart::Wildcard(DomainPatternWildcard { multi: true, .. }) = part {
+                        next.push(*path);
+                    }
+
+                    if accepts(part, class) {
+                        closure(steps, path + 1, &mut next);
+                    }
+                }
+
+                next.sort_unstable();
+                next.dedup();
+                transitions.push(intern(next, &mut sets)?);
+            }
+
+            state += 1;
+        }
+
+        let accepting = sets.iter().map(|set| set.contains(&steps.len())).collect();
+
+        // the start state has to be the second, right after the dead state
+        debug_assert_eq!(start, 1);
+        let literals = literal_labels.iter().enumerate().map(|(class, label)| (Box::from(*label), class as u32)).collect();
+
+        Ok(CompiledPattern {
+            literals,
+            predicates,
+            classes,
+            transitions,
+            accepting,
+        })
+    }
+}
+
+impl<const SPLITTER: char> CompiledPattern<SPLITTER> {
+    // the start state comes right after the dead state
+    const START: u32 = 1;
+
+    fn class(&self, label: &str) -> u32 {
+        if let Some(class) = self.literals.get(label) {
+            return *class;
+        }
+
+        let options = MatchOptions::new();
+        let mask = self.predicates.iter().enumerate()
+            .filter(|(_, predicate)| predicate.matches_label(label, &options))
+            .fold(0, |mask, (bit, _)| mask | 1 << bit);
+
+        self.literals.len() as u32 + mask
+    }
+
+    pub fn matches(&self, domain: &str) -> bool {
+        let mut state = Self::START;
+        for label in split::labels::<SPLITTER>(domain).filter(|label| !label.is_empty()) {
+            state = self.transitions[(state * self.classes + self.class(label)) as usize];
+            if state == DEAD {
+                return false;
+            }
+        }
+
+        self.accepting[state as usize]
+    }
+
+    /// Amount of states in the DFA, including the state nothing matches from
+    pub fn states(&self) -> usize {
+        self.accepting.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CompileError, DomainPattern};
+
+    #[test]
+    pub fn test_compile() {
+        let patterns = ["example.com", "*.example.com", "+.example.com", "**.example.com", "**+.example.com", "*.*.*.*.*.*", "**.a.**.b",
+            "{api,www}.#.com", "api-*.*-v1.com", "api-*.{tenant}.com", "a..b", "", "**", "*", "x.**.x.**"];
+        let domains = ["", ".", "com", "example.com", "api.example.com", "a.b.example.com", "api.example.org", "a.b", "a.b.c.d.e.f",
+            "a.b.c.d.e.f.g", "a.x.y.b", "b.a", "api.42.com", "www.x.com", "api-v1.com", "api-x.y-v1.com", "api-x.api-v1.com", "x.x", "x.y.x.z",
+            "example.com.", "..example..com"];
+
+        for pattern in patterns {
+            let pattern: DomainPattern = pattern.try_into().expect("failed to parse");
+            let compiled = pattern.compile().expect("pattern compiles");
+            for domain in domains {
+                assert_eq!(compiled.matches(domain), pattern.matches(domain), "{} compiled disagrees on {:?}", pattern, domain);
+            }
+        }
+
+        let pattern: DomainPattern = "*.*.*.*.*.*.*.*.*.*.*.*.*.*.*.*".try_into().expect("failed to parse");
+        let compiled = pattern.compile().expect("pattern compiles");
+        assert_eq!(compiled.states(), 18);
+        assert!(compiled.matches(&["a"; 16].join(".")));
+        assert!(!compiled.matches(&["a"; 17].join(".")));
+
+        let pattern: DomainPattern = "**.a.+.+.+.+.+.+.+.+.+.+.+.+".try_into().expect("failed to parse");
+        assert_eq!(pattern.compile().err(), Some(CompileError::TooManyStates));
+
+        let pattern: DomainPattern = "a*.b*.c*.d*.e*.f*.g*.h*.i*".try_into().expect("failed to parse");
+        assert_eq!(pattern.compile().err(), Some(CompileError::TooManyPredicates));
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    pub fn test_compile_regex() {
+        let pattern: DomainPattern = "~re:^v\\d+$~.**.example.com".try_into().expect("failed to parse");
+        let compiled = pattern.compile().expect("pattern compiles");
+        for domain in ["v1.example.com", "v1.a.example.com", "vx.example.com", "example.com"] {
+            assert_eq!(compiled.matches(domain), pattern.matches(domain), "{} compiled disagrees on {:?}", pattern, domain);
+        }
+    }
+}
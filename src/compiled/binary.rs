@@ -0,0 +1,129 @@
+//! Compiled patterns written to bytes, so the same DFA can be run somewhere else
+
+use std::collections::HashMap;
+use crate::binary::{Reader, Writer};
+use crate::compiled::{CompiledPattern, DEAD, MAX_PREDICATES, MAX_STATES};
+use crate::{DecodeError, DomainPatternPart};
+
+const MAGIC: &[u8; 4] = b"DMDF";
+const VERSION: u16 = 1;
+
+impl<const SPLITTER: char> CompiledPattern<SPLITTER> {
+    /// Writes the DFA to bytes, the same on every platform, so other implementations can run the same automaton
+    ///
+    /// All integers are little endian, strings are a `u64` length followed by UTF-8:
+    ///
+    /// - magic `DMDF`, `u16` version 1, and the splitter as a `u32`
+    /// - `u64` amount of literals, then every literal string, the n-th literal is class n
+    /// - `u64` amount of predicates, then every predicate part, a `u8` tag of 3 followed by a glob string, 5 for `#`, or
+    ///   6 followed by a regex string, labels that aren't a literal are class `literals + mask`, where bit n of the
+    ///   mask is set if the label matches predicate n
+    /// - `u32` amount of classes and `u64` amount of states, then a `u32` next state for every state and class, by
+    ///   state first, and a `u8` for every state that's 1 if it's accepting
+    /// - a `u64` FNV-1a checksum of everything before it
+    ///
+    /// Matching starts in state 1 and skips empty labels, state 0 is dead and never accepts.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut literals = vec![""; self.literals.len()];
+        for (label, class) in &self.literals {
+            literals[*class as usize] = label;
+        }
+
+        let mut writer = Writer::new(MAGIC, VERSION);
+        writer.u32(SPLITTER as u32);
+        writer.usize(literals.len());
+        literals.iter().for_each(|label| writer.str(label));
+        writer.usize(self.predicates.len());
+        self.predicates.iter().for_each(|predicate| writer.part(predicate));
+
+        writer.u32(self.classes);
+        writer.usize(self.accepting.len());
+        self.transitions.iter().for_each(|next| writer.u32(*next));
+        self.accepting.iter().for_each(|accepting| writer.bool(*accepting));
+        writer.finish()
+    }
+
+    /// Loads a DFA written by [`CompiledPattern::to_bytes`], checking it's one this build can run
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut reader = Reader::new(bytes, MAGIC, VERSION)?;
+        if reader.u32()? != SPLITTER as u32 {
+            return Err(DecodeError::Invalid);
+        }
+
+        let mut literals = HashMap::new();
+        for class in 0..reader.len(8)? {
+            let label = reader.str()?;
+            // empty labels never get a class, and a label can only be in one
+            if label.is_empty() || literals.insert(Box::from(label), class as u32).is_some() {
+                return Err(DecodeError::Invalid);
+            }
+        }
+
+        let predicates = (0..reader.len(1)?).map(|_| reader.part()).collect::<Result<Vec<_>, _>>()?;
+        // parts that take any label or a known one are never predicates
+        let predicate = |part: &DomainPatternPart| !matches!(part, DomainPatternPart::Static(_) | DomainPatternPart::OneOf(_) | DomainPatternPart::Wildcard(_) | DomainPatternPart::Named(_));
+        if predicates.len() > MAX_PREDICATES || !predicates.iter().all(predicate) {
+            return Err(DecodeError::Invalid);
+        }
+
+        let classes = reader.u32()?;
+        if classes as usize != literals.len() + (1 << predicates.len()) {
+            return Err(DecodeError::Invalid);
+        }
+
+        let states = reader.usize()?;
+        if !(2..=MAX_STATES).contains(&states) {
+            return Err(DecodeError::Invalid);
+        }
+
+        let transitions = (0..states * classes as usize).map(|_| match reader.u32()? {
+            next if (next as usize) < states => Ok(next),
+            _ => Err(DecodeError::Invalid),
+        }).collect::<Result<Vec<_>, _>>()?;
+
+        let accepting = (0..states).map(|_| reader.bool()).collect::<Result<Vec<_>, _>>()?;
+        reader.finish()?;
+
+        // the dead state has to stay dead
+        if accepting[DEAD as usize] || transitions[..classes as usize].iter().any(|next| *next != DEAD) {
+            return Err(DecodeError::Invalid);
+        }
+
+        Ok(CompiledPattern {
+            literals,
+            predicates,
+            classes,
+            transitions,
+            accepting,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CompiledPattern, DecodeError, DomainPattern};
+
+    #[test]
+    pub fn test_compiled_bytes() {
+        let domains = ["example.com", "api.example.com", "a.b.example.com", "api-v1.x.example.com", "api-v1.1.com", "www.22.com", "com", ""];
+        for pattern in ["example.com", "*.example.com", "**.example.com", "api-*.**.example.com", "{api,www}.#.com", "x.**+.*", "**"] {
+            let pattern: DomainPattern = pattern.try_into().expect("failed to parse");
+            let compiled = pattern.compile().expect("pattern compiles");
+            let bytes = compiled.to_bytes();
+            assert_eq!(compiled.to_bytes(), bytes, "{} isn't written the same every time", pattern);
+
+            let loaded = CompiledPattern::<'.'>::from_bytes(&bytes).expect("loads");
+            assert_eq!(loaded.states(), compiled.states());
+            for domain in domains {
+                assert_eq!(loaded.matches(domain), compiled.matches(domain), "{} loaded disagrees on {:?}", pattern, domain);
+            }
+        }
+
+        let pattern: DomainPattern = "*.example.com".try_into().expect("failed to parse");
+        let bytes = pattern.compile().expect("pattern compiles").to_bytes();
+        assert_eq!(&bytes[..6], b"DMDF\x01\x00");
+        assert_eq!(CompiledPattern::<':'>::from_bytes(&bytes).err(), Some(DecodeError::Invalid));
+        assert_eq!(CompiledPattern::<'.'>::from_bytes(&bytes[..bytes.len() - 1]).err(), Some(DecodeError::Checksum));
+        assert_eq!(CompiledPattern::<'.'>::from_bytes(b"DMPS\x01\x00").err(), Some(DecodeError::Magic));
+    }
+}
@@ -0,0 +1,68 @@
+//! Which hosts a cookie with a `Domain` attribute is sent to, following RFC 6265
+
+use std::net::IpAddr;
+use crate::DomainPatternSet;
+
+/// Whether a cookie with the `Domain` attribute is sent to the host, the domain-match of RFC 6265
+///
+/// `example.com` and `.example.com` both cover `example.com` and all of its subdomains, ignoring ASCII case. An IP
+/// address only matches itself, and an empty domain matches nothing. Public suffixes aren't rejected, see
+/// [`domain_matches_with_psl`] for that.
+pub fn domain_matches(request_host: &str, cookie_domain: &str) -> bool {
+    let domain = cookie_domain.strip_prefix('.').unwrap_or(cookie_domain);
+    if domain.is_empty() {
+        return false;
+    }
+
+    if request_host.eq_ignore_ascii_case(domain) {
+        return true;
+    }
+
+    if request_host.parse::<IpAddr>().is_ok() || request_host.len() <= domain.len() {
+        return false;
+    }
+
+    let (subdomain, suffix) = request_host.split_at(request_host.len() - domain.len());
+    subdomain.ends_with('.') && suffix.eq_ignore_ascii_case(domain)
+}
+
+/// Like [`domain_matches`], but a cookie for a public suffix like `co.uk` is only sent to that host itself, like
+/// browsers do
+///
+/// The suffixes are a set matching exactly the public suffixes, like
+/// [`DomainPatternSet::from_public_suffix_list`] loads.
+pub fn domain_matches_with_psl(request_host: &str, cookie_domain: &str, public_suffixes: &DomainPatternSet) -> bool {
+    let domain = cookie_domain.strip_prefix('.').unwrap_or(cookie_domain);
+    if public_suffixes.contains_match(&domain.to_ascii_lowercase()) {
+        return !domain.is_empty() && request_host.eq_ignore_ascii_case(domain);
+    }
+
+    domain_matches(request_host, cookie_domain)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cookie::{domain_matches, domain_matches_with_psl};
+    use crate::DomainPatternSet;
+
+    #[test]
+    pub fn test_domain_matches() {
+        for (host, domain, matches) in [("example.com", "example.com", true), ("www.example.com", "example.com", true),
+            ("a.b.example.com", ".example.com", true), ("WWW.Example.com", "example.COM", true), ("example.com", ".example.com", true),
+            ("badexample.com", "example.com", false), ("example.com", "www.example.com", false), ("example.org", "example.com", false),
+            ("example.com", "", false), ("example.com", ".", false), ("1.2.3.4", "1.2.3.4", true), ("1.2.3.4", "2.3.4", false),
+            ("::1", "1", false)] {
+            assert_eq!(domain_matches(host, domain), matches, "{:?} for {:?}", host, domain);
+        }
+    }
+
+    #[test]
+    pub fn test_domain_matches_with_psl() {
+        let suffixes = DomainPatternSet::from_public_suffix_list("com\nco.uk\n*.ck\n!www.ck\n".as_bytes()).expect("loads");
+        for (host, domain, matches) in [("www.example.com", "example.com", true), ("www.example.com", "com", false), ("com", ".com", true),
+            ("a.example.co.uk", ".co.uk", false), ("CO.uk", "co.UK", true), ("a.b.ck", "b.ck", false), ("a.www.ck", "www.ck", true),
+            ("example.com", "", false)] {
+            assert_eq!(domain_matches_with_psl(host, domain, &suffixes), matches, "{:?} for {:?}", host, domain);
+        }
+    }
+}
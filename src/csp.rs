@@ -0,0 +1,278 @@
+//! Evaluating the host sources of a Content-Security-Policy, like `https://*.example.com:443`
+
+use std::borrow::Cow;
+use std::fmt::{Display, Formatter};
+use crate::origin::{default_port, is_scheme};
+use crate::{DomainPattern, DomainPatternPart, DomainPatternWildcard, MatchOptions, OriginPort, StepVec};
+
+/// Why a source expression isn't a host source, see [`CspSource::parse`]
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum InvalidCspSource {
+    /// A keyword like `'self'`, or a nonce or hash, which don't name hosts
+    Keyword,
+    /// The scheme has a character a scheme can't have
+    Scheme,
+    /// The host is empty, has an empty label, or a character other than a letter, digit or `-`, or a `*` anywhere
+    /// but in front of the first label or as the whole host
+    Host,
+    /// The port isn't `*` or a number up to 65535
+    Port,
+}
+
+impl Display for InvalidCspSource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InvalidCspSource::Keyword => write!(f, "Source expression is a keyword, nonce or hash"),
+            InvalidCspSource::Scheme => write!(f, "Invalid scheme in source expression"),
+            InvalidCspSource::Host => write!(f, "Invalid host in source expression"),
+            InvalidCspSource::Port => write!(f, "Invalid port in source expression"),
+        }
+    }
+}
+
+impl std::error::Error for InvalidCspSource {}
+
+/// A scheme source like `https:`, a host source like `https://*.example.com:8443/api/`, or `*`
+///
+/// The host `*.example.com` takes every subdomain of `example.com` but not `example.com` itself, like `**+.example.com`,
+/// and hosts are compared ignoring ASCII case. Matching follows CSP level 3, so `http:` takes `https` URLs, `ws:` takes
+/// `wss`, `http` and `https` ones, and the port `80` takes `443`. Paths ending in `/` take everything below them, other
+/// paths only themselves, both compared as they are without decoding.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct CspSource<'a> {
+    scheme: Option<Cow<'a, str>>,
+    // None for scheme sources, `**` for `*`
+    host: Option<DomainPattern<'a>>,
+    port: OriginPort,
+    path: Option<&'a str>,
+    // the source `*`, which takes any scheme fetched over the network, unlike a host source like `https://*`
+    any: bool,
+}
+
+// whether a source with scheme a takes a URL with scheme b
+fn scheme_matches(a: &str, b: &str) -> bool {
+    let b = b.to_ascii_lowercase();
+    match a {
+        a if a.eq_ignore_ascii_case(&b) => true,
+        "http" => b == "https",
+        "ws" => matches!(b.as_str(), "wss" | "http" | "https"),
+        "wss" => b == "https",
+        _ => false,
+    }
+}
+
+// the host part of a host source, `*` takes every host
+fn host(host: &str) -> Result<DomainPattern<'_>, InvalidCspSource> {
+    let mut steps: StepVec = Default::default();
+    if host == "*" {
+        steps.push(DomainPatternPart::Wildcard(DomainPatternWildcard::new(true, true)));
+        return Ok(DomainPattern { steps, negated: false });
+    }
+
+    let labels = match host.strip_prefix("*.") {
+        Some(labels) => {
+            steps.push(DomainPatternPart::Wildcard(DomainPatternWildcard::new(true, false)));
+            labels
+        }
+        None => host,
+    };
+
+    for label in labels.split('.') {
+        if label.is_empty() || !label.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-') {
+            return Err(InvalidCspSource::Host);
+        }
+
+        steps.push(DomainPatternPart::Static(Cow::Borrowed(label)));
+    }
+
+    Ok(DomainPattern {
+        steps,
+        negated: false,
+    })
+}
+
+impl<'a> CspSource<'a> {
+    pub fn parse(source: &'a str) -> Result<Self, InvalidCspSource> {
+        if source.starts_with('\'') {
+            return Err(InvalidCspSource::Keyword);
+        }
+
+        if source == "*" {
+            return Ok(CspSource { scheme: None, host: Some(host(source)?), port: OriginPort::Any, path: None, any: true });
+        }
+
+        let (scheme, rest) = match source.split_once("://") {
+            Some((scheme, rest)) => (Some(scheme), rest),
+            None => match source.strip_suffix(':') {
+                Some(scheme) if !scheme.contains(['.', '/']) && !scheme.is_empty() => (Some(scheme), ""),
+                _ => (None, source),
+            },
+        };
+
+        if scheme.is_some_and(|scheme| !is_scheme(scheme)) {
+            return Err(InvalidCspSource::Scheme);
+        }
+
+        let scheme = scheme.map(|scheme| match scheme.bytes().any(|b| b.is_ascii_uppercase()) {
+            true => Cow::Owned(scheme.to_ascii_lowercase()),
+            false => Cow::Borrowed(scheme),
+        });
+
+        // a scheme source like `https:`
+        if rest.is_empty() && !source.contains("://") {
+            return Ok(CspSource { scheme, host: None, port: OriginPort::Any, path: None, any: false });
+        }
+
+        let (authority, path) = match rest.find('/') {
+            Some(slash) => (&rest[..slash], Some(&rest[slash..])),
+            None => (rest, None),
+        };
+
+        let (host_part, port) = match authority.split_once(':') {
+            Some((host, "*")) => (host, OriginPort::Any),
+            Some((host, port)) if !port.is_empty() && port.bytes().all(|b| b.is_ascii_digit()) => {
+                (host, OriginPort::Exact(port.parse().map_err(|_| InvalidCspSource::Port)?))
+            }
+            Some(_) => return Err(InvalidCspSource::Port),
+            None => (authority, OriginPort::Default),
+        };
+
+        Ok(CspSource { scheme, host: Some(host(host_part)?), port, path, any: false })
+    }
+
+    /// The scheme of the source, lowercased, None for host sources without one and `*`
+    pub fn scheme(&self) -> Option<&str> {
+        self.scheme.as_deref()
+    }
+
+    /// The host, None for scheme sources like `https:`
+    pub fn host(&self) -> Option<&DomainPattern<'a>> {
+        self.host.as_ref()
+    }
+
+    pub fn port(&self) -> OriginPort {
+        self.port
+    }
+
+    pub fn path(&self) -> Option<&str> {
+        self.path
+    }
+
+    /// Whether the source allows loading the URL, for a page served over `https`, see [`CspSource::matches_for`]
+    pub fn matches(&self, url: &str) -> bool {
+        self.matches_for("https", url)
+    }
+
+    /// Whether the source allows loading the URL, for a page served over the given scheme
+    ///
+    /// The scheme of the page decides what host sources without a scheme take, and `*` takes URLs with the scheme of the
+    /// page besides `http`, `https`, `ws` and `wss`.
+    pub fn matches_for(&self, page_scheme: &str, url: &str) -> bool {
+        let Some((scheme, rest)) = url.split_once(':') else {
+            return false;
+        };
+
+        if !is_scheme(scheme) {
+            return false;
+        }
+
+        let Some(pattern) = &self.host else {
+            return self.scheme.as_ref().is_some_and(|expected| scheme_matches(expected, scheme));
+        };
+
+        let scheme_allowed = match &self.scheme {
+            Some(expected) => scheme_matches(expected, scheme),
+            // `*` takes anything fetched over the network
+            None if self.any => {
+                matches!(scheme.to_ascii_lowercase().as_str(), "http" | "https" | "ws" | "wss") || scheme.eq_ignore_ascii_case(page_scheme)
+            }
+            None => scheme_matches(&page_scheme.to_ascii_lowercase(), scheme),
+        };
+
+        let Some(rest) = rest.strip_prefix("//").filter(|_| scheme_allowed) else {
+            return false;
+        };
+
+        let end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+        let (authority, path) = rest.split_at(end);
+        let authority = authority.rsplit_once('@').map_or(authority, |(_, host)| host);
+        if authority.starts_with('[') {
+            return false;
+        }
+
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) => match port.parse::<u16>() {
+                Ok(port) => (host, Some(port)),
+                Err(_) => return false,
+            },
+            None => (authority, None),
+        };
+
+        let default = default_port(scheme);
+        let port_matches = match self.port {
+            OriginPort::Any => true,
+            OriginPort::Default => port.is_none() || port == default,
+            OriginPort::Exact(expected) => {
+                let port = port.or(default);
+                port == Some(expected) || (expected == 80 && port == Some(443))
+            }
+        };
+
+        let path = path.split(['?', '#']).next().filter(|path| !path.is_empty()).unwrap_or("/");
+        let path_matches = match self.path {
+            None => true,
+            Some(expected) if expected.ends_with('/') => path.starts_with(expected),
+            Some(expected) => path == expected,
+        };
+
+        port_matches && path_matches && !host.is_empty() && pattern.matches_with(host, &MatchOptions::new().case_insensitive(true))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CspSource, InvalidCspSource, OriginPort};
+
+    fn allows(source: &str, url: &str) -> bool {
+        CspSource::parse(source).expect("valid source").matches(url)
+    }
+
+    #[test]
+    pub fn test_csp_source() {
+        let source = CspSource::parse("HTTPS://*.Example.com:8443/api/").expect("valid source");
+        assert_eq!(source.scheme(), Some("https"));
+        assert_eq!(source.host().map(ToString::to_string).as_deref(), Some("**+.Example.com"));
+        assert_eq!(source.port(), OriginPort::Exact(8443));
+        assert_eq!(source.path(), Some("/api/"));
+
+        for (source, url, allowed) in [("*.example.com", "https://cdn.example.com/lib.js", true), ("*.example.com", "https://a.b.example.com", true),
+            ("*.example.com", "https://example.com", false), ("example.com", "https://EXAMPLE.com", true), ("example.com", "http://example.com", false),
+            ("example.com", "wss://example.com", false), ("http://example.com", "https://example.com", true), ("ws://example.com", "http://example.com", true),
+            ("https://example.com", "http://example.com", false), ("example.com", "https://example.com:8443", false),
+            ("example.com:8443", "https://example.com:8443", true), ("example.com:*", "https://example.com:1", true),
+            ("http://example.com:80", "https://example.com", true), ("example.com/api/", "https://example.com/api/v1?x", true),
+            ("example.com/api/", "https://example.com/other", false), ("example.com/app.js", "https://example.com/app.js", true),
+            ("example.com/app.js", "https://example.com/app.js/x", false), ("example.com", "https://user@example.com/", true),
+            ("https:", "https://anything.test", true), ("http:", "https://anything.test", true), ("https:", "http://anything.test", false),
+            ("data:", "data:image/png;base64,AAAA", true), ("localhost:*", "http://localhost:3000", false),
+            ("localhost:*", "https://localhost:3000", true), ("*", "https://anything.test:8080/x", true), ("*", "data:text/plain,x", false),
+            ("*", "blob:https://example.com/uuid", false), ("example.com", "not a url", false),
+            ("https://*", "https://anything.test/x", true), ("https://*", "http://anything.test", false), ("*:443", "https://anything.test", true),
+            ("*:443", "https://anything.test:8443", false), ("*:443", "wss://anything.test", false), ("*:*", "https://anything.test:1", true)] {
+            assert_eq!(allows(source, url), allowed, "{:?} for {:?}", source, url);
+        }
+
+        let source = CspSource::parse("example.com").expect("valid source");
+        assert!(source.matches_for("http", "http://example.com"));
+        assert!(CspSource::parse("*").expect("valid source").matches_for("custom", "custom://example.com"));
+        let source = CspSource::parse("https://*:8443").expect("valid source");
+        assert_eq!((source.scheme(), source.port()), (Some("https"), OriginPort::Exact(8443)));
+        assert_eq!(source.host().map(ToString::to_string).as_deref(), Some("**"));
+
+        for (source, err) in [("'self'", InvalidCspSource::Keyword), ("'sha256-abc'", InvalidCspSource::Keyword),
+            ("1x://example.com", InvalidCspSource::Scheme), ("a.*.com", InvalidCspSource::Host), ("https://", InvalidCspSource::Host),
+            ("example..com", InvalidCspSource::Host), ("example.com:x", InvalidCspSource::Port), ("example.com:99999", InvalidCspSource::Port)] {
+            assert_eq!(CspSource::parse(source), Err(err), "{:?}", source);
+        }
+    }
+}
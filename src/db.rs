@@ -0,0 +1,181 @@
+//! Read-only pattern databases on disk, memory mapped so every process matching against them shares the same pages
+
+use std::fmt::{Display, Formatter};
+use std::fs::{self, File};
+use std::io;
+use std::io::Write;
+use std::path::Path;
+use memmap2::Mmap;
+use crate::binary::{checksum, Reader, Writer};
+use crate::{ArchivedSetArchive, DecodeError, DomainPattern, DomainPatternSet, Matches, SetArchive};
+
+const MAGIC: &[u8; 4] = b"DMDB";
+const VERSION: u16 = 1;
+// the archive starts at the next page, so it's aligned however it's mapped
+const PAGE: usize = 4096;
+// magic, version, splitter, archive length, archive checksum and header checksum
+const HEADER: usize = 4 + 2 + 4 + 8 + 8 + 8;
+
+/// Why a database couldn't be opened, see [`DomainDb::open`]
+#[derive(Debug)]
+pub enum DbError {
+    Io(io::Error),
+    /// The file isn't a database this build can match with, [`DecodeError::Version`] means it has to be built again
+    Decode(DecodeError),
+}
+
+impl Display for DbError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DbError::Io(err) => write!(f, "Failed to read database: {}", err),
+            DbError::Decode(err) => write!(f, "Failed to load database: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for DbError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DbError::Io(err) => Some(err),
+            DbError::Decode(err) => Some(err),
+        }
+    }
+}
+
+impl From<io::Error> for DbError {
+    fn from(err: io::Error) -> Self {
+        DbError::Io(err)
+    }
+}
+
+impl From<DecodeError> for DbError {
+    fn from(err: DecodeError) -> Self {
+        DbError::Decode(err)
+    }
+}
+
+/// A set of patterns in a memory mapped file, matched against without loading it
+///
+/// The file is a header page followed by a [`SetArchive`], it's validated once when opened. Building writes a new
+/// file next to it and moves it in place, so processes that still have the old one open keep matching against it.
+pub struct DomainDb<const SPLITTER: char = '.'> {
+    map: Mmap,
+    len: usize,
+}
+
+impl<const SPLITTER: char> DomainDb<SPLITTER> {
+    /// Writes the patterns to a database at the path, replacing what's there
+    pub fn build<'a>(patterns: impl IntoIterator<Item=DomainPattern<'a, SPLITTER>>, path: impl AsRef<Path>) -> io::Result<()> {
+        Self::build_set(&patterns.into_iter().collect(), path)
+    }
+
+    /// Writes the set to a database at the path, keeping its options and indices
+    pub fn build_set(set: &DomainPatternSet<'_, SPLITTER>, path: impl AsRef<Path>) -> io::Result<()> {
+        let archive = set.to_archive().to_bytes();
+        let mut writer = Writer::new(MAGIC, VERSION);
+        writer.u32(SPLITTER as u32);
+        writer.usize(archive.len());
+        writer.u64(checksum(&archive));
+
+        let mut header = writer.finish();
+        debug_assert_eq!(header.len(), HEADER);
+        header.resize(PAGE, 0);
+
+        let path = path.as_ref();
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+
+        let mut file = File::create(&tmp)?;
+        file.write_all(&header)?;
+        file.write_all(&archive)?;
+        file.sync_all()?;
+        fs::rename(&tmp, path)
+    }
+
+    /// Maps the database at the path, failing if it's corrupted or was built by an incompatible version
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, DbError> {
+        let file = File::open(path)?;
+        // SAFETY: the file is never written to after it's built, `build` replaces it with a new one instead
+        let map = unsafe { Mmap::map(&file)? };
+
+        let mut reader = Reader::new(&map[..HEADER.min(map.len())], MAGIC, VERSION)?;
+        if reader.u32()? != SPLITTER as u32 {
+            return Err(DecodeError::Invalid.into());
+        }
+
+        let len = reader.usize()?;
+        let expected = reader.u64()?;
+        reader.finish()?;
+
+        let archive = map.get(PAGE..).and_then(|archive| archive.get(..len)).ok_or(DecodeError::Truncated)?;
+        if checksum(archive) != expected {
+            return Err(DecodeError::Checksum.into());
+        }
+
+        SetArchive::<SPLITTER>::access(archive)?;
+        Ok(DomainDb { map, len })
+    }
+
+    fn archive(&self) -> &ArchivedSetArchive<SPLITTER> {
+        // SAFETY: the archive was validated when the database was opened, and the map never changes
+        unsafe { rkyv::access_unchecked::<ArchivedSetArchive<SPLITTER>>(&self.map[PAGE..PAGE + self.len]) }
+    }
+
+    /// Returns true if any pattern in the database matches the given domain
+    pub fn contains_match(&self, domain: &str) -> bool {
+        self.archive().contains_match(domain)
+    }
+
+    /// Returns the indices of all patterns matching the domain, in ascending order
+    pub fn matches_all(&self, domain: &str) -> Matches {
+        self.archive().matches_all(domain)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use crate::{DbError, DecodeError, DomainDb, DomainPattern, DomainPatternSet};
+
+    #[test]
+    pub fn test_domain_db() {
+        let dir = std::env::temp_dir().join(format!("domainmatcher-db-{}", std::process::id()));
+        fs::create_dir_all(&dir).expect("failed to create temporary directory");
+        let path = dir.join("patterns.db");
+
+        let patterns = ["example.com", "*.example.com", "**.example.org", "!blocked.example.com", "{a,b}.#.net"];
+        let set: DomainPatternSet = patterns.iter().map(|pattern| DomainPattern::try_from(*pattern).expect("failed to parse")).collect();
+        DomainDb::build(set.iter().map(|(_, pattern)| pattern.clone()), &path).expect("failed to build");
+
+        let db: DomainDb = DomainDb::open(&path).expect("failed to open");
+        for domain in ["example.com", "www.example.com", "blocked.example.com", "a.b.example.org", "a.1.net", "c.1.net", "example.net"] {
+            assert_eq!(db.contains_match(domain), set.contains_match(domain), "{:?} matches differently", domain);
+            assert_eq!(db.matches_all(domain).collect::<Vec<_>>(), set.matches_all(domain).collect::<Vec<_>>(), "{:?} matches differently", domain);
+        }
+
+        // the open database keeps matching against the old file
+        <DomainDb>::build([DomainPattern::parse("other.com").expect("failed to parse")], &path).expect("failed to build");
+        assert!(db.contains_match("example.com"));
+        let rebuilt: DomainDb = DomainDb::open(&path).expect("failed to open");
+        assert!(rebuilt.contains_match("other.com"));
+        assert!(!rebuilt.contains_match("example.com"));
+
+        let mut bytes = fs::read(&path).expect("failed to read");
+        bytes[4] = 9;
+        fs::write(&path, &bytes).expect("failed to write");
+        assert!(matches!(<DomainDb>::open(&path), Err(DbError::Decode(DecodeError::Version(9)))));
+
+        bytes[4] = 1;
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+        fs::write(&path, &bytes).expect("failed to write");
+        assert!(matches!(<DomainDb>::open(&path), Err(DbError::Decode(DecodeError::Checksum))));
+
+        bytes.truncate(100);
+        fs::write(&path, &bytes).expect("failed to write");
+        assert!(matches!(<DomainDb>::open(&path), Err(DbError::Decode(_))));
+
+        assert!(matches!(<DomainDb>::open(dir.join("missing.db")), Err(DbError::Io(_))));
+        fs::remove_dir_all(&dir).expect("failed to clean up");
+    }
+}
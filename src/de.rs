@@ -0,0 +1,103 @@
+//! Deserializing config values that may be a single pattern or a list of them
+//!
+//! Use it with `#[serde(deserialize_with = "eater_domainmatcher::de::pattern_or_list")]`, so both
+//! `allowed_hosts: "*.example.com"` and `allowed_hosts: ["*.example.com", "example.org"]` work.
+
+use std::fmt::Formatter;
+use serde::de::{Error, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer};
+use crate::{DomainPattern, DomainPatternSet};
+
+// a pattern that doesn't borrow from the input, so configs can be read from a temporary buffer
+struct OwnedPattern<const SPLITTER: char>(DomainPattern<'static, SPLITTER>);
+
+struct OwnedPatternVisitor<const SPLITTER: char>;
+
+impl<const SPLITTER: char> Visitor<'_> for OwnedPatternVisitor<SPLITTER> {
+    type Value = OwnedPattern<SPLITTER>;
+
+    fn expecting(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "a domain pattern like \"*.example.com\"")
+    }
+
+    fn visit_str<E: Error>(self, pattern: &str) -> Result<Self::Value, E> {
+        DomainPattern::parse(pattern).map(|pattern| OwnedPattern(pattern.into_owned())).map_err(E::custom)
+    }
+}
+
+impl<'de, const SPLITTER: char> Deserialize<'de> for OwnedPattern<SPLITTER> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(OwnedPatternVisitor)
+    }
+}
+
+struct PatternOrListVisitor<const SPLITTER: char>;
+
+impl<'de, const SPLITTER: char> Visitor<'de> for PatternOrListVisitor<SPLITTER> {
+    type Value = Vec<DomainPattern<'static, SPLITTER>>;
+
+    fn expecting(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "a domain pattern or a list of domain patterns")
+    }
+
+    fn visit_str<E: Error>(self, pattern: &str) -> Result<Self::Value, E> {
+        OwnedPatternVisitor.visit_str(pattern).map(|pattern| vec![pattern.0])
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut patterns = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(OwnedPattern(pattern)) = seq.next_element()? {
+            patterns.push(pattern);
+        }
+
+        Ok(patterns)
+    }
+}
+
+/// Deserializes a single pattern or a list of patterns into a list
+pub fn pattern_or_list<'de, D: Deserializer<'de>, const SPLITTER: char>(deserializer: D) -> Result<Vec<DomainPattern<'static, SPLITTER>>, D::Error> {
+    deserializer.deserialize_any(PatternOrListVisitor)
+}
+
+/// Deserializes a single pattern or a list of patterns into a set, see [`pattern_or_list`]
+pub fn pattern_set<'de, D: Deserializer<'de>, const SPLITTER: char>(deserializer: D) -> Result<DomainPatternSet<'static, SPLITTER>, D::Error> {
+    Ok(pattern_or_list(deserializer)?.into_iter().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+    use crate::{DomainPattern, DomainPatternSet};
+
+    #[derive(Deserialize)]
+    struct Config {
+        #[serde(deserialize_with = "crate::de::pattern_or_list")]
+        allowed_hosts: Vec<DomainPattern<'static>>,
+        #[serde(deserialize_with = "crate::de::pattern_set", default)]
+        blocked_hosts: DomainPatternSet<'static>,
+    }
+
+    #[test]
+    pub fn test_pattern_or_list() {
+        let config: Config = serde_json::from_str(r#"{"allowed_hosts": "*.example.com"}"#).expect("deserializes");
+        assert_eq!(config.allowed_hosts, vec![DomainPattern::parse("*.example.com").expect("failed to parse").into_owned()]);
+        assert!(config.blocked_hosts.is_empty());
+
+        let json = String::from(r#"{"allowed_hosts": ["*.example.com", "example.org"], "blocked_hosts": ["**.ads.com", "tracker.net"]}"#);
+        let config: Config = serde_json::from_str(&json).expect("deserializes");
+        drop(json);
+        assert_eq!(config.allowed_hosts.len(), 2);
+        assert!(config.allowed_hosts[1].matches("example.org"));
+        assert_eq!(config.blocked_hosts.len(), 2);
+        assert!(config.blocked_hosts.contains_match("a.ads.com"));
+        assert!(!config.blocked_hosts.contains_match("example.org"));
+
+        let config: Config = serde_json::from_str(r#"{"allowed_hosts": [], "blocked_hosts": "tracker.net"}"#).expect("deserializes");
+        assert!(config.allowed_hosts.is_empty());
+        assert!(config.blocked_hosts.contains_match("tracker.net"));
+
+        assert!(serde_json::from_str::<Config>(r#"{"allowed_hosts": "a.{b"}"#).is_err());
+        assert!(serde_json::from_str::<Config>(r#"{"allowed_hosts": 1}"#).is_err());
+        assert!(serde_json::from_str::<Config>(r#"{"allowed_hosts": [["a.com"]]}"#).is_err());
+    }
+}
@@ -0,0 +1,172 @@
+//! Parsing patterns written for other software, see [`ParseDialect`](crate::ParseDialect)
+
+use std::borrow::Cow;
+use crate::{shorten, split_labels, DomainPattern, DomainPatternPart, DomainPatternWildcard, InvalidToken, ParseErrorKind, StepVec, TranslateError};
+
+// the labels between the wildcards, which have to be plain labels
+fn push_labels<'a, const SPLITTER: char>(steps: &mut StepVec<'a>, labels: &'a str, mut offset: usize, full: &'a str) -> Result<(), InvalidToken<'a>> {
+    let mut first = true;
+    let mut labels = split_labels(labels, SPLITTER).peekable();
+    while let Some(label) = labels.next() {
+        if label.is_empty() {
+            let kind = match (first, labels.peek().is_none()) {
+                (true, _) => ParseErrorKind::LeadingSplitter,
+                (false, true) => ParseErrorKind::TrailingSplitter,
+                (false, false) => ParseErrorKind::EmptyLabel,
+            };
+
+            return Err(InvalidToken::new(kind, offset, label, full));
+        }
+
+        if let Some(star) = label.find('*') {
+            return Err(InvalidToken::new(ParseErrorKind::InvalidToken, offset + star, label, full));
+        }
+
+        steps.push(DomainPatternPart::Static(Cow::Borrowed(label)));
+        first = false;
+        offset += label.len() + SPLITTER.len_utf8();
+    }
+
+    Ok(())
+}
+
+// a regex server name, after its `~`
+fn nginx_regex<'a, const SPLITTER: char>(regex: &'a str, full: &'a str) -> Result<DomainPattern<'a, SPLITTER>, InvalidToken<'a>> {
+    let position = match DomainPattern::<SPLITTER>::from_simple_regex(regex) {
+        Ok(pattern) => return Ok(shorten(pattern)),
+        Err(TranslateError::Unanchored) => 0,
+        Err(TranslateError::EmptyLabel(position) | TranslateError::InvalidClass(position) | TranslateError::Unsupported(position)) => position,
+    };
+
+    let unexpected = regex.get(position..).unwrap_or(regex);
+    Err(InvalidToken::new(ParseErrorKind::InvalidToken, position + 1, unexpected, full))
+}
+
+pub(crate) fn nginx<'a, const SPLITTER: char>(full: &'a str) -> Result<DomainPattern<'a, SPLITTER>, InvalidToken<'a>> {
+    if let Some(regex) = full.strip_prefix('~') {
+        return nginx_regex(regex, full);
+    }
+
+    if full.is_empty() {
+        return Err(InvalidToken::new(ParseErrorKind::EmptyPattern, 0, full, full));
+    }
+
+    let mut steps: StepVec<'a> = Default::default();
+    let mut labels = full;
+    let mut offset = 0;
+
+    // `.example.com` and `*.example.com` differ in whether `example.com` itself matches
+    let prefix = format!("*{}", SPLITTER);
+    if let Some(rest) = labels.strip_prefix(prefix.as_str()) {
+        steps.push(DomainPatternPart::Wildcard(DomainPatternWildcard::new(true, false)));
+        offset = prefix.len();
+        labels = rest;
+    } else if let Some(rest) = labels.strip_prefix(SPLITTER) {
+        steps.push(DomainPatternPart::Wildcard(DomainPatternWildcard::new(true, true)));
+        offset = SPLITTER.len_utf8();
+        labels = rest;
+    }
+
+    // nginx takes a single wildcard only, so `*.example.*` is as invalid as `www.*.example.com`
+    let suffix = format!("{}*", SPLITTER);
+    let trailing = match labels.strip_suffix(suffix.as_str()) {
+        Some(rest) if steps.is_empty() => {
+            labels = rest;
+            true
+        }
+        _ => false,
+    };
+
+    push_labels::<SPLITTER>(&mut steps, labels, offset, full)?;
+    if trailing {
+        steps.push(DomainPatternPart::Wildcard(DomainPatternWildcard::new(true, false)));
+    }
+
+    Ok(DomainPattern {
+        steps,
+        negated: false,
+    })
+}
+
+pub(crate) fn squid<'a, const SPLITTER: char>(full: &'a str) -> Result<DomainPattern<'a, SPLITTER>, InvalidToken<'a>> {
+    if full.is_empty() {
+        return Err(InvalidToken::new(ParseErrorKind::EmptyPattern, 0, full, full));
+    }
+
+    let mut steps: StepVec<'a> = Default::default();
+    let (labels, offset) = match full.strip_prefix(SPLITTER) {
+        Some(rest) => {
+            steps.push(DomainPatternPart::Wildcard(DomainPatternWildcard::new(true, true)));
+            (rest, SPLITTER.len_utf8())
+        }
+        None => (full, 0),
+    };
+
+    push_labels::<SPLITTER>(&mut steps, labels, offset, full)?;
+    Ok(DomainPattern {
+        steps,
+        negated: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{DomainPattern, ParseDialect, ParseErrorKind, ParseOptions};
+
+    fn squid(name: &str) -> DomainPattern<'_> {
+        DomainPattern::parse_with(name, &ParseOptions::new().dialect(ParseDialect::Squid)).expect("failed to parse")
+    }
+
+    fn nginx(name: &str) -> DomainPattern<'_> {
+        DomainPattern::parse_with(name, &ParseOptions::new().dialect(ParseDialect::Nginx)).expect("failed to parse")
+    }
+
+    #[test]
+    pub fn test_nginx() {
+        assert_eq!(nginx("example.com"), DomainPattern::parse("example.com").unwrap());
+        assert_eq!(nginx("*.example.com"), DomainPattern::parse("**+.example.com").unwrap());
+        assert_eq!(nginx(".example.com"), DomainPattern::parse("**.example.com").unwrap());
+        assert_eq!(nginx("www.example.*"), DomainPattern::parse("www.example.**+").unwrap());
+        assert_eq!(nginx("~^(?:[^.]+\\.)*example\\.com$"), DomainPattern::parse("**.example.com").unwrap());
+
+        let pattern = nginx("*.example.com");
+        assert!(pattern.matches("a.b.example.com"));
+        assert!(!pattern.matches("example.com"));
+        assert!(nginx(".example.com").matches("example.com"));
+        assert!(nginx("www.example.*").matches("www.example.co.uk"));
+        assert!(!nginx("www.example.*").matches("www.example"));
+
+        // labels are taken as they are, without the native syntax
+        assert!(nginx("{a,b}.com").matches("{a,b}.com"));
+        assert!(!nginx("{a,b}.com").matches("a.com"));
+        assert!(!nginx("!a.com").is_negated());
+
+        let options = ParseOptions::new().dialect(ParseDialect::Nginx);
+        for (name, position, kind) in [("www.*.example.com", 4, ParseErrorKind::InvalidToken), ("*.example.*", 10, ParseErrorKind::InvalidToken),
+            ("a*.example.com", 1, ParseErrorKind::InvalidToken), ("*", 0, ParseErrorKind::InvalidToken), ("", 0, ParseErrorKind::EmptyPattern),
+            ("example..com", 8, ParseErrorKind::EmptyLabel), ("example.com.", 12, ParseErrorKind::TrailingSplitter),
+            ("..example.com", 1, ParseErrorKind::LeadingSplitter), ("~example\\.com", 1, ParseErrorKind::InvalidToken),
+            ("~^(a|b)\\.com$", 2, ParseErrorKind::InvalidToken)] {
+            let err = <DomainPattern>::parse_with(name, &options).expect_err(name);
+            assert_eq!((err.position(), err.kind()), (position, kind), "{}", name);
+        }
+    }
+
+    #[test]
+    pub fn test_squid() {
+        assert_eq!(squid(".example.com"), DomainPattern::parse("**.example.com").unwrap());
+        assert_eq!(squid("example.com"), DomainPattern::parse("example.com").unwrap());
+        assert!(squid(".example.com").matches("example.com"));
+        assert!(squid(".example.com").matches("a.b.example.com"));
+        assert!(!squid("example.com").matches("www.example.com"));
+        assert!(!squid("{a,b}.com").matches("a.com"));
+
+        let options = ParseOptions::new().dialect(ParseDialect::Squid);
+        for (name, position, kind) in [("*.example.com", 0, ParseErrorKind::InvalidToken), ("", 0, ParseErrorKind::EmptyPattern),
+            ("..example.com", 1, ParseErrorKind::LeadingSplitter), ("example.com.", 12, ParseErrorKind::TrailingSplitter),
+            (".", 1, ParseErrorKind::LeadingSplitter)] {
+            let err = <DomainPattern>::parse_with(name, &options).expect_err(name);
+            assert_eq!((err.position(), err.kind()), (position, kind), "{}", name);
+        }
+    }
+}
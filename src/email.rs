@@ -0,0 +1,144 @@
+//! Matching email addresses, like `*@**.example.com` for everyone at example.com and its subdomains
+
+use std::borrow::Cow;
+use std::fmt::{Display, Formatter};
+use crate::{glob, DomainPattern, InvalidToken, MatchOptions};
+
+/// Why an email pattern didn't parse, see [`EmailPattern::parse`]
+#[derive(Debug)]
+pub enum InvalidEmailPattern<'a> {
+    /// There's no `@` between the local part and the domain
+    MissingAt,
+    /// The local part is empty, or a glob with a malformed character class
+    LocalPart,
+    Domain(InvalidToken<'a>),
+}
+
+impl Display for InvalidEmailPattern<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InvalidEmailPattern::MissingAt => write!(f, "Email pattern has no @"),
+            InvalidEmailPattern::LocalPart => write!(f, "Invalid local part in email pattern"),
+            InvalidEmailPattern::Domain(err) => write!(f, "Invalid domain in email pattern: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for InvalidEmailPattern<'_> {}
+
+/// A pattern for email addresses, a glob for the local part and a [`DomainPattern`] for the domain
+///
+/// `alice@example.com` matches just that address, `*@**.example.com` everyone at `example.com` and its subdomains, and
+/// `noreply-*@example.com` or `alice+*@example.com` like the globs of a label. The domain is compared ignoring ASCII
+/// case, the local part as written, see [`EmailPattern::matches_with`].
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub struct EmailPattern<'a> {
+    // None for `*`, which takes any local part
+    local: Option<Cow<'a, str>>,
+    domain: DomainPattern<'a>,
+}
+
+impl<'a> EmailPattern<'a> {
+    /// Parses `local@domain`, where the domain is everything after the last `@`
+    pub fn parse(pattern: &'a str) -> Result<Self, InvalidEmailPattern<'a>> {
+        let (local, domain) = pattern.rsplit_once('@').ok_or(InvalidEmailPattern::MissingAt)?;
+        let local = match local {
+            "*" => None,
+            local if local.is_empty() || !glob::is_valid(local) => return Err(InvalidEmailPattern::LocalPart),
+            local => Some(Cow::Borrowed(local)),
+        };
+
+        Ok(EmailPattern {
+            local,
+            domain: DomainPattern::parse(domain).map_err(InvalidEmailPattern::Domain)?,
+        })
+    }
+
+    /// The glob for the local part, None if any local part matches
+    pub fn local_part(&self) -> Option<&str> {
+        self.local.as_deref()
+    }
+
+    pub fn domain(&self) -> &DomainPattern<'a> {
+        &self.domain
+    }
+
+    pub fn matches(&self, email: &str) -> bool {
+        self.matches_with(email, &MatchOptions::new())
+    }
+
+    /// Matches the address, with [`MatchOptions::case_insensitive`] comparing the local part ignoring ASCII case too
+    ///
+    /// The address is split at its last `@`, so quoted local parts like `"a@b"@example.com` work, and domain literals
+    /// like `alice@[192.0.2.1]` never match.
+    pub fn matches_with(&self, email: &str, options: &MatchOptions) -> bool {
+        let Some((local, domain)) = email.rsplit_once('@') else {
+            return false;
+        };
+
+        if local.is_empty() || domain.is_empty() || domain.starts_with('[') {
+            return false;
+        }
+
+        let local_matches = match &self.local {
+            None => true,
+            Some(glob) => glob::matches(glob, local, options.case_insensitive),
+        };
+
+        local_matches && self.domain.matches_with(domain, &options.case_insensitive(true))
+    }
+
+    pub fn into_owned(self) -> EmailPattern<'static> {
+        EmailPattern {
+            local: self.local.map(|local| Cow::Owned(local.into_owned())),
+            domain: self.domain.into_owned(),
+        }
+    }
+}
+
+impl<'a> TryFrom<&'a str> for EmailPattern<'a> {
+    type Error = InvalidEmailPattern<'a>;
+
+    fn try_from(pattern: &'a str) -> Result<Self, Self::Error> {
+        EmailPattern::parse(pattern)
+    }
+}
+
+impl Display for EmailPattern<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}@{}", self.local.as_deref().unwrap_or("*"), self.domain)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{EmailPattern, InvalidEmailPattern, MatchOptions};
+
+    #[test]
+    pub fn test_email_pattern() {
+        let pattern = EmailPattern::parse("*@**.example.com").expect("valid");
+        assert_eq!(pattern.local_part(), None);
+        assert_eq!(pattern.to_string(), "*@**.example.com");
+        for (email, matches) in [("alice@example.com", true), ("bob@mail.Example.COM", true), ("alice@example.org", false),
+            ("example.com", false), ("@example.com", false), ("alice@", false), ("\"a@b\"@example.com", true), ("alice@[192.0.2.1]", false)] {
+            assert_eq!(pattern.matches(email), matches, "{:?}", email);
+        }
+
+        let pattern = EmailPattern::parse("alice+*@example.com").expect("valid");
+        assert!(pattern.matches("alice+news@example.com"));
+        assert!(pattern.matches("alice+@example.com"));
+        assert!(!pattern.matches("alice@example.com"));
+        assert!(!pattern.matches("Alice+news@example.com"));
+        assert!(pattern.matches_with("Alice+news@EXAMPLE.com", &MatchOptions::new().case_insensitive(true)));
+
+        let pattern = EmailPattern::parse("noreply-?@{mail,smtp}.example.com").expect("valid");
+        assert!(pattern.matches("noreply-1@smtp.example.com"));
+        assert!(!pattern.matches("noreply-12@smtp.example.com"));
+        assert!(pattern.into_owned().matches("noreply-x@mail.example.com"));
+
+        assert!(matches!(EmailPattern::parse("example.com"), Err(InvalidEmailPattern::MissingAt)));
+        assert!(matches!(EmailPattern::parse("@example.com"), Err(InvalidEmailPattern::LocalPart)));
+        assert!(matches!(EmailPattern::parse("a[b@example.com"), Err(InvalidEmailPattern::LocalPart)));
+        assert!(matches!(EmailPattern::parse("a@{x"), Err(InvalidEmailPattern::Domain(_))));
+    }
+}
@@ -0,0 +1,124 @@
+//! Combining patterns into policies like "in `*.corp.tld`, but not in `*.guest.corp.tld`"
+
+use std::ops::Not;
+use crate::{DomainPattern, MatchOptions};
+
+/// Patterns combined with and, or and not
+///
+/// `DomainExpr::from(corp).and(!DomainExpr::from(guest))` matches domains matched by `corp`, but not by `guest`.
+/// Whether patterns are negated is ignored, use [`DomainExpr::Not`] instead.
+#[derive(Clone, Eq, PartialEq, Debug)]
+// patterns are big with the smallvec feature, but they're what most expressions are made of
+#[allow(clippy::large_enum_variant)]
+pub enum DomainExpr<'a, const SPLITTER: char = '.'> {
+    Pattern(DomainPattern<'a, SPLITTER>),
+    /// Matches if every expression matches, an empty list matches anything
+    All(Vec<DomainExpr<'a, SPLITTER>>),
+    /// Matches if any expression matches, an empty list matches nothing
+    Any(Vec<DomainExpr<'a, SPLITTER>>),
+    Not(Box<DomainExpr<'a, SPLITTER>>),
+}
+
+impl<'a, const SPLITTER: char> DomainExpr<'a, SPLITTER> {
+    /// Matches if both match, joining into an existing [`DomainExpr::All`]
+    pub fn and(self, other: impl Into<DomainExpr<'a, SPLITTER>>) -> Self {
+        match self {
+            DomainExpr::All(mut all) => {
+                all.push(other.into());
+                DomainExpr::All(all)
+            }
+
+            expr => DomainExpr::All(vec![expr, other.into()]),
+        }
+    }
+
+    /// Matches if either matches, joining into an existing [`DomainExpr::Any`]
+    pub fn or(self, other: impl Into<DomainExpr<'a, SPLITTER>>) -> Self {
+        match self {
+            DomainExpr::Any(mut any) => {
+                any.push(other.into());
+                DomainExpr::Any(any)
+            }
+
+            expr => DomainExpr::Any(vec![expr, other.into()]),
+        }
+    }
+
+    pub fn matches(&self, domain: &str) -> bool {
+        self.matches_with(domain, &MatchOptions::new())
+    }
+
+    pub fn matches_with(&self, domain: &str, options: &MatchOptions) -> bool {
+        match self {
+            DomainExpr::Pattern(pattern) => pattern.matches_with(domain, options),
+            DomainExpr::All(all) => all.iter().all(|expr| expr.matches_with(domain, options)),
+            DomainExpr::Any(any) => any.iter().any(|expr| expr.matches_with(domain, options)),
+            DomainExpr::Not(expr) => !expr.matches_with(domain, options),
+        }
+    }
+
+    /// Iterates over all patterns in the expression, depth first
+    pub fn patterns(&self) -> Box<dyn Iterator<Item=&DomainPattern<'a, SPLITTER>> + '_> {
+        match self {
+            DomainExpr::Pattern(pattern) => Box::new(std::iter::once(pattern)),
+            DomainExpr::All(exprs) | DomainExpr::Any(exprs) => Box::new(exprs.iter().flat_map(DomainExpr::patterns)),
+            DomainExpr::Not(expr) => expr.patterns(),
+        }
+    }
+}
+
+impl<'a, const SPLITTER: char> From<DomainPattern<'a, SPLITTER>> for DomainExpr<'a, SPLITTER> {
+    fn from(pattern: DomainPattern<'a, SPLITTER>) -> Self {
+        DomainExpr::Pattern(pattern)
+    }
+}
+
+impl<const SPLITTER: char> Not for DomainExpr<'_, SPLITTER> {
+    type Output = Self;
+
+    /// Matches if the expression doesn't, `!!expr` is `expr` again
+    fn not(self) -> Self::Output {
+        match self {
+            DomainExpr::Not(expr) => *expr,
+            expr => DomainExpr::Not(Box::new(expr)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{DomainExpr, DomainPattern, MatchOptions};
+
+    fn pattern(pattern: &str) -> DomainExpr<'_> {
+        DomainPattern::parse(pattern).expect("failed to parse").into()
+    }
+
+    #[test]
+    pub fn test_expr() {
+        let policy = pattern("**+.corp.tld").and(!pattern("**+.guest.corp.tld"));
+        assert!(policy.matches("wiki.corp.tld"));
+        assert!(policy.matches("a.b.corp.tld"));
+        assert!(!policy.matches("corp.tld"));
+        assert!(!policy.matches("laptop.guest.corp.tld"));
+        assert!(!policy.matches("example.com"));
+        assert!(policy.matches_with("WIKI.Corp.tld", &MatchOptions::new().case_insensitive(true)));
+
+        let policy = policy.or(pattern("status.guest.corp.tld")).or(pattern("*.public.tld"));
+        assert!(policy.matches("status.guest.corp.tld"));
+        assert!(policy.matches("public.tld"));
+        assert!(!policy.matches("laptop.guest.corp.tld"));
+        assert_eq!(policy.patterns().count(), 4);
+
+        let DomainExpr::Any(any) = &policy else {
+            panic!("or should join into a single Any");
+        };
+        assert_eq!(any.len(), 3);
+
+        assert_eq!(!!pattern("a.com"), pattern("a.com"));
+        assert!(DomainExpr::<'_, '.'>::All(vec![]).matches("anything"));
+        assert!(!DomainExpr::<'_, '.'>::Any(vec![]).matches("anything"));
+
+        // negated patterns match like they would without the `!`
+        assert!(pattern("!a.com").matches("a.com"));
+    }
+}
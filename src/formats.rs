@@ -0,0 +1,56 @@
+//! Loading patterns from the formats blocklists and configs are commonly written in
+
+use crate::{push_part, DomainPattern, DomainPatternPart, DomainPatternWildcard, StepVec};
+
+pub mod adblock;
+pub mod clash;
+pub mod dnsmasq;
+pub mod geosite;
+pub mod hosts;
+pub mod list;
+pub mod psl;
+pub mod ssh;
+
+// a pattern matching just the hostname, which has to be valid already, a trailing dot is dropped
+fn exact(hostname: &str) -> DomainPattern<'static> {
+    let name = hostname.strip_suffix('.').unwrap_or(hostname);
+    DomainPattern {
+        steps: name.split('.').map(|label| DomainPatternPart::Static(label.to_string().into())).collect(),
+        negated: false,
+    }
+}
+
+// a pattern matching the hostname and all of its subdomains, like `**.example.com`
+fn with_subdomains(hostname: &str) -> DomainPattern<'static> {
+    let mut pattern = exact(hostname);
+    pattern.steps.insert(0, DomainPatternPart::Wildcard(DomainPatternWildcard { multi: true, optional: true }));
+    pattern
+}
+
+// a pattern matching every domain containing the keyword, which may span labels, `oo.ba` is in `foo.bar`, so only the
+// labels at its ends take more than the keyword, and an empty one at an end, like in `.ba`, takes any label
+fn keyword(keyword: &str) -> Option<DomainPattern<'static>> {
+    if keyword.is_empty() || !keyword.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_' || b == b'.') {
+        return None;
+    }
+
+    let any = DomainPatternPart::Wildcard(DomainPatternWildcard { multi: true, optional: true });
+    let labels: Vec<&str> = keyword.split('.').collect();
+    let last = labels.len() - 1;
+    let mut steps: StepVec<'static> = Default::default();
+    push_part(&mut steps, any.clone());
+    for (idx, label) in labels.into_iter().enumerate() {
+        let (first, end) = (idx == 0, idx == last);
+        push_part(&mut steps, match label {
+            "" if first || end => DomainPatternPart::Wildcard(DomainPatternWildcard { multi: false, optional: false }),
+            label if first || end => DomainPatternPart::Glob(format!("{}{}{}", if first { "*" } else { "" }, label, if end { "*" } else { "" }).into()),
+            label => DomainPatternPart::Static(label.to_string().into()),
+        });
+    }
+
+    push_part(&mut steps, any);
+    Some(DomainPattern {
+        steps,
+        negated: false,
+    })
+}
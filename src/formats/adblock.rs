@@ -0,0 +1,206 @@
+//! Domain rules of Adblock Plus and uBlock Origin filter lists, like EasyList
+//!
+//! Only rules about whole domains are taken, `||ads.example.com^` blocks the domain and its subdomains, and
+//! `@@||ads.example.com^` makes an exception for them. Rules about URLs, paths, and element hiding are skipped.
+
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::io::BufRead;
+use crate::formats::with_subdomains;
+use crate::hostname::validate;
+use crate::{DomainPattern, DomainPatternSet};
+
+/// Why a filter list couldn't be loaded
+#[derive(Debug)]
+pub enum AdblockError {
+    Io(io::Error),
+    /// The domain of the rule on this line, counting from 1, isn't a valid hostname
+    InvalidRule { line: usize, rule: String },
+}
+
+impl Display for AdblockError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AdblockError::Io(err) => write!(f, "Failed to read filter list: {}", err),
+            AdblockError::InvalidRule { line, rule } => write!(f, "Invalid domain in rule {:?} on line {}", rule, line),
+        }
+    }
+}
+
+impl std::error::Error for AdblockError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AdblockError::Io(err) => Some(err),
+            AdblockError::InvalidRule { .. } => None,
+        }
+    }
+}
+
+/// A rule about a domain and its subdomains
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct AdblockRule {
+    /// The domains the rule is about, `**.example.com` for `||example.com^`, negated for exceptions
+    pub pattern: DomainPattern<'static>,
+    /// The domains of the pages the rule applies on, from `$domain=a.com|~b.com`, pages excluded with `~` are negated,
+    /// empty like [`AdblockRule::other_domains`] if it applies on every page
+    pub domains: Vec<DomainPattern<'static>>,
+    /// The page domains of `$domain=` that aren't hostnames, like the uBlock Origin entity `google.*`, as written
+    pub other_domains: Vec<String>,
+    /// Every other option of the rule, like `third-party` or `script`
+    pub options: Vec<String>,
+}
+
+impl AdblockRule {
+    /// Whether the rule is an exception, starting with `@@`
+    pub fn is_exception(&self) -> bool {
+        self.pattern.is_negated()
+    }
+
+    /// Whether the rule applies to every request to its domains, without any options narrowing it down
+    pub fn is_unconditional(&self) -> bool {
+        self.domains.is_empty() && self.other_domains.is_empty() && self.options.iter().all(|option| option == "important")
+    }
+}
+
+// the hostname as a pattern including its subdomains, None if it isn't a valid hostname
+fn domain(hostname: &str) -> Option<DomainPattern<'static>> {
+    let hostname = hostname.to_ascii_lowercase();
+    validate::<'.'>(&hostname).ok()?;
+    Some(with_subdomains(&hostname))
+}
+
+/// Parses a single line of a filter list, Ok(None) for comments and rules that aren't about a whole domain
+///
+/// Fails with the line if the domain of a rule can't be a hostname, like `||ads*.example.com^`, page domains of
+/// `$domain=` that can't be end up in [`AdblockRule::other_domains`] instead.
+pub fn parse_rule(line: &str) -> Result<Option<AdblockRule>, String> {
+    let line = line.trim();
+    let (exception, rule) = match line.strip_prefix("@@") {
+        Some(rule) => (true, rule),
+        None => (false, line),
+    };
+
+    let Some(rule) = rule.strip_prefix("||") else {
+        return Ok(None);
+    };
+
+    let (rule, options) = rule.split_once('$').unwrap_or((rule, ""));
+    // `^` ends the domain, anything after it but the end of the address is a path
+    let Some(hostname) = rule.strip_suffix('^').or_else(|| rule.strip_suffix("^|")) else {
+        return Ok(None);
+    };
+
+    let mut pattern = domain(hostname).ok_or_else(|| line.to_string())?;
+    pattern.negated = exception;
+
+    let mut domains = vec![];
+    let mut other_domains = vec![];
+    let mut other = vec![];
+    for option in options.split(',').map(str::trim).filter(|option| !option.is_empty()) {
+        let Some(list) = option.strip_prefix("domain=") else {
+            other.push(option.to_string());
+            continue;
+        };
+
+        for page in list.split('|') {
+            let (excluded, page) = match page.strip_prefix('~') {
+                Some(page) => (true, page),
+                None => (false, page),
+            };
+
+            match domain(page) {
+                Some(mut page) => {
+                    page.negated = excluded;
+                    domains.push(page);
+                }
+                None => other_domains.push(format!("{}{}", if excluded { "~" } else { "" }, page)),
+            }
+        }
+    }
+
+    Ok(Some(AdblockRule { pattern, domains, other_domains, options: other }))
+}
+
+/// Parses every domain rule of a filter list, see [`parse_rule`]
+pub fn parse_list(reader: impl BufRead) -> Result<Vec<AdblockRule>, AdblockError> {
+    let mut rules = vec![];
+    for (idx, line) in reader.lines().enumerate() {
+        let line = line.map_err(AdblockError::Io)?;
+        if let Some(rule) = parse_rule(&line).map_err(|rule| AdblockError::InvalidRule { line: idx + 1, rule })? {
+            rules.push(rule);
+        }
+    }
+
+    Ok(rules)
+}
+
+impl DomainPatternSet<'static> {
+    /// Loads the rules of a filter list that apply to every request to their domains, exceptions as negated patterns,
+    /// so an exception matching a domain means it isn't blocked
+    ///
+    /// Rules with `$domain=` or options like `third-party` only block some requests, so they're left out, use
+    /// [`parse_list`] to get at them.
+    pub fn from_adblock(reader: impl BufRead) -> Result<Self, AdblockError> {
+        Ok(parse_list(reader)?.into_iter().filter(AdblockRule::is_unconditional).map(|rule| rule.pattern).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formats::adblock::{parse_list, parse_rule, AdblockError};
+    use crate::{DomainPattern, DomainPatternSet};
+
+    fn pattern(pattern: &str) -> DomainPattern<'static> {
+        DomainPattern::parse(pattern).expect("failed to parse").into_owned()
+    }
+
+    #[test]
+    pub fn test_parse_rule() {
+        let rule = parse_rule("||Ads.Example.com^").expect("valid").expect("domain rule");
+        assert_eq!(rule.pattern, pattern("**.ads.example.com"));
+        assert!(!rule.is_exception());
+        assert!(rule.is_unconditional());
+
+        let rule = parse_rule("@@||cdn.example.com^$domain=a.com|~b.a.com,third-party").expect("valid").expect("domain rule");
+        assert_eq!(rule.pattern, pattern("!**.cdn.example.com"));
+        assert!(rule.is_exception());
+        assert_eq!(rule.domains, vec![pattern("**.a.com"), pattern("!**.b.a.com")]);
+        assert_eq!(rule.options, vec!["third-party"]);
+        assert!(!rule.is_unconditional());
+
+        let rule = parse_rule("||x.com^$domain=google.*|~a.com|~y.*").expect("valid").expect("domain rule");
+        assert_eq!(rule.domains, vec![pattern("!**.a.com")]);
+        assert_eq!(rule.other_domains, vec!["google.*", "~y.*"]);
+        let rule = parse_rule("||x.com^$domain=google.*").expect("valid").expect("domain rule");
+        assert!(rule.domains.is_empty());
+        assert!(!rule.is_unconditional());
+
+        assert_eq!(parse_rule("! Title: EasyList"), Ok(None));
+        assert_eq!(parse_rule("[Adblock Plus 2.0]"), Ok(None));
+        assert_eq!(parse_rule("example.com##.ad"), Ok(None));
+        assert_eq!(parse_rule("||example.com/ads/*"), Ok(None));
+        assert_eq!(parse_rule("||example.com^*/ads"), Ok(None));
+        assert_eq!(parse_rule("/banner/*"), Ok(None));
+        assert_eq!(parse_rule("||ads*.example.com^"), Err("||ads*.example.com^".to_string()));
+    }
+
+    #[test]
+    pub fn test_from_adblock() {
+        let list = "[Adblock Plus 2.0]\n! comment\n||ads.example.com^\n||tracker.net^$important\n@@||ok.ads.example.com^\n\
+            ||cdn.com^$script\n||pages.com^$domain=news.com\n||entity.com^$domain=google.*\nexample.org##.banner\n";
+        let set = DomainPatternSet::from_adblock(list.as_bytes()).expect("loads");
+        assert_eq!(set.len(), 3);
+        assert!(set.contains_match("ads.example.com"));
+        assert!(set.contains_match("x.ads.example.com"));
+        assert!(set.contains_match("tracker.net"));
+        assert!(!set.contains_match("ok.ads.example.com"));
+        assert!(!set.contains_match("a.ok.ads.example.com"));
+        assert!(!set.contains_match("cdn.com"));
+        assert!(!set.contains_match("example.com"));
+        assert!(!set.contains_match("entity.com"));
+
+        assert_eq!(parse_list(list.as_bytes()).expect("loads").len(), 6);
+        let err = parse_list("||a.com^\n\n||a..com^\n".as_bytes()).expect_err("invalid domain");
+        assert!(matches!(err, AdblockError::InvalidRule { line: 3, .. }), "{:?}", err);
+    }
+}
@@ -0,0 +1,159 @@
+//! Domain rules of Clash and Clash Meta, from configs or classical rule providers
+//!
+//! `DOMAIN,example.com` becomes `example.com`, `DOMAIN-SUFFIX,example.com` becomes `**.example.com`, and
+//! `DOMAIN-KEYWORD,ads` becomes `**.*ads*.**`. Rules about anything but domains, like `IP-CIDR`, are skipped.
+
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::io::BufRead;
+use crate::formats::{exact, keyword, with_subdomains};
+use crate::hostname::validate;
+use crate::{DomainPattern, DomainPatternSet};
+
+/// Why rules couldn't be loaded
+#[derive(Debug)]
+pub enum ClashError {
+    Io(io::Error),
+    /// The domain or keyword of the rule on this line, counting from 1, can't be part of a hostname
+    InvalidRule { line: usize, rule: String },
+}
+
+impl Display for ClashError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ClashError::Io(err) => write!(f, "Failed to read rules: {}", err),
+            ClashError::InvalidRule { line, rule } => write!(f, "Invalid domain in rule {:?} on line {}", rule, line),
+        }
+    }
+}
+
+impl std::error::Error for ClashError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ClashError::Io(err) => Some(err),
+            ClashError::InvalidRule { .. } => None,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum ClashRuleKind {
+    /// `DOMAIN`, just the domain itself
+    Domain,
+    /// `DOMAIN-SUFFIX`, the domain and all of its subdomains
+    DomainSuffix,
+    /// `DOMAIN-KEYWORD`, every domain containing the keyword
+    DomainKeyword,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct ClashRule {
+    pub kind: ClashRuleKind,
+    pub pattern: DomainPattern<'static>,
+    /// The policy the rule sends matching domains to, rule providers leave it out
+    pub policy: Option<String>,
+}
+
+/// Parses a single rule, Ok(None) for rules that aren't about domains, comments and the `payload:` of a provider
+///
+/// Lines of a rule provider like `- DOMAIN-SUFFIX,example.com` work too. Fails with the rule if its domain isn't a
+/// valid hostname, or its keyword couldn't be part of one.
+pub fn parse_rule(line: &str) -> Result<Option<ClashRule>, String> {
+    let line = line.trim();
+    let rule = line.strip_prefix('-').map_or(line, str::trim_start);
+    let rule = rule.trim_matches(|c| c == '\'' || c == '"');
+
+    let mut fields = rule.split(',').map(str::trim);
+    let kind = match fields.next() {
+        Some("DOMAIN") => ClashRuleKind::Domain,
+        Some("DOMAIN-SUFFIX") => ClashRuleKind::DomainSuffix,
+        Some("DOMAIN-KEYWORD") => ClashRuleKind::DomainKeyword,
+        _ => return Ok(None),
+    };
+
+    let value = fields.next().unwrap_or("").to_ascii_lowercase();
+    let pattern = match kind {
+        ClashRuleKind::Domain | ClashRuleKind::DomainSuffix if validate::<'.'>(&value).is_err() => None,
+        ClashRuleKind::Domain => Some(exact(&value)),
+        ClashRuleKind::DomainSuffix => Some(with_subdomains(&value)),
+        ClashRuleKind::DomainKeyword => keyword(&value),
+    };
+
+    let pattern = pattern.ok_or_else(|| line.to_string())?;
+    Ok(Some(ClashRule { kind, pattern, policy: fields.next().map(str::to_string) }))
+}
+
+/// Parses every domain rule, see [`parse_rule`]
+pub fn parse_rules(reader: impl BufRead) -> Result<Vec<ClashRule>, ClashError> {
+    let mut rules = vec![];
+    for (idx, line) in reader.lines().enumerate() {
+        let line = line.map_err(ClashError::Io)?;
+        if let Some(rule) = parse_rule(&line).map_err(|rule| ClashError::InvalidRule { line: idx + 1, rule })? {
+            rules.push(rule);
+        }
+    }
+
+    Ok(rules)
+}
+
+impl DomainPatternSet<'static> {
+    /// Loads the domain rules of a Clash config or rule provider, whatever their policy, see [`parse_rules`]
+    pub fn from_clash(reader: impl BufRead) -> Result<Self, ClashError> {
+        Ok(parse_rules(reader)?.into_iter().map(|rule| rule.pattern).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formats::clash::{parse_rule, parse_rules, ClashError, ClashRuleKind};
+    use crate::DomainPatternSet;
+
+    #[test]
+    pub fn test_parse_rule() {
+        let rule = parse_rule("DOMAIN-SUFFIX,Google.com,Proxy").expect("valid").expect("domain rule");
+        assert_eq!(rule.kind, ClashRuleKind::DomainSuffix);
+        assert_eq!(rule.pattern.to_string(), "**.google.com");
+        assert_eq!(rule.policy.as_deref(), Some("Proxy"));
+
+        let rule = parse_rule("  - 'DOMAIN,example.com'").expect("valid").expect("domain rule");
+        assert_eq!(rule.pattern.to_string(), "example.com");
+        assert_eq!(rule.policy, None);
+
+        assert_eq!(parse_rule("DOMAIN-KEYWORD,ads").expect("valid").expect("domain rule").pattern.to_string(), "**.*ads*.**");
+        assert_eq!(parse_rule("DOMAIN-KEYWORD,oo.ba").expect("valid").expect("domain rule").pattern.to_string(), "**.*oo.ba*.**");
+
+        // an empty label at an end of the keyword is a whole label of the domain
+        let pattern = parse_rule("DOMAIN-KEYWORD,.ba").expect("valid").expect("domain rule").pattern;
+        assert_eq!(pattern.to_string(), "**+.ba*.**");
+        assert!(pattern.matches("foo.bar"));
+        assert!(!pattern.matches("bar"));
+        let pattern = parse_rule("DOMAIN-KEYWORD,oo.").expect("valid").expect("domain rule").pattern;
+        assert_eq!(pattern.to_string(), "**.*oo.**+");
+        assert!(pattern.matches("foo.bar"));
+        assert!(!pattern.matches("foo"));
+        let pattern = parse_rule("DOMAIN-KEYWORD,.").expect("valid").expect("domain rule").pattern;
+        assert!(pattern.matches("foo.bar"));
+        assert!(!pattern.matches("foo"));
+
+        assert_eq!(parse_rule("payload:"), Ok(None));
+        assert_eq!(parse_rule("# comment"), Ok(None));
+        assert_eq!(parse_rule("IP-CIDR,10.0.0.0/8,DIRECT,no-resolve"), Ok(None));
+        assert_eq!(parse_rule("MATCH,Proxy"), Ok(None));
+        assert_eq!(parse_rule("DOMAIN,a..com"), Err("DOMAIN,a..com".to_string()));
+        assert_eq!(parse_rule("DOMAIN-KEYWORD,a*b"), Err("DOMAIN-KEYWORD,a*b".to_string()));
+    }
+
+    #[test]
+    pub fn test_from_clash() {
+        let provider = "payload:\n  # ads\n  - DOMAIN-SUFFIX,ads.com\n  - DOMAIN,exact.net\n  - DOMAIN-KEYWORD,track\n  - DOMAIN-KEYWORD,oo.ba\n  - IP-CIDR,1.1.1.1/32\n";
+        let set = DomainPatternSet::from_clash(provider.as_bytes()).expect("loads");
+        assert_eq!(set.len(), 4);
+        for (domain, matches) in [("ads.com", true), ("x.ads.com", true), ("exact.net", true), ("www.exact.net", false), ("tracker.example.com", true),
+            ("eventtracking.io", true), ("foo.bar", true), ("oo.ba", true), ("o.ba", false), ("example.com", false)] {
+            assert_eq!(set.contains_match(domain), matches, "{:?}", domain);
+        }
+
+        let err = parse_rules("DOMAIN,a.com\nDOMAIN-SUFFIX,\n".as_bytes()).expect_err("empty domain");
+        assert!(matches!(err, ClashError::InvalidRule { line: 2, .. }), "{:?}", err);
+    }
+}
@@ -0,0 +1,161 @@
+//! `address=/domain/` and `server=/domain/` rules of dnsmasq configs
+//!
+//! dnsmasq matches a domain and all of its subdomains, so `address=/example.com/0.0.0.0` becomes `**.example.com`,
+//! `*.example.com` only its subdomains, becoming `**+.example.com`, and `#` every domain, becoming `**`.
+
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::io::BufRead;
+use crate::formats::with_subdomains;
+use crate::hostname::validate;
+use crate::{DomainPattern, DomainPatternPart, DomainPatternWildcard};
+
+/// Why a config couldn't be loaded
+#[derive(Debug)]
+pub enum DnsmasqError {
+    Io(io::Error),
+    /// A domain of the directive on this line, counting from 1, isn't a valid hostname
+    InvalidDomain { line: usize, domain: String },
+}
+
+impl Display for DnsmasqError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DnsmasqError::Io(err) => write!(f, "Failed to read dnsmasq config: {}", err),
+            DnsmasqError::InvalidDomain { line, domain } => write!(f, "Invalid domain {:?} on line {}", domain, line),
+        }
+    }
+}
+
+impl std::error::Error for DnsmasqError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DnsmasqError::Io(err) => Some(err),
+            DnsmasqError::InvalidDomain { .. } => None,
+        }
+    }
+}
+
+/// The directive a rule came from
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Directive {
+    /// `address=`, answering with the address, or with nothing if it's empty
+    Address,
+    /// `server=`, forwarding to the server, or answering locally if it's empty
+    Server,
+    /// `local=`, answering locally, the same as `server=` without a server
+    Local,
+}
+
+/// A domain of a directive, with what it maps the domain to
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct DnsmasqRule {
+    pub directive: Directive,
+    pub pattern: DomainPattern<'static>,
+    /// What comes after the domains, like the address or server, empty if there's nothing
+    pub target: String,
+    /// The line the directive is on, counting from 1
+    pub line: usize,
+}
+
+fn pattern(domain: &str) -> Option<DomainPattern<'static>> {
+    if domain == "#" {
+        return Some(DomainPattern {
+            steps: std::iter::once(DomainPatternPart::Wildcard(DomainPatternWildcard { multi: true, optional: true })).collect(),
+            negated: false,
+        });
+    }
+
+    let (subdomains_only, domain) = match domain.strip_prefix("*.") {
+        Some(domain) => (true, domain),
+        None => (false, domain.strip_prefix('.').unwrap_or(domain)),
+    };
+
+    let domain = domain.to_ascii_lowercase();
+    validate::<'.'>(&domain).ok()?;
+    let mut pattern = with_subdomains(&domain);
+    if subdomains_only {
+        pattern.steps[0] = DomainPatternPart::Wildcard(DomainPatternWildcard { multi: true, optional: false });
+    }
+
+    Some(pattern)
+}
+
+/// Parses a line of a config, returning a rule for every domain of an `address`, `server` or `local` directive
+///
+/// Other lines, directives without domains like `server=1.1.1.1`, and comments give no rules. Fails with the domain
+/// that isn't a valid hostname.
+pub fn parse_line(line: &str, number: usize) -> Result<Vec<DnsmasqRule>, String> {
+    let line = line.trim();
+    let line = line.strip_prefix("--").unwrap_or(line);
+    let Some((name, value)) = line.split_once('=') else {
+        return Ok(vec![]);
+    };
+
+    let directive = match name.trim() {
+        "address" => Directive::Address,
+        "server" => Directive::Server,
+        "local" => Directive::Local,
+        _ => return Ok(vec![]),
+    };
+
+    let Some(value) = value.trim().strip_prefix('/') else {
+        return Ok(vec![]);
+    };
+
+    let Some((domains, target)) = value.rsplit_once('/') else {
+        return Ok(vec![]);
+    };
+
+    domains.split('/')
+        .filter(|domain| !domain.is_empty())
+        .map(|domain| match pattern(domain) {
+            Some(pattern) => Ok(DnsmasqRule { directive, pattern, target: target.to_string(), line: number }),
+            None => Err(domain.to_string()),
+        })
+        .collect()
+}
+
+/// Parses every `address`, `server` and `local` rule of a config, see [`parse_line`]
+pub fn parse_config(reader: impl BufRead) -> Result<Vec<DnsmasqRule>, DnsmasqError> {
+    let mut rules = vec![];
+    for (idx, line) in reader.lines().enumerate() {
+        let line = line.map_err(DnsmasqError::Io)?;
+        let parsed = parse_line(&line, idx + 1).map_err(|domain| DnsmasqError::InvalidDomain { line: idx + 1, domain })?;
+        rules.extend(parsed);
+    }
+
+    Ok(rules)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formats::dnsmasq::{parse_config, DnsmasqError, Directive};
+    use crate::DomainPatternSet;
+
+    #[test]
+    pub fn test_dnsmasq() {
+        let config = "# blocking\naddress=/ads.example.com/Tracker.net/0.0.0.0\naddress=/*.wild.com/\n\
+            server=/corp.lan/10.0.0.1\nlocal=/home.arpa/\nserver=1.1.1.1\ncache-size=1000\n--server=/#/9.9.9.9\n";
+        let rules = parse_config(config.as_bytes()).expect("loads");
+        let summary: Vec<_> = rules.iter().map(|rule| (rule.directive, rule.pattern.to_string(), rule.target.as_str(), rule.line)).collect();
+        assert_eq!(summary, vec![
+            (Directive::Address, "**.ads.example.com".to_string(), "0.0.0.0", 2),
+            (Directive::Address, "**.tracker.net".to_string(), "0.0.0.0", 2),
+            (Directive::Address, "**+.wild.com".to_string(), "", 3),
+            (Directive::Server, "**.corp.lan".to_string(), "10.0.0.1", 4),
+            (Directive::Local, "**.home.arpa".to_string(), "", 5),
+            (Directive::Server, "**".to_string(), "9.9.9.9", 8),
+        ]);
+
+        let blocked: DomainPatternSet = rules.into_iter().filter(|rule| rule.directive == Directive::Address).map(|rule| rule.pattern).collect();
+        assert!(blocked.contains_match("ads.example.com"));
+        assert!(blocked.contains_match("x.tracker.net"));
+        assert!(blocked.contains_match("a.wild.com"));
+        assert!(!blocked.contains_match("wild.com"));
+        assert!(!blocked.contains_match("example.com"));
+
+        let err = parse_config("address=/a.com/\naddress=/b..com/0.0.0.0\n".as_bytes()).expect_err("invalid domain");
+        assert!(matches!(err, DnsmasqError::InvalidDomain { line: 2, ref domain } if domain == "b..com"), "{:?}", err);
+    }
+}
@@ -0,0 +1,191 @@
+//! The plain text lists v2ray and xray build their geosite data from
+//!
+//! `full:example.com` becomes `example.com`, `domain:example.com` or just `example.com` becomes `**.example.com`,
+//! `keyword:ads` becomes `**.*ads*.**`, and `regexp:` lines are translated with [`DomainPattern::from_simple_regex`].
+//! Attributes like `@ads` after an entry are kept with its pattern, and `include:` lines are collected, so the lists
+//! they name can be loaded too.
+
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::io::BufRead;
+use crate::formats::{exact, keyword, with_subdomains};
+use crate::hostname::validate;
+use crate::{DomainPattern, DomainPatternSet, TranslateError};
+
+/// Why a list couldn't be loaded
+#[derive(Debug)]
+pub enum GeositeError {
+    Io(io::Error),
+    /// The domain or keyword of the entry on this line, counting from 1, can't be part of a hostname
+    InvalidEntry { line: usize, entry: String },
+}
+
+impl Display for GeositeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GeositeError::Io(err) => write!(f, "Failed to read geosite list: {}", err),
+            GeositeError::InvalidEntry { line, entry } => write!(f, "Invalid entry {:?} on line {}", entry, line),
+        }
+    }
+}
+
+impl std::error::Error for GeositeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GeositeError::Io(err) => Some(err),
+            GeositeError::InvalidEntry { .. } => None,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum GeositeKind {
+    /// `full:`, just the domain itself
+    Full,
+    /// `domain:`, or no prefix at all, the domain and all of its subdomains
+    Domain,
+    /// `keyword:`, every domain containing the keyword
+    Keyword,
+    /// `regexp:`, every domain the regex matches
+    Regexp,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct GeositeEntry {
+    pub kind: GeositeKind,
+    pub pattern: DomainPattern<'static>,
+    /// Attributes of the entry, without their `@`
+    pub attributes: Vec<String>,
+    /// The line of the entry, counting from 1
+    pub line: usize,
+}
+
+impl GeositeEntry {
+    pub fn has_attribute(&self, attribute: &str) -> bool {
+        self.attributes.iter().any(|attr| attr == attribute)
+    }
+}
+
+/// A regex that can't be a pattern, see [`DomainPattern::from_simple_regex`]
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct UnsupportedRegex {
+    pub line: usize,
+    pub regex: String,
+    pub error: TranslateError,
+}
+
+/// Everything in a geosite list, see [`GeositeList::parse`]
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct GeositeList {
+    pub entries: Vec<GeositeEntry>,
+    /// Names of the lists this list includes, with the attributes their entries need to have
+    pub includes: Vec<(String, Vec<String>)>,
+    /// Regexes that patterns can't express, these are left out of the entries
+    pub unsupported: Vec<UnsupportedRegex>,
+}
+
+impl GeositeList {
+    /// Parses a list, failing on entries that can't be a domain, but not on regexes patterns can't express
+    pub fn parse(reader: impl BufRead) -> Result<Self, GeositeError> {
+        let mut list = GeositeList::default();
+        for (idx, line) in reader.lines().enumerate() {
+            let line = line.map_err(GeositeError::Io)?;
+            let entry = line.split_once('#').map_or(line.as_str(), |(entry, _)| entry);
+            let mut fields = entry.split_whitespace();
+            let Some(value) = fields.next() else {
+                continue;
+            };
+
+            let attributes: Vec<String> = fields.filter_map(|attr| attr.strip_prefix('@')).map(str::to_string).collect();
+            let (kind, value) = match value.split_once(':') {
+                Some(("full", value)) => (GeositeKind::Full, value),
+                Some(("domain", value)) => (GeositeKind::Domain, value),
+                Some(("keyword", value)) => (GeositeKind::Keyword, value),
+                Some(("regexp", value)) => (GeositeKind::Regexp, value),
+                Some(("include", name)) => {
+                    list.includes.push((name.to_string(), attributes));
+                    continue;
+                }
+                _ => (GeositeKind::Domain, value),
+            };
+
+            let pattern = match kind {
+                GeositeKind::Regexp => match DomainPattern::from_simple_regex(value) {
+                    Ok(pattern) => Some(pattern),
+                    Err(error) => {
+                        list.unsupported.push(UnsupportedRegex { line: idx + 1, regex: value.to_string(), error });
+                        continue;
+                    }
+                },
+                GeositeKind::Keyword => keyword(&value.to_ascii_lowercase()),
+                GeositeKind::Full | GeositeKind::Domain => {
+                    let domain = value.to_ascii_lowercase();
+                    match (validate::<'.'>(&domain), kind) {
+                        (Err(_), _) => None,
+                        (Ok(_), GeositeKind::Full) => Some(exact(&domain)),
+                        (Ok(_), _) => Some(with_subdomains(&domain)),
+                    }
+                }
+            };
+
+            let pattern = pattern.ok_or_else(|| GeositeError::InvalidEntry { line: idx + 1, entry: entry.trim().to_string() })?;
+            list.entries.push(GeositeEntry { kind, pattern, attributes, line: idx + 1 });
+        }
+
+        Ok(list)
+    }
+
+    /// Entries with the attribute, like `ads` for `geosite:google@ads`
+    pub fn with_attribute<'l>(&'l self, attribute: &'l str) -> impl Iterator<Item=&'l GeositeEntry> + 'l {
+        self.entries.iter().filter(move |entry| entry.has_attribute(attribute))
+    }
+
+    /// A set of the patterns of all entries
+    pub fn into_set(self) -> DomainPatternSet<'static> {
+        self.entries.into_iter().map(|entry| entry.pattern).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formats::geosite::{GeositeError, GeositeKind, GeositeList};
+    use crate::TranslateError;
+
+    #[test]
+    pub fn test_geosite() {
+        let list = "# google\ninclude:google-ads @ads\nGoogle.com\ndomain:googleapis.com @cn\nfull:www.google.com @ads @cn\n\
+            keyword:gstatic\nregexp:^ad[0-9]\\.google\\.com$\nregexp:\\.goog$ # unanchored\n\n";
+        let list = GeositeList::parse(list.as_bytes()).expect("loads");
+
+        let kinds: Vec<_> = list.entries.iter().map(|entry| (entry.kind, entry.pattern.to_string(), entry.line)).collect();
+        assert_eq!(kinds, vec![
+            (GeositeKind::Domain, "**.google.com".to_string(), 3),
+            (GeositeKind::Domain, "**.googleapis.com".to_string(), 4),
+            (GeositeKind::Full, "www.google.com".to_string(), 5),
+            (GeositeKind::Keyword, "**.*gstatic*.**".to_string(), 6),
+            (GeositeKind::Regexp, "ad[0-9].google.com".to_string(), 7),
+        ]);
+
+        assert_eq!(list.includes, vec![("google-ads".to_string(), vec!["ads".to_string()])]);
+        assert_eq!(list.unsupported.len(), 1);
+        assert_eq!((list.unsupported[0].line, list.unsupported[0].regex.as_str(), list.unsupported[0].error), (8, "\\.goog$", TranslateError::Unanchored));
+
+        let cn: Vec<_> = list.with_attribute("cn").map(|entry| entry.pattern.to_string()).collect();
+        assert_eq!(cn, vec!["**.googleapis.com", "www.google.com"]);
+        assert_eq!(list.entries[2].attributes, vec!["ads", "cn"]);
+
+        let set = list.into_set();
+        for (domain, matches) in [("google.com", true), ("mail.google.com", true), ("fonts.gstatic.com", true), ("ad1.google.com", true),
+            ("googleapis.org", false), ("example.com", false)] {
+            assert_eq!(set.contains_match(domain), matches, "{:?}", domain);
+        }
+
+        let list = GeositeList::parse("keyword:.cn\n".as_bytes()).expect("loads");
+        assert_eq!(list.entries[0].pattern.to_string(), "**+.cn*.**");
+        assert!(!list.entries[0].pattern.matches("cn.com"));
+        assert!(list.entries[0].pattern.matches("www.cnn.com"));
+
+        let err = GeositeList::parse("full:a.com\nfull:a..com @x\n".as_bytes()).expect_err("invalid domain");
+        assert!(matches!(err, GeositeError::InvalidEntry { line: 2, ref entry } if entry == "full:a..com @x"), "{:?}", err);
+    }
+}
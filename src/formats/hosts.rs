@@ -0,0 +1,155 @@
+//! `/etc/hosts` style files, like the `0.0.0.0 ads.example.com` blocklists many ad blockers take
+
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::io::BufRead;
+use std::net::{IpAddr, Ipv6Addr};
+use crate::formats::exact;
+use crate::hostname::validate;
+use crate::{DomainPattern, DomainPatternSet, InvalidHostname};
+
+// names every hosts file maps to the machine itself, blocking them would break it
+const LOCAL_NAMES: &[&str] = &["localhost", "localhost.localdomain", "local", "broadcasthost", "ip6-localhost", "ip6-loopback",
+    "ip6-localnet", "ip6-mcastprefix", "ip6-allnodes", "ip6-allrouters", "ip6-allhosts", "0.0.0.0"];
+
+/// Why a hosts file, or a line of it, couldn't be loaded, with the line it went wrong on, counting from 1
+#[derive(Debug)]
+pub enum HostsError {
+    Io(io::Error),
+    /// The line doesn't start with an IPv4 or IPv6 address
+    InvalidAddress { line: usize },
+    InvalidHostname { line: usize, hostname: String, reason: InvalidHostname },
+}
+
+impl Display for HostsError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HostsError::Io(err) => write!(f, "Failed to read hosts file: {}", err),
+            HostsError::InvalidAddress { line } => write!(f, "Line {} doesn't start with an IP address", line),
+            HostsError::InvalidHostname { line, hostname, reason } => write!(f, "Invalid hostname {:?} on line {}: {}", hostname, line, reason),
+        }
+    }
+}
+
+impl std::error::Error for HostsError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            HostsError::Io(err) => Some(err),
+            HostsError::InvalidHostname { reason, .. } => Some(reason),
+            HostsError::InvalidAddress { .. } => None,
+        }
+    }
+}
+
+/// The hostnames of a line of a hosts file, skipping the names of the machine itself
+fn hostnames(line: &str) -> Result<impl Iterator<Item=&str>, ()> {
+    let line = line.split_once('#').map_or(line, |(line, _)| line);
+    let mut fields = line.split_whitespace();
+    if let Some(address) = fields.next() {
+        // scoped IPv6 addresses like `fe80::1%lo0` end in the interface they're on
+        let valid = match address.split_once('%') {
+            Some((address, zone)) => !zone.is_empty() && address.parse::<Ipv6Addr>().is_ok(),
+            None => address.parse::<IpAddr>().is_ok(),
+        };
+
+        if !valid {
+            return Err(());
+        }
+    }
+
+    Ok(fields.filter(|hostname| !LOCAL_NAMES.iter().any(|local| local.eq_ignore_ascii_case(hostname))))
+}
+
+/// The hostnames of a hosts file, and every line or hostname in it that isn't valid, see [`HostsFile::parse`]
+#[derive(Debug, Default)]
+pub struct HostsFile {
+    /// A pattern matching just the hostname for every hostname, lowercased
+    pub patterns: Vec<DomainPattern<'static>>,
+    /// Lines without an address and invalid hostnames, never [`HostsError::Io`]
+    pub errors: Vec<HostsError>,
+}
+
+impl HostsFile {
+    /// Parses every line of a hosts file, carrying on past invalid lines and hostnames so all of them are reported
+    ///
+    /// Comments start with `#`, a line can have multiple hostnames after its address, and names like `localhost`
+    /// that every hosts file has are skipped. Which address a hostname is mapped to doesn't matter.
+    pub fn parse(reader: impl BufRead) -> io::Result<Self> {
+        let mut file = HostsFile::default();
+        for (idx, line) in reader.lines().enumerate() {
+            let line = line?;
+            let Ok(hostnames) = hostnames(&line) else {
+                file.errors.push(HostsError::InvalidAddress { line: idx + 1 });
+                continue;
+            };
+
+            for hostname in hostnames {
+                let hostname = hostname.to_ascii_lowercase();
+                match validate::<'.'>(&hostname) {
+                    Ok(()) => file.patterns.push(exact(&hostname)),
+                    Err(reason) => file.errors.push(HostsError::InvalidHostname { line: idx + 1, hostname, reason }),
+                }
+            }
+        }
+
+        Ok(file)
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// A set of all valid hostnames of the file
+    pub fn into_set(self) -> DomainPatternSet<'static> {
+        self.patterns.into_iter().collect()
+    }
+}
+
+impl DomainPatternSet<'static> {
+    /// Loads every valid hostname of a hosts file as a pattern matching just that domain, lowercased
+    ///
+    /// Lines without an address and invalid hostnames are skipped, hosts files tend to have some, see
+    /// [`HostsFile::parse`] to get at them. Only failing to read the file is an error.
+    pub fn from_hosts_file(reader: impl BufRead) -> Result<Self, HostsError> {
+        Ok(HostsFile::parse(reader).map_err(HostsError::Io)?.into_set())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formats::hosts::{HostsError, HostsFile};
+    use crate::{DomainPatternSet, InvalidHostname};
+
+    #[test]
+    pub fn test_hosts_file() {
+        let hosts = "# blocklist\n127.0.0.1 localhost\n::1 localhost ip6-localhost ip6-loopback\n\n0.0.0.0 0.0.0.0\n\
+            0.0.0.0 ads.example.com tracker.example.net # trackers\n\t0.0.0.0\tAds.Other.com.\n127.0.0.1\nfe80::1%lo0 localhost\nfe80::2%en0 printer.lan\n";
+        let set = DomainPatternSet::from_hosts_file(hosts.as_bytes()).expect("loads");
+        assert_eq!(set.len(), 4);
+        assert!(set.contains_match("printer.lan"));
+        assert!(set.contains_match("ads.example.com"));
+        assert!(set.contains_match("tracker.example.net"));
+        assert!(set.contains_match("ads.other.com"));
+        assert!(!set.contains_match("www.ads.example.com"));
+        assert!(!set.contains_match("localhost"));
+        assert!(!set.contains_match("0.0.0.0"));
+
+        // invalid lines and hostnames are skipped, but reported by parse
+        let hosts = "0.0.0.0 a.com\nads.example.com\n127.0.0.1%lo0 b.com\nfe80::1% c.com\n0.0.0.0 d.com *.e.com\n";
+        let set = DomainPatternSet::from_hosts_file(hosts.as_bytes()).expect("loads");
+        assert_eq!(set.len(), 2);
+        assert!(set.contains_match("d.com"));
+
+        let file = HostsFile::parse(hosts.as_bytes()).expect("reads");
+        assert!(!file.is_valid());
+        assert_eq!(file.patterns.len(), 2);
+        assert_eq!(file.errors.len(), 4);
+        for (err, line) in file.errors[..3].iter().zip(2..) {
+            assert!(matches!(err, HostsError::InvalidAddress { line: l } if *l == line), "{:?}", err);
+        }
+
+        let err = &file.errors[3];
+        assert!(matches!(err, HostsError::InvalidHostname { line: 5, ref hostname, reason: InvalidHostname::InvalidCharacter(0) } if hostname == "*.e.com"), "{:?}", err);
+        assert_eq!(err.to_string(), "Invalid hostname \"*.e.com\" on line 5: Invalid character at position 0 in hostname");
+    }
+}
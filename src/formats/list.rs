@@ -0,0 +1,84 @@
+//! Plain lists of patterns, one or more per line, like `allowed_hosts.txt`
+
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::io::BufRead;
+use crate::{DomainPattern, DomainPatternSet, InvalidToken};
+
+/// A pattern of a list that couldn't be parsed, with the line it's on, counting from 1
+#[derive(Debug)]
+pub struct LineError {
+    pub line: usize,
+    pub error: InvalidToken<'static>,
+}
+
+impl Display for LineError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Line {}: {}", self.line, self.error)
+    }
+}
+
+/// The patterns of a list, and everything in it that isn't a valid pattern, see [`PatternList::parse_lines`]
+#[derive(Debug, Default)]
+pub struct PatternList {
+    pub patterns: Vec<DomainPattern<'static>>,
+    pub errors: Vec<LineError>,
+}
+
+impl PatternList {
+    /// Parses every pattern of the list, carrying on past invalid ones so all of them are reported at once
+    ///
+    /// Patterns are separated by whitespace, and blank lines are skipped. A `#` at the start of a pattern starts a
+    /// comment running to the end of the line, unless it's a numeric label like in `#.example.com`.
+    pub fn parse_lines(reader: impl BufRead) -> io::Result<Self> {
+        let mut list = PatternList::default();
+        for (idx, line) in reader.lines().enumerate() {
+            let line = line?;
+            for pattern in line.split_whitespace() {
+                if pattern.starts_with('#') && !pattern.starts_with("#.") {
+                    break;
+                }
+
+                match DomainPattern::parse(pattern) {
+                    Ok(pattern) => list.patterns.push(pattern.into_owned()),
+                    Err(error) => list.errors.push(LineError { line: idx + 1, error: error.into_owned() }),
+                }
+            }
+        }
+
+        Ok(list)
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// A set of all valid patterns of the list
+    pub fn into_set(self) -> DomainPatternSet<'static> {
+        self.patterns.into_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formats::list::PatternList;
+    use crate::{DomainPattern, ParseErrorKind};
+
+    #[test]
+    pub fn test_parse_lines() {
+        let list = "# allowed hosts\n\n  *.example.com   example.org # the main site\n\t#.numbers.com\nok.com {x\na.{b,c\n#\n**.internal\n";
+        let list = PatternList::parse_lines(list.as_bytes()).expect("reads");
+        let patterns: Vec<String> = list.patterns.iter().map(DomainPattern::to_string).collect();
+        assert_eq!(patterns, vec!["*.example.com", "example.org", "#.numbers.com", "ok.com", "**.internal"]);
+
+        assert!(!list.is_valid());
+        let errors: Vec<_> = list.errors.iter().map(|error| (error.line, error.error.kind())).collect();
+        assert_eq!(errors, vec![(5, ParseErrorKind::InvalidToken), (6, ParseErrorKind::InvalidToken)]);
+        assert!(list.errors[0].to_string().starts_with("Line 5: "));
+
+        let set = list.into_set();
+        assert!(set.contains_match("www.example.com"));
+        assert!(set.contains_match("1.numbers.com"));
+        assert!(!set.contains_match("ok.org"));
+    }
+}
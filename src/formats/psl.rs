@@ -0,0 +1,162 @@
+//! The [Public Suffix List](https://publicsuffix.org/list/), the suffixes under which anyone can register a domain
+//!
+//! `com` becomes `com`, the wildcard `*.ck` becomes `+.ck`, and the exception `!www.ck` becomes the negated `!www.ck`,
+//! so a set of the rules matches exactly the domains that are public suffixes. Rules are lowercased, and with the
+//! `idna` feature converted to A-labels, so `公司.cn` matches `xn--55qx5d.cn`.
+
+use std::borrow::Cow;
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::io::BufRead;
+use crate::{DomainNormalizer, DomainPattern, DomainPatternPart, DomainPatternSet, DomainPatternWildcard, StepVec};
+
+/// Why the list couldn't be loaded
+#[derive(Debug)]
+pub enum PslError {
+    Io(io::Error),
+    /// The rule on this line, counting from 1, has an empty label, or a label that can't be part of a hostname
+    InvalidRule { line: usize, rule: String },
+}
+
+impl Display for PslError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PslError::Io(err) => write!(f, "Failed to read public suffix list: {}", err),
+            PslError::InvalidRule { line, rule } => write!(f, "Invalid rule {:?} on line {}", rule, line),
+        }
+    }
+}
+
+impl std::error::Error for PslError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PslError::Io(err) => Some(err),
+            PslError::InvalidRule { .. } => None,
+        }
+    }
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct PslRule {
+    /// Negated for exceptions like `!www.ck`
+    pub pattern: DomainPattern<'static>,
+    /// Whether the rule is in the private section, for suffixes run by companies like `github.io`
+    pub private: bool,
+    /// The line the rule is on, counting from 1
+    pub line: usize,
+}
+
+impl PslRule {
+    pub fn is_exception(&self) -> bool {
+        self.pattern.is_negated()
+    }
+}
+
+// a label of a rule, lowercased, and an A-label with idna
+fn label(normalizer: &DomainNormalizer, label: &str) -> Option<String> {
+    let valid = |b: u8| b.is_ascii_alphanumeric() || b == b'-' || b == b'_' || !b.is_ascii();
+    if label.is_empty() || !label.bytes().all(valid) {
+        return None;
+    }
+
+    Some(normalizer.label(label).into_owned())
+}
+
+/// Parses a single rule, Ok(None) for comments and empty lines
+///
+/// Only the rule up to the first whitespace is looked at, like the list asks for. Fails with the rule if it has an empty
+/// label, or a label that can't be part of a hostname.
+pub fn parse_rule(line: &str) -> Result<Option<DomainPattern<'static>>, String> {
+    let Some(rule) = line.split_whitespace().next().filter(|rule| !rule.starts_with("//")) else {
+        return Ok(None);
+    };
+
+    let (labels, negated) = match rule.strip_prefix('!') {
+        Some(labels) => (labels, true),
+        None => (rule, false),
+    };
+
+    let normalizer = DomainNormalizer::new().lowercase(true);
+    #[cfg(feature = "idna")]
+    let normalizer = normalizer.idna(true);
+
+    let mut steps: StepVec<'static> = Default::default();
+    for part in labels.split('.') {
+        steps.push(match part {
+            "*" => DomainPatternPart::Wildcard(DomainPatternWildcard { multi: false, optional: false }),
+            part => DomainPatternPart::Static(Cow::Owned(label(&normalizer, part).ok_or_else(|| rule.to_string())?)),
+        });
+    }
+
+    Ok(Some(DomainPattern { steps, negated }))
+}
+
+/// Parses every rule of the list, keeping track of which are in the private section
+pub fn parse_list(reader: impl BufRead) -> Result<Vec<PslRule>, PslError> {
+    let mut rules = vec![];
+    let mut private = false;
+    for (idx, line) in reader.lines().enumerate() {
+        let line = line.map_err(PslError::Io)?;
+        match line.trim() {
+            "// ===BEGIN PRIVATE DOMAINS===" => private = true,
+            "// ===END PRIVATE DOMAINS===" => private = false,
+            line => {
+                if let Some(pattern) = parse_rule(line).map_err(|rule| PslError::InvalidRule { line: idx + 1, rule })? {
+                    rules.push(PslRule { pattern, private, line: idx + 1 });
+                }
+            }
+        }
+    }
+
+    Ok(rules)
+}
+
+impl DomainPatternSet<'static> {
+    /// Loads every rule of the public suffix list, so the set matches exactly the public suffixes, see [`parse_list`]
+    ///
+    /// Domains a wildcard rule covers but an exception takes back, like `www.ck`, don't match. The implicit `*` rule
+    /// of the list isn't added, so a TLD that isn't on it doesn't match either.
+    pub fn from_public_suffix_list(reader: impl BufRead) -> Result<Self, PslError> {
+        Ok(parse_list(reader)?.into_iter().map(|rule| rule.pattern).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formats::psl::{parse_list, parse_rule, PslError};
+    use crate::DomainPatternSet;
+
+    const LIST: &str = "// comment\n// ===BEGIN ICANN DOMAINS===\ncom\nCO.uk  trailing\n*.ck\n!www.ck\n\n// ===END ICANN DOMAINS===\n\
+        // ===BEGIN PRIVATE DOMAINS===\ngithub.io\n// ===END PRIVATE DOMAINS===\n";
+
+    #[test]
+    pub fn test_parse_list() {
+        let rules = parse_list(LIST.as_bytes()).expect("loads");
+        let parsed: Vec<_> = rules.iter().map(|rule| (rule.pattern.to_string(), rule.is_exception(), rule.private, rule.line)).collect();
+        assert_eq!(parsed, [("com".to_string(), false, false, 3), ("co.uk".to_string(), false, false, 4), ("+.ck".to_string(), false, false, 5),
+            ("!www.ck".to_string(), true, false, 6), ("github.io".to_string(), false, true, 10)]);
+
+        assert_eq!(parse_rule("// *.ck"), Ok(None));
+        assert_eq!(parse_rule("   "), Ok(None));
+        assert_eq!(parse_rule("a..b"), Err("a..b".to_string()));
+        assert_eq!(parse_rule("!a*.b"), Err("!a*.b".to_string()));
+
+        let err = parse_list("com\n.net\n".as_bytes()).expect_err("empty label");
+        assert!(matches!(err, PslError::InvalidRule { line: 2, .. }), "{:?}", err);
+    }
+
+    #[test]
+    pub fn test_from_public_suffix_list() {
+        let set = DomainPatternSet::from_public_suffix_list(LIST.as_bytes()).expect("loads");
+        for (domain, suffix) in [("com", true), ("example.com", false), ("co.uk", true), ("uk", false), ("anything.ck", true), ("www.ck", false),
+            ("a.b.ck", false), ("github.io", true), ("user.github.io", false)] {
+            assert_eq!(set.contains_match(domain), suffix, "{:?}", domain);
+        }
+    }
+
+    #[cfg(feature = "idna")]
+    #[test]
+    pub fn test_parse_rule_idna() {
+        assert_eq!(parse_rule("公司.cn").expect("valid").expect("rule").to_string(), "xn--55qx5d.cn");
+    }
+}
@@ -0,0 +1,137 @@
+//! Host patterns of OpenSSH, as in `known_hosts`, `Host` and `Match host`
+//!
+//! These don't work per label, `*` takes any characters, dots included, and `?` any single one, so `*.example.com`
+//! matches `a.b.example.com`, and `web?` matches `web1` and `web.`, but not `web12`. Patterns are compared ignoring
+//! case, and a pattern starting with `!` excludes the hosts it matches.
+
+/// A list of OpenSSH host patterns like `*.example.com,!bastion.example.com`
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct SshHostPatterns {
+    // negated, and the pattern lowercased without its `!`
+    patterns: Vec<(bool, String)>,
+}
+
+// OpenSSH's `match_pattern`, on bytes like it does, backtracking to the last `*`
+fn wildmatch(pattern: &[u8], name: &[u8]) -> bool {
+    let (mut p, mut n) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some(b'*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(c) if *c == b'?' || *c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((star_p, star_n)) => {
+                    p = star_p + 1;
+                    n = star_n + 1;
+                    star = Some((star_p, star_n + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == b'*')
+}
+
+impl SshHostPatterns {
+    /// Parses a list separated by commas, like in `known_hosts` and `Match host`, or whitespace, like after `Host`
+    pub fn parse(list: &str) -> Self {
+        let patterns = list.split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|pattern| !pattern.is_empty())
+            .map(|pattern| match pattern.strip_prefix('!') {
+                Some(pattern) => (true, pattern.to_ascii_lowercase()),
+                None => (false, pattern.to_ascii_lowercase()),
+            })
+            .collect();
+
+        SshHostPatterns { patterns }
+    }
+
+    /// The host patterns of a `known_hosts` line, after its `@cert-authority` or `@revoked` marker if it has one
+    ///
+    /// Comments and blank lines have none. Hashed hosts like `|1|...` are kept, but never match, as that takes
+    /// the key they were hashed with.
+    pub fn from_known_hosts_line(line: &str) -> Option<Self> {
+        let mut fields = line.split_whitespace();
+        let mut hosts = fields.next()?;
+        if hosts.starts_with('#') {
+            return None;
+        }
+
+        if hosts.starts_with('@') {
+            hosts = fields.next()?;
+        }
+
+        Some(Self::parse(hosts))
+    }
+
+    /// Whether the host matches, hosts on a port other than 22 are written as `[host]:port`
+    ///
+    /// A host matches if any pattern matches it and none of the negated ones do, so a list of only negated patterns
+    /// matches nothing.
+    pub fn matches(&self, host: &str) -> bool {
+        let host = host.to_ascii_lowercase();
+        let mut matched = false;
+        for (negated, pattern) in &self.patterns {
+            if wildmatch(pattern.as_bytes(), host.as_bytes()) {
+                if *negated {
+                    return false;
+                }
+
+                matched = true;
+            }
+        }
+
+        matched
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formats::ssh::SshHostPatterns;
+
+    #[test]
+    pub fn test_ssh_patterns() {
+        let patterns = SshHostPatterns::parse("*.example.com,!bastion.example.com,web?");
+        for (host, matches) in [("a.example.com", true), ("a.b.example.com", true), ("A.Example.COM", true), ("example.com", false),
+            ("bastion.example.com", false), ("web1", true), ("web", false), ("web12", false), ("web.", true), ("other.org", false)] {
+            assert_eq!(patterns.matches(host), matches, "{:?}", host);
+        }
+
+        let patterns = SshHostPatterns::parse("!*.internal");
+        assert!(!patterns.matches("a.internal"));
+        assert!(!patterns.matches("example.com"));
+
+        let patterns = SshHostPatterns::parse("db*prod  *a*b*c");
+        assert!(patterns.matches("db1.eu.prod"));
+        assert!(patterns.matches("xaxbxc"));
+        assert!(patterns.matches("abc"));
+        assert!(!patterns.matches("acb"));
+        assert!(SshHostPatterns::parse("*").matches(""));
+    }
+
+    #[test]
+    pub fn test_known_hosts() {
+        let hosts = SshHostPatterns::from_known_hosts_line("github.com,[gitlab.example.com]:2222 ssh-ed25519 AAAAC3Nza").expect("has hosts");
+        assert!(hosts.matches("github.com"));
+        assert!(hosts.matches("[gitlab.example.com]:2222"));
+        assert!(!hosts.matches("gitlab.example.com"));
+
+        let hosts = SshHostPatterns::from_known_hosts_line("@cert-authority *.example.com ssh-rsa AAAAB3").expect("has hosts");
+        assert!(hosts.matches("a.example.com"));
+
+        assert_eq!(SshHostPatterns::from_known_hosts_line("# comment"), None);
+        assert_eq!(SshHostPatterns::from_known_hosts_line("   "), None);
+        assert!(!SshHostPatterns::from_known_hosts_line("|1|c2FsdA==|aGFzaA== ssh-rsa AAAA").expect("has hosts").matches("example.com"));
+    }
+}
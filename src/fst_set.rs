@@ -0,0 +1,264 @@
+//! An immutable, compact representation of a [`DomainPatternSet`] as a finite state transducer
+//!
+//! Every pattern is encoded as a key, its parts from right to left, followed by the index of the pattern, so both common
+//! suffixes and common prefixes of patterns are only stored once. Matching walks the fst directly, so it can simply
+//! be loaded from (or mapped in from) a file.
+
+use std::fmt::{Display, Formatter};
+use fst::raw::{CompiledAddr, Fst, Node};
+use crate::{DomainPattern, DomainPatternPart, DomainPatternSet, DomainPatternWildcard, Matches};
+
+// a static label is TAG_STATIC, the label and then LABEL_END
+const TAG_STATIC: u8 = 0x01;
+const TAG_ONE: u8 = 0x02;
+const TAG_OPTIONAL: u8 = 0x03;
+const TAG_AT_LEAST_ONE: u8 = 0x04;
+const TAG_ANY: u8 = 0x05;
+const LABEL_END: u8 = 0x00;
+// followed by the big endian index of the pattern
+const TAG_END: u8 = 0xFF;
+
+#[derive(Debug)]
+pub enum FstError {
+    /// The pattern at this index in the set can't be encoded, because a label contains a NUL byte, is negated, or it has a glob, regex, `#` or `{a,b}` group
+    Unsupported(usize),
+    Fst(fst::Error),
+}
+
+impl Display for FstError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FstError::Unsupported(idx) => write!(f, "Pattern {} can't be encoded in an fst", idx),
+            FstError::Fst(err) => write!(f, "Invalid fst: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for FstError {}
+
+impl From<fst::Error> for FstError {
+    fn from(err: fst::Error) -> Self {
+        FstError::Fst(err)
+    }
+}
+
+fn wildcard_tag(wildcard: &DomainPatternWildcard) -> u8 {
+    match wildcard {
+        DomainPatternWildcard { multi: false, optional: false } => TAG_ONE,
+        DomainPatternWildcard { multi: false, optional: true } => TAG_OPTIONAL,
+        DomainPatternWildcard { multi: true, optional: false } => TAG_AT_LEAST_ONE,
+        DomainPatternWildcard { multi: true, optional: true } => TAG_ANY,
+    }
+}
+
+fn encode<const SPLITTER: char>(idx: usize, pattern: &DomainPattern<'_, SPLITTER>) -> Result<Vec<u8>, FstError> {
+    if pattern.negated {
+        return Err(FstError::Unsupported(idx));
+    }
+
+    let mut key = vec![];
+    for part in pattern.steps.iter().rev() {
+        match part {
+            DomainPatternPart::Static(label) => {
+                if label.as_bytes().contains(&LABEL_END) {
+                    return Err(FstError::Unsupported(idx));
+                }
+
+                key.push(TAG_STATIC);
+                key.extend_from_slice(label.as_bytes());
+                key.push(LABEL_END);
+            }
+
+            DomainPatternPart::Wildcard(wildcard) => key.push(wildcard_tag(wildcard)),
+            // names only matter for captures
+            DomainPatternPart::Named(_) => key.push(TAG_ONE),
+            DomainPatternPart::Glob(_) | DomainPatternPart::OneOf(_) | DomainPatternPart::Numeric => return Err(FstError::Unsupported(idx)),
+            #[cfg(feature = "regex")]
+            DomainPatternPart::Regex(_) => return Err(FstError::Unsupported(idx)),
+        }
+    }
+
+    key.push(TAG_END);
+    key.extend_from_slice(&(idx as u64).to_be_bytes());
+    Ok(key)
+}
+
+impl<const SPLITTER: char> DomainPatternSet<'_, SPLITTER> {
+    /// Compiles the set into an fst, the indices of the patterns stay the same
+    ///
+    /// The fst always matches case sensitively, a case insensitive set is compiled with its patterns lowercased,
+    /// so lowercase domains before matching them.
+    pub fn build_fst(&self) -> Result<FstPatternSet<Vec<u8>, SPLITTER>, FstError> {
+        let mut keys = self.iter()
+            .map(|(idx, pattern)| encode(idx, &self.indexed(pattern)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        keys.sort_unstable();
+
+        Ok(FstPatternSet {
+            fst: Fst::from_iter_set(keys)?,
+        })
+    }
+}
+
+// a position in the fst, multi if it was reached through a multi wildcard and may eat more labels while staying here
+type State = (CompiledAddr, bool);
+
+/// A pattern set compiled by [`DomainPatternSet::build_fst`], matching directly on the bytes of the fst
+#[derive(Clone)]
+pub struct FstPatternSet<D, const SPLITTER: char = '.'> {
+    fst: Fst<D>,
+}
+
+impl<D: AsRef<[u8]>, const SPLITTER: char> FstPatternSet<D, SPLITTER> {
+    /// Loads an fst previously created by [`DomainPatternSet::build_fst`]
+    pub fn new(data: D) -> Result<Self, FstError> {
+        Ok(FstPatternSet {
+            fst: Fst::new(data)?,
+        })
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        self.fst.as_bytes()
+    }
+
+    pub fn into_inner(self) -> D {
+        self.fst.into_inner()
+    }
+
+    /// Amount of patterns in the set
+    pub fn len(&self) -> usize {
+        self.fst.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fst.is_empty()
+    }
+
+    /// Returns true if any pattern in the set matches the given domain
+    pub fn contains_match(&self, domain: &str) -> bool {
+        self.walk(domain).iter().any(|(addr, _)| self.fst.node(*addr).find_input(TAG_END).is_some())
+    }
+
+    /// Returns the indices of all patterns matching the domain, in ascending order
+    pub fn matches_all(&self, domain: &str) -> Matches {
+        let mut indices = vec![];
+        for (addr, _) in self.walk(domain) {
+            let node = self.fst.node(addr);
+            if let Some(end) = node.find_input(TAG_END) {
+                self.collect_indices(self.fst.node(node.transition_addr(end)), 0, &mut indices);
+            }
+        }
+
+        indices.sort_unstable();
+        indices.dedup();
+        Matches::new(indices)
+    }
+
+    fn collect_indices(&self, node: Node<'_>, prefix: u64, indices: &mut Vec<usize>) {
+        if node.is_final() {
+            indices.push(prefix as usize);
+            return;
+        }
+
+        for transition in node.transitions() {
+            self.collect_indices(self.fst.node(transition.addr), prefix << 8 | transition.inp as u64, indices);
+        }
+    }
+
+    fn follow(&self, addr: CompiledAddr, input: &[u8]) -> Option<CompiledAddr> {
+        let mut addr = addr;
+        for byte in input {
+            let node = self.fst.node(addr);
+            addr = node.transition_addr(node.find_input(*byte)?);
+        }
+
+        Some(addr)
+    }
+
+    // adds every state reachable by skipping optional wildcards
+    fn follow_optional(&self, states: &mut Vec<State>) {
+        let mut i = 0;
+        while i < states.len() {
+            let node = self.fst.node(states[i].0);
+            for (tag, multi) in [(TAG_OPTIONAL, false), (TAG_ANY, true)] {
+                if let Some(transition) = node.find_input(tag) {
+                    states.push((node.transition_addr(transition), multi));
+                }
+            }
+
+            i += 1;
+        }
+    }
+
+    fn walk(&self, domain: &str) -> Vec<State> {
+        let mut active = vec![(self.fst.root().addr(), false)];
+        let mut next = vec![];
+        self.follow_optional(&mut active);
+
+        for label in domain.rsplit(SPLITTER) {
+            if label.is_empty() {
+                continue;
+            }
+
+            for (addr, multi) in &active {
+                if *multi {
+                    next.push((*addr, true));
+                }
+
+                let node = self.fst.node(*addr);
+                if let Some(child) = node.find_input(TAG_STATIC)
+                    .and_then(|transition| self.follow(node.transition_addr(transition), label.as_bytes()))
+                    .and_then(|addr| self.follow(addr, &[LABEL_END])) {
+                    next.push((child, false));
+                }
+
+                for (tag, multi) in [(TAG_ONE, false), (TAG_OPTIONAL, false), (TAG_AT_LEAST_ONE, true), (TAG_ANY, true)] {
+                    if let Some(transition) = node.find_input(tag) {
+                        next.push((node.transition_addr(transition), multi));
+                    }
+                }
+            }
+
+            self.follow_optional(&mut next);
+            next.sort_unstable();
+            next.dedup();
+
+            std::mem::swap(&mut active, &mut next);
+            next.truncate(0);
+
+            if active.is_empty() {
+                break;
+            }
+        }
+
+        active
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{DomainPattern, DomainPatternSet, FstPatternSet};
+
+    #[test]
+    pub fn test_agrees_with_set() {
+        let patterns = ["domain.tld", "*.domain.tld", "+.domain.tld", "**.domain.tld", "**+.domain.tld", "nice.**.nice", "+.nice.**", "x.*.*", "*.+", "domain.tld"];
+        let domains = ["", "tld", "domain.tld", "sub.domain.tld", "sub.sub.domain.tld", "nice", "nice.nice", "nice.nice.nice", "x", "x.x.x", "x.x.x.x"];
+
+        let mut set: DomainPatternSet = patterns.iter()
+            .map(|pattern| DomainPattern::parse(pattern).expect("failed to parse"))
+            .collect();
+        set.remove(1);
+
+        let fst = set.build_fst().expect("failed to build fst");
+        assert_eq!(fst.len(), patterns.len() - 1);
+
+        let loaded: FstPatternSet<&[u8]> = FstPatternSet::new(fst.as_bytes()).expect("failed to load fst");
+        for domain in domains {
+            assert_eq!(set.contains_match(domain), loaded.contains_match(domain), "{:?}", domain);
+            assert_eq!(set.matches_all(domain).collect::<Vec<_>>(), loaded.matches_all(domain).collect::<Vec<_>>(), "{:?}", domain);
+        }
+
+        assert_eq!(loaded.matches_all("domain.tld").collect::<Vec<_>>(), vec![0, 3, 8, 9]);
+    }
+}
@@ -0,0 +1,432 @@
+//! Matching a single label against a glob like `api-*`, `us-east-?` or `node[0-9][0-9]`
+
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, VecDeque};
+use std::ops::RangeInclusive;
+
+#[derive(Copy, Clone)]
+enum Token<'g> {
+    // any amount of characters
+    Star,
+    // exactly one character
+    Any,
+    // the part between the brackets, without the leading `!` or `^` if negated
+    Class { negated: bool, body: &'g str },
+    Char(char),
+}
+
+impl Token<'_> {
+    fn matches(&self, c: char, case_insensitive: bool) -> bool {
+        match self {
+            Token::Star | Token::Any => true,
+            Token::Class { negated, body } => {
+                let mut hit = false;
+                for_each_range(body, |from, to| {
+                    let within = |c: char| from <= c && c <= to;
+                    hit |= within(c) || (case_insensitive && (within(c.to_ascii_lowercase()) || within(c.to_ascii_uppercase())));
+                });
+                hit != *negated
+            }
+            Token::Char(expected) => *expected == c || (case_insensitive && expected.eq_ignore_ascii_case(&c)),
+        }
+    }
+}
+
+// calls f with every range in the body of a class, single characters are a range of one,
+// returns false if the class is malformed
+fn for_each_range(body: &str, mut f: impl FnMut(char, char)) -> bool {
+    let mut chars = body.chars().peekable();
+    let mut empty = true;
+
+    while let Some(c) = chars.next() {
+        let from = match c {
+            '\\' => match chars.next() {
+                Some(escaped) => escaped,
+                None => return false,
+            },
+            '[' => return false,
+            c => c,
+        };
+
+        let to = if chars.peek() == Some(&'-') && chars.clone().nth(1).is_some() {
+            chars.next();
+            match chars.next() {
+                Some('\\') => match chars.next() {
+                    Some(escaped) => escaped,
+                    None => return false,
+                },
+                Some(to) => to,
+                None => unreachable!("checked there's a character after the -"),
+            }
+        } else {
+            from
+        };
+
+        if to < from {
+            return false;
+        }
+
+        f(from, to);
+        empty = false;
+    }
+
+    !empty
+}
+
+// the token at the given byte offset in the glob, and the offset of the token after it
+fn token(glob: &str, pos: usize) -> Option<(Token<'_>, usize)> {
+    let c = glob[pos..].chars().next()?;
+    let next = pos + c.len_utf8();
+    Some(match c {
+        '*' => (Token::Star, next),
+        '?' => (Token::Any, next),
+        '\\' => match glob[next..].chars().next() {
+            Some(escaped) => (Token::Char(escaped), next + escaped.len_utf8()),
+            None => (Token::Char('\\'), next),
+        },
+        '[' => match class_end(glob, next) {
+            Some(end) => {
+                let body = &glob[next..end];
+                let (negated, body) = match body.strip_prefix(['!', '^']) {
+                    Some(body) => (true, body),
+                    None => (false, body),
+                };
+
+                (Token::Class { negated, body }, end + 1)
+            }
+
+            // malformed globs don't get past the parser
+            None => (Token::Char('['), next),
+        },
+        c => (Token::Char(c), next),
+    })
+}
+
+// offset of the `]` closing the class starting at the given offset
+fn class_end(glob: &str, start: usize) -> Option<usize> {
+    let mut chars = glob[start..].char_indices();
+    while let Some((idx, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            ']' => return Some(start + idx),
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// Returns false if the glob has an unclosed, empty or otherwise malformed character class, or a stray `]`
+pub(crate) fn is_valid(glob: &str) -> bool {
+    let mut pos = 0;
+    while let Some((token, next)) = token(glob, pos) {
+        match token {
+            Token::Class { body, .. } if !for_each_range(body, |_, _| {}) => return false,
+            Token::Char('[' | ']') if !glob[pos..].starts_with('\\') => return false,
+            _ => {}
+        }
+
+        pos = next;
+    }
+
+    true
+}
+
+/// Returns true if the glob matches the whole label
+pub(crate) fn matches(glob: &str, label: &str, case_insensitive: bool) -> bool {
+    let (mut g, mut l) = (0, 0);
+    // where to continue in the glob and label when the last star has to take another character
+    let mut star: Option<(usize, usize)> = None;
+
+    while let Some(c) = label[l..].chars().next() {
+        match token(glob, g) {
+            Some((Token::Star, next)) => {
+                star = Some((next, l));
+                g = next;
+                continue;
+            }
+
+            Some((token, next)) if token.matches(c, case_insensitive) => {
+                g = next;
+                l += c.len_utf8();
+                continue;
+            }
+
+            _ => {}
+        }
+
+        let Some((star_g, star_l)) = star else {
+            return false;
+        };
+
+        let taken = label[star_l..].chars().next().expect("star never goes past the label").len_utf8();
+        star = Some((star_g, star_l + taken));
+        g = star_g;
+        l = star_l + taken;
+    }
+
+    while let Some((Token::Star, next)) = token(glob, g) {
+        g = next;
+    }
+
+    g == glob.len()
+}
+
+/// A single character of a label, or any amount of them if it repeats
+#[derive(Copy, Clone)]
+pub(crate) struct Atom<'g> {
+    token: Token<'g>,
+    repeats: bool,
+}
+
+/// The glob as atoms, a star is any character repeated
+pub(crate) fn atoms(glob: &str) -> Vec<Atom<'_>> {
+    let mut atoms = vec![];
+    let mut pos = 0;
+    while let Some((token, next)) = token(glob, pos) {
+        atoms.push(match token {
+            Token::Star => Atom { token: Token::Any, repeats: true },
+            token => Atom { token, repeats: false },
+        });
+
+        pos = next;
+    }
+
+    atoms
+}
+
+/// Atoms matching only the label itself
+pub(crate) fn literal(label: &str) -> Vec<Atom<'static>> {
+    label.chars().map(|c| Atom { token: Token::Char(c), repeats: false }).collect()
+}
+
+/// Atoms matching any label
+pub(crate) fn any_label() -> Vec<Atom<'static>> {
+    vec![Atom { token: Token::Any, repeats: false }, Atom { token: Token::Any, repeats: true }]
+}
+
+/// Atoms matching a label of only digits, like `#`
+pub(crate) fn digits() -> Vec<Atom<'static>> {
+    let digit = Token::Class { negated: false, body: "0-9" };
+    vec![Atom { token: digit, repeats: false }, Atom { token: digit, repeats: true }]
+}
+
+// characters worth trying to find one both tokens match, the set a token matches is made of ranges,
+// and if two sets have a character in common, the start of one of their ranges is, so this misses none
+fn candidates(token: &Token, out: &mut Vec<char>) {
+    match token {
+        Token::Star | Token::Any => {}
+        Token::Class { body, .. } => {
+            for_each_range(body, |from, to| {
+                out.push(from);
+                out.extend(char::from_u32(to as u32 + 1));
+            });
+        }
+        Token::Char(c) => out.push(*c),
+    }
+}
+
+// the positions reachable from the given one by skipping repeating atoms
+fn skip(atoms: &[Atom], mut pos: usize) -> RangeInclusive<usize> {
+    let start = pos;
+    while atoms.get(pos).is_some_and(|atom| atom.repeats) {
+        pos += 1;
+    }
+
+    start..=pos
+}
+
+/// The shortest label matched by both, or None if there's no such label
+pub(crate) fn intersection(a: &[Atom], b: &[Atom]) -> Option<String> {
+    // how every position was reached, and with what character
+    let mut from = HashMap::new();
+    let mut queue = VecDeque::new();
+    for i in skip(a, 0) {
+        for j in skip(b, 0) {
+            from.insert((i, j), None);
+            queue.push_back((i, j));
+        }
+    }
+
+    while let Some((i, j)) = queue.pop_front() {
+        if i == a.len() && j == b.len() {
+            let mut label = vec![];
+            let mut pos = (i, j);
+            while let Some(Some((prev, c))) = from.get(&pos) {
+                label.push(*c);
+                pos = *prev;
+            }
+
+            return Some(label.into_iter().rev().collect());
+        }
+
+        let (Some(left), Some(right)) = (a.get(i), b.get(j)) else {
+            continue;
+        };
+
+        let mut chars = vec!['a', '0', '-'];
+        candidates(&left.token, &mut chars);
+        candidates(&right.token, &mut chars);
+        chars.push('\0');
+
+        let Some(c) = chars.into_iter().find(|c| left.token.matches(*c, false) && right.token.matches(*c, false)) else {
+            continue;
+        };
+
+        let next_i = if left.repeats { i } else { i + 1 };
+        let next_j = if right.repeats { j } else { j + 1 };
+        for next in skip(a, next_i).flat_map(|i| skip(b, next_j).map(move |j| (i, j))) {
+            if let Entry::Vacant(entry) = from.entry(next) {
+                entry.insert(Some(((i, j), c)));
+                queue.push_back(next);
+            }
+        }
+    }
+
+    None
+}
+
+/// Appends a regex matching the same labels as the glob, characters in `escape` are escaped,
+/// and none of the characters the regex takes are the splitter
+pub(crate) fn to_regex(glob: &str, splitter: &str, escape: impl Fn(char, &mut String), out: &mut String) {
+    let mut pos = 0;
+    while let Some((token, next)) = token(glob, pos) {
+        match token {
+            Token::Star => out.push_str(&format!("[^{}]*", splitter)),
+            Token::Any => out.push_str(&format!("[^{}]", splitter)),
+            Token::Class { negated, body } => {
+                out.push_str(if negated { "[^" } else { "[" });
+                for_each_range(body, |from, to| {
+                    escape(from, out);
+                    if from != to {
+                        out.push('-');
+                        escape(to, out);
+                    }
+                });
+
+                if negated {
+                    out.push_str(splitter);
+                }
+
+                out.push(']');
+            }
+            Token::Char(c) => escape(c, out),
+        }
+
+        pos = next;
+    }
+}
+
+/// A random label the glob matches, characters come from the alphabet where the glob allows it,
+/// and a star takes up to `max_len` of them
+#[cfg(feature = "rand")]
+pub(crate) fn random(glob: &str, rng: &mut impl rand::Rng, alphabet: &[char], max_len: usize) -> Option<String> {
+    use rand::seq::IndexedRandom;
+
+    let mut label = String::new();
+    let mut pos = 0;
+    while let Some((token, next)) = token(glob, pos) {
+        let take = match token {
+            Token::Star => rng.random_range(0..=max_len),
+            _ => 1,
+        };
+
+        let fits: Vec<char> = alphabet.iter().copied().filter(|c| token.matches(*c, false)).collect();
+        for _ in 0..take {
+            let c = match fits.choose(rng) {
+                Some(c) => *c,
+                None => {
+                    let mut chars = vec![];
+                    candidates(&token, &mut chars);
+                    chars.into_iter().find(|c| token.matches(*c, false))?
+                }
+            };
+
+            label.push(c);
+        }
+
+        pos = next;
+    }
+
+    matches(glob, &label, false).then_some(label)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::glob::{any_label, atoms, digits, intersection, is_valid, literal, matches};
+
+    #[test]
+    pub fn test_glob() {
+        assert!(matches("api-*", "api-", false));
+        assert!(matches("api-*", "api-v1", false));
+        assert!(!matches("api-*", "api", false));
+        assert!(!matches("api-*", "www-v1", false));
+        assert!(matches("*-staging", "web-staging", false));
+        assert!(!matches("*-staging", "web-staging-2", false));
+        assert!(matches("a*b*c", "abc", false));
+        assert!(matches("a*b*c", "axxbyybc", false));
+        assert!(!matches("a*b*c", "axxbyyb", false));
+        assert!(matches("*x*", "x", false));
+        assert!(matches("ü*ß", "üöß", false));
+
+        assert!(matches("us-east-?", "us-east-1", false));
+        assert!(!matches("us-east-?", "us-east-", false));
+        assert!(!matches("us-east-?", "us-east-12", false));
+        assert!(matches("?", "ö", false));
+        assert!(matches("?*?", "ab", false));
+        assert!(!matches("?*?", "a", false));
+        assert!(matches("*-?", "a-b-c", false));
+    }
+
+    #[test]
+    pub fn test_classes() {
+        assert!(matches("node[0-9][0-9]", "node42", false));
+        assert!(!matches("node[0-9][0-9]", "node4", false));
+        assert!(!matches("node[0-9][0-9]", "nodexx", false));
+        assert!(matches("[a-cx]", "x", false));
+        assert!(!matches("[a-cx]", "d", false));
+        assert!(matches("[!a-c]", "d", false));
+        assert!(matches("[^a-c]", "d", false));
+        assert!(!matches("[!a-c]", "b", false));
+        assert!(matches("[a-]", "-", false));
+        assert!(matches("[\\]]", "]", false));
+        assert!(matches("[*?]", "*", false));
+        assert!(!matches("[*?]", "a", false));
+        assert!(matches("web[0-9]*", "web1-staging", false));
+        assert!(matches("a\\*\\[*", "a*[b", false));
+        assert!(!matches("a\\*", "ab", false));
+        assert!(matches("Api-[a-c]", "aPI-B", true));
+        assert!(!matches("Api-[a-c]", "aPI-B", false));
+
+        for valid in ["a", "node[0-9]", "[!a]", "[a-]", "[\\]]", "[\\-]", "\\]", "\\["] {
+            assert!(is_valid(valid), "{:?} should be valid", valid);
+        }
+
+        for invalid in ["[", "a]", "[]", "[!]", "[z-a]", "[a", "[a[b]", "[\\"] {
+            assert!(!is_valid(invalid), "{:?} should be invalid", invalid);
+        }
+    }
+
+    #[test]
+    pub fn test_intersection() {
+        let both = |a: &str, b: &str| intersection(&atoms(a), &atoms(b));
+        assert_eq!(both("api-*", "*-v1"), Some("api-v1".to_string()));
+        assert_eq!(both("a*", "*b"), Some("ab".to_string()));
+        assert_eq!(both("node[0-9]", "node[5-7]"), Some("node5".to_string()));
+        assert_eq!(both("[!a-z]", "[a-c]"), None);
+        assert_eq!(both("[!a-z]", "[!0-9]"), Some("-".to_string()));
+        assert_eq!(both("??", "a*b*c"), None);
+        assert_eq!(both("api-*", "www-*"), None);
+
+        let label = both("[!a-z0-9-]x*", "*[!\\0-z]?").expect("globs have a label in common");
+        assert!(matches("[!a-z0-9-]x*", &label, false) && matches("*[!\\0-z]?", &label, false), "{:?} isn't matched by both", label);
+
+        assert_eq!(intersection(&digits(), &atoms("v*")), None);
+        assert_eq!(intersection(&digits(), &atoms("*7")), Some("7".to_string()));
+        assert_eq!(intersection(&any_label(), &atoms("*")), Some("a".to_string()));
+        assert_eq!(intersection(&literal("api"), &atoms("a*")), Some("api".to_string()));
+        assert_eq!(intersection(&literal("api"), &digits()), None);
+    }
+}
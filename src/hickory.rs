@@ -0,0 +1,101 @@
+//! Matching the names of hickory, like the query names a DNS server answers, label by label without formatting them
+
+use std::borrow::Cow;
+use std::fmt::{Display, Formatter};
+use hickory_proto::rr::Name;
+use crate::{DomainPattern, DomainPatternPart, DomainPatternWildcard, MatchOptions, MatchScratch, StepVec};
+
+/// A label of a [`Name`] isn't UTF-8, so it can't be a label of a pattern, with the index of the label
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct NonUtf8Label(pub usize);
+
+impl Display for NonUtf8Label {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Label {} of the name isn't UTF-8", self.0)
+    }
+}
+
+impl std::error::Error for NonUtf8Label {}
+
+impl DomainPattern<'_> {
+    /// Whether the pattern matches the name, ignoring ASCII case like DNS does, whether the name is fully qualified or not
+    ///
+    /// The labels are compared as they are in the name, so a query for `*.example.com` is a literal `*` label, and names
+    /// with a label that isn't UTF-8 never match.
+    pub fn matches_name(&self, name: &Name) -> bool {
+        if name.iter().any(|label| std::str::from_utf8(label).is_err()) {
+            return false;
+        }
+
+        let labels = name.iter().filter_map(|label| std::str::from_utf8(label).ok());
+        let options = MatchOptions::new().case_insensitive(true);
+        match self.run_labels(labels, &options, &mut (), &mut MatchScratch::new()) {
+            Ok(matched) => matched,
+            Err(never) => match never {},
+        }
+    }
+}
+
+impl TryFrom<&Name> for DomainPattern<'static> {
+    type Error = NonUtf8Label;
+
+    /// The pattern for the name, in lowercase, where a leftmost `*` is a wildcard for one or more labels, like
+    /// `*.example.com` in a zone is `**+.example.com`, and every other label is static
+    fn try_from(name: &Name) -> Result<Self, Self::Error> {
+        let mut steps: StepVec<'static> = Default::default();
+        for (idx, label) in name.iter().enumerate() {
+            let label = std::str::from_utf8(label).map_err(|_| NonUtf8Label(idx))?;
+            steps.push(match label {
+                "*" if idx == 0 => DomainPatternPart::Wildcard(DomainPatternWildcard::new(true, false)),
+                label => DomainPatternPart::Static(Cow::Owned(label.to_ascii_lowercase())),
+            });
+        }
+
+        Ok(DomainPattern {
+            steps,
+            negated: false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hickory_proto::rr::Name;
+    use crate::{DomainPattern, NonUtf8Label};
+
+    fn name(name: &str) -> Name {
+        Name::from_ascii(name).expect("valid name")
+    }
+
+    #[test]
+    pub fn test_matches_name() {
+        let pattern = DomainPattern::parse("**+.example.com").unwrap();
+        assert!(pattern.matches_name(&name("www.Example.COM.")));
+        assert!(pattern.matches_name(&name("a.b.example.com")));
+        assert!(!pattern.matches_name(&name("example.com.")));
+        assert!(!pattern.matches_name(&name("www.example.org.")));
+        assert!(!pattern.matches_name(&Name::from_labels([&b"\xff"[..], b"example", b"com"]).unwrap()));
+
+        assert!(DomainPattern::parse("**").unwrap().matches_name(&Name::root()));
+        assert!(!DomainPattern::parse("+").unwrap().matches_name(&Name::root()));
+
+        // a label with a dot in it stays a single label
+        let dotted = Name::from_labels([&b"a.b"[..], b"com"]).unwrap();
+        let pattern = DomainPattern::try_from(&dotted).unwrap();
+        assert_eq!(pattern.len(), 2);
+        assert!(pattern.matches_name(&dotted));
+        assert!(!pattern.matches_name(&name("a.b.com")));
+    }
+
+    #[test]
+    pub fn test_try_from_name() {
+        let pattern = DomainPattern::try_from(&name("*.Example.com.")).unwrap();
+        assert_eq!(pattern.to_string(), "**+.example.com");
+        assert!(pattern.matches("a.b.example.com"));
+        assert!(!pattern.matches("example.com"));
+
+        assert_eq!(DomainPattern::try_from(&name("www.*.com")).unwrap().to_string(), "www.\\*.com");
+        assert_eq!(DomainPattern::try_from(&Name::root()).unwrap().len(), 0);
+        assert_eq!(DomainPattern::try_from(&Name::from_labels([&b"a"[..], b"\xff"]).unwrap()), Err(NonUtf8Label(1)));
+    }
+}
@@ -0,0 +1,167 @@
+//! Turning `Host` header like inputs into domains
+
+use std::borrow::Cow;
+use std::net::Ipv6Addr;
+use crate::{DomainPattern, MatchOptions};
+
+/// Result of [`DomainPattern::match_host`]
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum HostMatch {
+    Matched,
+    NotMatched,
+    /// The host is an IPv6 literal like `[::1]:8080`, which isn't a domain so no pattern can match it
+    NotADomain(Ipv6Addr),
+}
+
+impl HostMatch {
+    pub fn is_match(&self) -> bool {
+        matches!(self, HostMatch::Matched)
+    }
+}
+
+/// The address if the host is an IPv6 literal, either bare like `::1` or in brackets like `[::1]` or `[::1]:8080`
+pub(crate) fn ipv6_literal(host: &str) -> Option<Ipv6Addr> {
+    if let Some(rest) = host.strip_prefix('[') {
+        let (address, port) = rest.split_once(']')?;
+        return match strip_port(port).is_empty() {
+            true => address.parse().ok(),
+            false => None,
+        };
+    }
+
+    host.parse().ok()
+}
+
+/// The host without a `:port` suffix, a lone trailing `:` is stripped too
+///
+/// Anything with more than one `:` is left alone, since that's not a domain with a port.
+pub(crate) fn strip_port(host: &str) -> &str {
+    match host.split_once(':') {
+        Some((domain, port)) if port.bytes().all(|b| b.is_ascii_digit()) => domain,
+        _ => host,
+    }
+}
+
+// the `%XX` escapes decoded, None if one is malformed or the result isn't UTF-8
+fn percent_decode(host: &str) -> Option<Cow<'_, str>> {
+    if !host.contains('%') {
+        return Some(Cow::Borrowed(host));
+    }
+
+    let bytes = host.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut idx = 0;
+    while idx < bytes.len() {
+        if bytes[idx] != b'%' {
+            decoded.push(bytes[idx]);
+            idx += 1;
+            continue;
+        }
+
+        let hex = host.get(idx + 1..idx + 3)?;
+        decoded.push(u8::from_str_radix(hex, 16).ok().filter(|_| hex.bytes().all(|b| b.is_ascii_hexdigit()))?);
+        idx += 3;
+    }
+
+    String::from_utf8(decoded).ok().map(Cow::Owned)
+}
+
+/// The domain of a `Host` header as sent by anyone, trimmed, decoded, without its port or trailing dot, and lowercased,
+/// None if it can't be a domain
+pub(crate) fn normalize_http_host<const SPLITTER: char>(host: &str) -> Option<Cow<'_, str>> {
+    let host = percent_decode(host.trim())?;
+    if ipv6_literal(&host).is_some() || host.chars().any(|c| c.is_whitespace() || c.is_control() || c == '/' || c == '@') {
+        return None;
+    }
+
+    let domain = strip_port(&host);
+    let domain = domain.strip_suffix(SPLITTER).unwrap_or(domain);
+    if domain.is_empty() {
+        return None;
+    }
+
+    Some(match (&host, domain.len() == host.len(), domain.bytes().any(|b| b.is_ascii_uppercase())) {
+        (Cow::Borrowed(host), true, false) => Cow::Borrowed(*host),
+        _ => Cow::Owned(domain.to_ascii_lowercase()),
+    })
+}
+
+impl<const SPLITTER: char> DomainPattern<'_, SPLITTER> {
+    /// Matches a `Host` header the way it arrives, for filtering requests without cleaning them up first
+    ///
+    /// Surrounding whitespace, `%XX` escapes, a port and a trailing dot are taken care of, and case is ignored. Headers
+    /// that can't be a domain, like IPv6 literals, malformed escapes, or anything with whitespace, `/` or `@` inside,
+    /// never match.
+    pub fn matches_http_host(&self, host: &str) -> bool {
+        match normalize_http_host::<SPLITTER>(host) {
+            Some(domain) => self.matches_with(&domain, &MatchOptions::new().case_insensitive(true)),
+            None => false,
+        }
+    }
+
+    /// Matches a `Host` header like [`DomainPattern::matches_host`], but tells IPv6 literals apart from domains that didn't match
+    pub fn match_host(&self, host: &str) -> HostMatch {
+        if let Some(address) = ipv6_literal(host) {
+            return HostMatch::NotADomain(address);
+        }
+
+        match self.matches_host(host) {
+            true => HostMatch::Matched,
+            false => HostMatch::NotMatched,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv6Addr;
+    use crate::host::{ipv6_literal, normalize_http_host, strip_port};
+    use crate::{DomainPattern, HostMatch};
+
+    #[test]
+    pub fn test_strip_port() {
+        assert_eq!(strip_port("example.com"), "example.com");
+        assert_eq!(strip_port("example.com:8080"), "example.com");
+        assert_eq!(strip_port("example.com:"), "example.com");
+        assert_eq!(strip_port("example.com.:443"), "example.com.");
+        assert_eq!(strip_port("example.com:http"), "example.com:http");
+        assert_eq!(strip_port("::1"), "::1");
+        assert_eq!(strip_port("fe80::1:80"), "fe80::1:80");
+    }
+
+    #[test]
+    pub fn test_ipv6_literal() {
+        assert_eq!(ipv6_literal("::1"), Some(Ipv6Addr::LOCALHOST));
+        assert_eq!(ipv6_literal("[::1]"), Some(Ipv6Addr::LOCALHOST));
+        assert_eq!(ipv6_literal("[::1]:8080"), Some(Ipv6Addr::LOCALHOST));
+        assert_eq!(ipv6_literal("[::1]:"), Some(Ipv6Addr::LOCALHOST));
+        assert_eq!(ipv6_literal("[::1]x"), None);
+        assert_eq!(ipv6_literal("[::1]:http"), None);
+        assert_eq!(ipv6_literal("[example.com]"), None);
+        assert_eq!(ipv6_literal("example.com:80"), None);
+        assert_eq!(ipv6_literal("127.0.0.1"), None);
+
+        let pattern: DomainPattern = "*".try_into().expect("failed to parse");
+        assert!(pattern.matches("[::1]:8080"));
+        assert!(!pattern.matches_host("[::1]:8080"));
+        assert_eq!(pattern.match_host("[::1]:8080"), HostMatch::NotADomain(Ipv6Addr::LOCALHOST));
+        assert_eq!(pattern.match_host("localhost:8080"), HostMatch::Matched);
+        assert_eq!(pattern.match_host("a.localhost"), HostMatch::NotMatched);
+        assert!(pattern.match_host("localhost").is_match());
+    }
+
+    #[test]
+    pub fn test_matches_http_host() {
+        for (host, normalized) in [("example.com", Some("example.com")), ("  Example.COM.:8080\t", Some("example.com")), ("ex%61mple.com", Some("example.com")),
+            ("example%2Ecom%3A443", Some("example.com")), ("%", None), ("%zz.com", None), ("%ff.com", None), ("exa mple.com", None),
+            ("evil.com%2Fgood.com", None), ("user@example.com", None), ("[::1]:80", None), ("", None), (":80", None), ("a%0a.com", None)] {
+            assert_eq!(normalize_http_host::<'.'>(host).as_deref(), normalized, "{:?}", host);
+        }
+
+        let pattern: DomainPattern = "**+.Example.com".try_into().expect("failed to parse");
+        assert!(pattern.matches_http_host(" WWW.example.com.:443 "));
+        assert!(pattern.matches_http_host("www%2Eexample.com"));
+        assert!(!pattern.matches_http_host("example.com"));
+        assert!(!pattern.matches_http_host("www.example.com/admin"));
+    }
+}
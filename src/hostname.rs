@@ -0,0 +1,113 @@
+//! Checking domains against the limits of RFC 1035, so matching can double as input sanitation
+
+use std::fmt::{Display, Formatter};
+
+/// Why a domain isn't a valid hostname, see [`validate_hostname`]
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum InvalidHostname {
+    /// The domain has no labels at all, like `""` or `.`
+    Empty,
+    /// The label at this index is empty, like the second one in `a..b`
+    EmptyLabel(usize),
+    /// The label at this index is longer than 63 octets
+    LabelTooLong(usize),
+    /// The whole name is longer than 255 octets on the wire
+    NameTooLong,
+    /// A character other than a letter, digit, `-` or `_` at this byte offset
+    InvalidCharacter(usize),
+    /// The label at this index starts or ends with a `-`
+    HyphenAtEdge(usize),
+}
+
+impl Display for InvalidHostname {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InvalidHostname::Empty => write!(f, "Hostname is empty"),
+            InvalidHostname::EmptyLabel(label) => write!(f, "Label {} of hostname is empty", label),
+            InvalidHostname::LabelTooLong(label) => write!(f, "Label {} of hostname is longer than {} octets", label, MAX_LABEL),
+            InvalidHostname::NameTooLong => write!(f, "Hostname is longer than {} octets", MAX_NAME),
+            InvalidHostname::InvalidCharacter(position) => write!(f, "Invalid character at position {} in hostname", position),
+            InvalidHostname::HyphenAtEdge(label) => write!(f, "Label {} of hostname starts or ends with a hyphen", label),
+        }
+    }
+}
+
+impl std::error::Error for InvalidHostname {}
+
+const MAX_LABEL: usize = 63;
+const MAX_NAME: usize = 255;
+
+/// Checks the domain is a hostname DNS can carry: labels of at most 63 octets, at most 255 octets in total,
+/// and only letters, digits, `-` and `_`, with no label starting or ending with a `-`
+///
+/// A single trailing dot is allowed. Internationalized labels have to be converted to their A-label first,
+/// [`MatchOptions::validate_input`](crate::MatchOptions::validate_input) does that when matching with `idna`.
+pub fn validate_hostname(domain: &str) -> Result<(), InvalidHostname> {
+    validate::<'.'>(domain)
+}
+
+pub(crate) fn validate<const SPLITTER: char>(domain: &str) -> Result<(), InvalidHostname> {
+    let name = domain.strip_suffix(SPLITTER).unwrap_or(domain);
+    if name.is_empty() {
+        return Err(InvalidHostname::Empty);
+    }
+
+    // every label is prefixed by its length, and the name ends with the empty root label
+    let mut wire = 1;
+    let mut offset = 0;
+    for (idx, label) in name.split(SPLITTER).enumerate() {
+        if label.is_empty() {
+            return Err(InvalidHostname::EmptyLabel(idx));
+        }
+
+        if label.len() > MAX_LABEL {
+            return Err(InvalidHostname::LabelTooLong(idx));
+        }
+
+        if let Some(position) = label.bytes().position(|b| !b.is_ascii_alphanumeric() && b != b'-' && b != b'_') {
+            return Err(InvalidHostname::InvalidCharacter(offset + position));
+        }
+
+        if label.starts_with('-') || label.ends_with('-') {
+            return Err(InvalidHostname::HyphenAtEdge(idx));
+        }
+
+        wire += label.len() + 1;
+        offset += label.len() + SPLITTER.len_utf8();
+    }
+
+    match wire > MAX_NAME {
+        true => Err(InvalidHostname::NameTooLong),
+        false => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{validate_hostname, InvalidHostname};
+
+    #[test]
+    pub fn test_validate_hostname() {
+        assert_eq!(validate_hostname("www.example.com"), Ok(()));
+        assert_eq!(validate_hostname("www.example.com."), Ok(()));
+        assert_eq!(validate_hostname("_sip._tcp.example.com"), Ok(()));
+        assert_eq!(validate_hostname("xn--bcher-kva.example"), Ok(()));
+        assert_eq!(validate_hostname(""), Err(InvalidHostname::Empty));
+        assert_eq!(validate_hostname("."), Err(InvalidHostname::Empty));
+        assert_eq!(validate_hostname("a..b"), Err(InvalidHostname::EmptyLabel(1)));
+        assert_eq!(validate_hostname("a.b.."), Err(InvalidHostname::EmptyLabel(2)));
+        assert_eq!(validate_hostname("a b.com"), Err(InvalidHostname::InvalidCharacter(1)));
+        assert_eq!(validate_hostname("www.bü.com"), Err(InvalidHostname::InvalidCharacter(5)));
+        assert_eq!(validate_hostname("www.-a.com"), Err(InvalidHostname::HyphenAtEdge(1)));
+        assert_eq!(validate_hostname("a-.com"), Err(InvalidHostname::HyphenAtEdge(0)));
+
+        let label = "a".repeat(63);
+        assert_eq!(validate_hostname(&format!("{}.com", label)), Ok(()));
+        assert_eq!(validate_hostname(&format!("b{}.com", label)), Err(InvalidHostname::LabelTooLong(0)));
+
+        // 3 labels of 63 octets and one of 61, each with a length octet, and the root label make exactly 255
+        let name = format!("{0}.{0}.{0}.{0}", label);
+        assert_eq!(validate_hostname(&name[..name.len() - 2]), Ok(()));
+        assert_eq!(validate_hostname(&format!("{}.a", name)), Err(InvalidHostname::NameTooLong));
+    }
+}
@@ -0,0 +1,73 @@
+//! Labels matched by a regex, like `~re:^v\d+$~`
+
+use std::fmt::{Debug, Formatter};
+use std::hash::{Hash, Hasher};
+use regex::Regex;
+
+/// A compiled regex matching a single label, compared by its source
+///
+/// The regex isn't anchored, so `~re:v\d~` matches `dev1` too, use `^` and `$` to match the whole label.
+#[derive(Clone)]
+pub struct LabelRegex(Regex);
+
+impl LabelRegex {
+    pub fn new(regex: &str) -> Result<Self, regex::Error> {
+        Regex::new(regex).map(LabelRegex)
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+
+    pub(crate) fn is_match(&self, label: &str) -> bool {
+        self.0.is_match(label)
+    }
+}
+
+impl PartialEq for LabelRegex {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for LabelRegex {}
+
+impl Hash for LabelRegex {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state);
+    }
+}
+
+impl Debug for LabelRegex {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("LabelRegex").field(&self.as_str()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{DomainPattern, DomainPatternPart, DomainPatternSet, LabelRegex};
+
+    #[test]
+    pub fn test_regex() {
+        let pattern: DomainPattern = "~re:^v\\d+$~.api.example.com".try_into().expect("failed to parse");
+        assert_eq!(pattern.steps[0], DomainPatternPart::Regex(LabelRegex::new("^v\\d+$").expect("valid regex")));
+        assert!(pattern.matches("v1.api.example.com"));
+        assert!(pattern.matches("v42.api.example.com"));
+        assert!(!pattern.matches("vx.api.example.com"));
+        assert!(!pattern.matches("api.example.com"));
+
+        // splitters inside the regex don't split the pattern
+        let pattern: DomainPattern = "~re:^a.c$~.example.com".try_into().expect("failed to parse");
+        assert_eq!(pattern.steps.len(), 3);
+        assert!(pattern.matches("abc.example.com"));
+
+        let set: DomainPatternSet = [pattern].into_iter().collect();
+        assert!(set.contains_match("abc.example.com"));
+        assert!(!set.contains_match("ab.example.com"));
+
+        for invalid in ["~re:(~.tld", "~re:a~b.tld", "~re:a.tld"] {
+            assert!(DomainPattern::<'_, '.'>::parse(invalid).is_err(), "{:?} should not parse", invalid);
+        }
+    }
+}
@@ -10,18 +10,173 @@
 //! `**.domain.tld`|✅|✅|✅
 //! `**+.domain.tld`|🅾️|✅|✅
 //!
+//! A pattern starting with `!`, like `!internal.domain.tld`, is an exception, see [`DomainPattern::is_negated`].
+//!
+//! A label can also be named, `{tenant}.domain.tld` matches like `+.domain.tld`, but what it matched can be retrieved
+//! by name through [`DomainPattern::captures`], or used to rewrite the domain with a [`DomainRewriter`].
+//!
+//! Within a label `*` matches any amount of characters, so `api-*.domain.tld` matches `api-v1.domain.tld`,
+//! and `?` matches a single character, so `us-east-?.domain.tld` matches `us-east-1.domain.tld`.
+//! Character classes like `node[0-9][0-9].domain.tld` work too, `[!0-9]` matches anything but a digit,
+//! and a `\` in a class escapes the next character.
+//!
+//! `{api,www}.domain.tld` matches both `api.domain.tld` and `www.domain.tld`.
+//!
+//! `#` matches a label of only digits, so `#.#.#.#.in-addr.arpa` matches reverse DNS names of IPv4 addresses, and
+//! [`DomainPattern::from_ip_prefix`] builds the pattern for the reverse names of a prefix like `10.0.0.0/8`.
+//!
+//! Any of these characters can be escaped with a `\`, so `tag\+1.domain.tld` matches the label `tag+1`, and `\!` at
+//! the start of a pattern is a literal `!`.
+//!
+//! The amount of labels a wildcard takes can be bounded, `*{2,4}.domain.tld` takes 2 to 4 labels, `+{2}` exactly 2,
+//! and `*{2,}` at least 2. Both `*` and `+` work here, the bounds decide how many labels are taken.
+//!
 //! # Implementation notes
 //!
-//! There's some form of algorithmic blow up when doing `*.*.*.*.*.*`, this could be worked out in future versions, TODO etc
+//! There's some form of algorithmic blow up when doing `*.*.*.*.*.*`, [`DomainPattern::compile`] compiles a pattern into
+//! a DFA that matches in a single pass over the labels instead. Patterns of up to 127 parts keep the paths they're on
+//! in a bitset while matching, so even without compiling there's no sorting or allocating going on, and walk the domain
+//! from the right when the pattern is anchored at the TLD, see [`Direction`].
+//!
+//! When matching against a lot of patterns, use a [`DomainPatternSet`] instead of looping over them,
+//! it compiles all patterns into a single trie so a domain only has to be walked once.
+//! A [`DomainMap`] does the same, but also attaches a value to every pattern, which is handy for routing.
+//! A [`DomainExpr`] combines patterns with and, or and not, for policies a single pattern can't express.
+//!
+//! Labels are compared case sensitively by default, use [`DomainPattern::matches_with`] or
+//! [`DomainPatternSet::with_options`] with [`MatchOptions::case_insensitive`] to ignore ASCII case like DNS does.
 //!
+//! Empty labels are skipped, so the absolute domain `domain.tld.` matches like `domain.tld`,
+//! use [`MatchOptions::trailing_dot`] to require or forbid the trailing dot instead.
+//! A domain without any labels, like `""` or `.`, is the root domain, see [`MatchOptions::root_matches`] for what matches it.
+//! [`validate_hostname`] checks a domain against the limits of RFC 1035, with [`MatchOptions::validate_input`]
+//! invalid domains never match.
+//!
+//! [`DomainPattern::covers`], [`DomainPattern::overlaps`] and [`DomainPattern::equivalent`] compare what two patterns
+//! match, to find rules that are shadowed by, conflict with, or duplicate another rule.
+//!
+//! [`CertHostMatcher`] checks hostnames against the names of TLS certificates, and [`cookie::domain_matches`] which hosts
+//! a cookie is sent to, the way TLS clients and browsers do. An [`OriginPattern`] matches the `Origin` of requests for CORS,
+//! and a [`CspSource`] the host sources of a Content-Security-Policy. A [`DnsZone`] answers wildcards like authoritative
+//! DNS servers do, only where no closer name exists. An [`EmailPattern`] matches addresses like `*@**.domain.tld`,
+//! and a [`UrlPattern`] URLs by their host and path, like `**.domain.tld/api/**`.
+//!
+//! # Features
+//!
+//! - `smallvec`: keeps the matching state on the stack
+//! - `fst`: adds [`DomainPatternSet::build_fst`], compiling a set into a compact, immutable [`FstPatternSet`]
+//! - `idna`: adds [`MatchOptions::idna`], comparing labels by their punycode A-label
+//! - `unicode-normalization`: adds [`ParseOptions::nfc`] and [`MatchOptions::nfc`], comparing labels in NFC
+//! - `regex`: labels like `~re:^v\d+$~` matching a single label against a regex
+//! - `rand`: adds [`DomainPattern::sample`] and [`DomainPattern::sample_non_matching`], generating random domains for tests
+//! - `memchr`: splits domains and patterns into labels with `memchr`, when the splitter is ASCII
+//! - `rayon`: adds [`DomainPatternSet::par_matches`], matching a lot of domains on all cores
+//! - `serde`: patterns serialize as their string, or as their parts with [`structured`], and maps as a versioned document, [`de`] takes a pattern or a list of them
+//! - `rkyv`: adds [`DomainPattern::to_archive`] and [`DomainPatternSet::to_archive`], archives that match without deserializing
+//! - `mmap`: adds [`DomainDb`], a read-only pattern database on disk that's memory mapped
+//! - `rustls`: adds [`DomainPattern::matches_server_name`] and [`DomainPattern::to_server_name`], for routing on the SNI of rustls
+//! - `url`: adds [`UrlPattern::matches`], matching a parsed `url::Url`
+//! - `hickory`: adds [`DomainPattern::matches_name`] and a `TryFrom<&Name>` for patterns, for matching the query names of hickory
 //!
 
 use std::borrow::Cow;
 use std::fmt::{Display, Formatter};
 use std::mem;
+use std::str::FromStr;
 #[cfg(feature = "smallvec")]
 use smallvec::SmallVec;
 
+#[cfg(feature = "rkyv")]
+mod archive;
+mod binary;
+mod bitset;
+mod builder;
+mod canonical;
+mod captures;
+mod cert;
+mod compiled;
+pub mod cookie;
+mod csp;
+#[cfg(feature = "serde")]
+pub mod de;
+#[cfg(feature = "mmap")]
+mod db;
+mod dialect;
+mod email;
+mod expr;
+pub mod formats;
+#[cfg(feature = "fst")]
+mod fst_set;
+mod glob;
+#[cfg(feature = "hickory")]
+mod hickory;
+mod host;
+mod hostname;
+#[cfg(feature = "regex")]
+mod label_regex;
+mod map;
+mod memory;
+mod normalize;
+mod options;
+mod origin;
+mod regex_string;
+mod relations;
+mod reverse;
+mod rewrite;
+#[cfg(feature = "rand")]
+mod sample;
+#[cfg(feature = "serde")]
+mod serialize;
+#[cfg(feature = "rustls")]
+mod server_name;
+mod scratch;
+mod set;
+mod specificity;
+mod split;
+#[cfg(feature = "serde")]
+pub mod structured;
+mod trace;
+mod translate;
+mod url_pattern;
+mod zone;
+
+#[cfg(feature = "rkyv")]
+pub use archive::{ArchivedPartArchive, ArchivedPatternArchive, PartArchive, PatternArchive};
+pub use binary::DecodeError;
+pub use builder::{DomainPatternBuilder, InvalidPart};
+pub use captures::{Captures, MatchKind, MatchSpan};
+pub use cert::CertHostMatcher;
+pub use compiled::{CompileError, CompiledPattern};
+pub use csp::{CspSource, InvalidCspSource};
+#[cfg(feature = "mmap")]
+pub use db::{DbError, DomainDb};
+pub use email::{EmailPattern, InvalidEmailPattern};
+pub use expr::DomainExpr;
+#[cfg(feature = "fst")]
+pub use fst_set::{FstError, FstPatternSet};
+#[cfg(feature = "hickory")]
+pub use hickory::NonUtf8Label;
+pub use host::HostMatch;
+pub use hostname::{validate_hostname, InvalidHostname};
+#[cfg(feature = "regex")]
+pub use label_regex::LabelRegex;
+pub use map::DomainMap;
+pub use normalize::DomainNormalizer;
+pub use options::{Direction, MatchOptions, ParseDialect, ParseOptions, TrailingDot};
+pub use origin::{InvalidOrigin, OriginPattern, OriginPort};
+pub use rewrite::{DomainRewriter, InvalidTemplate};
+#[cfg(feature = "rand")]
+pub use sample::SampleOptions;
+pub use scratch::MatchScratch;
+pub use set::{DomainPatternSet, FrozenDomainSet, Lint, Matches, MemoryStats, SetStats};
+#[cfg(feature = "rkyv")]
+pub use set::{ArchivedSetArchive, SetArchive};
+pub use trace::{BudgetExceeded, Decision, MatchStats, MatchTrace, TraceStep};
+pub use translate::TranslateError;
+pub use url_pattern::{InvalidUrlPattern, UrlPattern};
+pub use zone::{DnsZone, ZoneMatch};
+use trace::Observer;
+
 #[cfg(not(feature = "smallvec"))]
 type StepVec<'a> = Vec<DomainPatternPart<'a>>;
 
@@ -34,9 +189,24 @@ type StepVec<'a> = SmallVec<[DomainPatternPart<'a>; 24]>;
 #[cfg(feature = "smallvec")]
 type StackVec = SmallVec<[usize; 32]>;
 
-#[derive(Clone, Eq, PartialEq, Debug)]
+// patterns aren't covariant with the smallvec feature, so an owned pattern is shortened part by part
+pub(crate) fn shorten<'a, const SPLITTER: char>(pattern: DomainPattern<'static, SPLITTER>) -> DomainPattern<'a, SPLITTER> {
+    let mut steps: StepVec<'a> = Default::default();
+    for part in pattern.steps {
+        steps.push(part);
+    }
+
+    DomainPattern {
+        steps,
+        negated: pattern.negated,
+    }
+}
+
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
 pub struct DomainPattern<'a, const SPLITTER: char = '.'> {
     steps: StepVec<'a>,
+    // `!` in front, an exception to other patterns in a set
+    negated: bool,
 }
 
 impl<'a, const SPLITTER: char> DomainPattern<'a, SPLITTER> {
@@ -44,45 +214,136 @@ impl<'a, const SPLITTER: char> DomainPattern<'a, SPLITTER> {
         pattern.try_into()
     }
 
-    pub fn to_owned(&self) -> DomainPattern<'static> {
+    /// Copies the pattern so it doesn't borrow anymore, unlike [`Clone`], which keeps borrowing
+    pub fn to_owned(&self) -> DomainPattern<'static, SPLITTER> {
+        DomainPattern {
+            steps: self.steps.iter().map(DomainPatternPart::to_owned).collect(),
+            negated: self.negated,
+        }
+    }
+
+    /// Like [`DomainPattern::to_owned`], but labels that are already owned are moved instead of copied
+    pub fn into_owned(self) -> DomainPattern<'static, SPLITTER> {
+        DomainPattern {
+            steps: self.steps.into_iter().map(DomainPatternPart::into_owned).collect(),
+            negated: self.negated,
+        }
+    }
+
+    /// Appends the suffix to the pattern, `*.{service}` joined with `example.com` is `*.{service}.example.com`
+    ///
+    /// Wildcards meeting at the seam are folded like the parser would, the result is negated if this pattern is.
+    pub fn join<'b, 'c>(&self, suffix: &DomainPattern<'b, SPLITTER>) -> DomainPattern<'c, SPLITTER> where 'a: 'c, 'b: 'c {
+        let mut steps: StepVec<'c> = Default::default();
+        for part in self.steps.iter().chain(suffix.steps.iter()) {
+            push_part(&mut steps, part.clone());
+        }
+
         DomainPattern {
-            steps: self.steps.iter().map(|e| match e {
-                DomainPatternPart::Static(s) => DomainPatternPart::Static(Cow::Owned(s.as_ref().to_owned())),
-                DomainPatternPart::Wildcard(w) => DomainPatternPart::Wildcard(*w),
-            }).collect(),
+            steps,
+            negated: self.negated,
         }
     }
 
+    /// Returns the pattern with a static label in front, `+.example.com` with `api` is `api.+.example.com`
+    pub fn prepend_label(&self, label: impl Into<Cow<'a, str>>) -> Result<DomainPattern<'a, SPLITTER>, InvalidPart> {
+        let label = DomainPatternPart::Static(label.into());
+        builder::validate::<SPLITTER>(0, &label)?;
+
+        let mut steps: StepVec<'a> = Default::default();
+        steps.push(label);
+        steps.extend(self.steps.iter().cloned());
+
+        Ok(DomainPattern {
+            steps,
+            negated: self.negated,
+        })
+    }
+
+    /// Creates a pattern matching any domain containing the keyword, like `DOMAIN-KEYWORD` rules
+    ///
+    /// The keyword may span labels, `ads.track` matches `ads.tracker.tld` and `cdn-ads.track.tld`, but not `ads.tld`.
+    pub fn keyword(keyword: &str) -> DomainPattern<'static, SPLITTER> {
+        let pattern = format!("**{}*{}*{}**", SPLITTER, escape(keyword), SPLITTER);
+        let parsed = DomainPattern::<'_, SPLITTER>::parse(&pattern).expect("escaped keyword is a valid pattern");
+        parsed.to_owned()
+    }
+
+    /// Whether the pattern started with a `!`
+    ///
+    /// A negated pattern matches the same domains as it would without the `!`, but in a [`DomainPatternSet`] or
+    /// [`DomainMap`] it's an exception, when it matches a domain no pattern in the set matches that domain.
+    pub fn is_negated(&self) -> bool {
+        self.negated
+    }
+
+    /// The parts of the pattern after optimizing, one per label of the pattern, except for folded wildcards
+    pub fn parts(&self) -> impl ExactSizeIterator<Item=&DomainPatternPart<'a>> + DoubleEndedIterator + '_ {
+        self.steps.iter()
+    }
+
+    /// Amount of parts, see [`DomainPattern::parts`]
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// Same as `to_string()`, see the [`Display`] implementation
+    pub fn to_pattern_string(&self) -> String {
+        self.to_string()
+    }
+
     pub fn matches(&self, domain: &str) -> bool {
-        let mut stack: StackVec = Default::default();
-        let mut next_stack: StackVec = Default::default();
+        match self.run(domain, &MatchOptions::new(), &mut ()) {
+            Ok(matched) => matched,
+            Err(never) => match never {},
+        }
+    }
 
-        let mut next_idx = 0;
-        stack.push(next_idx);
+    // the actual matcher, telling the observer about every decision it makes
+    pub(crate) fn run<O: Observer>(&self, domain: &str, options: &MatchOptions, observer: &mut O) -> Result<bool, O::Break> {
+        self.run_in(domain, options, observer, &mut MatchScratch::new())
+    }
 
-        let mut saw_last = false;
-        while let DomainPatternPart::Wildcard(DomainPatternWildcard { optional: true, .. }) = &self.steps[next_idx] {
-            let jump_idx = next_idx + 1;
-            if jump_idx == self.steps.len() {
-                saw_last = true;
-                break;
-            }
+    // the matcher, keeping its paths in the scratch buffers
+    pub(crate) fn run_in<O: Observer>(&self, domain: &str, options: &MatchOptions, observer: &mut O, scratch: &mut MatchScratch) -> Result<bool, O::Break> {
+        let domain = options.comparable_domain::<SPLITTER>(domain);
+        let domain = domain.as_ref();
+        if !options.allows::<SPLITTER>(domain) {
+            return Ok(false);
+        }
 
-            stack.push(jump_idx);
-            next_idx = jump_idx;
+        let labels = split::labels::<SPLITTER>(domain).filter(|label| !label.is_empty());
+        self.run_labels(labels, options, observer, scratch)
+    }
+
+    // the matcher for labels that are already comparable and allowed by the options, without empty ones
+    pub(crate) fn run_labels<'l, O: Observer>(&self, mut labels: impl DoubleEndedIterator<Item=&'l str>, options: &MatchOptions, observer: &mut O, scratch: &mut MatchScratch) -> Result<bool, O::Break> {
+        if self.steps.is_empty() {
+            return Ok(labels.next().is_none());
         }
 
-        for label in domain.split(SPLITTER) {
-            if label == "" {
-                continue;
-            }
+        if !O::WATCHES && self.steps.len() <= bitset::MAX_STEPS {
+            return Ok(self.run_bitset(labels, options));
+        }
+
+        scratch.clear();
+        let MatchScratch { active: stack, next: next_stack } = scratch;
 
+        let mut saw_last = self.push_skipping_optional(0, None, stack, observer)?;
+        observer.stack(stack.len());
+
+        for (label_index, label) in labels.enumerate() {
+            let current = Some((label_index, label));
             saw_last = false;
             stack.sort();
 
             let mut last_path = None;
 
-            for path in &stack {
+            for path in stack.iter() {
                 if *path >= self.steps.len() {
                     continue;
                 }
@@ -92,49 +353,236 @@ impl<'a, const SPLITTER: char> DomainPattern<'a, SPLITTER> {
                 }
 
                 last_path = Some(path);
+                observer.visit(*path)?;
 
                 let part = &self.steps[*path];
-                match part {
-                    DomainPatternPart::Static(d) => {
-                        if d != label {
-                            continue;
-                        }
-                    }
+                let multi = match part {
                     DomainPatternPart::Wildcard(w) => {
                         if w.multi {
                             next_stack.push(*path);
+                            observer.decision(current, *path, Decision::Forked)?;
                         }
+
+                        w.multi
                     }
-                }
+                    part => {
+                        if !part.matches_label(label, options) {
+                            observer.decision(current, *path, Decision::DeadEnd)?;
+                            continue;
+                        }
 
+                        false
+                    }
+                };
 
-                let mut next_idx = path + 1;
+                let next_idx = path + 1;
 
                 if next_idx == self.steps.len() {
                     saw_last |= true;
+                    observer.decision(current, *path, Decision::Completed)?;
                     continue;
                 }
 
-                next_stack.push(next_idx);
-
-                while let DomainPatternPart::Wildcard(DomainPatternWildcard { optional: true, .. }) = &self.steps[next_idx] {
-                    let jump_idx = next_idx + 1;
-                    if jump_idx == self.steps.len() {
-                        saw_last |= true;
-                        break;
-                    }
-
-                    next_stack.push(jump_idx);
-                    next_idx = jump_idx;
+                if !multi {
+                    observer.decision(current, *path, Decision::Accepted)?;
                 }
+
+                saw_last |= self.push_skipping_optional(next_idx, current, next_stack, observer)?;
             }
 
-            mem::swap(&mut stack, &mut next_stack);
+            observer.stack(next_stack.len());
+            mem::swap(stack, next_stack);
             next_stack.truncate(0);
         }
 
-        saw_last
+        Ok(saw_last)
+    }
+
+    // pushes the step and every step reachable from it by skipping optional wildcards,
+    // returns true if the end of the pattern can be reached that way
+    fn push_skipping_optional<O: Observer>(&self, mut next_idx: usize, label: Option<(usize, &str)>, stack: &mut StackVec, observer: &mut O) -> Result<bool, O::Break> {
+        stack.push(next_idx);
+
+        while let DomainPatternPart::Wildcard(DomainPatternWildcard { optional: true, .. }) = &self.steps[next_idx] {
+            observer.decision(label, next_idx, Decision::Skipped)?;
+
+            let jump_idx = next_idx + 1;
+            if jump_idx == self.steps.len() {
+                return Ok(true);
+            }
+
+            stack.push(jump_idx);
+            next_idx = jump_idx;
+        }
+
+        Ok(false)
+    }
+}
+
+// "optimizer"
+// folds parts together, or changes the previous for better performance
+// adds the part like the parser does, folding wildcards into the wildcards before them
+fn push_part<'a>(steps: &mut StepVec<'a>, part: DomainPatternPart<'a>) {
+    match part {
+        DomainPatternPart::Wildcard(wildcard) => push_wildcard(steps, wildcard.optional, wildcard.multi),
+        part => steps.push(part),
+    }
+}
+
+// pushes the wildcard, folding it into the wildcards before it until nothing folds anymore, so the steps come out
+// the same however they're pushed, and a pattern parses back from its text as it was
+fn push_wildcard(steps: &mut StepVec, mut optional: bool, mut multi: bool) {
+    while let Some(&DomainPatternPart::Wildcard(DomainPatternWildcard { multi: last_multi, optional: last_optional })) = steps.last() {
+        // **.** = **
+        if last_multi && last_optional && multi && optional {
+            return;
+        }
+
+        // **.+ = **+
+        // **+.* = **+
+        // *.**+ = **+, which folds again with a `*` before it, like in *.*.**+
+        // +.* != **.+
+        if optional != last_optional && (last_multi || multi) {
+            steps.pop();
+            (optional, multi) = (false, true);
+            continue;
+        }
+
+        // this should limit the amount of forking needed
+        // **+.**+ = +.**+
+        // Too make sure it keeps cascading, it'll also apply:
+        // **+.+ = +.**+
+        if last_multi && !optional && !last_optional {
+            steps.pop();
+            push_wildcard(steps, false, false);
+            (optional, multi) = (false, true);
+            continue;
+        }
+
+        break;
     }
+
+    steps.push(DomainPatternPart::Wildcard(DomainPatternWildcard {
+        multi,
+        optional,
+    }));
+}
+
+// no domain has more labels than this, so neither does a repetition
+const MAX_REPEAT: usize = 127;
+
+// `*{2,4}`, `+{2}` or `*{2,}`, the minimum and maximum amount of labels, None for no maximum
+fn repetition(x: &str) -> Option<(usize, Option<usize>)> {
+    let bounds = x.strip_prefix(['*', '+'])?.strip_prefix('{')?.strip_suffix('}')?;
+    let number = |n: &str| n.bytes().all(|b| b.is_ascii_digit()).then(|| n.parse::<usize>().ok()).flatten();
+    let (min, max) = match bounds.split_once(',') {
+        Some((min, "")) => (number(min)?, None),
+        Some((min, max)) => (number(min)?, Some(number(max)?)),
+        None => (number(bounds)?, Some(number(bounds)?)),
+    };
+
+    if max.is_some_and(|max| max < min || max == 0) || max.unwrap_or(min) > MAX_REPEAT {
+        return None;
+    }
+
+    Some((min, max))
+}
+
+// splits the pattern into labels, splitters within a `~re:...~` label belong to the regex
+fn split_labels(pattern: &str, splitter: char) -> impl Iterator<Item=&str> {
+    let mut rest = Some(pattern);
+    std::iter::from_fn(move || {
+        let current = rest?;
+        let regex_end = current.strip_prefix("~re:")
+            .and_then(|regex| regex.find('~'))
+            .map_or(0, |end| end + 5);
+
+        match split::find(&current[regex_end..], splitter) {
+            Some(idx) => {
+                rest = Some(&current[regex_end + idx + splitter.len_utf8()..]);
+                Some(&current[..regex_end + idx])
+            }
+
+            None => {
+                rest = None;
+                Some(current)
+            }
+        }
+    })
+}
+
+#[cfg(feature = "regex")]
+fn regex_part(regex: &str) -> Option<DomainPatternPart<'static>> {
+    LabelRegex::new(regex).ok().map(DomainPatternPart::Regex)
+}
+
+// regexes need the regex feature
+#[cfg(not(feature = "regex"))]
+fn regex_part(_regex: &str) -> Option<DomainPatternPart<'static>> {
+    None
+}
+
+// a name, or an option of a `{a,b}` group
+fn is_plain(x: &str) -> bool {
+    !x.is_empty() && !x.contains(['{', '}', ',', '*', '+', '?', '[', ']', '\\'])
+}
+
+// the characters of the label that aren't escaped by a `\`, including a `\` at the end that escapes nothing
+fn unescaped(x: &str) -> impl Iterator<Item=char> + '_ {
+    let mut chars = x.chars();
+    std::iter::from_fn(move || loop {
+        match chars.next()? {
+            '\\' => match chars.next() {
+                Some(_) => continue,
+                None => return Some('\\'),
+            },
+            c => return Some(c),
+        }
+    })
+}
+
+// whether the parser reads the label as a glob, anything with `*`, `?` or a class that isn't only stars, a wildcard
+pub(crate) fn is_glob(x: &str) -> bool {
+    unescaped(x).any(|c| matches!(c, '*' | '?' | '[' | ']'))
+        && !unescaped(x).any(|c| matches!(c, '+' | '{' | '}' | '\\'))
+        && !x.chars().all(|c| c == '*')
+        && glob::is_valid(x)
+}
+
+// the label with a `\` in front of every character the parser would otherwise read as something special
+fn escape(x: &str) -> Cow<'_, str> {
+    if !x.contains(['*', '+', '?', '[', ']', '{', '}', '\\', '~', '#']) {
+        return Cow::Borrowed(x);
+    }
+
+    let mut escaped = String::with_capacity(x.len() + 2);
+    for c in x.chars() {
+        if matches!(c, '*' | '+' | '?' | '[' | ']' | '{' | '}' | '\\' | '~' | '#') {
+            escaped.push('\\');
+        }
+
+        escaped.push(c);
+    }
+
+    Cow::Owned(escaped)
+}
+
+// the label with every `\` escape replaced by the character it escapes
+fn unescape(x: &str) -> Cow<'_, str> {
+    if !x.contains('\\') {
+        return Cow::Borrowed(x);
+    }
+
+    let mut label = String::with_capacity(x.len());
+    let mut chars = x.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => label.extend(chars.next()),
+            c => label.push(c),
+        }
+    }
+
+    Cow::Owned(label)
 }
 
 impl<'a, const SPLITTER: char> TryFrom<&'a str> for DomainPattern<'a, SPLITTER> {
@@ -142,112 +590,341 @@ impl<'a, const SPLITTER: char> TryFrom<&'a str> for DomainPattern<'a, SPLITTER>
 
     fn try_from(s: &'a str) -> Result<Self, Self::Error> {
         let mut steps: StepVec = Default::default();
-        let mut offset = 0;
-        for part in s.split(SPLITTER) {
+        let (negated, pattern) = match s.strip_prefix('!') {
+            Some(pattern) => (true, pattern),
+            None => (false, s),
+        };
+
+        let mut offset = s.len() - pattern.len();
+        for part in split_labels(pattern, SPLITTER) {
             let position = offset;
-            offset += part.len() + 1;
+            offset += part.len() + SPLITTER.len_utf8();
 
-            let (optional, mut multi) = match part {
+            let (optional, multi) = match part {
                 "*" => (true, false),
                 "+" => (false, false),
                 "**" => (true, true),
                 "**+" => (false, true),
-                x if x.contains(|e| e == '*' || e == '+') => {
-                    return Err(InvalidToken {
-                        position,
-                        unexpected_token: Cow::Borrowed(x),
-                        full_string: Cow::Borrowed(s),
-                    });
+                "#" => {
+                    steps.push(DomainPatternPart::Numeric);
+                    continue;
                 }
 
-                _ => {
-                    steps.push(DomainPatternPart::Static(Cow::Borrowed(part)));
-                    offset += 1 + part.len();
+                x if repetition(x).is_some() => {
+                    let (min, max) = repetition(x).expect("checked by the guard");
+                    for _ in 0..min {
+                        push_wildcard(&mut steps, false, false);
+                    }
+
+                    match max {
+                        Some(max) => for _ in min..max {
+                            push_wildcard(&mut steps, true, false);
+                        },
+                        None => push_wildcard(&mut steps, true, true),
+                    }
+
+                    continue;
+                }
+
+                x if x.len() > 5 && x.starts_with("~re:") && x.ends_with('~') => match regex_part(&x[4..x.len() - 1]) {
+                    Some(part) => {
+                        steps.push(part);
+                        continue;
+                    }
+
+                    None => return Err(InvalidToken::new(ParseErrorKind::InvalidToken, position, x, s)),
+                },
+
+                x if x.len() > 2 && x.starts_with('{') && x.ends_with('}') && is_plain(&x[1..x.len() - 1]) => {
+                    steps.push(DomainPatternPart::Named(Cow::Borrowed(&x[1..x.len() - 1])));
                     continue;
                 }
-            };
 
-            // "optimizer"
-            // folds parts together, or changes the previous for better performance
-            if let Some(DomainPatternPart::Wildcard(DomainPatternWildcard { multi: last_multi, optional: last_optional })) = steps.last_mut() {
-                // **.** = **
-                if *last_multi && *last_optional && multi && optional {
+                x if x.len() > 2 && x.starts_with('{') && x.ends_with('}') && x[1..x.len() - 1].split(',').all(is_plain) => {
+                    steps.push(DomainPatternPart::OneOf(x[1..x.len() - 1].split(',').map(Cow::Borrowed).collect()));
                     continue;
                 }
 
-                // **.+ = **+
-                // **+.* = **+
-                // +.* != **.+
-                if optional != *last_optional && (*last_multi || multi) {
-                    *last_multi = true;
-                    *last_optional = false;
+                x if is_glob(x) => {
+                    steps.push(DomainPatternPart::Glob(Cow::Borrowed(x)));
                     continue;
                 }
 
-                // this should limit the amount of forking needed
-                // **+.**+ = +.**+
-                // Too make sure it keeps cascading, it'll also apply:
-                // **+.+ = +.**+
-                if *last_multi && !optional && !*last_optional {
-                    *last_multi = false;
-                    multi = true;
+                x if x.starts_with("~re:") || unescaped(x).any(|c| matches!(c, '*' | '+' | '{' | '}' | '[' | ']' | '\\')) => {
+                    return Err(InvalidToken::new(ParseErrorKind::InvalidToken, position, x, s));
                 }
-            }
 
-            steps.push(DomainPatternPart::Wildcard(DomainPatternWildcard {
-                multi,
-                optional,
-            }));
+                _ => {
+                    steps.push(DomainPatternPart::Static(unescape(part)));
+                    continue;
+                }
+            };
 
-            offset += 1 + part.len();
+            push_wildcard(&mut steps, optional, multi);
         }
 
         Ok(DomainPattern {
-            steps
+            steps,
+            negated,
         })
     }
 }
 
+/// Writes the pattern back as a string that parses into the same pattern
+///
+/// The text is the optimized form, so it may differ from what was parsed while matching the same,
+/// `**.**` is written as `**`, and `*{2}` as `+.+`, and static labels have their special characters escaped.
+impl<const SPLITTER: char> Display for DomainPattern<'_, SPLITTER> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.negated {
+            write!(f, "!")?;
+        }
+
+        for (idx, part) in self.steps.iter().enumerate() {
+            if idx > 0 {
+                write!(f, "{}", SPLITTER)?;
+            }
+
+            let text = part.text();
+            // a `!` at the start would negate a pattern that isn't negated
+            if idx == 0 && !self.negated && text.starts_with('!') {
+                write!(f, "\\")?;
+            }
+
+            write!(f, "{}", text)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl DomainPatternPart<'_> {
+    // the part as it's written in a pattern, borrowed for the parts most patterns are made of
+    pub(crate) fn text(&self) -> Cow<'_, str> {
+        match self {
+            DomainPatternPart::Static(label) => escape(label),
+            DomainPatternPart::Wildcard(DomainPatternWildcard { multi, optional }) => Cow::Borrowed(match (multi, optional) {
+                (false, true) => "*",
+                (false, false) => "+",
+                (true, true) => "**",
+                (true, false) => "**+",
+            }),
+            DomainPatternPart::Glob(glob) => Cow::Borrowed(glob),
+            DomainPatternPart::OneOf(labels) if labels.len() == 1 => escape(&labels[0]),
+            DomainPatternPart::Numeric => Cow::Borrowed("#"),
+            part => Cow::Owned(part.to_string()),
+        }
+    }
+}
+
+/// Writes the part as it's written in a pattern
+impl Display for DomainPatternPart<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DomainPatternPart::Named(name) => write!(f, "{{{}}}", name),
+            DomainPatternPart::OneOf(labels) if labels.len() != 1 => write!(f, "{{{}}}", labels.join(",")),
+            #[cfg(feature = "regex")]
+            DomainPatternPart::Regex(regex) => write!(f, "~re:{}~", regex.as_str()),
+            part => write!(f, "{}", part.text()),
+        }
+    }
+}
+
+impl<const SPLITTER: char> FromStr for DomainPattern<'static, SPLITTER> {
+    type Err = InvalidToken<'static>;
+
+    /// Parses the pattern into an owned pattern, see [`DomainPattern::parse`] for borrowing from the string instead
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parsed = DomainPattern::<'_, SPLITTER>::parse(s);
+        match parsed {
+            Ok(pattern) => Ok(pattern.into_owned()),
+            Err(err) => Err(err.into_owned()),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct InvalidToken<'a> {
     position: usize,
     unexpected_token: Cow<'a, str>,
     full_string: Cow<'a, str>,
+    kind: ParseErrorKind,
+}
+
+/// Why a pattern didn't parse
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum ParseErrorKind {
+    /// A label the parser doesn't understand, like `a+b`
+    InvalidToken,
+    /// The pattern has no labels at all, only with [`ParseOptions::allow_empty_labels`] off
+    EmptyPattern,
+    /// Two splitters in a row, only with [`ParseOptions::allow_empty_labels`] off
+    EmptyLabel,
+    /// The pattern starts with a splitter, only with [`ParseOptions::allow_empty_labels`] off
+    LeadingSplitter,
+    /// The pattern ends with a splitter, only with [`ParseOptions::allow_empty_labels`] off
+    TrailingSplitter,
 }
 
-impl InvalidToken<'_> {
+impl<'a> InvalidToken<'a> {
+    pub(crate) fn new(kind: ParseErrorKind, position: usize, unexpected_token: &'a str, full_string: &'a str) -> Self {
+        InvalidToken {
+            position,
+            unexpected_token: Cow::Borrowed(unexpected_token),
+            full_string: Cow::Borrowed(full_string),
+            kind,
+        }
+    }
+
     pub fn to_owned(&self) -> InvalidToken<'static> {
         InvalidToken {
             position: self.position,
             unexpected_token: Cow::Owned(self.unexpected_token.as_ref().to_owned()),
             full_string: Cow::Owned(self.full_string.as_ref().to_owned()),
+            kind: self.kind,
+        }
+    }
+
+    pub fn into_owned(self) -> InvalidToken<'static> {
+        InvalidToken {
+            position: self.position,
+            unexpected_token: Cow::Owned(self.unexpected_token.into_owned()),
+            full_string: Cow::Owned(self.full_string.into_owned()),
+            kind: self.kind,
         }
     }
+
+    pub fn kind(&self) -> ParseErrorKind {
+        self.kind
+    }
+
+    /// Byte offset of the offending label in the pattern
+    pub fn position(&self) -> usize {
+        self.position
+    }
 }
 
 impl Display for InvalidToken<'_> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Invalid token {:?} at position {} in pattern  {:?}", self.unexpected_token, self.position, self.full_string)
+        match self.kind {
+            ParseErrorKind::InvalidToken => write!(f, "Invalid token {:?} at position {} in pattern  {:?}", self.unexpected_token, self.position, self.full_string),
+            ParseErrorKind::EmptyPattern => write!(f, "Pattern {:?} is empty", self.full_string),
+            ParseErrorKind::EmptyLabel => write!(f, "Empty label at position {} in pattern {:?}", self.position, self.full_string),
+            ParseErrorKind::LeadingSplitter => write!(f, "Pattern {:?} starts with a splitter", self.full_string),
+            ParseErrorKind::TrailingSplitter => write!(f, "Pattern {:?} ends with a splitter", self.full_string),
+        }
     }
 }
 
 impl std::error::Error for InvalidToken<'_> {}
 
-#[derive(Clone, Eq, PartialEq, Debug)]
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "snake_case"))]
 pub enum DomainPatternPart<'a> {
+    #[cfg_attr(feature = "serde", serde(borrow, deserialize_with = "structured::borrow_cow"))]
     Static(Cow<'a, str>),
     Wildcard(DomainPatternWildcard),
+    /// `{name}`, matches like `+`, but what it matched can be retrieved by name, see [`Captures::name`]
+    #[cfg_attr(feature = "serde", serde(borrow, deserialize_with = "structured::borrow_cow"))]
+    Named(Cow<'a, str>),
+    /// A label with a `*`, `?` or character class in it, like `api-*`, where `*` matches any amount of characters
+    /// within the label, `?` exactly one, and `[a-z0-9]` one of the characters in the class, or not in it for `[!a-z]`
+    #[cfg_attr(feature = "serde", serde(borrow, deserialize_with = "structured::borrow_cow"))]
+    Glob(Cow<'a, str>),
+    /// `{a,b,c}`, matches any of the listed labels
+    #[cfg_attr(feature = "serde", serde(borrow))]
+    OneOf(Vec<Cow<'a, str>>),
+    /// `#`, matches a label consisting of only digits
+    Numeric,
+    /// `~re:^v\d+$~`, matches labels matching the regex
+    #[cfg(feature = "regex")]
+    Regex(LabelRegex),
 }
 
-#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+impl DomainPatternPart<'_> {
+    pub fn to_owned(&self) -> DomainPatternPart<'static> {
+        match self {
+            DomainPatternPart::Static(s) => DomainPatternPart::Static(Cow::Owned(s.as_ref().to_owned())),
+            DomainPatternPart::Wildcard(w) => DomainPatternPart::Wildcard(*w),
+            DomainPatternPart::Named(n) => DomainPatternPart::Named(Cow::Owned(n.as_ref().to_owned())),
+            DomainPatternPart::Glob(g) => DomainPatternPart::Glob(Cow::Owned(g.as_ref().to_owned())),
+            DomainPatternPart::OneOf(options) => DomainPatternPart::OneOf(options.iter().map(|o| Cow::Owned(o.as_ref().to_owned())).collect()),
+            DomainPatternPart::Numeric => DomainPatternPart::Numeric,
+            #[cfg(feature = "regex")]
+            DomainPatternPart::Regex(regex) => DomainPatternPart::Regex(regex.clone()),
+        }
+    }
+
+    pub fn into_owned(self) -> DomainPatternPart<'static> {
+        let owned = |label: Cow<'_, str>| Cow::Owned(label.into_owned());
+        match self {
+            DomainPatternPart::Static(s) => DomainPatternPart::Static(owned(s)),
+            DomainPatternPart::Wildcard(w) => DomainPatternPart::Wildcard(w),
+            DomainPatternPart::Named(n) => DomainPatternPart::Named(owned(n)),
+            DomainPatternPart::Glob(g) => DomainPatternPart::Glob(owned(g)),
+            DomainPatternPart::OneOf(options) => DomainPatternPart::OneOf(options.into_iter().map(owned).collect()),
+            DomainPatternPart::Numeric => DomainPatternPart::Numeric,
+            #[cfg(feature = "regex")]
+            DomainPatternPart::Regex(regex) => DomainPatternPart::Regex(regex),
+        }
+    }
+
+    // whether this part accepts the label, wildcards accept any label
+    pub(crate) fn matches_label(&self, label: &str, options: &MatchOptions) -> bool {
+        let same = |expected: &str| {
+            // ASCII labels are the same in any form they're converted to, so they're compared as bytes, length first
+            if expected.is_ascii() {
+                return expected.len() == label.len() && match options.case_insensitive {
+                    true => expected.as_bytes().eq_ignore_ascii_case(label.as_bytes()),
+                    false => expected.as_bytes() == label.as_bytes(),
+                };
+            }
+
+            let expected = options.comparable_label(expected);
+            expected == label || (options.case_insensitive && expected.eq_ignore_ascii_case(label))
+        };
+
+        match self {
+            DomainPatternPart::Static(d) => same(d),
+            DomainPatternPart::Wildcard(_) | DomainPatternPart::Named(_) => true,
+            DomainPatternPart::Glob(g) => glob::matches(g, label, options.case_insensitive),
+            DomainPatternPart::OneOf(labels) => labels.iter().any(|o| same(o)),
+            DomainPatternPart::Numeric => !label.is_empty() && label.bytes().all(|b| b.is_ascii_digit()),
+            #[cfg(feature = "regex")]
+            DomainPatternPart::Regex(regex) => regex.is_match(label),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DomainPatternWildcard {
     multi: bool,
     optional: bool,
 }
 
+impl DomainPatternWildcard {
+    /// `*` is optional, `+` is neither, `**` is both, and `**+` is multi
+    pub const fn new(multi: bool, optional: bool) -> Self {
+        DomainPatternWildcard { multi, optional }
+    }
+
+    /// Whether the wildcard takes any amount of labels, `**` and `**+`
+    pub fn is_multi(&self) -> bool {
+        self.multi
+    }
+
+    /// Whether the wildcard may take no labels at all, `*` and `**`
+    pub fn is_optional(&self) -> bool {
+        self.optional
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::{DomainPattern, DomainPatternWildcard, DomainPatternPart};
+    use std::borrow::Cow;
+    use std::collections::HashSet;
+    use crate::{DomainPattern, DomainPatternWildcard, DomainPatternPart, InvalidPart, MatchOptions, ParseErrorKind};
 
     #[test]
     pub fn test_algorithmic_blowup() {
@@ -360,6 +1037,288 @@ mod tests {
         assert!(pattern.matches("domain.tld"));
     }
 
+    #[test]
+    fn test_named() {
+        let pattern: DomainPattern = "{tenant}.app.example.com".try_into().expect("failed to parse");
+        assert_eq!(pattern.steps[0], DomainPatternPart::Named("tenant".into()));
+        assert!(pattern.matches("cijber.app.example.com"));
+        assert!(!pattern.matches("app.example.com"));
+        assert!(!pattern.matches("very.cijber.app.example.com"));
+
+        // optimizer leaves named parts alone
+        let pattern: DomainPattern = "**.{name}.**".try_into().expect("failed to parse");
+        assert_eq!(pattern.steps.len(), 3);
+
+        for invalid in ["{}.tld", "{a.tld", "a}.tld", "a{b}.tld", "{a{b}}.tld", "{*}.tld"] {
+            assert!(DomainPattern::<'_, '.'>::parse(invalid).is_err(), "{:?} should not parse", invalid);
+        }
+    }
+
+    #[test]
+    fn test_glob() {
+        let pattern: DomainPattern = "api-*.example.com".try_into().expect("failed to parse");
+        assert_eq!(pattern.steps[0], DomainPatternPart::Glob("api-*".into()));
+        assert!(pattern.matches("api-v1.example.com"));
+        assert!(pattern.matches("api-.example.com"));
+        assert!(!pattern.matches("api.example.com"));
+        assert!(!pattern.matches("www.api-v1.example.com"));
+
+        let pattern: DomainPattern = "**.*-staging.example.com".try_into().expect("failed to parse");
+        assert!(pattern.matches("deep.web-staging.example.com"));
+        assert!(!pattern.matches("web-staging-2.example.com"));
+
+        let pattern: DomainPattern = "us-east-?.*.cloud.tld".try_into().expect("failed to parse");
+        assert!(pattern.matches("us-east-1.cloud.tld"));
+        assert!(pattern.matches("us-east-2.eu.cloud.tld"));
+        assert!(!pattern.matches("us-east-12.cloud.tld"));
+        assert!(!pattern.matches("cloud.tld"));
+
+        let pattern: DomainPattern = "node[0-9][0-9].cluster.tld".try_into().expect("failed to parse");
+        assert!(pattern.matches("node01.cluster.tld"));
+        assert!(!pattern.matches("node1.cluster.tld"));
+
+        for invalid in ["***.tld", "a*+.tld", "{a*}.tld", "a?+.tld", "node[0-9.tld", "node[].tld", "node[9-0].tld", "a]b.tld"] {
+            assert!(DomainPattern::<'_, '.'>::parse(invalid).is_err(), "{:?} should not parse", invalid);
+        }
+    }
+
+    #[test]
+    fn test_one_of() {
+        let pattern: DomainPattern = "{api,www}.example.com".try_into().expect("failed to parse");
+        assert_eq!(pattern.steps[0], DomainPatternPart::OneOf(vec!["api".into(), "www".into()]));
+        assert!(pattern.matches("api.example.com"));
+        assert!(pattern.matches("www.example.com"));
+        assert!(!pattern.matches("ftp.example.com"));
+        assert!(!pattern.matches("example.com"));
+
+        for invalid in ["{a,}.tld", "{,a}.tld", "{a,,b}.tld", "{a,*}.tld", "{a,b.tld"] {
+            assert!(DomainPattern::<'_, '.'>::parse(invalid).is_err(), "{:?} should not parse", invalid);
+        }
+    }
+
+    #[test]
+    fn test_repetition() {
+        let pattern: DomainPattern = "*{2,4}.example.com".try_into().expect("failed to parse");
+        assert!(!pattern.matches("a.example.com"));
+        assert!(pattern.matches("a.b.example.com"));
+        assert!(pattern.matches("a.b.c.d.example.com"));
+        assert!(!pattern.matches("a.b.c.d.e.example.com"));
+
+        let pattern: DomainPattern = "+{2}.example.com".try_into().expect("failed to parse");
+        assert_eq!(pattern.steps.len(), 4);
+        assert!(pattern.matches("a.b.example.com"));
+        assert!(!pattern.matches("a.b.c.example.com"));
+
+        let pattern: DomainPattern = "*{0,3}.example.com".try_into().expect("failed to parse");
+        assert!(pattern.matches("example.com"));
+        assert!(pattern.matches("a.b.c.example.com"));
+        assert!(!pattern.matches("a.b.c.d.example.com"));
+
+        let pattern: DomainPattern = "*{2,}.example.com".try_into().expect("failed to parse");
+        assert!(!pattern.matches("a.example.com"));
+        assert!(pattern.matches("a.b.c.d.e.example.com"));
+
+        for invalid in ["*{}.tld", "*{4,2}.tld", "*{0}.tld", "*{a}.tld", "*{-1}.tld", "*{1,2,3}.tld", "*{1000}.tld", "**{2}.tld", "a{2}.tld"] {
+            assert!(DomainPattern::<'_, '.'>::parse(invalid).is_err(), "{:?} should not parse", invalid);
+        }
+    }
+
+    #[test]
+    fn test_negated() {
+        let pattern: DomainPattern = "!internal.*.example.com".try_into().expect("failed to parse");
+        assert!(pattern.is_negated());
+        assert_eq!(pattern.steps.len(), 4);
+        assert!(pattern.matches("internal.example.com"));
+        assert!(!DomainPattern::<'_, '.'>::parse("internal.example.com").expect("failed to parse").is_negated());
+
+        let err = DomainPattern::<'_, '.'>::parse("!a.b+").expect_err("should not parse");
+        assert_eq!(err.position, 3);
+    }
+
+    #[test]
+    fn test_escapes() {
+        let pattern: DomainPattern = "tag\\+1.\\*.example.com".try_into().expect("failed to parse");
+        assert_eq!(pattern.steps[0], DomainPatternPart::Static("tag+1".into()));
+        assert_eq!(pattern.steps[1], DomainPatternPart::Static("*".into()));
+        assert!(pattern.matches("tag+1.*.example.com"));
+        assert!(!pattern.matches("tag+1.www.example.com"));
+
+        let pattern: DomainPattern = "\\!a.\\{b\\}.\\\\".try_into().expect("failed to parse");
+        assert!(!pattern.is_negated());
+        assert!(pattern.matches("!a.{b}.\\"));
+
+        let pattern: DomainPattern = "a\\*-*.example.com".try_into().expect("failed to parse");
+        assert!(pattern.matches("a*-b.example.com"));
+        assert!(!pattern.matches("ab-b.example.com"));
+
+        for invalid in ["a\\.tld", "a+\\*.tld"] {
+            assert!(DomainPattern::<'_, '.'>::parse(invalid).is_err(), "{:?} should not parse", invalid);
+        }
+    }
+
+    #[test]
+    fn test_keyword() {
+        let pattern: DomainPattern = DomainPattern::keyword("tracker");
+        assert!(pattern.matches("tracker.tld"));
+        assert!(pattern.matches("a.bad-tracker-cdn.tld"));
+        assert!(pattern.matches("www.tracker"));
+        assert!(!pattern.matches("track.er.tld"));
+
+        let pattern: DomainPattern = DomainPattern::keyword("ads.track");
+        assert!(pattern.matches("ads.tracker.tld"));
+        assert!(pattern.matches("cdn-ads.track.tld"));
+        assert!(!pattern.matches("ads.tld"));
+
+        let pattern: DomainPattern = DomainPattern::keyword("a*b");
+        assert!(pattern.matches("xa*by.tld"));
+        assert!(!pattern.matches("xaaby.tld"));
+
+        let pattern: DomainPattern<'_, '/'> = DomainPattern::keyword("nice");
+        assert!(pattern.matches("very/nicely/done"));
+    }
+
+    #[test]
+    fn test_numeric() {
+        let pattern: DomainPattern = "#.#.#.#.in-addr.arpa".try_into().expect("failed to parse");
+        assert_eq!(pattern.steps[0], DomainPatternPart::Numeric);
+        assert!(pattern.matches("1.0.168.192.in-addr.arpa"));
+        assert!(!pattern.matches("1.0.168.in-addr.arpa"));
+        assert!(!pattern.matches("1.0.x.192.in-addr.arpa"));
+
+        let pattern: DomainPattern = "v.\\#.tld".try_into().expect("failed to parse");
+        assert!(pattern.matches("v.#.tld"));
+        assert!(!pattern.matches("v.1.tld"));
+        assert!(DomainPattern::<'_, '.'>::keyword("a.#.b").matches("a.#.b"));
+    }
+
+    #[test]
+    fn test_from_str() {
+        let pattern: DomainPattern = "*.example.com".parse().expect("failed to parse");
+        assert!(pattern.matches("www.example.com"));
+
+        let pattern: DomainPattern<'static, '/'> = String::from("+/nice").parse().expect("failed to parse");
+        assert!(pattern.matches("wow/nice"));
+
+        let err = "a.b+".parse::<DomainPattern>().expect_err("should not parse");
+        assert_eq!(err.position(), 2);
+    }
+
+    #[test]
+    fn test_display() {
+        for pattern in ["*.example.com", "+.**+.x", "!internal.example.com", "{tenant}.app.tld", "api-*.node[0-9].tld", "{www,api}.tld", "#.in-addr.arpa", "tag\\+1.\\#.tld", "\\!a.b", "!!a.b", "a..b", "c\\~re:x\\~.y"] {
+            let parsed: DomainPattern = pattern.try_into().expect("failed to parse");
+            assert_eq!(parsed.to_string(), pattern);
+        }
+
+        for (pattern, display) in [("**.**.x", "**.x"), ("**+.+.x", "+.**+.x"), ("*{2}.x", "+.+.x"), ("*{0,2}.x", "*.*.x"), ("+{2,}.x", "+.**+.x"), ("*.*.**+", "**+"), ("#.*{0,2}.**+", "#.**+"), ("*{0,2}.**+.a", "**+.a"), ("\\~re:x~.y", "\\~re:x\\~.y")] {
+            let parsed: DomainPattern = pattern.try_into().expect("failed to parse");
+            assert_eq!(parsed.to_pattern_string(), display);
+            let reparsed: DomainPattern = display.try_into().expect("failed to parse");
+            assert_eq!(reparsed, parsed);
+        }
+
+        // every combination of a few parts reads back as the same steps
+        let parts = ["*", "+", "**", "**+", "*{0,2}", "+{2}", "*{2,}", "a", "#", "a-*"];
+        let mut patterns: Vec<String> = parts.iter().map(|part| part.to_string()).collect();
+        for _ in 0..3 {
+            patterns = patterns.iter().flat_map(|pattern| parts.iter().map(move |part| format!("{}.{}", pattern, part))).collect();
+            for pattern in &patterns {
+                let parsed: DomainPattern = pattern.parse().expect("failed to parse");
+                let reparsed: DomainPattern = parsed.to_string().parse().expect("failed to parse display");
+                assert_eq!(reparsed.steps, parsed.steps, "{} displayed as {}", pattern, parsed);
+            }
+        }
+
+        let pattern = DomainPattern::<'_, '.'>::keyword("a*b");
+        let reparsed: DomainPattern = pattern.to_string().parse().expect("failed to parse");
+        assert_eq!(reparsed, pattern);
+    }
+
+    #[test]
+    fn test_into_owned() {
+        let owned = {
+            let pattern = String::from("tag\\+1.*.{a,b}.example");
+            let parsed: DomainPattern = pattern.as_str().try_into().expect("failed to parse");
+            parsed.into_owned()
+        };
+
+        assert_eq!(owned, "tag\\+1.*.{a,b}.example".try_into().expect("failed to parse"));
+        assert!(owned.matches("tag+1.a.example"));
+
+        let borrowed: DomainPattern = "*.example".try_into().expect("failed to parse");
+        let cow: Cow<DomainPattern> = Cow::Borrowed(&borrowed);
+        assert!(cow.matches("www.example"));
+        assert_eq!(cow.into_owned(), borrowed);
+
+        let err = {
+            let pattern = String::from("a+b");
+            let err = DomainPattern::<'_, '.'>::parse(&pattern).expect_err("should not parse");
+            err.into_owned()
+        };
+
+        assert_eq!(err.position(), 0);
+    }
+
+    #[test]
+    fn test_join() {
+        let service: DomainPattern = "*.{service}".try_into().expect("failed to parse");
+        let base: DomainPattern = "example.com".try_into().expect("failed to parse");
+        let joined = service.join(&base);
+        assert_eq!(joined, "*.{service}.example.com".try_into().expect("failed to parse"));
+
+        let prefix: DomainPattern = "a.**".try_into().expect("failed to parse");
+        let suffix: DomainPattern = "+.b".try_into().expect("failed to parse");
+        assert_eq!(prefix.join(&suffix), "a.**.+.b".try_into().expect("failed to parse"));
+        assert_eq!(prefix.join(&suffix).len(), 3);
+
+        let negated: DomainPattern = "!internal".try_into().expect("failed to parse");
+        assert!(negated.join(&base).is_negated());
+
+        let prepended = base.prepend_label("api").expect("valid label");
+        assert_eq!(prepended.to_string(), "api.example.com");
+        assert_eq!(base.prepend_label("a.b").err(), Some(InvalidPart::Splitter(0)));
+    }
+
+    #[test]
+    fn test_parts() {
+        let pattern: DomainPattern = "*.x.**.**+.{a,b}.example".try_into().expect("failed to parse");
+        assert_eq!(pattern.len(), 5);
+        assert!(!pattern.is_empty());
+
+        let wildcards: Vec<_> = pattern.parts().filter_map(|part| match part {
+            DomainPatternPart::Wildcard(wildcard) => Some((wildcard.is_multi(), wildcard.is_optional())),
+            _ => None,
+        }).collect();
+
+        assert_eq!(wildcards, vec![(false, true), (true, false)]);
+        assert_eq!(pattern.parts().last(), Some(&DomainPatternPart::Static("example".into())));
+        assert_eq!(pattern.parts().rev().nth(1), Some(&DomainPatternPart::OneOf(vec!["a".into(), "b".into()])));
+    }
+
+    #[test]
+    // regexes compile to something with a cache inside, their hash only uses the source
+    #[allow(clippy::mutable_key_type)]
+    fn test_hash() {
+        let patterns: HashSet<DomainPattern> = ["*.example.com", "**.**.example.com", "**.example.com", "*.example.com", "!*.example.com"]
+            .into_iter()
+            .map(|pattern| pattern.try_into().expect("failed to parse"))
+            .collect();
+
+        assert_eq!(patterns.len(), 3);
+        assert!(patterns.contains(&"**.example.com".parse().expect("failed to parse")));
+    }
+
+    #[test]
+    fn test_error_position() {
+        let err = DomainPattern::<'_, '.'>::parse("a.bc.d+.e").expect_err("should not parse");
+        assert_eq!(err.position, 5);
+        assert_eq!(err.unexpected_token, "d+");
+
+        let err = DomainPattern::<'_, '→'>::parse("a→+x").expect_err("should not parse");
+        assert_eq!(err.position, 4);
+        assert_eq!(err.kind(), ParseErrorKind::InvalidToken);
+    }
+
     #[test]
     fn test_readme_example() {
         let pattern: DomainPattern = "domain.tld".try_into().expect("failed to parse");
@@ -387,4 +1346,21 @@ mod tests {
         assert!(pattern.matches("sub.domain.tld"));
         assert!(pattern.matches("sub.sub.domain.tld"));
     }
+
+    #[test]
+    pub fn test_ascii_labels() {
+        let insensitive = MatchOptions::new().case_insensitive(true);
+        let pattern: DomainPattern = "{www,cdn}.strasse.example".try_into().expect("failed to parse");
+        assert!(pattern.matches("www.strasse.example"));
+        assert!(!pattern.matches("www.straße.example"));
+        assert!(!pattern.matches("www.strass.example"));
+        assert!(!pattern.matches("WWW.strasse.example"));
+        assert!(pattern.matches_with("WWW.StrassE.example", &insensitive));
+        assert!(!pattern.matches_with("www.STRAßE.example", &insensitive));
+
+        let pattern: DomainPattern = "straße.example".try_into().expect("failed to parse");
+        assert!(pattern.matches("straße.example"));
+        assert!(pattern.matches_with("STRAßE.example", &insensitive));
+        assert!(!pattern.matches("strasse.example"));
+    }
 }
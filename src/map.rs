@@ -0,0 +1,261 @@
+//! Associating values with patterns, e.g. for routing requests by hostname
+
+use crate::{DomainPattern, DomainPatternSet, MatchOptions};
+
+#[cfg(feature = "serde")]
+mod serialize;
+
+// patterns only compare with the same lifetime, and with smallvec they can't be shortened, parts can
+fn same<const SPLITTER: char>(a: &DomainPattern<'_, SPLITTER>, b: &DomainPattern<'_, SPLITTER>) -> bool {
+    a.negated == b.negated && a.steps.len() == b.steps.len() && a.steps.iter().zip(b.steps.iter()).all(|(a, b)| a == b)
+}
+
+/// A map from patterns to values, looked up by domain
+///
+/// When multiple patterns match a domain the most specific one wins, static labels beat `+`, which beats `*`,
+/// which beats `**+`, which beats `**`, so `api.example.com` wins over `+.example.com` which wins over `**.example.com`.
+/// When two patterns are equally specific the one with the highest priority wins, and then the one inserted first,
+/// see [`DomainPatternSet::best_match`].
+#[derive(Clone, Debug)]
+pub struct DomainMap<'a, V, const SPLITTER: char = '.'> {
+    set: DomainPatternSet<'a, SPLITTER>,
+    // indexed by the index of the pattern in the set, removed patterns are left as None
+    entries: Vec<Option<V>>,
+}
+
+impl<'a, V, const SPLITTER: char> DomainMap<'a, V, SPLITTER> {
+    pub fn new() -> Self {
+        DomainMap {
+            set: DomainPatternSet::new(),
+            entries: vec![],
+        }
+    }
+
+    /// Creates a map with room for the given amount of patterns, see [`DomainPatternSet::with_capacity`]
+    pub fn with_capacity(capacity: usize) -> Self {
+        DomainMap {
+            set: DomainPatternSet::with_capacity(capacity),
+            entries: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Creates a map matching with the given options, see [`DomainPatternSet::with_options`]
+    pub fn with_options(options: MatchOptions) -> Self {
+        DomainMap {
+            set: DomainPatternSet::with_options(options),
+            entries: vec![],
+        }
+    }
+
+    /// Reserves room for at least `additional` more patterns
+    pub fn reserve(&mut self, additional: usize) {
+        self.set.reserve(additional);
+        self.entries.reserve(additional);
+    }
+
+    /// Frees all capacity that isn't used, see [`DomainPatternSet::shrink_to_fit`]
+    pub fn shrink_to_fit(&mut self) {
+        self.set.shrink_to_fit();
+        self.entries.shrink_to_fit();
+    }
+
+    pub fn len(&self) -> usize {
+        self.set.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.set.is_empty()
+    }
+
+    fn position(&self, pattern: &DomainPattern<'_, SPLITTER>) -> Option<usize> {
+        for idx in self.set.find(pattern) {
+            if same(self.set.get(*idx).expect("find only returns patterns in the set"), pattern) {
+                return Some(*idx);
+            }
+        }
+
+        None
+    }
+
+    /// Associates the value with the pattern, returning the previous value of the pattern if it was already in the map
+    ///
+    /// New patterns get a priority of 0, existing patterns keep their priority.
+    pub fn insert(&mut self, pattern: DomainPattern<'a, SPLITTER>, value: V) -> Option<V> {
+        if let Some(idx) = self.position(&pattern) {
+            return self.entries[idx].replace(value);
+        }
+
+        self.insert_new(pattern, value, 0);
+        None
+    }
+
+    /// Associates the value and priority with the pattern, returning the previous value of the pattern if it was already in the map
+    pub fn insert_with_priority(&mut self, pattern: DomainPattern<'a, SPLITTER>, value: V, priority: i32) -> Option<V> {
+        if let Some(idx) = self.position(&pattern) {
+            self.set.set_priority(idx, priority);
+            return self.entries[idx].replace(value);
+        }
+
+        self.insert_new(pattern, value, priority);
+        None
+    }
+
+    fn insert_new(&mut self, pattern: DomainPattern<'a, SPLITTER>, value: V, priority: i32) {
+        let idx = self.set.insert_with_priority(pattern, priority);
+        debug_assert_eq!(idx, self.entries.len());
+        self.entries.push(Some(value));
+    }
+
+    /// Returns the priority of the pattern, if it's in the map
+    pub fn priority(&self, pattern: &DomainPattern<'_, SPLITTER>) -> Option<i32> {
+        self.set.priority(self.position(pattern)?)
+    }
+
+    /// Removes the pattern from the map, returning its value if it was in the map
+    pub fn remove(&mut self, pattern: &DomainPattern<'_, SPLITTER>) -> Option<V> {
+        let idx = self.position(pattern)?;
+        self.set.remove(idx);
+        self.entries[idx].take()
+    }
+
+    /// Returns the value of the most specific pattern matching the domain
+    pub fn get(&self, domain: &str) -> Option<&V> {
+        self.get_key_value(domain).map(|(_, value)| value)
+    }
+
+    /// Returns the most specific pattern matching the domain together with its value
+    pub fn get_key_value(&self, domain: &str) -> Option<(&DomainPattern<'a, SPLITTER>, &V)> {
+        let idx = self.set.best_match(domain)?;
+        Some((self.set.get(idx).expect("entries and set are in sync"), self.entries[idx].as_ref()?))
+    }
+
+    /// Iterates over all patterns, their values and priorities, highest priority first, and in insertion order for equal priorities
+    pub fn iter_by_priority(&self) -> impl Iterator<Item=(&DomainPattern<'a, SPLITTER>, &V, i32)> + '_ {
+        self.set.iter_by_priority()
+            .map(|(idx, pattern, priority)| (pattern, self.entries[idx].as_ref().expect("entries and set are in sync"), priority))
+    }
+
+    /// Iterates over all patterns and their values, in insertion order
+    pub fn iter(&self) -> impl Iterator<Item=(&DomainPattern<'a, SPLITTER>, &V)> + '_ {
+        self.entries.iter()
+            .enumerate()
+            .filter_map(|(idx, value)| value.as_ref().map(|value| (self.set.get(idx).expect("entries and set are in sync"), value)))
+    }
+}
+
+impl<V, const SPLITTER: char> Default for DomainMap<'_, V, SPLITTER> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, V, const SPLITTER: char> Extend<(DomainPattern<'a, SPLITTER>, V)> for DomainMap<'a, V, SPLITTER> {
+    fn extend<T: IntoIterator<Item=(DomainPattern<'a, SPLITTER>, V)>>(&mut self, iter: T) {
+        for (pattern, value) in iter {
+            self.insert(pattern, value);
+        }
+    }
+}
+
+impl<'a, V, const SPLITTER: char> FromIterator<(DomainPattern<'a, SPLITTER>, V)> for DomainMap<'a, V, SPLITTER> {
+    fn from_iter<T: IntoIterator<Item=(DomainPattern<'a, SPLITTER>, V)>>(iter: T) -> Self {
+        let mut map = Self::new();
+        map.extend(iter);
+        map
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{DomainMap, DomainPattern, MatchOptions};
+
+    fn pattern(s: &str) -> DomainPattern<'_> {
+        s.try_into().expect("failed to parse")
+    }
+
+    #[test]
+    pub fn test_precedence() {
+        let mut map = DomainMap::new();
+        map.insert(pattern("**.example.com"), "any");
+        map.insert(pattern("*.example.com"), "optional");
+        map.insert(pattern("+.example.com"), "one");
+        map.insert(pattern("api.example.com"), "api");
+
+        assert_eq!(map.get("api.example.com"), Some(&"api"));
+        assert_eq!(map.get("www.example.com"), Some(&"one"));
+        assert_eq!(map.get("example.com"), Some(&"optional"));
+        assert_eq!(map.get("deep.www.example.com"), Some(&"any"));
+        assert_eq!(map.get("example.org"), None);
+
+        let mut map = DomainMap::new();
+        map.insert(pattern("+.example.com"), 1);
+        map.insert(pattern("www.+.com"), 2);
+        assert_eq!(map.get("www.example.com"), Some(&1));
+    }
+
+    #[test]
+    pub fn test_priority() {
+        let mut map = DomainMap::new();
+        map.insert(pattern("+.example.com"), "vendor");
+        map.insert_with_priority(pattern("www.+.com"), "user", 1);
+        assert_eq!(map.get("www.example.com"), Some(&"user"));
+
+        assert_eq!(map.insert_with_priority(pattern("+.example.com"), "vendor override", 2), Some("vendor"));
+        assert_eq!(map.priority(&pattern("+.example.com")), Some(2));
+        assert_eq!(map.get("www.example.com"), Some(&"vendor override"));
+
+        let order: Vec<_> = map.iter_by_priority().map(|(_, value, priority)| (*value, priority)).collect();
+        assert_eq!(order, vec![("vendor override", 2), ("user", 1)]);
+    }
+
+    #[test]
+    pub fn test_negated() {
+        let mut map = DomainMap::new();
+        map.insert(pattern("**.example.com"), 1);
+        map.insert(pattern("!internal.example.com"), 0);
+        assert_eq!(map.get("www.example.com"), Some(&1));
+        assert_eq!(map.get("internal.example.com"), None);
+
+        assert_eq!(map.remove(&pattern("internal.example.com")), None);
+        assert_eq!(map.remove(&pattern("!internal.example.com")), Some(0));
+        assert_eq!(map.get("internal.example.com"), Some(&1));
+    }
+
+    #[test]
+    pub fn test_case_insensitive() {
+        let mut map = DomainMap::with_options(MatchOptions::new().case_insensitive(true));
+        map.insert(pattern("+.Example.com"), 1);
+        assert_eq!(map.get("WWW.example.COM"), Some(&1));
+        assert_eq!(map.insert(pattern("+.Example.com"), 2), Some(1));
+        assert_eq!(map.remove(&pattern("+.Example.com")), Some(2));
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    pub fn test_insert_remove() {
+        let mut map = DomainMap::new();
+        assert!(map.is_empty());
+        assert_eq!(map.insert(pattern("+.example.com"), 1), None);
+        assert_eq!(map.insert(pattern("**.example.com"), 2), None);
+        assert_eq!(map.insert(pattern("+.example.com"), 3), Some(1));
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get("www.example.com"), Some(&3));
+
+        assert_eq!(map.remove(&pattern("+.example.com")), Some(3));
+        assert_eq!(map.remove(&pattern("+.example.com")), None);
+        assert_eq!(map.len(), 1);
+        assert_eq!(map.get("www.example.com"), Some(&2));
+
+        assert_eq!(map.insert(pattern("+.example.com"), 4), None);
+        assert_eq!(map.get("www.example.com"), Some(&4));
+
+        let items: Vec<_> = map.iter().map(|(pattern, value)| (pattern.clone(), *value)).collect();
+        assert_eq!(items, vec![(pattern("**.example.com"), 2), (pattern("+.example.com"), 4)]);
+
+        // shares its place in the trie with +, but is a different pattern
+        assert_eq!(map.insert(pattern("{name}.example.com"), 5), None);
+        assert_eq!(map.len(), 3);
+        assert_eq!(map.remove(&pattern("{name}.example.com")), Some(5));
+        assert_eq!(map.get("www.example.com"), Some(&4));
+    }
+}
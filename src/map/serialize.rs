@@ -0,0 +1,138 @@
+//! Maps saved as a single document with their patterns, priorities and values
+
+use std::fmt::Formatter;
+use std::marker::PhantomData;
+use serde::de::{Error, IgnoredAny, MapAccess, Visitor};
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use crate::{DomainMap, DomainPattern};
+
+// documents with a newer version are rejected, fields this version doesn't know are ignored
+const VERSION: u32 = 1;
+
+#[derive(Serialize)]
+struct EntryRef<'m, 'a, V, const SPLITTER: char> {
+    pattern: &'m DomainPattern<'a, SPLITTER>,
+    priority: i32,
+    value: &'m V,
+}
+
+#[derive(Deserialize)]
+struct Entry<'a, V, const SPLITTER: char> {
+    #[serde(borrow)]
+    pattern: DomainPattern<'a, SPLITTER>,
+    #[serde(default)]
+    priority: i32,
+    value: V,
+}
+
+/// Serializes as `{"version": 1, "entries": [{"pattern": "*.example.com", "priority": 0, "value": ...}]}`, in
+/// insertion order
+///
+/// The options of the map aren't part of the document, see [`DomainMap::with_options`].
+impl<V: Serialize, const SPLITTER: char> Serialize for DomainMap<'_, V, SPLITTER> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let entries: Vec<_> = self.entries.iter().enumerate()
+            .filter_map(|(idx, value)| {
+                let value = value.as_ref()?;
+                let pattern = self.set.get(idx).expect("entries and set are in sync");
+                let priority = self.set.priority(idx).expect("entries and set are in sync");
+                Some(EntryRef { pattern, priority, value })
+            })
+            .collect();
+
+        let mut document = serializer.serialize_struct("DomainMap", 2)?;
+        document.serialize_field("version", &VERSION)?;
+        document.serialize_field("entries", &entries)?;
+        document.end()
+    }
+}
+
+struct MapVisitor<'a, V, const SPLITTER: char>(PhantomData<DomainMap<'a, V, SPLITTER>>);
+
+impl<'de: 'a, 'a, V: Deserialize<'de>, const SPLITTER: char> Visitor<'de> for MapVisitor<'a, V, SPLITTER> {
+    type Value = DomainMap<'a, V, SPLITTER>;
+
+    fn expecting(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "a domain map with a version and entries")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut document: A) -> Result<Self::Value, A::Error> {
+        let mut version = None;
+        let mut entries: Option<Vec<Entry<'a, V, SPLITTER>>> = None;
+        while let Some(key) = document.next_key::<std::borrow::Cow<str>>()? {
+            match key.as_ref() {
+                "version" => {
+                    let found: u32 = document.next_value()?;
+                    // checked right away, entries of a newer version may not even parse
+                    if found > VERSION {
+                        return Err(A::Error::custom(format!("unsupported domain map version {}, at most {} is supported", found, VERSION)));
+                    }
+
+                    version = Some(found);
+                }
+                "entries" => entries = Some(document.next_value()?),
+                _ => {
+                    document.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+
+        version.ok_or_else(|| A::Error::missing_field("version"))?;
+        let entries = entries.ok_or_else(|| A::Error::missing_field("entries"))?;
+
+        let mut map = DomainMap::new();
+        for entry in entries {
+            map.insert_with_priority(entry.pattern, entry.value, entry.priority);
+        }
+
+        Ok(map)
+    }
+}
+
+/// Deserializes a document written by serializing a map, later entries replace earlier ones with the same pattern
+impl<'de: 'a, 'a, V: Deserialize<'de>, const SPLITTER: char> Deserialize<'de> for DomainMap<'a, V, SPLITTER> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_map(MapVisitor(PhantomData))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use crate::{DomainMap, DomainPattern};
+
+    fn pattern(s: &str) -> DomainPattern<'_> {
+        s.try_into().expect("failed to parse")
+    }
+
+    #[test]
+    pub fn test_map_serde() {
+        let mut map = DomainMap::new();
+        map.insert(pattern("**.example.com"), HashMap::from([("upstream", "default")]));
+        map.insert_with_priority(pattern("api.+.com"), HashMap::from([("upstream", "api")]), 2);
+        map.insert(pattern("!internal.example.com"), HashMap::new());
+
+        let json = serde_json::to_string(&map).expect("serializes");
+        assert!(json.starts_with("{\"version\":1,\"entries\":[{\"pattern\":\"**.example.com\",\"priority\":0,"), "{}", json);
+
+        let loaded: DomainMap<HashMap<String, String>> = serde_json::from_str(&json).expect("deserializes");
+        assert_eq!(loaded.len(), 3);
+        assert_eq!(loaded.priority(&pattern("api.+.com")), Some(2));
+        assert_eq!(loaded.get("api.example.com").and_then(|value| value.get("upstream")).map(String::as_str), Some("api"));
+        assert_eq!(loaded.get("www.example.com").and_then(|value| value.get("upstream")).map(String::as_str), Some("default"));
+        assert_eq!(loaded.get("internal.example.com"), None);
+        assert_eq!(serde_json::to_string(&loaded).expect("serializes"), json);
+
+        // fields a newer version added are ignored, as long as the version is one this version knows
+        let json = r#"{"entries":[{"pattern":"*.a.com","value":1,"comment":"new"}],"version":1,"checksum":"abc"}"#;
+        let loaded: DomainMap<u32> = serde_json::from_str(json).expect("deserializes");
+        assert_eq!(loaded.get("a.com"), Some(&1));
+        assert_eq!(loaded.priority(&pattern("*.a.com")), Some(0));
+
+        let err = serde_json::from_str::<DomainMap<u32>>(r#"{"version":2,"entries":[{"patterns":["*.a.com"]}]}"#).expect_err("newer version");
+        assert!(err.to_string().contains("unsupported domain map version 2"), "{}", err);
+        assert!(serde_json::from_str::<DomainMap<u32>>(r#"{"entries":[]}"#).is_err());
+        assert!(serde_json::from_str::<DomainMap<u32>>(r#"{"version":1,"entries":[{"pattern":"a.{b","value":1}]}"#).is_err());
+    }
+}
@@ -0,0 +1,69 @@
+//! Estimating how much memory patterns and sets take, capacities included, allocator overhead not
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::mem::size_of;
+use crate::{DomainPattern, DomainPatternPart};
+
+// only owned labels take memory of their own, which clippy can't know
+#[allow(clippy::ptr_arg)]
+pub(crate) fn cow(label: &Cow<'_, str>) -> usize {
+    match label {
+        Cow::Borrowed(_) => 0,
+        Cow::Owned(label) => label.capacity(),
+    }
+}
+
+pub(crate) fn vec<T>(vec: &[T], capacity: usize) -> usize {
+    debug_assert!(vec.len() <= capacity);
+    capacity * size_of::<T>()
+}
+
+// a hash map stores its entries and a control byte per bucket
+pub(crate) fn map<K, V, S>(map: &HashMap<K, V, S>) -> usize {
+    map.capacity() * (size_of::<(K, V)>() + 1)
+}
+
+pub(crate) fn part(part: &DomainPatternPart<'_>) -> usize {
+    match part {
+        DomainPatternPart::Static(label) | DomainPatternPart::Named(label) | DomainPatternPart::Glob(label) => cow(label),
+        DomainPatternPart::OneOf(labels) => vec(labels, labels.capacity()) + labels.iter().map(cow).sum::<usize>(),
+        DomainPatternPart::Wildcard(_) | DomainPatternPart::Numeric => 0,
+        // the compiled regex is opaque, its source is the least it takes
+        #[cfg(feature = "regex")]
+        DomainPatternPart::Regex(regex) => regex.as_str().len(),
+    }
+}
+
+impl<const SPLITTER: char> DomainPattern<'_, SPLITTER> {
+    /// Bytes the pattern takes on the heap, its steps once they don't fit inline and the labels it owns
+    ///
+    /// Borrowed labels take nothing, so a pattern parsed from a string takes less than one made owned.
+    pub fn heap_size(&self) -> usize {
+        #[cfg(feature = "smallvec")]
+        let steps = match self.steps.spilled() {
+            true => vec(&self.steps, self.steps.capacity()),
+            false => 0,
+        };
+
+        #[cfg(not(feature = "smallvec"))]
+        let steps = vec(&self.steps, self.steps.capacity());
+
+        steps + self.steps.iter().map(part).sum::<usize>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::DomainPattern;
+
+    #[test]
+    pub fn test_heap_size() {
+        let source = format!("*.{}.com", "a".repeat(1000));
+        let borrowed: DomainPattern = DomainPattern::parse(&source).expect("failed to parse");
+        let long = borrowed.to_owned();
+        let short = DomainPattern::<'_, '.'>::parse("*.a.com").expect("failed to parse").to_owned();
+        assert_eq!(long.heap_size() - short.heap_size(), 999);
+        assert!(borrowed.heap_size() < 1000);
+    }
+}
@@ -0,0 +1,234 @@
+//! Bringing domains and patterns into one form before matching them
+
+use std::borrow::Cow;
+use crate::{DomainPattern, DomainPatternPart, InvalidToken};
+
+/// Normalizes domains, and the labels of patterns, so both can be compared as is
+///
+/// Nothing is enabled by default, steps run in the order trim, strip trailing dot, NFC, IDNA, lowercase.
+/// [`MatchOptions`](crate::MatchOptions) uses the same steps for `case_insensitive`, `idna` and `nfc`.
+#[derive(Copy, Clone, Eq, PartialEq, Default, Debug)]
+pub struct DomainNormalizer<const SPLITTER: char = '.'> {
+    trim: bool,
+    strip_trailing_dot: bool,
+    #[cfg(feature = "unicode-normalization")]
+    nfc: bool,
+    #[cfg(feature = "idna")]
+    idna: bool,
+    lowercase: bool,
+}
+
+impl<const SPLITTER: char> DomainNormalizer<SPLITTER> {
+    pub const fn new() -> Self {
+        DomainNormalizer {
+            trim: false,
+            strip_trailing_dot: false,
+            #[cfg(feature = "unicode-normalization")]
+            nfc: false,
+            #[cfg(feature = "idna")]
+            idna: false,
+            lowercase: false,
+        }
+    }
+
+    /// Remove whitespace around the domain
+    pub const fn trim(mut self, trim: bool) -> Self {
+        self.trim = trim;
+        self
+    }
+
+    /// Remove a single trailing splitter, so `domain.tld.` becomes `domain.tld`
+    pub const fn strip_trailing_dot(mut self, strip_trailing_dot: bool) -> Self {
+        self.strip_trailing_dot = strip_trailing_dot;
+        self
+    }
+
+    /// Convert labels to Unicode normalization form C
+    #[cfg(feature = "unicode-normalization")]
+    pub const fn nfc(mut self, nfc: bool) -> Self {
+        self.nfc = nfc;
+        self
+    }
+
+    /// Convert labels to their A-label, so `bücher` becomes `xn--bcher-kva`
+    #[cfg(feature = "idna")]
+    pub const fn idna(mut self, idna: bool) -> Self {
+        self.idna = idna;
+        self
+    }
+
+    /// Lowercase ASCII letters
+    pub const fn lowercase(mut self, lowercase: bool) -> Self {
+        self.lowercase = lowercase;
+        self
+    }
+
+    // whether labels change other than their case
+    fn converts(&self) -> bool {
+        #[cfg(feature = "idna")]
+        if self.idna {
+            return true;
+        }
+
+        #[cfg(feature = "unicode-normalization")]
+        if self.nfc {
+            return true;
+        }
+
+        false
+    }
+
+    pub fn normalize<'d>(&self, domain: &'d str) -> Cow<'d, str> {
+        let mut domain = domain;
+        if self.trim {
+            domain = domain.trim();
+        }
+
+        if self.strip_trailing_dot {
+            domain = domain.strip_suffix(SPLITTER).unwrap_or(domain);
+        }
+
+        if !self.converts() || domain.is_ascii() {
+            return self.fold(Cow::Borrowed(domain));
+        }
+
+        let mut converted = String::with_capacity(domain.len());
+        for (idx, label) in domain.split(SPLITTER).enumerate() {
+            if idx > 0 {
+                converted.push(SPLITTER);
+            }
+
+            converted.push_str(&self.label(label));
+        }
+
+        Cow::Owned(converted)
+    }
+
+    /// Normalizes a single label, which skips trimming and stripping the trailing dot
+    pub fn label<'l>(&self, label: &'l str) -> Cow<'l, str> {
+        if !self.converts() || label.is_ascii() {
+            return self.fold(Cow::Borrowed(label));
+        }
+
+        #[allow(unused_mut)]
+        let mut label = Cow::Borrowed(label);
+
+        #[cfg(feature = "unicode-normalization")]
+        if self.nfc {
+            if let Cow::Owned(composed) = nfc(&label) {
+                label = Cow::Owned(composed);
+            }
+        }
+
+        #[cfg(feature = "idna")]
+        if self.idna {
+            if let Ok(ascii) = idna::domain_to_ascii(&label) {
+                return self.fold(Cow::Owned(ascii));
+            }
+        }
+
+        self.fold(label)
+    }
+
+    fn fold<'l>(&self, label: Cow<'l, str>) -> Cow<'l, str> {
+        match self.lowercase && label.bytes().any(|b| b.is_ascii_uppercase()) {
+            true => Cow::Owned(label.to_ascii_lowercase()),
+            false => label,
+        }
+    }
+
+    // keeps the borrow of the original label if normalizing didn't change it
+    fn label_cow<'l>(&self, label: &Cow<'l, str>) -> Cow<'l, str> {
+        match self.label(label) {
+            Cow::Borrowed(_) => label.clone(),
+            Cow::Owned(normalized) => Cow::Owned(normalized),
+        }
+    }
+
+    /// Parses the pattern after trimming it and stripping its trailing dot, with its labels normalized,
+    /// so it matches domains normalized by this normalizer
+    ///
+    /// Globs are only lowercased, regexes are left alone.
+    pub fn parse<'a>(&self, pattern: &'a str) -> Result<DomainPattern<'a, SPLITTER>, InvalidToken<'a>> {
+        let mut pattern = pattern;
+        if self.trim {
+            pattern = pattern.trim();
+        }
+
+        if self.strip_trailing_dot {
+            pattern = pattern.strip_suffix(SPLITTER).unwrap_or(pattern);
+        }
+
+        let parsed = DomainPattern::parse(pattern)?;
+        Ok(parsed.normalize(self))
+    }
+
+    /// Normalizes the domain, and then matches it against the pattern, which should have been parsed with [`DomainNormalizer::parse`]
+    pub fn matches(&self, pattern: &DomainPattern<'_, SPLITTER>, domain: &str) -> bool {
+        pattern.matches(&self.normalize(domain))
+    }
+}
+
+#[cfg(feature = "unicode-normalization")]
+pub(crate) fn nfc(label: &str) -> Cow<'_, str> {
+    use unicode_normalization::UnicodeNormalization;
+
+    match unicode_normalization::is_nfc(label) {
+        true => Cow::Borrowed(label),
+        false => Cow::Owned(label.nfc().collect()),
+    }
+}
+
+impl<'a, const SPLITTER: char> DomainPattern<'a, SPLITTER> {
+    // the pattern as it's compared against a domain normalized by the normalizer
+    pub(crate) fn normalize(&self, normalizer: &DomainNormalizer<SPLITTER>) -> DomainPattern<'a, SPLITTER> {
+        let glob = |glob: &Cow<'a, str>| match normalizer.lowercase && glob.bytes().any(|b| b.is_ascii_uppercase()) {
+            true => Cow::Owned(glob.to_ascii_lowercase()),
+            false => glob.clone(),
+        };
+
+        DomainPattern {
+            steps: self.steps.iter().map(|part| match part {
+                DomainPatternPart::Static(label) => DomainPatternPart::Static(normalizer.label_cow(label)),
+                DomainPatternPart::Glob(g) => DomainPatternPart::Glob(glob(g)),
+                DomainPatternPart::OneOf(labels) => DomainPatternPart::OneOf(labels.iter().map(|label| normalizer.label_cow(label)).collect()),
+                part => part.clone(),
+            }).collect(),
+            negated: self.negated,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{DomainNormalizer, DomainPattern};
+
+    #[test]
+    pub fn test_normalize() {
+        let normalizer: DomainNormalizer = DomainNormalizer::new().trim(true).strip_trailing_dot(true).lowercase(true);
+        assert_eq!(normalizer.normalize(" WWW.Example.com. "), "www.example.com");
+        assert_eq!(normalizer.normalize("www.example.com"), "www.example.com");
+        assert_eq!(normalizer.label("Api"), "api");
+
+        let plain: DomainNormalizer = DomainNormalizer::new();
+        assert_eq!(plain.normalize(" WWW.Example.com. "), " WWW.Example.com. ");
+
+        let pattern = normalizer.parse(" API-*.{Www,cdn}.Example.com. ").expect("failed to parse");
+        assert!(normalizer.matches(&pattern, "api-v1.WWW.example.com"));
+        assert!(normalizer.matches(&pattern, "API-v1.cdn.example.com.\n"));
+        assert!(!normalizer.matches(&pattern, "api-v1.web.example.com"));
+
+        let pattern: DomainPattern = "API.example.com".try_into().expect("failed to parse");
+        assert!(!normalizer.matches(&pattern, "api.example.com"));
+    }
+
+    #[cfg(feature = "idna")]
+    #[test]
+    pub fn test_normalize_idna() {
+        let normalizer: DomainNormalizer = DomainNormalizer::new().idna(true).lowercase(true);
+        assert_eq!(normalizer.normalize("WWW.Bücher.example"), "www.xn--bcher-kva.example");
+
+        let pattern = normalizer.parse("*.bücher.example").expect("failed to parse");
+        assert!(normalizer.matches(&pattern, "WWW.xn--bcher-kva.example"));
+    }
+}
@@ -0,0 +1,509 @@
+//! Options changing how domains are matched
+
+use std::borrow::Cow;
+use crate::{dialect, host, hostname, split, split_labels, DomainNormalizer, DomainPattern, InvalidToken, ParseErrorKind};
+#[cfg(feature = "unicode-normalization")]
+use crate::{normalize, DomainPatternPart};
+
+/// Options for matching, see [`DomainPattern::matches_with`] and [`DomainPatternSet::with_options`](crate::DomainPatternSet::with_options)
+///
+/// The default matches exactly like [`DomainPattern::matches`].
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct MatchOptions {
+    /// Compare labels ignoring ASCII case, like DNS does (RFC 4343)
+    ///
+    /// Regexes are matched as written, use `(?i)` in the regex itself.
+    pub case_insensitive: bool,
+    /// Compare labels by their A-label (punycode), so `bücher` and `xn--bcher-kva` are the same label
+    ///
+    /// Only static labels and `{a,b}` groups are converted, globs and regexes see the A-label of the domain.
+    #[cfg(feature = "idna")]
+    pub idna: bool,
+    /// Compare labels in Unicode normalization form C, so a decomposed `é` matches a composed one
+    #[cfg(feature = "unicode-normalization")]
+    pub nfc: bool,
+    /// What to do with absolute domains, like `domain.tld.`
+    pub trailing_dot: TrailingDot,
+    /// Only match domains passing [`validate_hostname`](crate::validate_hostname), checked after converting to A-labels with `idna`
+    pub validate_input: bool,
+    /// Whether the root domain can match, on by default
+    ///
+    /// A domain without any labels, like `""`, `.` or `..`, is the root domain. It's matched by patterns that can take
+    /// no labels at all, like `*`, `**` or `*{0,2}`, and never by patterns with a static label, `+` or `#`.
+    /// The empty pattern `""` is a single empty label, and like any pattern with an empty label never matches.
+    pub root_matches: bool,
+    /// Strip a `:port` suffix from the input first, so `Host` headers like `example.com:8080` can be matched as is
+    ///
+    /// IPv6 literals like `[::1]:8080` never match then, see [`DomainPattern::match_host`].
+    pub strip_port: bool,
+    /// Which end of the domain labels are compared from, which doesn't change what matches, only how fast
+    pub direction: Direction,
+}
+
+impl Default for MatchOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// How a domain ending in the splitter, like `domain.tld.`, is matched, see [`MatchOptions::trailing_dot`]
+#[derive(Copy, Clone, Eq, PartialEq, Default, Debug)]
+pub enum TrailingDot {
+    /// `domain.tld.` is the same domain as `domain.tld`
+    #[default]
+    Ignore,
+    /// Only absolute domains like `domain.tld.` can match
+    Require,
+    /// Absolute domains like `domain.tld.` never match
+    Forbid,
+}
+
+/// Which end of the domain the matcher starts at, see [`MatchOptions::direction`]
+///
+/// Patterns like `**.example.com` are anchored at the TLD, starting from the right a domain in another TLD is
+/// rejected after a single label, while starting from the left every label has to be looked at first.
+/// Patterns of more than 127 parts, and traced matching like [`DomainPattern::explain`], always go left to right.
+#[derive(Copy, Clone, Eq, PartialEq, Default, Debug)]
+pub enum Direction {
+    /// Right to left when the pattern ends in a label that has to be compared, but starts with one that doesn't
+    #[default]
+    Auto,
+    LeftToRight,
+    RightToLeft,
+}
+
+impl MatchOptions {
+    pub const fn new() -> Self {
+        MatchOptions {
+            case_insensitive: false,
+            #[cfg(feature = "idna")]
+            idna: false,
+            #[cfg(feature = "unicode-normalization")]
+            nfc: false,
+            trailing_dot: TrailingDot::Ignore,
+            validate_input: false,
+            root_matches: true,
+            strip_port: false,
+            direction: Direction::Auto,
+        }
+    }
+
+    pub const fn case_insensitive(mut self, case_insensitive: bool) -> Self {
+        self.case_insensitive = case_insensitive;
+        self
+    }
+
+    #[cfg(feature = "idna")]
+    pub const fn idna(mut self, idna: bool) -> Self {
+        self.idna = idna;
+        self
+    }
+
+    #[cfg(feature = "unicode-normalization")]
+    pub const fn nfc(mut self, nfc: bool) -> Self {
+        self.nfc = nfc;
+        self
+    }
+
+    pub const fn trailing_dot(mut self, trailing_dot: TrailingDot) -> Self {
+        self.trailing_dot = trailing_dot;
+        self
+    }
+
+    pub const fn validate_input(mut self, validate_input: bool) -> Self {
+        self.validate_input = validate_input;
+        self
+    }
+
+    pub const fn root_matches(mut self, root_matches: bool) -> Self {
+        self.root_matches = root_matches;
+        self
+    }
+
+    pub const fn strip_port(mut self, strip_port: bool) -> Self {
+        self.strip_port = strip_port;
+        self
+    }
+
+    pub const fn direction(mut self, direction: Direction) -> Self {
+        self.direction = direction;
+        self
+    }
+
+    // whether the converted domain can match at all, going by its trailing splitter and validity
+    pub(crate) fn allows<const SPLITTER: char>(&self, domain: &str) -> bool {
+        if self.validate_input && hostname::validate::<SPLITTER>(domain).is_err() {
+            return false;
+        }
+
+        if self.strip_port && host::ipv6_literal(domain).is_some() {
+            return false;
+        }
+
+        if !self.root_matches && split::labels::<SPLITTER>(domain).all(str::is_empty) {
+            return false;
+        }
+
+        match self.trailing_dot {
+            TrailingDot::Ignore => true,
+            TrailingDot::Require => domain.ends_with(SPLITTER),
+            TrailingDot::Forbid => !domain.ends_with(SPLITTER),
+        }
+    }
+
+    // whether patterns have to be normalized before they can be compared as is against a normalized domain
+    pub(crate) fn normalizes(&self) -> bool {
+        #[cfg(feature = "idna")]
+        if self.idna {
+            return true;
+        }
+
+        #[cfg(feature = "unicode-normalization")]
+        if self.nfc {
+            return true;
+        }
+
+        self.case_insensitive
+    }
+
+    // the steps converting labels before they're compared, without folding their case
+    fn converter<const SPLITTER: char>(&self) -> DomainNormalizer<SPLITTER> {
+        let normalizer = DomainNormalizer::new();
+        #[cfg(feature = "unicode-normalization")]
+        let normalizer = normalizer.nfc(self.nfc);
+        #[cfg(feature = "idna")]
+        let normalizer = normalizer.idna(self.idna);
+        normalizer
+    }
+
+    // the steps normalizing both domains and patterns when they're compared as is
+    pub(crate) fn normalizer<const SPLITTER: char>(&self) -> DomainNormalizer<SPLITTER> {
+        self.converter().lowercase(self.case_insensitive)
+    }
+
+    // the label in NFC when matching with nfc, and then as A-label when matching with idna
+    pub(crate) fn comparable_label<'l>(&self, label: &'l str) -> Cow<'l, str> {
+        self.converter::<'.'>().label(label)
+    }
+
+    fn host<'d>(&self, domain: &'d str) -> &'d str {
+        match self.strip_port {
+            true => host::strip_port(domain),
+            false => domain,
+        }
+    }
+
+    // the domain without its port if asked for, with every label converted by comparable_label
+    pub(crate) fn comparable_domain<'d, const SPLITTER: char>(&self, domain: &'d str) -> Cow<'d, str> {
+        self.converter::<SPLITTER>().normalize(self.host(domain))
+    }
+
+    // the domain as it's compared against normalized patterns
+    pub(crate) fn normalize_domain<'d, const SPLITTER: char>(&self, domain: &'d str) -> Cow<'d, str> {
+        self.normalizer::<SPLITTER>().normalize(self.host(domain))
+    }
+}
+
+/// Options for parsing, see [`DomainPattern::parse_with`]
+///
+/// The default parses exactly like [`DomainPattern::parse`].
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct ParseOptions {
+    /// Convert labels to Unicode normalization form C, so the pattern matches composed input with [`DomainPattern::matches`]
+    #[cfg(feature = "unicode-normalization")]
+    pub nfc: bool,
+    /// Accept empty labels like in `a..b`, `.a` or `a.`, and empty patterns, on by default, other dialects never do
+    pub allow_empty_labels: bool,
+    /// The syntax of the pattern, [`ParseDialect::Native`] by default
+    pub dialect: ParseDialect,
+}
+
+/// The syntax patterns are written in, for taking them from other software as is
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub enum ParseDialect {
+    /// Patterns like `*.{tenant}.example.com`, see [`DomainPattern::parse`]
+    #[default]
+    Native,
+    /// nginx `server_name`s, `*.example.com` takes one or more labels in front, `www.example.*` one or more at the end,
+    /// `.example.com` is `example.com` and all of its subdomains, and `~` starts a regex, as far as
+    /// [`DomainPattern::from_simple_regex`] can translate it
+    Nginx,
+    /// Squid `dstdomain` ACLs, `.example.com` is `example.com` and all of its subdomains, and anything else only the
+    /// domain itself
+    Squid,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ParseOptions {
+    pub const fn new() -> Self {
+        ParseOptions {
+            #[cfg(feature = "unicode-normalization")]
+            nfc: false,
+            allow_empty_labels: true,
+            dialect: ParseDialect::Native,
+        }
+    }
+
+    pub const fn dialect(mut self, dialect: ParseDialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+
+    pub const fn allow_empty_labels(mut self, allow_empty_labels: bool) -> Self {
+        self.allow_empty_labels = allow_empty_labels;
+        self
+    }
+
+    #[cfg(feature = "unicode-normalization")]
+    pub const fn nfc(mut self, nfc: bool) -> Self {
+        self.nfc = nfc;
+        self
+    }
+}
+
+fn check_empty_labels<const SPLITTER: char>(full: &str) -> Result<(), InvalidToken<'_>> {
+    let pattern = full.strip_prefix('!').unwrap_or(full);
+    let mut offset = full.len() - pattern.len();
+    if pattern.is_empty() {
+        return Err(InvalidToken::new(ParseErrorKind::EmptyPattern, offset, pattern, full));
+    }
+
+    let mut labels = split_labels(pattern, SPLITTER).peekable();
+    let mut first = true;
+    while let Some(label) = labels.next() {
+        if label.is_empty() {
+            let kind = match (first, labels.peek().is_none()) {
+                (true, _) => ParseErrorKind::LeadingSplitter,
+                (false, true) => ParseErrorKind::TrailingSplitter,
+                (false, false) => ParseErrorKind::EmptyLabel,
+            };
+
+            return Err(InvalidToken::new(kind, offset, label, full));
+        }
+
+        first = false;
+        offset += label.len() + SPLITTER.len_utf8();
+    }
+
+    Ok(())
+}
+
+impl<'a, const SPLITTER: char> DomainPattern<'a, SPLITTER> {
+    pub fn parse_with(pattern: &'a str, options: &ParseOptions) -> Result<Self, InvalidToken<'a>> {
+        #[allow(unused_mut)]
+        let mut parsed = match options.dialect {
+            ParseDialect::Native => {
+                if !options.allow_empty_labels {
+                    check_empty_labels::<SPLITTER>(pattern)?;
+                }
+
+                DomainPattern::parse(pattern)?
+            }
+            ParseDialect::Nginx => dialect::nginx(pattern)?,
+            ParseDialect::Squid => dialect::squid(pattern)?,
+        };
+
+        #[cfg(feature = "unicode-normalization")]
+        if options.nfc {
+            let compose = |label: &mut Cow<'a, str>| {
+                if let Cow::Owned(composed) = normalize::nfc(label) {
+                    *label = Cow::Owned(composed);
+                }
+            };
+
+            for part in parsed.steps.iter_mut() {
+                match part {
+                    DomainPatternPart::Static(label) | DomainPatternPart::Glob(label) => compose(label),
+                    DomainPatternPart::OneOf(labels) => labels.iter_mut().for_each(compose),
+                    _ => {}
+                }
+            }
+        }
+
+        Ok(parsed)
+    }
+
+    /// Parses like [`DomainPattern::parse`], but rejects empty labels and empty patterns
+    pub fn parse_strict(pattern: &'a str) -> Result<Self, InvalidToken<'a>> {
+        Self::parse_with(pattern, &ParseOptions::new().allow_empty_labels(false))
+    }
+
+    /// Matches a `Host` header like `example.com:8080`, see [`MatchOptions::strip_port`]
+    pub fn matches_host(&self, host: &str) -> bool {
+        self.matches_with(host, &MatchOptions::new().strip_port(true))
+    }
+
+    pub fn matches_with(&self, domain: &str, options: &MatchOptions) -> bool {
+        match self.run(domain, options, &mut ()) {
+            Ok(matched) => matched,
+            Err(never) => match never {},
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::DomainPatternSet;
+    use crate::{DomainPattern, MatchOptions, ParseErrorKind, ParseOptions, TrailingDot};
+
+    #[cfg(feature = "idna")]
+    #[test]
+    pub fn test_idna() {
+        let options = MatchOptions::new().idna(true);
+        let pattern: DomainPattern = "*.bücher.example".try_into().expect("failed to parse");
+        assert!(pattern.matches_with("xn--bcher-kva.example", &options));
+        assert!(pattern.matches_with("www.bücher.example", &options));
+        assert!(!pattern.matches("xn--bcher-kva.example"));
+
+        let pattern: DomainPattern = "{xn--bcher-kva,books}.example".try_into().expect("failed to parse");
+        assert!(pattern.matches_with("bücher.example", &options));
+        assert!(!pattern.matches_with("bucher.example", &options));
+
+        let mut set: DomainPatternSet = DomainPatternSet::with_options(options.case_insensitive(true));
+        set.insert("*.Bücher.example".try_into().expect("failed to parse"));
+        assert!(set.contains_match("WWW.xn--bcher-kva.example"));
+        assert!(set.contains_match("www.BÜCHER.example"));
+    }
+
+    #[cfg(feature = "unicode-normalization")]
+    #[test]
+    pub fn test_nfc() {
+        let decomposed = "cafe\u{301}.example";
+        let composed = "caf\u{e9}.example";
+
+        let pattern: DomainPattern = DomainPattern::parse_with(decomposed, &ParseOptions::new().nfc(true)).expect("failed to parse");
+        assert!(pattern.matches(composed));
+        assert!(!pattern.matches(decomposed));
+        assert!(pattern.matches_with(decomposed, &MatchOptions::new().nfc(true)));
+
+        let pattern: DomainPattern = decomposed.try_into().expect("failed to parse");
+        assert!(!pattern.matches(composed));
+        assert!(pattern.matches_with(composed, &MatchOptions::new().nfc(true)));
+
+        let mut set: DomainPatternSet = DomainPatternSet::with_options(MatchOptions::new().nfc(true));
+        set.insert("*.{cafe\u{301},bar}.example".try_into().expect("failed to parse"));
+        assert!(set.contains_match("www.caf\u{e9}.example"));
+        assert!(set.contains_match("cafe\u{301}.example"));
+    }
+
+    #[test]
+    pub fn test_strict() {
+        let strict = |pattern| DomainPattern::<'_, '.'>::parse_strict(pattern).map(|_| ()).map_err(|err| (err.kind(), err.position()));
+        assert_eq!(strict("a.b"), Ok(()));
+        assert_eq!(strict("!a.*.b"), Ok(()));
+        assert_eq!(strict(""), Err((ParseErrorKind::EmptyPattern, 0)));
+        assert_eq!(strict("!"), Err((ParseErrorKind::EmptyPattern, 1)));
+        assert_eq!(strict("a..b"), Err((ParseErrorKind::EmptyLabel, 2)));
+        assert_eq!(strict(".a"), Err((ParseErrorKind::LeadingSplitter, 0)));
+        assert_eq!(strict("!a."), Err((ParseErrorKind::TrailingSplitter, 3)));
+        assert_eq!(strict("a.b+"), Err((ParseErrorKind::InvalidToken, 2)));
+
+        assert!(DomainPattern::<'_, '.'>::parse_with("a..b", &ParseOptions::default()).is_ok());
+        assert!(DomainPattern::<'_, '.'>::parse("").is_ok());
+
+        let err = DomainPattern::<'_, '.'>::parse_strict("a..b").expect_err("should not parse");
+        assert_eq!(err.to_string(), "Empty label at position 2 in pattern \"a..b\"");
+    }
+
+    #[test]
+    pub fn test_validate_input() {
+        let options = MatchOptions::new().validate_input(true);
+        let pattern: DomainPattern = "**.domain.tld".try_into().expect("failed to parse");
+        assert!(pattern.matches_with("www.domain.tld", &options));
+        assert!(pattern.matches("www..domain.tld"));
+        assert!(!pattern.matches_with("www..domain.tld", &options));
+        assert!(!pattern.matches_with("w w.domain.tld", &options));
+
+        let mut set: DomainPatternSet = DomainPatternSet::with_options(options);
+        set.insert(pattern);
+        assert!(set.contains_match("www.domain.tld"));
+        assert!(!set.contains_match(&format!("{}.domain.tld", "a".repeat(64))));
+    }
+
+    #[test]
+    pub fn test_root() {
+        let no_root = MatchOptions::new().root_matches(false);
+        for pattern in ["*", "**", "*{0,2}"] {
+            let pattern: DomainPattern = pattern.try_into().expect("failed to parse");
+            for root in ["", ".", ".."] {
+                assert!(pattern.matches(root), "{:?} should match {:?}", pattern, root);
+                assert!(!pattern.matches_with(root, &no_root), "{:?} shouldn't match {:?}", pattern, root);
+            }
+        }
+
+        for pattern in ["", "a..b", "com", "+", "**+", "#", "*.com"] {
+            let pattern: DomainPattern = pattern.try_into().expect("failed to parse");
+            assert!(!pattern.matches(""), "{:?} shouldn't match the root", pattern);
+            assert!(!pattern.matches("."), "{:?} shouldn't match the root", pattern);
+        }
+
+        let pattern: DomainPattern = "**".try_into().expect("failed to parse");
+        assert!(pattern.matches_with("com", &no_root));
+        assert!(pattern.matches_with(".", &MatchOptions::new().trailing_dot(TrailingDot::Require)));
+        assert!(!pattern.matches_with("", &MatchOptions::new().trailing_dot(TrailingDot::Require)));
+
+        let mut set: DomainPatternSet = DomainPatternSet::with_options(no_root);
+        set.insert(pattern);
+        assert!(!set.contains_match("."));
+        assert!(set.contains_match("com"));
+    }
+
+    #[test]
+    pub fn test_strip_port() {
+        let pattern: DomainPattern = "+.example.com".try_into().expect("failed to parse");
+        assert!(pattern.matches_host("www.example.com:8080"));
+        assert!(pattern.matches_host("www.example.com:"));
+        assert!(pattern.matches_host("www.example.com"));
+        assert!(!pattern.matches("www.example.com:8080"));
+        assert!(!pattern.matches_host("example.com:8080"));
+
+        let mut set: DomainPatternSet = DomainPatternSet::with_options(MatchOptions::new().strip_port(true).case_insensitive(true));
+        set.insert("example.com".try_into().expect("failed to parse"));
+        assert!(set.contains_match("Example.com:443"));
+        assert_eq!(set.matches_all("example.com:80").len(), 1);
+
+        set.insert("*".try_into().expect("failed to parse"));
+        assert!(!set.contains_match("[::1]:443"));
+        assert!(!set.contains_match("::1"));
+    }
+
+    #[test]
+    pub fn test_trailing_dot() {
+        let pattern: DomainPattern = "*.domain.tld".try_into().expect("failed to parse");
+        assert!(pattern.matches("domain.tld."));
+        assert!(pattern.matches_with("sub.domain.tld.", &MatchOptions::new().trailing_dot(TrailingDot::Ignore)));
+
+        let require = MatchOptions::new().trailing_dot(TrailingDot::Require);
+        assert!(pattern.matches_with("sub.domain.tld.", &require));
+        assert!(!pattern.matches_with("sub.domain.tld", &require));
+
+        let forbid = MatchOptions::new().trailing_dot(TrailingDot::Forbid);
+        assert!(!pattern.matches_with("sub.domain.tld.", &forbid));
+        assert!(pattern.matches_with("sub.domain.tld", &forbid));
+
+        let mut set: DomainPatternSet = DomainPatternSet::with_options(require);
+        set.insert("domain.tld".try_into().expect("failed to parse"));
+        set.insert("*.domain.tld".try_into().expect("failed to parse"));
+        assert!(set.contains_match("domain.tld."));
+        assert!(!set.contains_match("domain.tld"));
+        assert_eq!(set.matches_all("sub.domain.tld").len(), 0);
+        assert_eq!(set.matches_all("domain.tld.").len(), 2);
+    }
+
+    #[test]
+    pub fn test_case_insensitive() {
+        let options = MatchOptions::new().case_insensitive(true);
+        let pattern: DomainPattern = "API-*.{www,Cdn}.node[A-C].Example.com".try_into().expect("failed to parse");
+        assert!(pattern.matches_with("api-v1.WWW.nodeb.example.COM", &options));
+        assert!(pattern.matches_with("Api-V1.cdn.NODEA.example.com", &options));
+        assert!(!pattern.matches_with("api-v1.www.noded.example.com", &options));
+        assert!(!pattern.matches("api-v1.www.nodeb.example.com"));
+        assert!(!pattern.matches_with("api-v1.www.nodeb.example.com", &MatchOptions::default()));
+        assert!(pattern.matches("API-v1.www.nodeB.Example.com"));
+    }
+}
@@ -0,0 +1,206 @@
+//! Matching the `Origin` of requests, like CORS middleware does
+
+use std::borrow::Cow;
+use std::fmt::{Display, Formatter};
+use crate::{DomainPattern, InvalidToken, MatchOptions};
+
+/// Why an origin pattern didn't parse, see [`OriginPattern::parse`]
+#[derive(Debug)]
+pub enum InvalidOrigin<'a> {
+    /// The scheme before `://` is empty, or has a character a scheme can't have
+    Scheme,
+    Host(InvalidToken<'a>),
+    /// The port isn't `*` or a number up to 65535
+    Port,
+}
+
+impl Display for InvalidOrigin<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InvalidOrigin::Scheme => write!(f, "Invalid scheme in origin pattern"),
+            InvalidOrigin::Host(err) => write!(f, "Invalid host in origin pattern: {}", err),
+            InvalidOrigin::Port => write!(f, "Invalid port in origin pattern"),
+        }
+    }
+}
+
+impl std::error::Error for InvalidOrigin<'_> {}
+
+/// Which ports an [`OriginPattern`] takes
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub enum OriginPort {
+    /// Only the default port of the scheme, like `443` for `https`, whether the origin spells it out or not
+    #[default]
+    Default,
+    /// `:*`, any port
+    Any,
+    Exact(u16),
+}
+
+/// A pattern for origins like `https://api.example.com:8443`, a scheme, a [`DomainPattern`] for the host, and a port
+///
+/// `https://*.app.example.com:8443` takes `https` origins on port 8443 of `app.example.com` and its subdomains. Without
+/// a scheme, or with `*://`, any scheme is taken, without a port only the default port of the scheme, and with `:*`
+/// any port. Schemes and hosts are compared ignoring ASCII case, and IPv6 hosts like `[::1]` never match.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub struct OriginPattern<'a> {
+    scheme: Option<Cow<'a, str>>,
+    host: DomainPattern<'a>,
+    port: OriginPort,
+}
+
+// the port an origin has when it doesn't spell one out
+pub(crate) fn default_port(scheme: &str) -> Option<u16> {
+    match scheme.to_ascii_lowercase().as_str() {
+        "http" | "ws" => Some(80),
+        "https" | "wss" => Some(443),
+        "ftp" => Some(21),
+        _ => None,
+    }
+}
+
+pub(crate) fn is_scheme(scheme: &str) -> bool {
+    scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'+' || b == b'-' || b == b'.')
+}
+
+impl<'a> OriginPattern<'a> {
+    pub fn parse(pattern: &'a str) -> Result<Self, InvalidOrigin<'a>> {
+        let (scheme, authority) = match pattern.split_once("://") {
+            Some(("*", authority)) => (None, authority),
+            Some((scheme, _)) if !is_scheme(scheme) => return Err(InvalidOrigin::Scheme),
+            Some((scheme, authority)) => (Some(scheme), authority),
+            None => (None, pattern),
+        };
+
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, "*")) => (host, OriginPort::Any),
+            Some((host, port)) => (host, OriginPort::Exact(port.parse().map_err(|_| InvalidOrigin::Port)?)),
+            None => (authority, OriginPort::Default),
+        };
+
+        let scheme = scheme.map(|scheme| match scheme.bytes().any(|b| b.is_ascii_uppercase()) {
+            true => Cow::Owned(scheme.to_ascii_lowercase()),
+            false => Cow::Borrowed(scheme),
+        });
+
+        Ok(OriginPattern {
+            scheme,
+            host: DomainPattern::parse(host).map_err(InvalidOrigin::Host)?,
+            port,
+        })
+    }
+
+    /// The scheme the origin has to have, lowercased, or None for any scheme
+    pub fn scheme(&self) -> Option<&str> {
+        self.scheme.as_deref()
+    }
+
+    pub fn host(&self) -> &DomainPattern<'a> {
+        &self.host
+    }
+
+    pub fn port(&self) -> OriginPort {
+        self.port
+    }
+
+    /// Whether the pattern takes the value of an `Origin` header, like `https://www.example.com`
+    ///
+    /// Anything that isn't a `scheme://host[:port]` origin, like `null` or a URL with a path, never matches.
+    pub fn matches(&self, origin: &str) -> bool {
+        let Some((scheme, authority)) = origin.split_once("://") else {
+            return false;
+        };
+
+        if !is_scheme(scheme) || self.scheme.as_ref().is_some_and(|expected| !scheme.eq_ignore_ascii_case(expected)) {
+            return false;
+        }
+
+        if authority.is_empty() || authority.starts_with('[') || authority.contains(['/', '?', '#', '@']) {
+            return false;
+        }
+
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) => match port.parse::<u16>() {
+                Ok(port) => (host, Some(port)),
+                Err(_) => return false,
+            },
+            None => (authority, None),
+        };
+
+        let default = default_port(scheme);
+        let port_matches = match self.port {
+            OriginPort::Any => true,
+            OriginPort::Default => port.is_none() || port == default,
+            OriginPort::Exact(expected) => port.or(default) == Some(expected),
+        };
+
+        port_matches && !host.is_empty() && self.host.matches_with(host, &MatchOptions::new().case_insensitive(true))
+    }
+
+    pub fn into_owned(self) -> OriginPattern<'static> {
+        OriginPattern {
+            scheme: self.scheme.map(|scheme| Cow::Owned(scheme.into_owned())),
+            host: self.host.into_owned(),
+            port: self.port,
+        }
+    }
+}
+
+impl<'a> TryFrom<&'a str> for OriginPattern<'a> {
+    type Error = InvalidOrigin<'a>;
+
+    fn try_from(pattern: &'a str) -> Result<Self, Self::Error> {
+        OriginPattern::parse(pattern)
+    }
+}
+
+impl Display for OriginPattern<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}://{}", self.scheme.as_deref().unwrap_or("*"), self.host)?;
+        match self.port {
+            OriginPort::Default => Ok(()),
+            OriginPort::Any => write!(f, ":*"),
+            OriginPort::Exact(port) => write!(f, ":{}", port),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{InvalidOrigin, OriginPattern, OriginPort};
+
+    #[test]
+    pub fn test_origin_pattern() {
+        let pattern = OriginPattern::parse("https://*.app.example.com:8443").expect("valid");
+        assert_eq!(pattern.scheme(), Some("https"));
+        assert_eq!(pattern.port(), OriginPort::Exact(8443));
+        assert_eq!(pattern.to_string(), "https://*.app.example.com:8443");
+        for (origin, matches) in [("https://app.example.com:8443", true), ("https://www.app.example.com:8443", true),
+            ("HTTPS://WWW.App.Example.com:8443", true), ("https://www.app.example.com", false), ("http://www.app.example.com:8443", false),
+            ("https://a.b.app.example.com:8443", false), ("https://app.example.com:8443/path", false), ("null", false)] {
+            assert_eq!(pattern.matches(origin), matches, "{:?}", origin);
+        }
+
+        let pattern = OriginPattern::parse("https://**.example.com").expect("valid");
+        for (origin, matches) in [("https://example.com", true), ("https://a.b.example.com:443", true), ("https://example.com:8443", false),
+            ("http://example.com", false), ("https://example.com:x", false), ("https://[::1]", false)] {
+            assert_eq!(pattern.matches(origin), matches, "{:?}", origin);
+        }
+
+        let pattern = OriginPattern::parse("localhost:*").expect("valid");
+        assert_eq!(pattern.to_string(), "*://localhost:*");
+        assert!(pattern.matches("http://localhost:3000"));
+        assert!(pattern.matches("chrome-extension://localhost"));
+        assert!(!pattern.matches("http://localhost.example.com:3000"));
+
+        let pattern = OriginPattern::parse("*://example.com:80").expect("valid");
+        assert!(pattern.matches("http://example.com"));
+        assert!(!pattern.matches("https://example.com"));
+        assert!(pattern.into_owned().matches("ws://example.com:80"));
+
+        assert!(matches!(OriginPattern::parse("1http://example.com"), Err(InvalidOrigin::Scheme)));
+        assert!(matches!(OriginPattern::parse("https://example.com:65536"), Err(InvalidOrigin::Port)));
+        assert!(matches!(OriginPattern::parse("https://{a.com"), Err(InvalidOrigin::Host(_))));
+    }
+}
@@ -0,0 +1,190 @@
+//! Translating patterns into regexes, for systems that only take regexes
+
+use std::collections::HashSet;
+use crate::{glob, DomainPattern, DomainPatternPart, DomainPatternWildcard};
+
+fn escape(c: char, out: &mut String) {
+    if matches!(c, '\\' | '.' | '+' | '*' | '?' | '(' | ')' | '|' | '[' | ']' | '{' | '}' | '^' | '$' | '#' | '&' | '-' | '~') {
+        out.push('\\');
+    }
+
+    out.push(c);
+}
+
+// where a part is compared to the first part that takes a label
+#[derive(Copy, Clone, Eq, PartialEq)]
+enum Side {
+    // parts before it, which take their labels with the splitter after them
+    Before,
+    // the first part that takes a label, without any splitter
+    First,
+    // parts after it take their labels with the splitter in front
+    After,
+}
+
+struct Translator<'p, 'a> {
+    splitter: String,
+    parts: &'p [DomainPatternPart<'a>],
+    names: HashSet<&'p str>,
+}
+
+impl<'p, 'a> Translator<'p, 'a> {
+    // a regex for a single label the part takes
+    fn label(&mut self, part: &'p DomainPatternPart<'a>) -> Option<String> {
+        let mut out = String::new();
+        match part {
+            DomainPatternPart::Static(label) if label.is_empty() => out.push_str("[^\\s\\S]"),
+            DomainPatternPart::Static(label) => label.chars().for_each(|c| escape(c, &mut out)),
+            DomainPatternPart::Wildcard(_) => out.push_str(&format!("[^{}]+", self.splitter)),
+            DomainPatternPart::Named(name) => {
+                let valid = name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') && !name.starts_with(|c: char| c.is_ascii_digit());
+                match valid && self.names.insert(name) {
+                    true => out.push_str(&format!("(?P<{}>[^{}]+)", name, self.splitter)),
+                    false => out.push_str(&format!("[^{}]+", self.splitter)),
+                }
+            }
+            DomainPatternPart::Glob(g) => glob::to_regex(g, &self.splitter, escape, &mut out),
+            DomainPatternPart::OneOf(labels) => {
+                out.push_str("(?:");
+                for (idx, label) in labels.iter().enumerate() {
+                    if idx > 0 {
+                        out.push('|');
+                    }
+
+                    label.chars().for_each(|c| escape(c, &mut out));
+                }
+                out.push(')');
+            }
+            DomainPatternPart::Numeric => out.push_str("[0-9]+"),
+            #[cfg(feature = "regex")]
+            DomainPatternPart::Regex(_) => return None,
+        }
+
+        Some(out)
+    }
+
+    // the parts, where the part at `first` is the first to take a label, and all parts before it take none
+    fn translate(&mut self, first: usize, out: &mut String) -> Option<()> {
+        let parts = self.parts;
+        for (idx, part) in parts.iter().enumerate() {
+            let side = match idx {
+                idx if idx < first => Side::Before,
+                idx if idx == first => Side::First,
+                _ => Side::After,
+            };
+
+            let label = self.label(part)?;
+            let (min, multi) = match part {
+                DomainPatternPart::Wildcard(DomainPatternWildcard { multi, optional }) => (!optional as usize, *multi),
+                _ => (1, false),
+            };
+
+            let quantifier = match (min, multi) {
+                (0, false) => "?",
+                (0, true) => "*",
+                (_, true) => "+",
+                _ => "",
+            };
+
+            match side {
+                Side::Before => out.push_str(&format!("(?:{}{}){}", label, self.splitter, quantifier)),
+                Side::First if multi => out.push_str(&format!("{}(?:{}{})*", label, self.splitter, label)),
+                Side::First => out.push_str(&label),
+                Side::After if quantifier.is_empty() => out.push_str(&format!("{}{}", self.splitter, label)),
+                Side::After => out.push_str(&format!("(?:{}{}){}", self.splitter, label, quantifier)),
+            }
+        }
+
+        Some(())
+    }
+}
+
+impl<const SPLITTER: char> DomainPattern<'_, SPLITTER> {
+    /// An anchored regex matching the same domains as the pattern, for systems that only take regexes, like Envoy or
+    /// nginx maps, so `*.example.com` becomes `^(?:[^\.]+\.)?example\.com$`
+    ///
+    /// The regex only takes domains without empty labels, so `example.com.` doesn't match it, and is case sensitive,
+    /// prefix it with `(?i)` to ignore case. Named labels become named groups, if their name is a valid group name that
+    /// wasn't used yet. Whether the pattern is negated is ignored, and regex labels can't be translated, so patterns with
+    /// one return None.
+    pub fn to_regex_string(&self) -> Option<String> {
+        let mut escaped_splitter = String::new();
+        escape(SPLITTER, &mut escaped_splitter);
+
+        let parts = &self.steps[..];
+        let mut translator = Translator {
+            splitter: escaped_splitter,
+            parts,
+            names: HashSet::new(),
+        };
+
+        let mut regex = String::from("^");
+        match parts.iter().position(|part| !matches!(part, DomainPatternPart::Wildcard(DomainPatternWildcard { optional: true, .. }))) {
+            Some(first) => translator.translate(first, &mut regex)?,
+            // the first label can be taken by any of the parts, or there's no label at all
+            None => {
+                let mut alternatives = vec![];
+                for first in 0..parts.len() {
+                    let mut alternative = String::new();
+                    translator.translate(first, &mut alternative)?;
+                    alternatives.push(alternative);
+                }
+
+                if !alternatives.is_empty() {
+                    regex.push_str(&format!("(?:{})?", alternatives.join("|")));
+                }
+            }
+        }
+
+        regex.push('$');
+        Some(regex)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::DomainPattern;
+
+    fn regex(pattern: &str) -> String {
+        let pattern: DomainPattern = pattern.try_into().expect("failed to parse");
+        pattern.to_regex_string().expect("pattern can be translated")
+    }
+
+    #[test]
+    pub fn test_to_regex_string() {
+        assert_eq!(regex("example.com"), "^example\\.com$");
+        assert_eq!(regex("*.example.com"), "^(?:[^\\.]+\\.)?example\\.com$");
+        assert_eq!(regex("+.example.com"), "^[^\\.]+\\.example\\.com$");
+        assert_eq!(regex("**.example.com"), "^(?:[^\\.]+\\.)*example\\.com$");
+        assert_eq!(regex("**+.example.com"), "^[^\\.]+(?:\\.[^\\.]+)*\\.example\\.com$");
+        assert_eq!(regex("example.**"), "^example(?:\\.[^\\.]+)*$");
+        assert_eq!(regex("example.+.*"), "^example\\.[^\\.]+(?:\\.[^\\.]+)?$");
+        assert_eq!(regex("{api,www}.#.com"), "^(?:api|www)\\.[0-9]+\\.com$");
+        assert_eq!(regex("api-*.node[!0-9].com"), "^api\\-[^\\.]*\\.node[^0-9\\.]\\.com$");
+        assert_eq!(regex("{tenant}.{tenant}.com"), "^(?P<tenant>[^\\.]+)\\.[^\\.]+\\.com$");
+        assert_eq!(regex("tag\\+1.com"), "^tag\\+1\\.com$");
+        assert_eq!(regex("**"), "^(?:[^\\.]+(?:\\.[^\\.]+)*)?$");
+        assert_eq!(regex("*.*"), "^(?:[^\\.]+(?:\\.[^\\.]+)?|(?:[^\\.]+\\.)?[^\\.]+)?$");
+        assert_eq!(regex("a..b"), "^a\\.[^\\s\\S]\\.b$");
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    pub fn test_to_regex_string_agrees() {
+        let patterns = ["example.com", "*.example.com", "**.example.com", "**+.example.com", "example.**", "*.*", "**", "*.a.*",
+            "+.**.b", "{api,www}.#.com", "api-*.node[!0-9].com", "{tenant}.{tenant}.com", "us-?.[a-c]x*"];
+        let domains = ["", "com", "example.com", "api.example.com", "a.b.example.com", "example.org", "a", "a.b", "a.b.c", "x.a.y.z",
+            "a.x.b", "b", "api.42.com", "www.x.com", "api-v1.node-.com", "api-v1.node1.com", "t.u.com", "us-1.bx", "us-1.dxx", "us-12.a"];
+
+        for pattern in patterns {
+            let pattern: DomainPattern = pattern.try_into().expect("failed to parse");
+            let regex = regex::Regex::new(&pattern.to_regex_string().expect("pattern can be translated")).expect("valid regex");
+            for domain in domains {
+                assert_eq!(regex.is_match(domain), pattern.matches(domain), "{} and {} disagree on {:?}", pattern, regex, domain);
+            }
+        }
+
+        let pattern: DomainPattern = "~re:^v\\d+$~.com".try_into().expect("failed to parse");
+        assert_eq!(pattern.to_regex_string(), None);
+    }
+}
@@ -0,0 +1,387 @@
+//! How the sets of domains two patterns match relate to each other
+
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use crate::{glob, DomainPattern, DomainPatternPart, DomainPatternWildcard, MatchOptions};
+
+// a pattern broken down into parts that each take at most a single label at once
+enum Step<'p, 'a> {
+    // a single label the part accepts, None accepts any label
+    Label(Option<&'p DomainPatternPart<'a>>),
+    // a single label or nothing
+    Optional,
+    // any amount of labels
+    Repeat,
+}
+
+fn steps<'p, 'a, const SPLITTER: char>(pattern: &'p DomainPattern<'a, SPLITTER>) -> Vec<Step<'p, 'a>> {
+    let mut steps = vec![];
+    for part in pattern.steps.iter() {
+        match part {
+            DomainPatternPart::Wildcard(DomainPatternWildcard { multi: false, optional: true }) => steps.push(Step::Optional),
+            DomainPatternPart::Wildcard(DomainPatternWildcard { multi: false, optional: false }) | DomainPatternPart::Named(_) => steps.push(Step::Label(None)),
+            DomainPatternPart::Wildcard(DomainPatternWildcard { multi: true, optional: true }) => steps.push(Step::Repeat),
+            DomainPatternPart::Wildcard(DomainPatternWildcard { multi: true, optional: false }) => {
+                steps.push(Step::Label(None));
+                steps.push(Step::Repeat);
+            }
+            part => steps.push(Step::Label(Some(part))),
+        }
+    }
+
+    steps
+}
+
+// the states reachable from the given states without taking a label, the state after the last step accepts
+fn closure(steps: &[Step], states: impl IntoIterator<Item=usize>) -> BTreeSet<usize> {
+    let mut closure = BTreeSet::new();
+    for mut state in states {
+        while closure.insert(state) && matches!(steps.get(state), Some(Step::Optional | Step::Repeat)) {
+            state += 1;
+        }
+    }
+
+    closure
+}
+
+// the ways a state can take a label, with what labels it takes and the state it ends up in
+fn moves<'p, 'a>(steps: &[Step<'p, 'a>], state: usize) -> Option<(Option<&'p DomainPatternPart<'a>>, usize)> {
+    match steps.get(state)? {
+        Step::Label(part) => Some((*part, state + 1)),
+        Step::Optional => Some((None, state + 1)),
+        Step::Repeat => Some((None, state)),
+    }
+}
+
+fn accepts(part: Option<&DomainPatternPart>, label: &str) -> bool {
+    !label.is_empty() && part.is_none_or(|part| part.matches_label(label, &MatchOptions::new()))
+}
+
+// whether the part accepts every label the other part accepts
+fn accepts_all(part: Option<&DomainPatternPart>, other: Option<&DomainPatternPart>) -> bool {
+    match (part, other) {
+        (None, _) => true,
+        // a glob of only stars, at least one, and at most one `?` takes any label
+        (Some(DomainPatternPart::Glob(glob)), _) if glob.contains('*') && glob.chars().all(|c| c == '*' || c == '?') && glob.chars().filter(|c| *c == '?').count() <= 1 => true,
+        (Some(part), Some(other)) => part == other,
+        (Some(_), None) => false,
+    }
+}
+
+// the states after taking the label, or a subset of them that's reached for every label the part accepts
+fn advance(steps: &[Step], states: &BTreeSet<usize>, taken: Option<&DomainPatternPart>, label: Option<&str>) -> BTreeSet<usize> {
+    closure(steps, states.iter().filter_map(|state| {
+        let (part, next) = moves(steps, *state)?;
+        let takes = match label {
+            Some(label) => accepts(part, label),
+            None => accepts_all(part, taken),
+        };
+
+        takes.then_some(next)
+    }))
+}
+
+// what two parts have in common
+enum Shared {
+    Nothing,
+    // a label both parts accept
+    Label(String),
+    // a regex is involved and no label was found, but there might be one
+    Unknown,
+}
+
+// the labels a part accepts, if there's a limited amount of them
+fn finite<'p>(part: Option<&'p DomainPatternPart>) -> Option<Vec<&'p str>> {
+    match part {
+        Some(DomainPatternPart::Static(label)) => Some(vec![label]),
+        Some(DomainPatternPart::OneOf(labels)) => Some(labels.iter().map(|label| label.as_ref()).collect()),
+        _ => None,
+    }
+}
+
+// the labels a part accepts as glob atoms, not possible for regexes
+fn atoms<'p>(part: Option<&'p DomainPatternPart>) -> Option<Vec<glob::Atom<'p>>> {
+    match part {
+        None | Some(DomainPatternPart::Wildcard(_) | DomainPatternPart::Named(_)) => Some(glob::any_label()),
+        Some(DomainPatternPart::Static(label)) => Some(glob::literal(label)),
+        Some(DomainPatternPart::Glob(glob)) => Some(glob::atoms(glob)),
+        Some(DomainPatternPart::Numeric) => Some(glob::digits()),
+        _ => None,
+    }
+}
+
+fn shared(a: Option<&DomainPatternPart>, b: Option<&DomainPatternPart>) -> Shared {
+    let found = |label: Option<String>| label.filter(|label| !label.is_empty()).map_or(Shared::Nothing, Shared::Label);
+
+    for (finite_part, other) in [(a, b), (b, a)] {
+        if let Some(labels) = finite(finite_part) {
+            return found(labels.into_iter().find(|label| accepts(other, label)).map(str::to_string));
+        }
+    }
+
+    match (atoms(a), atoms(b)) {
+        (Some(a), Some(b)) => found(glob::intersection(&a, &b)),
+        (a_atoms, b_atoms) => {
+            // at least one is a regex, try a label the other part accepts
+            let samples = [a_atoms, b_atoms].into_iter().flatten().filter_map(|atoms| glob::intersection(&atoms, &glob::any_label()));
+            samples.chain(["a".to_string(), "0".to_string()])
+                .find(|label| accepts(a, label) && accepts(b, label))
+                .map_or(Shared::Unknown, Shared::Label)
+        }
+    }
+}
+
+// walks both patterns at once over labels both accept, and returns the labels of the shortest walk to a domain
+// both match, labels that are unknown are only taken if asked to, and left empty
+fn overlap(ours: &[Step], theirs: &[Step], unknown: bool) -> Option<Vec<String>> {
+    let mut from = HashMap::new();
+    let mut queue = VecDeque::new();
+    for i in closure(ours, [0]) {
+        for j in closure(theirs, [0]) {
+            from.insert((i, j), None::<((usize, usize), String)>);
+            queue.push_back((i, j));
+        }
+    }
+
+    while let Some((i, j)) = queue.pop_front() {
+        if i == ours.len() && j == theirs.len() {
+            let mut labels = vec![];
+            let mut pos = (i, j);
+            while let Some(Some((prev, label))) = from.get(&pos) {
+                labels.push(label.clone());
+                pos = *prev;
+            }
+
+            labels.reverse();
+            return Some(labels);
+        }
+
+        let (Some((a, next_i)), Some((b, next_j))) = (moves(ours, i), moves(theirs, j)) else {
+            continue;
+        };
+
+        let label = match shared(a, b) {
+            Shared::Label(label) => label,
+            Shared::Unknown if unknown => String::new(),
+            _ => continue,
+        };
+
+        for next_i in closure(ours, [next_i]) {
+            for next_j in closure(theirs, [next_j]) {
+                if let Entry::Vacant(entry) = from.entry((next_i, next_j)) {
+                    entry.insert(Some(((i, j), label.clone())));
+                    queue.push_back((next_i, next_j));
+                }
+            }
+        }
+    }
+
+    None
+}
+
+impl<const SPLITTER: char> DomainPattern<'_, SPLITTER> {
+    /// Whether both patterns match the same domains, so `+.**` is equivalent to `**+`, and `{a,b}` to `{b,a}`,
+    /// unlike `==`, which compares how the patterns are written
+    ///
+    /// Like [`DomainPattern::covers`], negation is ignored, and globs or regexes written differently may not be
+    /// found equivalent even if they are.
+    pub fn equivalent<const OTHER: char>(&self, other: &DomainPattern<'_, OTHER>) -> bool {
+        self.covers(other) && other.covers(self)
+    }
+
+    /// Whether there's a domain both patterns match, like `api.*.com` and `*.example.com` both matching `api.example.com`
+    ///
+    /// Whether patterns are negated is ignored. Labels matched by a regex are only known to exist if one is found,
+    /// so patterns with regexes may overlap where [`DomainPattern::example_overlap`] finds no domain.
+    pub fn overlaps(&self, other: &DomainPattern<'_, SPLITTER>) -> bool {
+        overlap(&steps(self), &steps(other), true).is_some()
+    }
+
+    /// A short domain both patterns match, if one was found, see [`DomainPattern::overlaps`]
+    ///
+    /// The domain may be the root domain `""` when both patterns can match no labels, like `**` and `*.*`.
+    pub fn example_overlap(&self, other: &DomainPattern<'_, SPLITTER>) -> Option<String> {
+        let labels = overlap(&steps(self), &steps(other), false)?;
+        Some(labels.join(SPLITTER.encode_utf8(&mut [0; 4])))
+    }
+
+    /// Whether this pattern matches every domain the other pattern matches, so `**.example.com` covers `api.example.com`
+    ///
+    /// Whether patterns are negated is ignored. Globs and regexes are only known to cover themselves, and labels
+    /// a glob like `*` or `?*` takes, so this may return false for a glob that does cover the other pattern, but never
+    /// returns true if it doesn't.
+    pub fn covers<const OTHER: char>(&self, other: &DomainPattern<'_, OTHER>) -> bool {
+        let ours = steps(self);
+        let theirs = steps(other);
+        let start = closure(&ours, [0]);
+
+        let mut seen: HashSet<(usize, Vec<usize>)> = HashSet::new();
+        let mut queue: VecDeque<(usize, BTreeSet<usize>)> = closure(&theirs, [0]).into_iter().map(|state| (state, start.clone())).collect();
+        while let Some((state, states)) = queue.pop_front() {
+            if !seen.insert((state, states.iter().copied().collect())) {
+                continue;
+            }
+
+            // a domain the other pattern matches, but this one doesn't
+            if state == theirs.len() && !states.contains(&ours.len()) {
+                return false;
+            }
+
+            let Some((part, next)) = moves(&theirs, state) else {
+                continue;
+            };
+
+            let next_states: Vec<BTreeSet<usize>> = match finite(part) {
+                // an empty label is never matched, so it doesn't lead anywhere
+                Some(labels) => labels.into_iter().filter(|label| !label.is_empty()).map(|label| advance(&ours, &states, part, Some(label))).collect(),
+                None => vec![advance(&ours, &states, part, None)],
+            };
+
+            for next_states in next_states {
+                for next in closure(&theirs, [next]) {
+                    queue.push_back((next, next_states.clone()));
+                }
+            }
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::DomainPattern;
+
+    fn parse(pattern: &str) -> DomainPattern<'_> {
+        pattern.try_into().expect("failed to parse")
+    }
+
+    #[test]
+    pub fn test_covers() {
+        let covering = [
+            ("**.example.com", "api.example.com"),
+            ("**.example.com", "*.example.com"),
+            ("**.example.com", "example.com"),
+            ("**", "anything.**+.at.all"),
+            ("*.example.com", "+.example.com"),
+            ("**+.example.com", "+.+.example.com"),
+            ("+.example.com", "{api,www}.example.com"),
+            ("{api,www,cdn}.example.com", "{api,www}.example.com"),
+            ("+.example.com", "api-*.example.com"),
+            ("api-*.example.com", "api-v1.example.com"),
+            ("#.example.com", "42.example.com"),
+            ("a.**.b", "a.x.**.y.b"),
+            ("**.b.**", "a.b.c"),
+            ("a.b", "a..b"),
+            ("*?.com", "+.com"),
+        ];
+
+        for (pattern, other) in covering {
+            assert!(parse(pattern).covers(&parse(other)), "{:?} should cover {:?}", pattern, other);
+        }
+
+        let not_covering = [
+            ("api.example.com", "**.example.com"),
+            ("*.example.com", "**.example.com"),
+            ("+.example.com", "example.com"),
+            ("+.example.com", "*.example.com"),
+            ("{api,www}.example.com", "+.example.com"),
+            ("{api,cdn}.example.com", "{api,www}.example.com"),
+            ("api-*.example.com", "+.example.com"),
+            ("a.**.b", "a.**"),
+            ("#.example.com", "+.example.com"),
+            ("a..b", "a.b"),
+            ("?.com", "+.com"),
+        ];
+
+        for (pattern, other) in not_covering {
+            assert!(!parse(pattern).covers(&parse(other)), "{:?} shouldn't cover {:?}", pattern, other);
+        }
+    }
+
+    #[test]
+    pub fn test_equivalent() {
+        let equivalent = [
+            ("**.**", "**"),
+            ("+.**", "**+"),
+            ("**.+.**", "**+"),
+            ("*{2}", "+.+"),
+            ("+{2,}", "+.+.**"),
+            ("{a,b}.com", "{b,a}.com"),
+            ("{tenant}.com", "+.com"),
+            ("api-*.com", "api-*.com"),
+        ];
+
+        for (pattern, other) in equivalent {
+            let (pattern, other) = (parse(pattern), parse(other));
+            assert!(pattern.equivalent(&other), "{} should be equivalent to {}", pattern, other);
+            assert!(other.equivalent(&pattern), "{} should be equivalent to {}", other, pattern);
+        }
+
+        let different = [
+            ("**", "**+"),
+            ("*.*", "+.+"),
+            ("{a,b}.com", "{a,b,c}.com"),
+            ("api-*.com", "+.com"),
+            ("a.com", "A.com"),
+            ("?.com", "+.com"),
+        ];
+
+        for (pattern, other) in different {
+            let (pattern, other) = (parse(pattern), parse(other));
+            assert!(!pattern.equivalent(&other), "{} shouldn't be equivalent to {}", pattern, other);
+        }
+    }
+
+    #[test]
+    pub fn test_overlaps() {
+        let overlapping = [
+            ("api.*.com", "*.example.com", "api.example.com"),
+            ("**.example.com", "api.**", "api.example.com"),
+            ("+.+", "**.com", "a.com"),
+            ("api-*.com", "*-v1.com", "api-v1.com"),
+            ("{api,www}.com", "w*.com", "www.com"),
+            ("#.in-addr.arpa", "*7.**", "7.in-addr.arpa"),
+            ("**", "*.*", ""),
+            ("{tenant}.example.com", "+.example.com", "a.example.com"),
+        ];
+
+        for (pattern, other, expected) in overlapping {
+            let (pattern, other) = (parse(pattern), parse(other));
+            assert!(pattern.overlaps(&other), "{} should overlap {}", pattern, other);
+            assert!(other.overlaps(&pattern), "{} should overlap {}", other, pattern);
+
+            let example = pattern.example_overlap(&other).expect("patterns overlap");
+            assert_eq!(example, expected);
+            assert!(pattern.matches(&example) && other.matches(&example), "{:?} isn't matched by both {} and {}", example, pattern, other);
+        }
+
+        let disjoint = [
+            ("api.example.com", "www.example.com"),
+            ("+.example.com", "example.com"),
+            ("*.example.com", "+.+.example.com"),
+            ("{api,www}.com", "cdn-*.com"),
+            ("#.com", "v*.com"),
+            ("api-*.com", "www-*.com"),
+            ("a.**", "b.**"),
+            ("a..b", "**"),
+        ];
+
+        for (pattern, other) in disjoint {
+            let (pattern, other) = (parse(pattern), parse(other));
+            assert!(!pattern.overlaps(&other), "{} shouldn't overlap {}", pattern, other);
+            assert_eq!(pattern.example_overlap(&other), None);
+        }
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    pub fn test_overlaps_regex() {
+        let pattern = parse("~re:^v\\d+$~.example.com");
+        assert_eq!(pattern.example_overlap(&parse("*.example.com")), None);
+        assert!(pattern.overlaps(&parse("*.example.com")));
+        assert_eq!(pattern.example_overlap(&parse("{v1,v2}.example.com")), Some("v1.example.com".to_string()));
+        assert!(!pattern.overlaps(&parse("{a,b}.example.com")));
+        assert!(!pattern.overlaps(&parse("+.+.example.com")));
+    }
+}
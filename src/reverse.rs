@@ -0,0 +1,119 @@
+//! Patterns for the reverse DNS names of address prefixes, under `in-addr.arpa` and `ip6.arpa`
+
+use std::borrow::Cow;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use crate::{DomainPattern, DomainPatternPart, DomainPatternWildcard, StepVec};
+
+// the reverse name of the prefix, one label per digit of the address from the last digit on, with `bits` bits per digit
+fn reverse(digits: &[u8], bits: u8, len: u8, zone: &[&'static str]) -> DomainPattern<'static> {
+    let full = (len / bits) as usize;
+    let partial = len % bits;
+    let mut steps: StepVec<'static> = Default::default();
+    // digits the prefix takes at least some bits of, any labels for the digits after them
+    let taken = full + usize::from(partial > 0);
+    if taken < digits.len() {
+        steps.push(DomainPatternPart::Wildcard(DomainPatternWildcard::new(true, true)));
+    }
+
+    // the digit only the high bits of are in the prefix, which takes every value with those bits
+    if partial > 0 {
+        let free = bits - partial;
+        let base = digits[full] >> free << free;
+        let options = (base..=base | ((1 << free) - 1)).map(|digit| Cow::Owned(format_digit(digit, bits))).collect();
+        steps.push(DomainPatternPart::OneOf(options));
+    }
+
+    for digit in digits[..full].iter().rev() {
+        steps.push(DomainPatternPart::Static(Cow::Owned(format_digit(*digit, bits))));
+    }
+
+    steps.extend(zone.iter().map(|label| DomainPatternPart::Static(Cow::Borrowed(*label))));
+    DomainPattern {
+        steps,
+        negated: false,
+    }
+}
+
+fn format_digit(digit: u8, bits: u8) -> String {
+    match bits {
+        4 => format!("{:x}", digit),
+        _ => digit.to_string(),
+    }
+}
+
+impl DomainPattern<'static> {
+    /// The reverse DNS names of the addresses in the prefix, so `10.0.0.0/8` is `**.10.in-addr.arpa`, None if the
+    /// prefix is longer than 32 bits
+    ///
+    /// Bits past the prefix are ignored. A prefix that doesn't end on an octet takes every value of its last octet, so
+    /// `10.16.0.0/12` is `**.{16,17,...,31}.10.in-addr.arpa`, and a whole address is just its name.
+    pub fn from_ipv4_prefix(address: Ipv4Addr, len: u8) -> Option<Self> {
+        if len > 32 {
+            return None;
+        }
+
+        Some(reverse(&address.octets(), 8, len, &["in-addr", "arpa"]))
+    }
+
+    /// The reverse DNS names of the addresses in the prefix, a label per nibble, so `2001:db8::/32` is
+    /// `**.8.b.d.0.1.0.0.2.ip6.arpa`, None if the prefix is longer than 128 bits
+    ///
+    /// Like with [`DomainPattern::from_ipv4_prefix`], a prefix that doesn't end on a nibble takes every value of its
+    /// last nibble.
+    pub fn from_ipv6_prefix(address: Ipv6Addr, len: u8) -> Option<Self> {
+        if len > 128 {
+            return None;
+        }
+
+        let nibbles: Vec<u8> = address.octets().iter().flat_map(|octet| [octet >> 4, octet & 0xf]).collect();
+        Some(reverse(&nibbles, 4, len, &["ip6", "arpa"]))
+    }
+
+    pub fn from_ip_prefix(address: IpAddr, len: u8) -> Option<Self> {
+        match address {
+            IpAddr::V4(address) => Self::from_ipv4_prefix(address, len),
+            IpAddr::V6(address) => Self::from_ipv6_prefix(address, len),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{Ipv4Addr, Ipv6Addr};
+    use crate::DomainPattern;
+
+    #[test]
+    pub fn test_from_ipv4_prefix() {
+        let pattern = DomainPattern::from_ipv4_prefix(Ipv4Addr::new(10, 0, 0, 0), 8).unwrap();
+        assert_eq!(pattern.to_string(), "**.10.in-addr.arpa");
+        assert!(pattern.matches("4.3.2.10.in-addr.arpa"));
+        assert!(!pattern.matches("4.3.2.11.in-addr.arpa"));
+
+        let pattern = DomainPattern::from_ipv4_prefix(Ipv4Addr::new(172, 20, 1, 1), 12).unwrap();
+        assert_eq!(pattern.to_string(), "**.{16,17,18,19,20,21,22,23,24,25,26,27,28,29,30,31}.172.in-addr.arpa");
+        assert!(pattern.matches("1.0.31.172.in-addr.arpa"));
+        assert!(!pattern.matches("1.0.32.172.in-addr.arpa"));
+
+        assert_eq!(DomainPattern::from_ipv4_prefix(Ipv4Addr::new(192, 0, 2, 1), 32).unwrap().to_string(), "1.2.0.192.in-addr.arpa");
+        assert_eq!(DomainPattern::from_ipv4_prefix(Ipv4Addr::new(192, 0, 2, 1), 24).unwrap().to_string(), "**.2.0.192.in-addr.arpa");
+        assert_eq!(DomainPattern::from_ipv4_prefix(Ipv4Addr::new(192, 0, 2, 17), 31).unwrap().to_string(), "{16,17}.2.0.192.in-addr.arpa");
+        assert_eq!(DomainPattern::from_ipv4_prefix(Ipv4Addr::UNSPECIFIED, 0).unwrap().to_string(), "**.in-addr.arpa");
+        assert_eq!(DomainPattern::from_ipv4_prefix(Ipv4Addr::UNSPECIFIED, 33), None);
+    }
+
+    #[test]
+    pub fn test_from_ipv6_prefix() {
+        let address: Ipv6Addr = "2001:db8::".parse().unwrap();
+        let pattern = DomainPattern::from_ipv6_prefix(address, 32).unwrap();
+        assert_eq!(pattern.to_string(), "**.8.b.d.0.1.0.0.2.ip6.arpa");
+        assert!(pattern.matches("1.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.8.b.d.0.1.0.0.2.ip6.arpa"));
+        assert!(!pattern.matches("1.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.9.b.d.0.1.0.0.2.ip6.arpa"));
+
+        assert_eq!(DomainPattern::from_ipv6_prefix("fc00::".parse().unwrap(), 7).unwrap().to_string(), "**.{c,d}.f.ip6.arpa");
+        let pattern = DomainPattern::from_ipv6_prefix("::1".parse().unwrap(), 128).unwrap();
+        assert!(pattern.matches("1.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.ip6.arpa"));
+        assert_eq!(pattern.steps.len(), 34);
+        assert_eq!(DomainPattern::from_ip_prefix("::".parse().unwrap(), 129), None);
+        assert_eq!(DomainPattern::from_ip_prefix("10.0.0.0".parse().unwrap(), 8).unwrap().to_string(), "**.10.in-addr.arpa");
+    }
+}
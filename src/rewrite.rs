@@ -0,0 +1,174 @@
+//! Rewriting domains matching a pattern using what the parts of the pattern matched
+
+use std::fmt::{Display, Formatter};
+use crate::{DomainPattern, DomainPatternPart};
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+enum Segment {
+    Text(String),
+    // {0}
+    Domain,
+    // {n} or {name}, resolved to the index of the part
+    Part(usize),
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum InvalidTemplate {
+    /// A `{` without `}`, or the other way around, at this position in the template
+    Unbalanced(usize),
+    /// `{n}` where the pattern doesn't have n parts
+    UnknownPart(usize),
+    /// `{name}` where the pattern doesn't have a part with that name
+    UnknownName(String),
+}
+
+impl Display for InvalidTemplate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InvalidTemplate::Unbalanced(position) => write!(f, "Unbalanced brace at position {} in template", position),
+            InvalidTemplate::UnknownPart(part) => write!(f, "Template refers to part {}, which the pattern doesn't have", part),
+            InvalidTemplate::UnknownName(name) => write!(f, "Template refers to {{{}}}, which the pattern doesn't have", name),
+        }
+    }
+}
+
+impl std::error::Error for InvalidTemplate {}
+
+/// Rewrites domains matching a pattern according to a template, e.g. `+.old.tld` with `{1}.new.tld`
+/// rewrites `www.old.tld` to `www.new.tld`
+///
+/// In the template `{0}` is the whole domain, `{n}` is whatever the nth part of the pattern matched, counting from 1,
+/// and `{name}` is whatever the `{name}` part matched. Like with [`DomainPattern::captures`] parts are counted after
+/// the pattern has been optimized. Empty labels are dropped from the result, so a skipped `*` doesn't leave a splitter behind.
+#[derive(Clone, Debug)]
+pub struct DomainRewriter<'a, const SPLITTER: char = '.'> {
+    pattern: DomainPattern<'a, SPLITTER>,
+    template: Vec<Segment>,
+}
+
+impl<'a, const SPLITTER: char> DomainRewriter<'a, SPLITTER> {
+    pub fn new(pattern: DomainPattern<'a, SPLITTER>, template: &str) -> Result<Self, InvalidTemplate> {
+        let mut segments = vec![];
+        let mut rest = template;
+        let mut offset = 0;
+
+        while !rest.is_empty() {
+            let Some(open) = rest.find('{') else {
+                if let Some(close) = rest.find('}') {
+                    return Err(InvalidTemplate::Unbalanced(offset + close));
+                }
+
+                segments.push(Segment::Text(rest.to_string()));
+                break;
+            };
+
+            let text = &rest[..open];
+            if let Some(close) = text.find('}') {
+                return Err(InvalidTemplate::Unbalanced(offset + close));
+            }
+
+            if !text.is_empty() {
+                segments.push(Segment::Text(text.to_string()));
+            }
+
+            let Some(len) = rest[open + 1..].find(['{', '}']).filter(|len| rest[open + 1 + len..].starts_with('}')) else {
+                return Err(InvalidTemplate::Unbalanced(offset + open));
+            };
+
+            let name = &rest[open + 1..open + 1 + len];
+            segments.push(pattern.template_segment(name)?);
+
+            let consumed = open + len + 2;
+            rest = &rest[consumed..];
+            offset += consumed;
+        }
+
+        Ok(DomainRewriter {
+            pattern,
+            template: segments,
+        })
+    }
+
+    pub fn pattern(&self) -> &DomainPattern<'a, SPLITTER> {
+        &self.pattern
+    }
+
+    /// Rewrites the domain according to the template, or returns None if the pattern doesn't match it
+    pub fn rewrite(&self, domain: &str) -> Option<String> {
+        let captures = self.pattern.captures(domain)?;
+
+        let mut rewritten = String::new();
+        for segment in &self.template {
+            match segment {
+                Segment::Text(text) => rewritten.push_str(text),
+                Segment::Domain => rewritten.push_str(captures.domain()),
+                Segment::Part(part) => rewritten.push_str(captures.get(*part).unwrap_or_default()),
+            }
+        }
+
+        let mut result = String::with_capacity(rewritten.len());
+        for label in rewritten.split(SPLITTER).filter(|label| !label.is_empty()) {
+            if !result.is_empty() {
+                result.push(SPLITTER);
+            }
+
+            result.push_str(label);
+        }
+
+        Some(result)
+    }
+}
+
+impl<const SPLITTER: char> DomainPattern<'_, SPLITTER> {
+    fn template_segment(&self, name: &str) -> Result<Segment, InvalidTemplate> {
+        if let Ok(part) = name.parse::<usize>() {
+            return match part {
+                0 => Ok(Segment::Domain),
+                part if part <= self.steps.len() => Ok(Segment::Part(part - 1)),
+                part => Err(InvalidTemplate::UnknownPart(part)),
+            };
+        }
+
+        self.steps.iter()
+            .position(|part| matches!(part, DomainPatternPart::Named(part_name) if part_name == name))
+            .map(Segment::Part)
+            .ok_or_else(|| InvalidTemplate::UnknownName(name.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{DomainPattern, DomainRewriter, InvalidTemplate};
+
+    fn build(pattern: &str, template: &str) -> Result<DomainRewriter<'static>, InvalidTemplate> {
+        let pattern: DomainPattern = pattern.try_into().expect("failed to parse");
+        DomainRewriter::new(pattern.to_owned(), template)
+    }
+
+    #[test]
+    pub fn test_rewrite() {
+        let rewriter = build("+.old.tld", "{1}.new.tld").expect("valid template");
+        assert_eq!(rewriter.rewrite("www.old.tld"), Some("www.new.tld".to_string()));
+        assert_eq!(rewriter.rewrite("old.tld"), None);
+
+        let rewriter = build("{tenant}.app.example.com", "{tenant}.tenants.internal").expect("valid template");
+        assert_eq!(rewriter.rewrite("cijber.app.example.com"), Some("cijber.tenants.internal".to_string()));
+
+        let rewriter = build("**.example.com", "{1}.cdn.example.net").expect("valid template");
+        assert_eq!(rewriter.rewrite("a.b.example.com"), Some("a.b.cdn.example.net".to_string()));
+        assert_eq!(rewriter.rewrite("example.com"), Some("cdn.example.net".to_string()));
+
+        let rewriter = build("+.example.com", "{0}.proxy.tld").expect("valid template");
+        assert_eq!(rewriter.rewrite("www.example.com"), Some("www.example.com.proxy.tld".to_string()));
+    }
+
+    #[test]
+    pub fn test_invalid_template() {
+        assert_eq!(build("+.old.tld", "{4}.new.tld").err(), Some(InvalidTemplate::UnknownPart(4)));
+        assert_eq!(build("+.old.tld", "{name}.new.tld").err(), Some(InvalidTemplate::UnknownName("name".to_string())));
+        assert_eq!(build("+.old.tld", "new.{1").err(), Some(InvalidTemplate::Unbalanced(4)));
+        assert_eq!(build("+.old.tld", "new.1}").err(), Some(InvalidTemplate::Unbalanced(5)));
+        assert_eq!(build("+.old.tld", "{{1}}").err(), Some(InvalidTemplate::Unbalanced(0)));
+        assert!(build("+.old.tld", "{3}.new.tld").is_ok());
+    }
+}
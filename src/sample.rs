@@ -0,0 +1,200 @@
+//! Generating random domains matching a pattern, or nearly matching it
+
+use rand::seq::IndexedRandom;
+use rand::Rng;
+use crate::{glob, DomainPattern, DomainPatternPart, DomainPatternWildcard};
+
+// how often a sample is tried before giving up
+const ATTEMPTS: usize = 64;
+
+/// What [`DomainPattern::sample_with`] builds domains from
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct SampleOptions {
+    alphabet: Vec<char>,
+    max_depth: usize,
+    max_label_len: usize,
+}
+
+impl Default for SampleOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SampleOptions {
+    /// Labels of up to 8 characters out of `a-z0-9`, and wildcards taking up to 3 labels
+    pub fn new() -> Self {
+        SampleOptions {
+            alphabet: ('a'..='z').chain('0'..='9').collect(),
+            max_depth: 3,
+            max_label_len: 8,
+        }
+    }
+
+    /// Characters random labels are made of, globs may still add others they require
+    pub fn alphabet(mut self, alphabet: &str) -> Self {
+        self.alphabet = alphabet.chars().collect();
+        self.alphabet.sort_unstable();
+        self.alphabet.dedup();
+        self
+    }
+
+    /// Most labels `**` and `**+` take, `**+` always takes at least one
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Most characters in a random label, and in what a `*` within a glob takes
+    pub fn max_label_len(mut self, max_label_len: usize) -> Self {
+        self.max_label_len = max_label_len.max(1);
+        self
+    }
+
+    fn label<const SPLITTER: char>(&self, rng: &mut impl Rng) -> Option<String> {
+        let alphabet: Vec<char> = self.alphabet.iter().copied().filter(|c| *c != SPLITTER).collect();
+        if alphabet.is_empty() {
+            return None;
+        }
+
+        let len = rng.random_range(1..=self.max_label_len);
+        Some((0..len).map(|_| *alphabet.choose(rng).expect("alphabet isn't empty")).collect())
+    }
+}
+
+impl<const SPLITTER: char> DomainPattern<'_, SPLITTER> {
+    /// A random domain the pattern matches, see [`DomainPattern::sample_with`]
+    pub fn sample(&self, rng: &mut impl Rng) -> Option<String> {
+        self.sample_with(rng, &SampleOptions::new())
+    }
+
+    /// A random domain the pattern matches, made of the characters in the options
+    ///
+    /// Returns None if no domain was found, like for patterns with an empty label, or a regex none of the random
+    /// labels matched. Negation is ignored, a negated pattern samples the domains it excludes.
+    pub fn sample_with(&self, rng: &mut impl Rng, options: &SampleOptions) -> Option<String> {
+        (0..ATTEMPTS).find_map(|_| {
+            let domain = self.generate(rng, options)?;
+            self.matches(&domain).then_some(domain)
+        })
+    }
+
+    /// A random domain close to one the pattern matches, that it doesn't match, see [`DomainPattern::sample_non_matching_with`]
+    pub fn sample_non_matching(&self, rng: &mut impl Rng) -> Option<String> {
+        self.sample_non_matching_with(rng, &SampleOptions::new())
+    }
+
+    /// A random domain close to one the pattern matches, with a label missing, added, or changed,
+    /// that the pattern doesn't match
+    ///
+    /// Returns None if no such domain was found, patterns like `**` match anything.
+    pub fn sample_non_matching_with(&self, rng: &mut impl Rng, options: &SampleOptions) -> Option<String> {
+        (0..ATTEMPTS).find_map(|_| {
+            let domain = self.generate(rng, options)?;
+            let mut labels: Vec<String> = domain.split(SPLITTER).filter(|label| !label.is_empty()).map(str::to_string).collect();
+
+            match rng.random_range(0..3) {
+                0 if !labels.is_empty() => {
+                    labels.remove(rng.random_range(0..labels.len()));
+                }
+
+                1 if !labels.is_empty() => {
+                    let idx = rng.random_range(0..labels.len());
+                    labels[idx] = options.label::<SPLITTER>(rng)?;
+                }
+
+                _ => labels.insert(rng.random_range(0..=labels.len()), options.label::<SPLITTER>(rng)?),
+            }
+
+            let near_miss = labels.join(SPLITTER.encode_utf8(&mut [0; 4]));
+            (!near_miss.is_empty() && !self.matches(&near_miss)).then_some(near_miss)
+        })
+    }
+
+    // a domain built part by part, which may not match when a regex is involved
+    fn generate(&self, rng: &mut impl Rng, options: &SampleOptions) -> Option<String> {
+        let mut labels = vec![];
+        for part in self.steps.iter() {
+            match part {
+                DomainPatternPart::Static(label) => labels.push(label.to_string()),
+                DomainPatternPart::OneOf(labels_of) => labels.push(labels_of.choose(rng)?.to_string()),
+                DomainPatternPart::Wildcard(DomainPatternWildcard { multi, optional }) => {
+                    let min = !optional as usize;
+                    let max = match multi {
+                        true => options.max_depth.max(min),
+                        false => 1,
+                    };
+
+                    for _ in 0..rng.random_range(min..=max) {
+                        labels.push(options.label::<SPLITTER>(rng)?);
+                    }
+                }
+
+                DomainPatternPart::Named(_) => labels.push(options.label::<SPLITTER>(rng)?),
+                DomainPatternPart::Numeric => {
+                    let len = rng.random_range(1..=options.max_label_len);
+                    labels.push((0..len).map(|_| char::from(b'0' + rng.random_range(0..10))).collect());
+                }
+
+                DomainPatternPart::Glob(glob) => labels.push(glob::random(glob, rng, &options.alphabet, options.max_label_len)?),
+                #[cfg(feature = "regex")]
+                DomainPatternPart::Regex(_) => labels.push(options.label::<SPLITTER>(rng)?),
+            }
+        }
+
+        Some(labels.join(SPLITTER.encode_utf8(&mut [0; 4])))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use crate::{DomainPattern, SampleOptions};
+
+    #[test]
+    pub fn test_sample() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let patterns = ["api.example.com", "*.example.com", "**+.example.com", "{a,b}.{tenant}.#.com", "api-*.node[0-9].us-?", "**"];
+        for pattern in patterns {
+            let pattern: DomainPattern = pattern.try_into().expect("failed to parse");
+            for _ in 0..50 {
+                let domain = pattern.sample(&mut rng).expect("pattern has matching domains");
+                assert!(pattern.matches(&domain), "{} doesn't match {:?}", pattern, domain);
+            }
+        }
+
+        let pattern: DomainPattern = "a..b".try_into().expect("failed to parse");
+        assert_eq!(pattern.sample(&mut rng), None);
+    }
+
+    #[test]
+    pub fn test_sample_options() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let options = SampleOptions::new().alphabet("xy").max_depth(2).max_label_len(3);
+        let pattern: DomainPattern = "**.com".try_into().expect("failed to parse");
+        for _ in 0..50 {
+            let domain = pattern.sample_with(&mut rng, &options).expect("pattern has matching domains");
+            let labels: Vec<&str> = domain.split('.').collect();
+            assert!(labels.len() <= 3, "{:?} is too deep", domain);
+            assert!(labels[..labels.len() - 1].iter().all(|label| label.len() <= 3 && label.chars().all(|c| "xy".contains(c))), "{:?} isn't made of the alphabet", domain);
+        }
+
+        assert_eq!(pattern.sample_with(&mut rng, &SampleOptions::new().alphabet(".")), Some("com".to_string()));
+    }
+
+    #[test]
+    pub fn test_sample_non_matching() {
+        let mut rng = StdRng::seed_from_u64(42);
+        for pattern in ["api.example.com", "+.example.com", "{a,b}.#.com", "api-*.com"] {
+            let pattern: DomainPattern = pattern.try_into().expect("failed to parse");
+            for _ in 0..50 {
+                let domain = pattern.sample_non_matching(&mut rng).expect("pattern has near misses");
+                assert!(!pattern.matches(&domain), "{} matches {:?}", pattern, domain);
+            }
+        }
+
+        let pattern: DomainPattern = "**".try_into().expect("failed to parse");
+        assert_eq!(pattern.sample_non_matching(&mut rng), None);
+    }
+}
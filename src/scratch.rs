@@ -0,0 +1,70 @@
+//! Buffers for matching that can be kept around between matches
+
+use crate::{DomainPattern, DomainPatternSet, MatchOptions, StackVec};
+
+/// The paths a match is on, kept around so matching a lot of domains doesn't allocate for every one of them,
+/// see [`DomainPattern::matches_in`] and [`DomainPatternSet::contains_match_in`]
+///
+/// Keep one per thread or per connection, it can be used with any pattern or set.
+#[derive(Clone, Default, Debug)]
+pub struct MatchScratch {
+    pub(crate) active: StackVec,
+    pub(crate) next: StackVec,
+}
+
+impl MatchScratch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.active.truncate(0);
+        self.next.truncate(0);
+    }
+}
+
+impl<const SPLITTER: char> DomainPattern<'_, SPLITTER> {
+    /// Matches like [`DomainPattern::matches`], keeping its paths in the scratch buffers
+    pub fn matches_in(&self, scratch: &mut MatchScratch, domain: &str) -> bool {
+        self.matches_in_with(scratch, domain, &MatchOptions::new())
+    }
+
+    /// Matches like [`DomainPattern::matches_with`], keeping its paths in the scratch buffers
+    pub fn matches_in_with(&self, scratch: &mut MatchScratch, domain: &str, options: &MatchOptions) -> bool {
+        match self.run_in(domain, options, &mut (), scratch) {
+            Ok(matched) => matched,
+            Err(never) => match never {},
+        }
+    }
+}
+
+impl<const SPLITTER: char> DomainPatternSet<'_, SPLITTER> {
+    /// Matches like [`DomainPatternSet::contains_match`], keeping the nodes it walks in the scratch buffers
+    pub fn contains_match_in(&self, scratch: &mut MatchScratch, domain: &str) -> bool {
+        self.contains(domain, scratch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{DomainPattern, DomainPatternSet, MatchScratch};
+
+    #[test]
+    pub fn test_scratch() {
+        let mut scratch = MatchScratch::new();
+        // too long for the bitset, so it's walked with the scratch buffers
+        let pattern = format!("{}.nice", ["*"; 130].join("."));
+        let pattern: DomainPattern = pattern.as_str().try_into().expect("failed to parse");
+        assert!(pattern.len() > 127);
+        for domain in ["nice", "a.nice", "a.b.nice", "a.b", "nice.a"] {
+            assert_eq!(pattern.matches_in(&mut scratch, domain), pattern.matches(domain), "{:?} matches differently", domain);
+        }
+
+        let mut set: DomainPatternSet = DomainPatternSet::new();
+        set.insert("**.example.com".try_into().expect("failed to parse"));
+        set.insert("api.*.org".try_into().expect("failed to parse"));
+        for domain in ["example.com", "a.b.example.com", "api.x.org", "api.x.y.org", "example.org"] {
+            assert_eq!(set.contains_match_in(&mut scratch, domain), set.contains_match(domain), "{:?} matches differently", domain);
+        }
+    }
+}
@@ -0,0 +1,79 @@
+//! Patterns serialized as their string, and deserialized with the parser
+
+use std::fmt::Formatter;
+use std::marker::PhantomData;
+use serde::de::{Error, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use crate::{shorten, DomainPattern};
+#[cfg(feature = "regex")]
+use crate::LabelRegex;
+
+impl<const SPLITTER: char> Serialize for DomainPattern<'_, SPLITTER> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+struct PatternVisitor<'a, const SPLITTER: char>(PhantomData<DomainPattern<'a, SPLITTER>>);
+
+impl<'de: 'a, 'a, const SPLITTER: char> Visitor<'de> for PatternVisitor<'a, SPLITTER> {
+    type Value = DomainPattern<'a, SPLITTER>;
+
+    fn expecting(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "a domain pattern like \"*.example.com\"")
+    }
+
+    fn visit_borrowed_str<E: Error>(self, pattern: &'de str) -> Result<Self::Value, E> {
+        DomainPattern::parse(pattern).map_err(E::custom)
+    }
+
+    fn visit_str<E: Error>(self, pattern: &str) -> Result<Self::Value, E> {
+        DomainPattern::parse(pattern).map(|pattern| shorten(pattern.into_owned())).map_err(E::custom)
+    }
+}
+
+/// Deserializes by parsing a string, borrowing from the input when it can
+impl<'de: 'a, 'a, const SPLITTER: char> Deserialize<'de> for DomainPattern<'a, SPLITTER> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(PatternVisitor(PhantomData))
+    }
+}
+
+#[cfg(feature = "regex")]
+impl Serialize for LabelRegex {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+#[cfg(feature = "regex")]
+impl<'de> Deserialize<'de> for LabelRegex {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let regex = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+        LabelRegex::new(&regex).map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::DomainPattern;
+
+    #[test]
+    pub fn test_serde() {
+        let pattern: DomainPattern = "!*.{api,www}.example.com".try_into().expect("failed to parse");
+        let json = serde_json::to_string(&pattern).expect("serializes");
+        assert_eq!(json, "\"!*.{api,www}.example.com\"");
+
+        let parsed: DomainPattern = serde_json::from_str(&json).expect("deserializes");
+        assert_eq!(parsed.to_string(), pattern.to_string());
+        assert!(parsed.is_negated());
+
+        // escaped strings can't be borrowed
+        let parsed: DomainPattern = serde_json::from_str("\"tag\\\\+1.com\"").expect("deserializes");
+        assert_eq!(parsed.to_string(), "tag\\+1.com");
+
+        let err = serde_json::from_str::<DomainPattern>("\"a.b+\"").expect_err("doesn't parse");
+        assert!(err.to_string().contains("a.b+"), "{} should mention the pattern", err);
+        assert!(serde_json::from_str::<DomainPattern>("42").is_err());
+    }
+}
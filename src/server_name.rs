@@ -0,0 +1,90 @@
+//! Matching the server names of rustls, like the SNI an acceptor routes on
+
+use rustls_pki_types::{DnsName, ServerName};
+use crate::{DomainMap, DomainPattern, DomainPatternPart, DomainPatternSet, MatchOptions};
+
+impl DomainPattern<'_> {
+    /// Whether the pattern matches the DNS name, ignoring ASCII case like rustls compares names, IP addresses never match
+    pub fn matches_server_name(&self, name: &ServerName<'_>) -> bool {
+        match name {
+            ServerName::DnsName(name) => self.matches_dns_name(name),
+            _ => false,
+        }
+    }
+
+    pub fn matches_dns_name(&self, name: &DnsName<'_>) -> bool {
+        self.matches_with(name.as_ref(), &MatchOptions::new().case_insensitive(true))
+    }
+
+    /// The name the pattern matches, if it only matches a single DNS name, so `example.com` but not `*.example.com`
+    pub fn to_server_name(&self) -> Option<ServerName<'static>> {
+        if self.negated {
+            return None;
+        }
+
+        let mut labels = Vec::with_capacity(self.steps.len());
+        for part in self.steps.iter() {
+            match part {
+                DomainPatternPart::Static(label) => labels.push(label.as_ref()),
+                _ => return None,
+            }
+        }
+
+        DnsName::try_from(labels.join(".")).ok().map(ServerName::DnsName)
+    }
+}
+
+impl DomainPatternSet<'_> {
+    /// Whether any pattern matches the DNS name, with the options of the set, IP addresses never match
+    ///
+    /// rustls compares names ignoring case, so the set should too, see [`MatchOptions::case_insensitive`].
+    pub fn contains_server_name(&self, name: &ServerName<'_>) -> bool {
+        match name {
+            ServerName::DnsName(name) => self.contains_match(name.as_ref()),
+            _ => false,
+        }
+    }
+}
+
+impl<V> DomainMap<'_, V> {
+    /// The value for the DNS name, like [`DomainMap::get`], IP addresses never match
+    pub fn get_server_name(&self, name: &ServerName<'_>) -> Option<&V> {
+        match name {
+            ServerName::DnsName(name) => self.get(name.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rustls_pki_types::ServerName;
+    use crate::{DomainMap, DomainPattern, DomainPatternSet, MatchOptions};
+
+    fn name(name: &str) -> ServerName<'_> {
+        ServerName::try_from(name).expect("valid server name")
+    }
+
+    #[test]
+    pub fn test_matches_server_name() {
+        let pattern = DomainPattern::parse("**+.example.com").unwrap();
+        assert!(pattern.matches_server_name(&name("api.Example.com")));
+        assert!(!pattern.matches_server_name(&name("example.com")));
+        assert!(!pattern.matches_server_name(&name("127.0.0.1")));
+
+        assert_eq!(DomainPattern::parse("example.com").unwrap().to_server_name(), Some(name("example.com").to_owned()));
+        assert_eq!(pattern.to_server_name(), None);
+        assert_eq!(DomainPattern::parse("!example.com").unwrap().to_server_name(), None);
+        assert_eq!(DomainPattern::parse("a..com").unwrap().to_server_name(), None);
+
+        let mut set: DomainPatternSet = DomainPatternSet::with_options(MatchOptions::new().case_insensitive(true));
+        set.insert(pattern);
+        assert!(set.contains_server_name(&name("API.example.com")));
+        assert!(!set.contains_server_name(&name("127.0.0.1")));
+
+        let mut map = DomainMap::new();
+        map.insert(DomainPattern::parse("*.example.com").unwrap(), "backend");
+        assert_eq!(map.get_server_name(&name("www.example.com")), Some(&"backend"));
+        assert_eq!(map.get_server_name(&name("example.org")), None);
+    }
+}
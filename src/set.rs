@@ -0,0 +1,1073 @@
+//! Matching a domain against a lot of patterns at once
+//!
+//! All patterns get compiled into a single label trie, which is walked from the right (TLD first),
+//! since that's where most patterns are anchored, so patterns sharing a suffix share the same nodes.
+//!
+//! This also means patterns are sharded by their rightmost static label, the first label of a domain picks a single
+//! bucket from the root, and only patterns ending in a wildcard are walked for every domain.
+//!
+//! Patterns without any wildcards skip the trie entirely, and are looked up by their full domain in a hash map.
+//!
+//! Static labels in the trie are interned, labels like `com` are stored once and nodes refer to them by a `u32`,
+//! so a label of the domain is hashed once and then compared as a number against every node it reaches.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::mem;
+use crate::{memory, split, DomainPattern, DomainPatternPart, DomainPatternWildcard, MatchOptions, MatchScratch, StackVec};
+use crate::specificity::Specificity;
+#[cfg(feature = "rkyv")]
+pub use archive::{ArchivedSetArchive, SetArchive};
+pub use frozen::FrozenDomainSet;
+pub use lint::Lint;
+use interner::{Interner, Symbols};
+use label_hash::LabelQuery;
+use prefilter::Prefilter;
+
+#[cfg(feature = "rkyv")]
+mod archive;
+mod binary;
+mod frozen;
+mod interner;
+mod label_hash;
+mod lint;
+#[cfg(feature = "rayon")]
+mod parallel;
+mod prefilter;
+
+const ROOT: usize = 0;
+
+#[derive(Clone, Debug, Default)]
+struct Node<'a> {
+    // by the symbol of their label in the interner
+    statics: Symbols,
+    // indexed by `wildcard_slot`
+    wildcards: [Option<usize>; 4],
+    // other single label parts like globs and `{a,b}` groups, which have to be tried one by one
+    labels: Vec<(DomainPatternPart<'a>, usize)>,
+    // reached through a multi wildcard, so it may eat more labels while staying here
+    multi: bool,
+    // patterns ending in this node
+    terminal: Vec<usize>,
+}
+
+impl Node<'_> {
+    fn children(&self) -> impl Iterator<Item=usize> + '_ {
+        self.statics.values().copied()
+            .chain(self.wildcards.iter().flatten().copied())
+            .chain(self.labels.iter().map(|(_, child)| *child))
+    }
+}
+
+// how a part is stored in the trie
+enum Edge<'p, 'a> {
+    Static(&'p Cow<'a, str>),
+    Wildcard(DomainPatternWildcard),
+    Label(&'p DomainPatternPart<'a>),
+}
+
+impl<'p, 'a> Edge<'p, 'a> {
+    fn of(part: &'p DomainPatternPart<'a>) -> Self {
+        match part {
+            DomainPatternPart::Static(label) => Edge::Static(label),
+            DomainPatternPart::Wildcard(wildcard) => Edge::Wildcard(*wildcard),
+            // names only matter for captures
+            DomainPatternPart::Named(_) => Edge::Wildcard(DomainPatternWildcard { multi: false, optional: false }),
+            part @ (DomainPatternPart::Glob(_) | DomainPatternPart::OneOf(_) | DomainPatternPart::Numeric) => Edge::Label(part),
+            #[cfg(feature = "regex")]
+            part @ DomainPatternPart::Regex(_) => Edge::Label(part),
+        }
+    }
+}
+
+fn wildcard_slot(wildcard: &DomainPatternWildcard) -> usize {
+    (wildcard.multi as usize) << 1 | wildcard.optional as usize
+}
+
+fn slot_is_optional(slot: usize) -> bool {
+    slot & 1 == 1
+}
+
+#[derive(Clone, Debug)]
+struct Entry<'a, const SPLITTER: char> {
+    pattern: DomainPattern<'a, SPLITTER>,
+    specificity: Specificity,
+    priority: i32,
+}
+
+#[derive(Clone, Debug)]
+pub struct DomainPatternSet<'a, const SPLITTER: char = '.'> {
+    nodes: Vec<Node<'a>>,
+    // static labels of the nodes, every label is stored once
+    interner: Interner<'a>,
+    // nodes which were pruned and can be reused
+    free_nodes: Vec<usize>,
+    // removed patterns are left as None, so indices stay stable
+    entries: Vec<Option<Entry<'a, SPLITTER>>>,
+    // patterns without wildcards, by their domain
+    literals: HashMap<Cow<'a, str>, Vec<usize>>,
+    len: usize,
+    // amount of negated patterns, as long as there's none matching can stop at the first match
+    negated: usize,
+    prefilter: Option<Prefilter>,
+    options: MatchOptions,
+}
+
+// the domain a pattern without wildcards matches, patterns with empty labels never match so don't get one
+fn literal_key<'a, const SPLITTER: char>(pattern: &DomainPattern<'a, SPLITTER>) -> Option<Cow<'a, str>> {
+    let mut labels = pattern.steps.iter().map(|part| match part {
+        DomainPatternPart::Static(label) if !label.is_empty() => Some(label),
+        _ => None,
+    });
+
+    let first = labels.next()??;
+    let Some(second) = labels.next() else {
+        return Some(first.clone());
+    };
+
+    let mut key = format!("{}{}{}", first, SPLITTER, second?);
+    for label in labels {
+        key.push(SPLITTER);
+        key.push_str(label?);
+    }
+
+    Some(Cow::Owned(key))
+}
+
+// the domain without any empty labels, so it can be looked up in the literals
+fn normalize_literal<const SPLITTER: char>(domain: &str) -> Cow<'_, str> {
+    if !split::labels::<SPLITTER>(domain).any(str::is_empty) {
+        return Cow::Borrowed(domain);
+    }
+
+    let mut normalized = String::with_capacity(domain.len());
+    for label in split::labels::<SPLITTER>(domain).filter(|label| !label.is_empty()) {
+        if !normalized.is_empty() {
+            normalized.push(SPLITTER);
+        }
+
+        normalized.push_str(label);
+    }
+
+    Cow::Owned(normalized)
+}
+
+impl<'a, const SPLITTER: char> DomainPatternSet<'a, SPLITTER> {
+    pub fn new() -> Self {
+        DomainPatternSet {
+            nodes: vec![Node::default()],
+            interner: Interner::default(),
+            free_nodes: vec![],
+            entries: vec![],
+            literals: HashMap::new(),
+            len: 0,
+            negated: 0,
+            prefilter: None,
+            options: MatchOptions::new(),
+        }
+    }
+
+    /// Creates a set matching with the given options
+    ///
+    /// With `case_insensitive` the patterns are stored lowercased, and with `idna` as their A-labels,
+    /// [`DomainPatternSet::get`] still returns them as inserted.
+    pub fn with_options(options: MatchOptions) -> Self {
+        DomainPatternSet {
+            options,
+            ..Self::new()
+        }
+    }
+
+    pub fn options(&self) -> &MatchOptions {
+        &self.options
+    }
+
+    // the pattern as it's stored in the trie
+    pub(crate) fn indexed<'p, 'b>(&self, pattern: &'p DomainPattern<'b, SPLITTER>) -> Cow<'p, DomainPattern<'b, SPLITTER>> {
+        match self.options.normalizes() {
+            true => Cow::Owned(pattern.normalize(&self.options.normalizer())),
+            false => Cow::Borrowed(pattern),
+        }
+    }
+
+    // the domain as it's looked up in the trie
+    fn input<'d>(&self, domain: &'d str) -> Cow<'d, str> {
+        self.options.normalize_domain::<SPLITTER>(domain)
+    }
+
+    /// Creates a set with room for the given amount of patterns, so building it doesn't keep reallocating
+    ///
+    /// Patterns don't take the same amount of nodes, so only a node per pattern is reserved, which is what patterns
+    /// sharing all but their first label take.
+    pub fn with_capacity(capacity: usize) -> Self {
+        let mut set = Self::new();
+        set.reserve(capacity);
+        set
+    }
+
+    /// Reserves room for at least `additional` more patterns, see [`DomainPatternSet::with_capacity`]
+    pub fn reserve(&mut self, additional: usize) {
+        self.entries.reserve(additional);
+        self.nodes.reserve(additional.saturating_sub(self.free_nodes.len()));
+    }
+
+    /// Frees all capacity that isn't used, in the trie and every node of it too
+    pub fn shrink_to_fit(&mut self) {
+        for node in &mut self.nodes {
+            node.statics.shrink_to_fit();
+            node.labels.shrink_to_fit();
+            node.terminal.shrink_to_fit();
+        }
+
+        for entry in self.entries.iter_mut().flatten() {
+            entry.pattern.steps.shrink_to_fit();
+        }
+
+        for indices in self.literals.values_mut() {
+            indices.shrink_to_fit();
+        }
+
+        self.nodes.shrink_to_fit();
+        self.free_nodes.shrink_to_fit();
+        self.entries.shrink_to_fit();
+        self.literals.shrink_to_fit();
+        self.interner.shrink_to_fit();
+    }
+
+    /// Shrinks the set and seals it, so it can't be changed anymore, see [`FrozenDomainSet`]
+    pub fn freeze(mut self) -> FrozenDomainSet<'a, SPLITTER> {
+        self.shrink_to_fit();
+        FrozenDomainSet::new(self)
+    }
+
+    /// Creates a set with a prefilter of the given size, see [`DomainPatternSet::enable_prefilter`]
+    pub fn with_prefilter(size: usize) -> Self {
+        let mut set = Self::new();
+        set.enable_prefilter(size);
+        set
+    }
+
+    /// Enables a bloom filter over the last one or two static labels of all patterns, replacing any existing one
+    ///
+    /// This lets most domains that don't match return without walking the trie, but only works as long as every
+    /// pattern in the set ends in a static label, a single pattern like `example.*` disables it until it's removed.
+    ///
+    /// `size` is the amount of counters used, around 10 per pattern keeps false positives low.
+    pub fn enable_prefilter(&mut self, size: usize) {
+        let mut prefilter = Prefilter::new(size);
+        for entry in self.entries.iter().flatten() {
+            prefilter.insert(&self.indexed(&entry.pattern));
+        }
+
+        self.prefilter = Some(prefilter);
+    }
+
+    pub fn disable_prefilter(&mut self) {
+        self.prefilter = None;
+    }
+
+    /// Adds a pattern to the set, returns the index of the pattern inside the set
+    ///
+    /// Indices are never reused, even after the pattern is removed again.
+    pub fn insert(&mut self, pattern: DomainPattern<'a, SPLITTER>) -> usize {
+        self.insert_with_priority(pattern, 0)
+    }
+
+    /// Adds a pattern with a priority to the set, see [`DomainPatternSet::best_match`] for what the priority is used for
+    ///
+    /// [`DomainPatternSet::insert`] uses a priority of 0.
+    pub fn insert_with_priority(&mut self, pattern: DomainPattern<'a, SPLITTER>, priority: i32) -> usize {
+        let idx = self.entries.len();
+        let indexed = self.indexed(&pattern);
+        if let Some(prefilter) = &mut self.prefilter {
+            prefilter.insert(&indexed);
+        }
+
+        if let Some(key) = literal_key(&indexed) {
+            self.literals.entry(key).or_default().push(idx);
+            self.push_entry(pattern, priority);
+            return idx;
+        }
+
+        let mut node = ROOT;
+        for part in indexed.steps.iter().rev() {
+            node = match self.child(node, part) {
+                Some(child) => child,
+                None => match Edge::of(part) {
+                    Edge::Static(label) => {
+                        let child = self.push_node(false);
+                        let symbol = self.interner.intern(label);
+                        self.nodes[node].statics.insert(symbol, child);
+                        child
+                    }
+
+                    Edge::Wildcard(wildcard) => {
+                        let child = self.push_node(wildcard.multi);
+                        self.nodes[node].wildcards[wildcard_slot(&wildcard)] = Some(child);
+                        child
+                    }
+
+                    Edge::Label(part) => {
+                        let child = self.push_node(false);
+                        self.nodes[node].labels.push((part.clone(), child));
+                        child
+                    }
+                },
+            };
+        }
+
+        self.nodes[node].terminal.push(idx);
+        self.push_entry(pattern, priority);
+        idx
+    }
+
+    fn push_entry(&mut self, pattern: DomainPattern<'a, SPLITTER>, priority: i32) {
+        self.negated += pattern.negated as usize;
+        self.entries.push(Some(Entry {
+            specificity: Specificity::of(&pattern),
+            pattern,
+            priority,
+        }));
+
+        self.len += 1;
+    }
+
+    /// Removes the pattern at the given index from the set, returns the pattern if it was still in the set
+    ///
+    /// Nodes only used by this pattern are pruned from the trie, the rest of the set is left untouched.
+    pub fn remove(&mut self, idx: usize) -> Option<DomainPattern<'a, SPLITTER>> {
+        let entry = self.entries.get_mut(idx)?.take()?;
+        self.len -= 1;
+        self.negated -= entry.pattern.negated as usize;
+        let indexed = self.indexed(&entry.pattern);
+        if let Some(prefilter) = &mut self.prefilter {
+            prefilter.remove(&indexed);
+        }
+
+        if let Some(key) = literal_key(&indexed) {
+            let indices = self.literals.get_mut(key.as_ref()).expect("literals contain every literal pattern in the set");
+            indices.retain(|literal| *literal != idx);
+            if indices.is_empty() {
+                self.literals.remove(key.as_ref());
+            }
+
+            return Some(entry.pattern);
+        }
+
+        let mut path = Vec::with_capacity(indexed.steps.len());
+        let mut node = ROOT;
+        for part in indexed.steps.iter().rev() {
+            path.push((node, part));
+            node = self.child(node, part).expect("trie contains every pattern in the set");
+        }
+
+        self.nodes[node].terminal.retain(|terminal| *terminal != idx);
+
+        for (parent, part) in path.into_iter().rev() {
+            let current = &self.nodes[node];
+            if !current.terminal.is_empty() || !current.statics.is_empty() || !current.labels.is_empty() || current.wildcards.iter().any(Option::is_some) {
+                break;
+            }
+
+            match Edge::of(part) {
+                Edge::Static(label) => {
+                    let symbol = self.interner.get(&LabelQuery::new(label)).expect("labels in the trie are interned");
+                    self.nodes[parent].statics.remove(&symbol);
+                    self.interner.release(label);
+                }
+
+                Edge::Wildcard(wildcard) => {
+                    self.nodes[parent].wildcards[wildcard_slot(&wildcard)] = None;
+                }
+
+                Edge::Label(part) => {
+                    self.nodes[parent].labels.retain(|(label, _)| label != part);
+                }
+            }
+
+            self.nodes[node] = Node::default();
+            self.free_nodes.push(node);
+            node = parent;
+        }
+
+        Some(entry.pattern)
+    }
+
+    fn child(&self, node: usize, part: &DomainPatternPart<'_>) -> Option<usize> {
+        match Edge::of(part) {
+            Edge::Static(label) => self.nodes[node].statics.get(&self.interner.get(&LabelQuery::new(label))?).copied(),
+            Edge::Wildcard(wildcard) => self.nodes[node].wildcards[wildcard_slot(&wildcard)],
+            Edge::Label(part) => self.nodes[node].labels.iter().find(|(label, _)| label == part).map(|(_, child)| *child),
+        }
+    }
+
+    fn push_node(&mut self, multi: bool) -> usize {
+        let node = Node {
+            multi,
+            ..Default::default()
+        };
+
+        if let Some(idx) = self.free_nodes.pop() {
+            self.nodes[idx] = node;
+            return idx;
+        }
+
+        self.nodes.push(node);
+        self.nodes.len() - 1
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the pattern at the given index, if it's still in the set
+    pub fn get(&self, idx: usize) -> Option<&DomainPattern<'a, SPLITTER>> {
+        self.entries.get(idx)?.as_ref().map(|entry| &entry.pattern)
+    }
+
+    /// Returns the priority of the pattern at the given index, if it's still in the set
+    pub fn priority(&self, idx: usize) -> Option<i32> {
+        self.entries.get(idx)?.as_ref().map(|entry| entry.priority)
+    }
+
+    /// Changes the priority of the pattern at the given index, returns the old priority if the pattern is still in the set
+    pub fn set_priority(&mut self, idx: usize, priority: i32) -> Option<i32> {
+        self.entries.get_mut(idx)?.as_mut().map(|entry| std::mem::replace(&mut entry.priority, priority))
+    }
+
+    /// Iterates over all patterns in the set together with their index, in insertion order
+    pub fn iter(&self) -> impl Iterator<Item=(usize, &DomainPattern<'a, SPLITTER>)> + '_ {
+        self.entries.iter()
+            .enumerate()
+            .filter_map(|(idx, entry)| entry.as_ref().map(|entry| (idx, &entry.pattern)))
+    }
+
+    /// Iterates over all patterns in the set together with their index and priority,
+    /// highest priority first, and in insertion order for equal priorities
+    pub fn iter_by_priority(&self) -> impl Iterator<Item=(usize, &DomainPattern<'a, SPLITTER>, i32)> + '_ {
+        let mut entries: Vec<_> = self.entries.iter()
+            .enumerate()
+            .filter_map(|(idx, entry)| entry.as_ref().map(|entry| (idx, &entry.pattern, entry.priority)))
+            .collect();
+
+        entries.sort_by_key(|(_, _, priority)| std::cmp::Reverse(*priority));
+        entries.into_iter()
+    }
+
+    /// Returns true if any pattern in the set matches the given domain
+    pub fn contains_match(&self, domain: &str) -> bool {
+        self.contains(domain, &mut MatchScratch::new())
+    }
+
+    pub(crate) fn contains(&self, domain: &str, scratch: &mut MatchScratch) -> bool {
+        let domain = self.input(domain);
+        let domain = domain.as_ref();
+        if !self.options.allows::<SPLITTER>(domain) || !self.may_match(domain) {
+            return false;
+        }
+
+        if self.negated > 0 {
+            return self.matches_all(domain).len() > 0;
+        }
+
+        if self.literals.contains_key(normalize_literal::<SPLITTER>(domain).as_ref()) {
+            return true;
+        }
+
+        self.walk(domain, scratch).iter().any(|path| !self.nodes[*path].terminal.is_empty())
+    }
+
+    /// Returns the indices of all patterns matching the domain, in ascending order
+    ///
+    /// If a negated pattern matches the domain nothing matches, see [`DomainPattern::is_negated`].
+    pub fn matches_all(&self, domain: &str) -> Matches {
+        let domain = self.input(domain);
+        let domain = domain.as_ref();
+        let mut indices: Vec<usize> = vec![];
+        if self.options.allows::<SPLITTER>(domain) && self.may_match(domain) {
+            if let Some(literals) = self.literals.get(normalize_literal::<SPLITTER>(domain).as_ref()) {
+                indices.extend_from_slice(literals);
+            }
+
+            indices.extend(self.walk(domain, &mut MatchScratch::new()).iter().flat_map(|path| self.nodes[*path].terminal.iter().copied()));
+        }
+
+        if self.negated > 0 && indices.iter().any(|idx| self.entries[*idx].as_ref().is_some_and(|entry| entry.pattern.negated)) {
+            indices.clear();
+        }
+
+        indices.sort_unstable();
+        Matches::new(indices)
+    }
+
+    /// Returns the index of the most specific pattern matching the domain
+    ///
+    /// Patterns are ranked by their parts, static labels > `+` > `*` > `**+` > `**`. The parts of a pattern are sorted
+    /// from most to least specific and compared one by one against the other pattern, the first difference decides,
+    /// and a pattern running out of parts beats a pattern that continues with a part that may match nothing.
+    ///
+    /// So `api.example.com` > `+.example.com` > `*.example.com` > `**.example.com`,
+    /// and `*.example.com` > `*.*.example.com`.
+    ///
+    /// If multiple patterns are equally specific, the one with the highest priority wins,
+    /// and if those are equal too, the one inserted first.
+    pub fn best_match(&self, domain: &str) -> Option<usize> {
+        let mut best: Option<(usize, &Entry<'a, SPLITTER>)> = None;
+        // matches_all yields the indices in ascending order, so only strictly better patterns replace the best
+        for idx in self.matches_all(domain) {
+            let entry = self.entries[idx].as_ref().expect("trie only contains patterns in the set");
+            if best.is_none_or(|(_, best)| (&entry.specificity, entry.priority) > (&best.specificity, best.priority)) {
+                best = Some((idx, entry));
+            }
+        }
+
+        best.map(|(idx, _)| idx)
+    }
+
+    /// Returns statistics about how the patterns are distributed over the trie
+    pub fn stats(&self) -> SetStats {
+        let root = &self.nodes[ROOT];
+        let mut buckets: Vec<(String, usize)> = self.interner.labels()
+            .filter_map(|(symbol, label)| Some((label.to_string(), self.count_patterns(*root.statics.get(&symbol)?))))
+            .collect();
+
+        buckets.sort_unstable_by(|(a_label, a_count), (b_label, b_count)| b_count.cmp(a_count).then_with(|| a_label.cmp(b_label)));
+
+        SetStats {
+            patterns: self.len,
+            literals: self.literals.values().map(Vec::len).sum(),
+            nodes: self.nodes.len() - self.free_nodes.len(),
+            labels: self.interner.len(),
+            buckets,
+            unbucketed: root.terminal.len() + root.wildcards.iter().flatten().chain(root.labels.iter().map(|(_, child)| child))
+                .map(|child| self.count_patterns(*child))
+                .sum::<usize>(),
+        }
+    }
+
+    /// Bytes the set takes on the heap, by what takes them, including the patterns in it as far as they own their labels
+    ///
+    /// The trie shares the nodes of patterns ending in the same labels, so a million rules ending in `.example.com`
+    /// store `com` and `example` once. Capacities count too, allocator overhead doesn't, so it's an estimate.
+    pub fn memory_usage(&self) -> MemoryStats {
+        let nodes = self.nodes.iter().map(|node| {
+            memory::map(&node.statics)
+                + memory::vec(&node.labels, node.labels.capacity())
+                + node.labels.iter().map(|(part, _)| memory::part(part)).sum::<usize>()
+                + memory::vec(&node.terminal, node.terminal.capacity())
+        }).sum::<usize>();
+
+        let entries = self.entries.iter().flatten().map(|entry| entry.pattern.heap_size()).sum::<usize>();
+        let literals = memory::map(&self.literals)
+            + self.literals.iter().map(|(domain, indices)| memory::cow(domain) + memory::vec(indices, indices.capacity())).sum::<usize>();
+
+        MemoryStats {
+            patterns: memory::vec(&self.entries, self.entries.capacity()) + entries,
+            literals,
+            labels: self.interner.memory_usage(),
+            nodes: memory::vec(&self.nodes, self.nodes.capacity()) + nodes + memory::vec(&self.free_nodes, self.free_nodes.capacity()),
+            prefilter: self.prefilter.as_ref().map_or(0, Prefilter::memory_usage),
+        }
+    }
+
+    fn count_patterns(&self, node: usize) -> usize {
+        let mut count = 0;
+        let mut todo = vec![node];
+        while let Some(node) = todo.pop() {
+            let node = &self.nodes[node];
+            count += node.terminal.len();
+            todo.extend(node.children());
+        }
+
+        count
+    }
+
+    // indices of every pattern stored in the same place in the trie as the given pattern, e.g. `{name}` and `+` share it
+    pub(crate) fn find(&self, pattern: &DomainPattern<'_, SPLITTER>) -> &[usize] {
+        let pattern = self.indexed(pattern);
+        if let Some(key) = literal_key(&pattern) {
+            return self.literals.get(key.as_ref()).map_or(&[], Vec::as_slice);
+        }
+
+        let mut node = ROOT;
+        for part in pattern.steps.iter().rev() {
+            match self.child(node, part) {
+                Some(child) => node = child,
+                None => return &[],
+            }
+        }
+
+        &self.nodes[node].terminal
+    }
+
+    fn may_match(&self, domain: &str) -> bool {
+        !self.is_empty() && self.prefilter.as_ref().is_none_or(|prefilter| prefilter.may_match::<SPLITTER>(domain))
+    }
+
+    // all nodes in the trie the domain ends up in, empty if nothing can match
+    fn walk<'s>(&self, domain: &str, scratch: &'s mut MatchScratch) -> &'s [usize] {
+        scratch.clear();
+        let MatchScratch { active, next } = scratch;
+
+        active.push(ROOT);
+        self.follow_optional(active);
+
+        for label in split::labels::<SPLITTER>(domain).rev() {
+            if label.is_empty() {
+                continue;
+            }
+
+            // a label that isn't interned isn't a static label of any node
+            let symbol = self.interner.get(&LabelQuery::new(label));
+            for path in active.iter() {
+                let node = &self.nodes[*path];
+                if node.multi {
+                    next.push(*path);
+                }
+
+                if let Some(child) = symbol.and_then(|symbol| node.statics.get(&symbol)) {
+                    next.push(*child);
+                }
+
+                next.extend(node.wildcards.iter().flatten().copied());
+                next.extend(node.labels.iter().filter(|(part, _)| part.matches_label(label, &self.options)).map(|(_, child)| *child));
+            }
+
+            self.follow_optional(next);
+            next.sort_unstable();
+            next.dedup();
+
+            mem::swap(active, next);
+            next.truncate(0);
+
+            if active.is_empty() {
+                break;
+            }
+        }
+
+        active
+    }
+
+    // adds every node reachable by skipping optional wildcards
+    fn follow_optional(&self, paths: &mut StackVec) {
+        let mut i = 0;
+        while i < paths.len() {
+            let node = &self.nodes[paths[i]];
+            for (slot, child) in node.wildcards.iter().enumerate() {
+                if let Some(child) = child {
+                    if slot_is_optional(slot) {
+                        paths.push(*child);
+                    }
+                }
+            }
+
+            i += 1;
+        }
+    }
+}
+
+/// Statistics about a [`DomainPatternSet`], see [`DomainPatternSet::stats`]
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct SetStats {
+    pub patterns: usize,
+    /// Patterns without wildcards, these aren't stored in the trie
+    pub literals: usize,
+    /// Nodes in the trie, including the root
+    pub nodes: usize,
+    /// Amount of patterns in the trie per rightmost static label, biggest bucket first
+    pub buckets: Vec<(String, usize)>,
+    /// Patterns not ending in a static label, these are walked for every domain
+    pub unbucketed: usize,
+    /// Different static labels in the trie, each stored once however many patterns share it
+    pub labels: usize,
+}
+
+/// Bytes a [`DomainPatternSet`] takes on the heap, see [`DomainPatternSet::memory_usage`]
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub struct MemoryStats {
+    /// The patterns themselves, their steps and the labels they own
+    pub patterns: usize,
+    /// Patterns without wildcards, by their domain
+    pub literals: usize,
+    /// Static labels of the trie, interned so every label is stored once
+    pub labels: usize,
+    /// Nodes of the trie, and the edges between them
+    pub nodes: usize,
+    pub prefilter: usize,
+}
+
+impl MemoryStats {
+    pub fn total(&self) -> usize {
+        self.patterns + self.literals + self.labels + self.nodes + self.prefilter
+    }
+}
+
+/// Iterator over the indices of the patterns matching a domain, see [`DomainPatternSet::matches_all`]
+#[derive(Clone, Debug)]
+pub struct Matches {
+    indices: std::vec::IntoIter<usize>,
+}
+
+impl Matches {
+    pub(crate) fn new(indices: Vec<usize>) -> Self {
+        Matches {
+            indices: indices.into_iter(),
+        }
+    }
+}
+
+impl Iterator for Matches {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.indices.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.indices.size_hint()
+    }
+}
+
+impl ExactSizeIterator for Matches {}
+
+impl DoubleEndedIterator for Matches {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.indices.next_back()
+    }
+}
+
+impl<const SPLITTER: char> Default for DomainPatternSet<'_, SPLITTER> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, const SPLITTER: char> Extend<DomainPattern<'a, SPLITTER>> for DomainPatternSet<'a, SPLITTER> {
+    fn extend<T: IntoIterator<Item=DomainPattern<'a, SPLITTER>>>(&mut self, iter: T) {
+        for pattern in iter {
+            self.insert(pattern);
+        }
+    }
+}
+
+impl<'a, const SPLITTER: char> FromIterator<DomainPattern<'a, SPLITTER>> for DomainPatternSet<'a, SPLITTER> {
+    fn from_iter<T: IntoIterator<Item=DomainPattern<'a, SPLITTER>>>(iter: T) -> Self {
+        let mut set = Self::new();
+        set.extend(iter);
+        set
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{DomainPattern, DomainPatternSet, MatchOptions};
+
+    const PATTERNS: &[&str] = &[
+        "domain.tld",
+        "*.domain.tld",
+        "+.domain.tld",
+        "**.domain.tld",
+        "**+.domain.tld",
+        "nice.**.nice",
+        "nice.**+.nice",
+        "nice.*.nice",
+        "+.nice.**",
+        "x.*.*",
+        "*.+",
+        "**",
+        "*",
+        "ni*.**",
+        "*ce.domain.tld",
+        "+.d*n.tld",
+        "?.domain.tld",
+        "{sub,nice}.domain.tld",
+        "#.x.**",
+    ];
+
+    const DOMAINS: &[&str] = &[
+        "",
+        "tld",
+        "domain.tld",
+        "sub.domain.tld",
+        "sub.sub.domain.tld",
+        "nice",
+        "nice.nice",
+        "nice.nice.nice",
+        "nice.nice.nice.nice",
+        "nice.wow",
+        "x",
+        "x.x.x",
+        "x.x.x.x",
+        "nice.domain.tld",
+        "sub.nice.domain.tld",
+        "a.domain.tld",
+        "1.x.x",
+        "12.x",
+    ];
+
+    #[test]
+    pub fn test_agrees_with_single_patterns() {
+        for pattern in PATTERNS.iter().copied() {
+            let single: DomainPattern = pattern.try_into().expect("failed to parse");
+            let set: DomainPatternSet = [single.clone()].into_iter().collect();
+
+            for domain in DOMAINS.iter().copied() {
+                assert_eq!(single.matches(domain), set.contains_match(domain), "{:?} against {:?}", pattern, domain);
+            }
+        }
+    }
+
+    #[test]
+    pub fn test_many() {
+        let mut set: DomainPatternSet = DomainPatternSet::new();
+        assert!(set.is_empty());
+        assert!(!set.contains_match("domain.tld"));
+
+        assert_eq!(set.insert("+.domain.tld".try_into().expect("failed to parse")), 0);
+        assert_eq!(set.insert("other.tld".try_into().expect("failed to parse")), 1);
+        assert_eq!(set.insert("**.nice".try_into().expect("failed to parse")), 2);
+        assert_eq!(set.len(), 3);
+
+        assert!(set.contains_match("sub.domain.tld"));
+        assert!(set.contains_match("other.tld"));
+        assert!(set.contains_match("nice"));
+        assert!(set.contains_match("very.nice"));
+        assert!(!set.contains_match("domain.tld"));
+        assert!(!set.contains_match("sub.other.tld"));
+        assert!(!set.contains_match("nice.wow"));
+    }
+
+    #[test]
+    pub fn test_matches_all() {
+        let set: DomainPatternSet = ["**.domain.tld", "sub.domain.tld", "other.tld", "+.domain.tld", "**.domain.tld"]
+            .into_iter()
+            .map(|pattern| pattern.try_into().expect("failed to parse"))
+            .collect();
+
+        assert_eq!(set.matches_all("sub.domain.tld").collect::<Vec<_>>(), vec![0, 1, 3, 4]);
+        assert_eq!(set.matches_all("domain.tld").collect::<Vec<_>>(), vec![0, 4]);
+        assert_eq!(set.matches_all("other.tld").len(), 1);
+        assert_eq!(set.matches_all("nope.tld").next(), None);
+    }
+
+    #[test]
+    pub fn test_best_match() {
+        let set: DomainPatternSet = ["**.example.com", "*.*.example.com", "*.example.com", "+.example.com", "api.example.com", "+.example.com"]
+            .into_iter()
+            .map(|pattern| pattern.try_into().expect("failed to parse"))
+            .collect();
+
+        assert_eq!(set.best_match("api.example.com"), Some(4));
+        assert_eq!(set.best_match("www.example.com"), Some(3));
+        assert_eq!(set.best_match("example.com"), Some(2));
+        assert_eq!(set.best_match("deep.www.example.com"), Some(1));
+        assert_eq!(set.best_match("very.deep.www.example.com"), Some(0));
+        assert_eq!(set.best_match("example.org"), None);
+    }
+
+    #[test]
+    pub fn test_priority() {
+        let mut set: DomainPatternSet = DomainPatternSet::new();
+        let vendor = set.insert("+.example.com".try_into().expect("failed to parse"));
+        let user = set.insert_with_priority("www.+.com".try_into().expect("failed to parse"), 10);
+        let specific = set.insert_with_priority("api.example.com".try_into().expect("failed to parse"), -10);
+        let other = set.insert("**.example.com".try_into().expect("failed to parse"));
+
+        // priority only breaks ties between equally specific patterns
+        assert_eq!(set.best_match("www.example.com"), Some(user));
+        assert_eq!(set.best_match("api.example.com"), Some(specific));
+
+        assert_eq!(set.set_priority(vendor, 20), Some(0));
+        assert_eq!(set.priority(vendor), Some(20));
+        assert_eq!(set.best_match("www.example.com"), Some(vendor));
+
+        let order: Vec<_> = set.iter_by_priority().map(|(idx, _, priority)| (idx, priority)).collect();
+        assert_eq!(order, vec![(vendor, 20), (user, 10), (other, 0), (specific, -10)]);
+
+        set.remove(vendor);
+        assert_eq!(set.priority(vendor), None);
+        assert_eq!(set.set_priority(vendor, 1), None);
+    }
+
+    #[test]
+    pub fn test_remove() {
+        let mut set: DomainPatternSet = DomainPatternSet::new();
+        let any = set.insert("**.example.com".try_into().expect("failed to parse"));
+        let api = set.insert("api.example.com".try_into().expect("failed to parse"));
+        let other = set.insert("other.tld".try_into().expect("failed to parse"));
+        let nodes = set.nodes.len();
+
+        assert_eq!(set.remove(api), Some("api.example.com".try_into().expect("failed to parse")));
+        assert_eq!(set.remove(api), None);
+        assert_eq!(set.get(api), None);
+        assert_eq!(set.len(), 2);
+        assert_eq!(set.best_match("api.example.com"), Some(any));
+        assert!(set.contains_match("other.tld"));
+
+        assert_eq!(set.remove(any).map(|pattern| pattern.steps.len()), Some(3));
+        assert!(!set.contains_match("api.example.com"));
+        assert!(set.contains_match("other.tld"));
+
+        // pruned nodes get reused
+        let api = set.insert("api.example.com".try_into().expect("failed to parse"));
+        assert_eq!(api, 3);
+        assert!(set.nodes.len() <= nodes);
+        assert!(set.contains_match("api.example.com"));
+
+        set.remove(other);
+        set.remove(api);
+        assert!(set.is_empty());
+        assert!(set.nodes[0].statics.is_empty());
+        assert_eq!(set.free_nodes.len(), set.nodes.len() - 1);
+    }
+
+    #[test]
+    pub fn test_prefilter() {
+        let mut set: DomainPatternSet = DomainPatternSet::with_prefilter(1024);
+        set.insert("**.example.com".try_into().expect("failed to parse"));
+        set.insert("api.+.org".try_into().expect("failed to parse"));
+        set.insert("exact.tld".try_into().expect("failed to parse"));
+
+        let filter = set.prefilter.as_ref().expect("prefilter enabled");
+        assert!(filter.may_match::<'.'>("example.com"));
+        assert!(filter.may_match::<'.'>("deep.sub.example.com"));
+        assert!(filter.may_match::<'.'>("api.www.org"));
+        assert!(filter.may_match::<'.'>("exact.tld"));
+        assert!(!filter.may_match::<'.'>(""));
+        assert!(!filter.may_match::<'.'>("nice.nice"));
+
+        assert!(set.contains_match("deep.sub.example.com"));
+        assert!(set.contains_match("api.www.org"));
+        assert!(!set.contains_match("www.org"));
+        assert!(!set.contains_match("nice.nice"));
+
+        // a trailing wildcard can't be filtered on
+        let wildcard = set.insert("example.*".try_into().expect("failed to parse"));
+        assert!(set.prefilter.as_ref().expect("prefilter enabled").may_match::<'.'>("nice.nice"));
+        assert!(set.contains_match("example.nice"));
+
+        set.remove(wildcard);
+        assert!(!set.prefilter.as_ref().expect("prefilter enabled").may_match::<'.'>("nice.nice"));
+        assert!(set.contains_match("example.com"));
+    }
+
+    #[test]
+    pub fn test_negated() {
+        let mut set: DomainPatternSet = ["**.example.com", "!internal.example.com", "!**.corp.example.com"]
+            .into_iter()
+            .map(|pattern| pattern.try_into().expect("failed to parse"))
+            .collect();
+
+        assert!(set.contains_match("www.example.com"));
+        assert!(!set.contains_match("internal.example.com"));
+        assert!(!set.contains_match("a.corp.example.com"));
+        assert_eq!(set.matches_all("www.example.com").collect::<Vec<_>>(), vec![0]);
+        assert_eq!(set.matches_all("internal.example.com").len(), 0);
+        assert_eq!(set.best_match("internal.example.com"), None);
+
+        set.remove(1);
+        assert!(set.contains_match("internal.example.com"));
+    }
+
+    #[test]
+    pub fn test_case_insensitive() {
+        let mut set: DomainPatternSet = DomainPatternSet::with_options(MatchOptions::new().case_insensitive(true));
+        set.insert("**.Example.com".try_into().expect("failed to parse"));
+        set.insert("API.example.com".try_into().expect("failed to parse"));
+        set.insert("Node-*.example.com".try_into().expect("failed to parse"));
+        set.enable_prefilter(64);
+
+        assert_eq!(set.matches_all("api.EXAMPLE.com").collect::<Vec<_>>(), vec![0, 1]);
+        assert_eq!(set.matches_all("node-1.example.COM").collect::<Vec<_>>(), vec![0, 2]);
+        // globs in the trie are matched with the options of the set too
+        set.insert("Node[A-C].example.org".try_into().expect("failed to parse"));
+        assert!(set.contains_match("nodeb.example.org"));
+        assert_eq!(set.matches_all("NODEB.example.org").collect::<Vec<_>>(), vec![3]);
+        assert!(set.contains_match("WWW.EXAMPLE.COM"));
+        assert_eq!(set.get(1).map(|pattern| pattern.matches("API.example.com")), Some(true));
+
+        assert!(set.remove(1).is_some());
+        assert_eq!(set.matches_all("api.example.com").collect::<Vec<_>>(), vec![0]);
+
+        let set: DomainPatternSet = ["API.example.com"].iter().map(|pattern| DomainPattern::parse(pattern).expect("failed to parse")).collect();
+        assert!(!set.contains_match("api.example.com"));
+    }
+
+    #[test]
+    pub fn test_memory_usage() {
+        let shared: DomainPatternSet = (0..100).map(|n| DomainPattern::parse(&format!("+.a{}.example.com", n)).expect("failed to parse").into_owned()).collect();
+        let separate: DomainPatternSet = (0..100).map(|n| DomainPattern::parse(&format!("+.a{}.example{}.com", n, n)).expect("failed to parse").into_owned()).collect();
+
+        // root, com and example once, then a label and a `+` for every pattern
+        assert_eq!(shared.stats().nodes, 203);
+        assert_eq!(separate.stats().nodes, 302);
+        let (shared, separate) = (shared.memory_usage(), separate.memory_usage());
+        assert!(shared.total() < separate.total());
+        assert!(shared.labels < separate.labels);
+        assert!(shared.nodes < separate.nodes);
+        assert_eq!(shared.prefilter, 0);
+
+        let empty = DomainPatternSet::<'_, '.'>::new().memory_usage();
+        assert_eq!(empty.patterns, 0);
+        assert!(empty.total() < shared.total());
+    }
+
+    #[test]
+    pub fn test_stats() {
+        let mut set: DomainPatternSet = ["**.example.com", "api.example.com", "*.org", "example.*", "**", "other.com"]
+            .into_iter()
+            .map(|pattern| pattern.try_into().expect("failed to parse"))
+            .collect();
+
+        let stats = set.stats();
+        assert_eq!(stats.patterns, 6);
+        assert_eq!(stats.literals, 2);
+        assert_eq!(stats.buckets, vec![("com".to_string(), 1), ("org".to_string(), 1)]);
+        assert_eq!(stats.unbucketed, 2);
+        // root, com, example, **, org, *, *, example, **
+        assert_eq!(stats.nodes, 9);
+        // com, example, org
+        assert_eq!(stats.labels, 3);
+
+        set.remove(0);
+        set.remove(1);
+        let stats = set.stats();
+        assert_eq!(stats.literals, 1);
+        assert_eq!(stats.buckets, vec![("org".to_string(), 1)]);
+        assert_eq!(stats.nodes, 6);
+        assert_eq!(stats.labels, 2);
+    }
+
+    #[test]
+    pub fn test_literals() {
+        let mut set: DomainPatternSet = ["example.com", "api.example.com", "+.example.com", "example.com", "a..b"]
+            .into_iter()
+            .map(|pattern| pattern.try_into().expect("failed to parse"))
+            .collect();
+
+        assert_eq!(set.stats().literals, 3);
+        assert_eq!(set.matches_all("example.com").collect::<Vec<_>>(), vec![0, 3]);
+        assert_eq!(set.matches_all(".example..com.").collect::<Vec<_>>(), vec![0, 3]);
+        assert_eq!(set.matches_all("api.example.com").collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(set.best_match("api.example.com"), Some(1));
+        assert!(!set.contains_match("a..b"));
+        assert!(!set.contains_match("a.b"));
+
+        set.remove(0);
+        assert_eq!(set.matches_all("example.com").collect::<Vec<_>>(), vec![3]);
+        set.remove(3);
+        assert!(!set.contains_match("example.com"));
+        assert_eq!(set.stats().literals, 1);
+    }
+
+    #[test]
+    pub fn test_splitter() {
+        let set: DomainPatternSet<'_, '/'> = ["+/nice/**".try_into().expect("failed to parse")].into_iter().collect();
+        assert!(set.contains_match("nice/nice/nice"));
+        assert!(!set.contains_match("nice/wow"));
+    }
+}
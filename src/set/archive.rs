@@ -0,0 +1,238 @@
+//! Sets as rkyv archives, queried right where they're mapped in, so a big ruleset loads without building anything
+
+use std::mem;
+use rkyv::util::AlignedVec;
+use rkyv::{Archive, Deserialize, Serialize};
+use crate::binary::{decode_options, encode_options};
+use crate::set::{normalize_literal, slot_is_optional, DomainPatternSet, Matches, ROOT};
+use crate::{DecodeError, MatchOptions, PartArchive, StackVec};
+
+const NO_CHILD: u32 = u32::MAX;
+
+/// A node of the trie, with static labels sorted so they can be binary searched in the archive
+#[derive(Archive, Serialize, Deserialize, Clone, Debug)]
+struct NodeArchive {
+    multi: bool,
+    wildcards: [u32; 4],
+    statics: Vec<(String, u32)>,
+    labels: Vec<(PartArchive, u32)>,
+    terminal: Vec<u32>,
+}
+
+/// A set that can be archived with rkyv, see [`DomainPatternSet::to_archive`]
+///
+/// Only what's needed to match is archived, the patterns themselves aren't, and neither is the prefilter.
+#[derive(Archive, Serialize, Deserialize, Clone, Debug)]
+pub struct SetArchive<const SPLITTER: char = '.'> {
+    options: [u8; 3],
+    // indices of the negated patterns, sorted
+    negated: Vec<u32>,
+    // patterns without wildcards, sorted by their domain
+    literals: Vec<(String, Vec<u32>)>,
+    nodes: Vec<NodeArchive>,
+}
+
+impl<const SPLITTER: char> DomainPatternSet<'_, SPLITTER> {
+    /// The trie of the set as something rkyv can archive, matching the archive doesn't need deserializing it
+    ///
+    /// Indices of patterns stay the same as in the set.
+    pub fn to_archive(&self) -> SetArchive<SPLITTER> {
+        let labels: Vec<&str> = {
+            let mut labels = vec![""; self.interner.labels().map(|(symbol, _)| symbol as usize + 1).max().unwrap_or(0)];
+            self.interner.labels().for_each(|(symbol, label)| labels[symbol as usize] = label);
+            labels
+        };
+
+        let index = |idx: &usize| *idx as u32;
+        let nodes = self.nodes.iter().map(|node| {
+            let mut statics: Vec<(String, u32)> = node.statics.iter().map(|(symbol, child)| (labels[*symbol as usize].to_string(), *child as u32)).collect();
+            statics.sort_unstable();
+
+            NodeArchive {
+                multi: node.multi,
+                wildcards: node.wildcards.map(|child| child.map_or(NO_CHILD, |child| child as u32)),
+                statics,
+                labels: node.labels.iter().map(|(part, child)| (PartArchive::from(part), *child as u32)).collect(),
+                terminal: node.terminal.iter().map(index).collect(),
+            }
+        }).collect();
+
+        let mut literals: Vec<(String, Vec<u32>)> = self.literals.iter().map(|(domain, indices)| (domain.to_string(), indices.iter().map(index).collect())).collect();
+        literals.sort_unstable();
+
+        SetArchive {
+            options: encode_options(&self.options),
+            negated: self.iter().filter(|(_, pattern)| pattern.negated).map(|(idx, _)| idx as u32).collect(),
+            literals,
+            nodes,
+        }
+    }
+}
+
+impl<const SPLITTER: char> SetArchive<SPLITTER> {
+    pub fn to_bytes(&self) -> AlignedVec {
+        rkyv::to_bytes::<rkyv::rancor::Error>(self).expect("serializing into memory doesn't fail")
+    }
+
+    /// Checks the bytes are a valid archive of a set this build can match with, and returns it without copying anything
+    ///
+    /// The bytes have to be aligned like the [`AlignedVec`] they were written to, a memory map always is.
+    pub fn access(bytes: &[u8]) -> Result<&ArchivedSetArchive<SPLITTER>, DecodeError> {
+        let archive = rkyv::access::<ArchivedSetArchive<SPLITTER>, rkyv::rancor::Error>(bytes).map_err(|_| DecodeError::Invalid)?;
+        decode_options(archive.options)?;
+
+        let nodes = archive.nodes.len() as u32;
+        let children = archive.nodes.iter().flat_map(|node| {
+            let wildcards = node.wildcards.iter().map(|child| child.to_native()).filter(|child| *child != NO_CHILD);
+            let statics = node.statics.iter().map(|label| label.1.to_native());
+            let labels = node.labels.iter().map(|label| label.1.to_native());
+            wildcards.chain(statics).chain(labels)
+        });
+
+        match nodes > 0 && children.into_iter().all(|child| child < nodes) {
+            true => Ok(archive),
+            false => Err(DecodeError::Invalid),
+        }
+    }
+}
+
+impl<const SPLITTER: char> ArchivedSetArchive<SPLITTER> {
+    // only archives with options this build can't match with fail, and those are rejected by `SetArchive::access`
+    fn options(&self) -> MatchOptions {
+        decode_options(self.options).unwrap_or_default()
+    }
+
+    /// Returns true if any pattern in the set matches the given domain, like [`DomainPatternSet::contains_match`]
+    pub fn contains_match(&self, domain: &str) -> bool {
+        match self.negated.is_empty() {
+            true => !self.matches(domain, true).is_empty(),
+            false => self.matches_all(domain).len() > 0,
+        }
+    }
+
+    /// Returns the indices of all patterns matching the domain, in ascending order, like [`DomainPatternSet::matches_all`]
+    pub fn matches_all(&self, domain: &str) -> Matches {
+        let mut indices = self.matches(domain, false);
+        if indices.iter().any(|idx| self.negated.binary_search_by_key(&(*idx as u32), |negated| negated.to_native()).is_ok()) {
+            indices.clear();
+        }
+
+        indices.sort_unstable();
+        Matches::new(indices)
+    }
+
+    fn matches(&self, domain: &str, any: bool) -> Vec<usize> {
+        let options = self.options();
+        let domain = options.normalize_domain::<SPLITTER>(domain);
+        let domain = domain.as_ref();
+        if !options.allows::<SPLITTER>(domain) {
+            return vec![];
+        }
+
+        let mut indices = vec![];
+        let literal = normalize_literal::<SPLITTER>(domain);
+        if let Ok(found) = self.literals.binary_search_by(|literal_domain| literal_domain.0.as_str().cmp(literal.as_ref())) {
+            indices.extend(self.literals[found].1.iter().map(|idx| idx.to_native() as usize));
+            if any && !indices.is_empty() {
+                return indices;
+            }
+        }
+
+        for path in self.walk(domain) {
+            indices.extend(self.nodes[path].terminal.iter().map(|idx| idx.to_native() as usize));
+        }
+
+        indices
+    }
+
+    // all nodes in the trie the domain ends up in, like `DomainPatternSet::walk`
+    fn walk(&self, domain: &str) -> StackVec {
+        let mut active = StackVec::new();
+        let mut next = StackVec::new();
+        active.push(ROOT);
+        self.follow_optional(&mut active);
+
+        for label in crate::split::labels::<SPLITTER>(domain).rev() {
+            if label.is_empty() {
+                continue;
+            }
+
+            for path in active.iter() {
+                let node = &self.nodes[*path];
+                if node.multi {
+                    next.push(*path);
+                }
+
+                if let Ok(found) = node.statics.binary_search_by(|child| child.0.as_str().cmp(label)) {
+                    next.push(node.statics[found].1.to_native() as usize);
+                }
+
+                next.extend(node.wildcards.iter().map(|child| child.to_native()).filter(|child| *child != NO_CHILD).map(|child| child as usize));
+                next.extend(node.labels.iter()
+                    .filter(|child| child.0.to_part().is_some_and(|part| part.matches_label(label, &MatchOptions::new())))
+                    .map(|child| child.1.to_native() as usize));
+            }
+
+            self.follow_optional(&mut next);
+            next.sort_unstable();
+            next.dedup();
+
+            mem::swap(&mut active, &mut next);
+            next.truncate(0);
+
+            if active.is_empty() {
+                break;
+            }
+        }
+
+        active
+    }
+
+    fn follow_optional(&self, paths: &mut StackVec) {
+        let mut i = 0;
+        while i < paths.len() {
+            let node = &self.nodes[paths[i]];
+            for (slot, child) in node.wildcards.iter().enumerate() {
+                if slot_is_optional(slot) && child.to_native() != NO_CHILD {
+                    paths.push(child.to_native() as usize);
+                }
+            }
+
+            i += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{DecodeError, DomainPattern, DomainPatternSet, MatchOptions, SetArchive};
+
+    #[test]
+    pub fn test_set_archive() {
+        let patterns = ["example.com", "*.example.com", "**.example.org", "api-*.{tenant}.com", "{a,b}.#.net", "x.**+.y", "!blocked.example.com",
+            "a..b", "**"];
+        let domains = ["example.com", "www.example.com", "blocked.example.com", "a.b.example.org", "example.org", "api-v1.t.com", "a.1.net",
+            "c.1.net", "x.y", "x.z.y", "a.b", "", "anything"];
+
+        for size in [patterns.len(), patterns.len() - 1, 3] {
+            let mut set: DomainPatternSet = patterns[..size].iter().map(|pattern| DomainPattern::try_from(*pattern).expect("failed to parse")).collect();
+            set.remove(1);
+
+            let bytes = set.to_archive().to_bytes();
+            let archived = <SetArchive>::access(&bytes).expect("valid archive");
+            for domain in domains {
+                assert_eq!(archived.contains_match(domain), set.contains_match(domain), "{:?} matches differently", domain);
+                assert_eq!(archived.matches_all(domain).collect::<Vec<_>>(), set.matches_all(domain).collect::<Vec<_>>(), "{:?} matches differently", domain);
+            }
+        }
+
+        let mut set: DomainPatternSet = DomainPatternSet::with_options(MatchOptions::new().case_insensitive(true));
+        set.insert("*.Example.com".try_into().expect("failed to parse"));
+        let bytes = set.to_archive().to_bytes();
+        let archived = <SetArchive>::access(&bytes).expect("valid archive");
+        assert!(archived.contains_match("WWW.example.COM"));
+        assert!(!archived.contains_match("example.org"));
+
+        assert_eq!(<SetArchive>::access(&bytes[..bytes.len() - 4]).err(), Some(DecodeError::Invalid));
+    }
+}
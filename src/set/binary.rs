@@ -0,0 +1,259 @@
+//! Sets written to bytes as they are in memory, so loading them skips parsing and building the trie
+
+use std::borrow::Cow;
+use crate::binary::{decode_options, encode_options, Reader, Writer};
+use crate::set::interner::Interner;
+use crate::set::{Entry, Node};
+use crate::specificity::Specificity;
+use crate::{DecodeError, DomainPatternSet};
+
+const MAGIC: &[u8; 4] = b"DMPS";
+const VERSION: u16 = 1;
+const NO_CHILD: u64 = u64::MAX;
+
+// whether every node but the free ones is reached from the root over exactly one edge, so walking the trie ends
+fn is_tree(nodes: &[Node], free_nodes: &[usize]) -> bool {
+    let mut seen = vec![false; nodes.len()];
+    seen[0] = true;
+    let mut todo = vec![0];
+    while let Some(node) = todo.pop() {
+        for child in nodes[node].children() {
+            if std::mem::replace(&mut seen[child], true) {
+                return false;
+            }
+
+            todo.push(child);
+        }
+    }
+
+    free_nodes.iter().all(|node| !std::mem::replace(&mut seen[*node], true)) && seen.into_iter().all(|seen| seen)
+}
+
+impl<const SPLITTER: char> DomainPatternSet<'_, SPLITTER> {
+    /// Writes the set to bytes, trie and all, so [`DomainPatternSet::deserialize_compiled`] can load it without
+    /// building it again
+    ///
+    /// The bytes are versioned and checksummed, the same on every platform. Only sets with the same splitter can
+    /// load them, and a set matching with `idna` or `nfc` only loads with that feature enabled.
+    pub fn serialize_compiled(&self) -> Vec<u8> {
+        let mut writer = Writer::new(MAGIC, VERSION);
+        writer.u32(SPLITTER as u32);
+        encode_options(&self.options).iter().for_each(|byte| writer.u8(*byte));
+
+        writer.usize(self.entries.len());
+        for entry in &self.entries {
+            writer.bool(entry.is_some());
+            if let Some(entry) = entry {
+                writer.pattern(&entry.pattern);
+                writer.u32(entry.priority as u32);
+            }
+        }
+
+        writer.usize(self.literals.len());
+        for (domain, indices) in &self.literals {
+            writer.str(domain);
+            writer.usize(indices.len());
+            indices.iter().for_each(|idx| writer.usize(*idx));
+        }
+
+        self.interner.encode(&mut writer);
+
+        writer.usize(self.nodes.len());
+        for node in &self.nodes {
+            writer.bool(node.multi);
+            node.wildcards.iter().for_each(|child| writer.u64(child.map_or(NO_CHILD, |child| child as u64)));
+
+            writer.usize(node.statics.len());
+            for (symbol, child) in &node.statics {
+                writer.u32(*symbol);
+                writer.usize(*child);
+            }
+
+            writer.usize(node.labels.len());
+            for (part, child) in &node.labels {
+                writer.part(part);
+                writer.usize(*child);
+            }
+
+            writer.usize(node.terminal.len());
+            node.terminal.iter().for_each(|idx| writer.usize(*idx));
+        }
+
+        writer.usize(self.free_nodes.len());
+        self.free_nodes.iter().for_each(|node| writer.usize(*node));
+
+        // the prefilter hashes aren't stable between builds, so it's filled again when loading
+        writer.usize(self.prefilter.as_ref().map_or(0, |prefilter| prefilter.size()));
+        writer.finish()
+    }
+}
+
+impl<const SPLITTER: char> DomainPatternSet<'static, SPLITTER> {
+    /// Loads a set written by [`DomainPatternSet::serialize_compiled`]
+    pub fn deserialize_compiled(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut reader = Reader::new(bytes, MAGIC, VERSION)?;
+        if reader.u32()? != SPLITTER as u32 {
+            return Err(DecodeError::Invalid);
+        }
+
+        let options = [reader.u8()?, reader.u8()?, reader.u8()?];
+        let mut set = DomainPatternSet::with_options(decode_options(options)?);
+        set.nodes.clear();
+
+        for _ in 0..reader.len(1)? {
+            if !reader.bool()? {
+                set.entries.push(None);
+                continue;
+            }
+
+            let pattern = reader.pattern()?;
+            let priority = reader.u32()? as i32;
+            set.len += 1;
+            set.negated += pattern.negated as usize;
+            set.entries.push(Some(Entry {
+                specificity: Specificity::of(&pattern),
+                pattern,
+                priority,
+            }));
+        }
+
+        // patterns are only referenced while they're in the set
+        let live: Vec<bool> = set.entries.iter().map(Option::is_some).collect();
+        let entry = |reader: &mut Reader| match reader.index(live.len())? {
+            idx if live[idx] => Ok(idx),
+            _ => Err(DecodeError::Invalid),
+        };
+
+        for _ in 0..reader.len(16)? {
+            let domain = reader.str()?.to_string();
+            let indices = (0..reader.len(8)?).map(|_| entry(&mut reader)).collect::<Result<Vec<_>, _>>()?;
+            set.literals.insert(Cow::Owned(domain), indices);
+        }
+
+        set.interner = Interner::decode(&mut reader)?;
+
+        let nodes = reader.len(41)?;
+        let child = |reader: &mut Reader| reader.index(nodes);
+        for _ in 0..nodes {
+            let mut node = Node {
+                multi: reader.bool()?,
+                ..Default::default()
+            };
+
+            for slot in node.wildcards.iter_mut() {
+                *slot = match reader.u64()? {
+                    NO_CHILD => None,
+                    child if child < nodes as u64 => Some(child as usize),
+                    _ => return Err(DecodeError::Invalid),
+                };
+            }
+
+            for _ in 0..reader.len(12)? {
+                let symbol = reader.u32()?;
+                if !set.interner.is_live(symbol) {
+                    return Err(DecodeError::Invalid);
+                }
+
+                node.statics.insert(symbol, child(&mut reader)?);
+            }
+
+            for _ in 0..reader.len(9)? {
+                let part = reader.part()?;
+                node.labels.push((part, child(&mut reader)?));
+            }
+
+            node.terminal = (0..reader.len(8)?).map(|_| entry(&mut reader)).collect::<Result<_, _>>()?;
+            set.nodes.push(node);
+        }
+
+        set.free_nodes = (0..reader.len(8)?).map(|_| child(&mut reader)).collect::<Result<_, _>>()?;
+
+        let prefilter = reader.usize()?;
+        reader.finish()?;
+
+        if set.nodes.is_empty() || !is_tree(&set.nodes, &set.free_nodes) {
+            return Err(DecodeError::Invalid);
+        }
+
+        if prefilter > 0 {
+            set.enable_prefilter(prefilter);
+        }
+
+        Ok(set)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{DecodeError, DomainPattern, DomainPatternSet, MatchOptions};
+
+    #[test]
+    pub fn test_compiled_set() {
+        let mut set: DomainPatternSet = DomainPatternSet::with_options(MatchOptions::new().case_insensitive(true));
+        for pattern in ["**.Example.com", "api.example.com", "*.{a,b}.org", "node-#.example.*", "!blocked.example.com", "other.*.net"] {
+            set.insert(DomainPattern::parse(pattern).expect("failed to parse"));
+        }
+
+        set.insert_with_priority("**.important.com".try_into().expect("failed to parse"), 10);
+        set.remove(5);
+        set.enable_prefilter(64);
+
+        let bytes = set.serialize_compiled();
+        let loaded: DomainPatternSet = DomainPatternSet::deserialize_compiled(&bytes).expect("loads");
+        assert_eq!(loaded.len(), set.len());
+        assert_eq!(loaded.options(), set.options());
+        assert_eq!(loaded.iter().map(|(idx, pattern)| (idx, pattern.to_string())).collect::<Vec<_>>(),
+            set.iter().map(|(idx, pattern)| (idx, pattern.to_string())).collect::<Vec<_>>());
+
+        let domains = ["www.example.com", "API.example.com", "blocked.example.com", "x.a.org", "a.org", "node-1.example.io",
+            "other.x.net", "a.important.com", "nothing.tld", ""];
+        for domain in domains {
+            assert_eq!(loaded.matches_all(domain).collect::<Vec<_>>(), set.matches_all(domain).collect::<Vec<_>>(), "{:?} matches differently", domain);
+            assert_eq!(loaded.best_match(domain), set.best_match(domain), "{:?} matches differently", domain);
+        }
+
+        // removing and inserting keeps working after loading
+        let mut loaded = loaded;
+        loaded.remove(0);
+        loaded.insert("www.example.com".try_into().expect("failed to parse"));
+        assert_eq!(loaded.matches_all("www.example.com").collect::<Vec<_>>(), vec![7]);
+        assert_eq!(loaded.serialize_compiled().len(), loaded.serialize_compiled().len());
+    }
+
+    #[test]
+    pub fn test_compiled_set_errors() {
+        let set: DomainPatternSet = ["*.example.com"].iter().map(|pattern| DomainPattern::parse(pattern).expect("failed to parse")).collect();
+        let bytes = set.serialize_compiled();
+
+        assert!(DomainPatternSet::<'_, '.'>::deserialize_compiled(&bytes).is_ok());
+        assert_eq!(DomainPatternSet::<'_, '/'>::deserialize_compiled(&bytes).err(), Some(DecodeError::Invalid));
+        assert_eq!(DomainPatternSet::<'_, '.'>::deserialize_compiled(b"nope").err(), Some(DecodeError::Magic));
+        assert_eq!(DomainPatternSet::<'_, '.'>::deserialize_compiled(&bytes[..bytes.len() - 3]).err(), Some(DecodeError::Checksum));
+
+        // checksummed, but pointing at patterns, labels or nodes that aren't there
+        let invalid = |break_set: fn(&mut DomainPatternSet)| {
+            let mut set: DomainPatternSet = ["*.example.com", "api.example.com"].iter().map(|pattern| DomainPattern::parse(pattern).expect("failed to parse")).collect();
+            break_set(&mut set);
+            DomainPatternSet::<'_, '.'>::deserialize_compiled(&set.serialize_compiled()).err()
+        };
+
+        assert_eq!(invalid(|_| {}), None);
+        assert_eq!(invalid(|set| set.entries[1] = None), Some(DecodeError::Invalid));
+        assert_eq!(invalid(|set| {
+            set.nodes[0].statics.insert(999, 1);
+        }), Some(DecodeError::Invalid));
+        assert_eq!(invalid(|set| {
+            let last = set.nodes.len() - 1;
+            set.nodes[last].wildcards[0] = Some(0);
+        }), Some(DecodeError::Invalid));
+        assert_eq!(invalid(|set| {
+            let last = set.nodes.len() - 1;
+            set.nodes[0].wildcards[0] = Some(last);
+        }), Some(DecodeError::Invalid));
+        assert_eq!(invalid(|set| set.free_nodes.push(1)), Some(DecodeError::Invalid));
+
+        let mut newer = bytes.clone();
+        newer[4] = 2;
+        assert_eq!(DomainPatternSet::<'_, '.'>::deserialize_compiled(&newer).err(), Some(DecodeError::Version(2)));
+    }
+}
@@ -0,0 +1,114 @@
+//! Sets that are done being built, shrunk and sealed so nothing can change them anymore
+
+use crate::{DomainPattern, DomainPatternSet, Matches, MemoryStats, SetStats};
+
+/// A [`DomainPatternSet`] that can't be changed anymore, see [`DomainPatternSet::freeze`]
+///
+/// Freezing frees all capacity the set reserved while it was being built, so a set loaded once at startup takes
+/// no more memory than it needs.
+#[derive(Clone, Debug)]
+pub struct FrozenDomainSet<'a, const SPLITTER: char = '.'> {
+    set: DomainPatternSet<'a, SPLITTER>,
+}
+
+impl<'a, const SPLITTER: char> FrozenDomainSet<'a, SPLITTER> {
+    pub(crate) fn new(set: DomainPatternSet<'a, SPLITTER>) -> Self {
+        FrozenDomainSet { set }
+    }
+
+    pub fn len(&self) -> usize {
+        self.set.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.set.is_empty()
+    }
+
+    /// Returns the pattern at the given index, if it was in the set when it was frozen
+    pub fn get(&self, idx: usize) -> Option<&DomainPattern<'a, SPLITTER>> {
+        self.set.get(idx)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item=(usize, &DomainPattern<'a, SPLITTER>)> + '_ {
+        self.set.iter()
+    }
+
+    /// See [`DomainPatternSet::contains_match`]
+    pub fn contains_match(&self, domain: &str) -> bool {
+        self.set.contains_match(domain)
+    }
+
+    /// See [`DomainPatternSet::matches_all`]
+    pub fn matches_all(&self, domain: &str) -> Matches {
+        self.set.matches_all(domain)
+    }
+
+    /// See [`DomainPatternSet::best_match`]
+    pub fn best_match(&self, domain: &str) -> Option<usize> {
+        self.set.best_match(domain)
+    }
+
+    pub fn stats(&self) -> SetStats {
+        self.set.stats()
+    }
+
+    pub fn memory_usage(&self) -> MemoryStats {
+        self.set.memory_usage()
+    }
+
+    /// The frozen set, for reading it in ways this doesn't expose
+    pub fn as_set(&self) -> &DomainPatternSet<'a, SPLITTER> {
+        &self.set
+    }
+
+    /// The set, so it can be changed again
+    pub fn into_inner(self) -> DomainPatternSet<'a, SPLITTER> {
+        self.set
+    }
+}
+
+impl<'a, const SPLITTER: char> From<DomainPatternSet<'a, SPLITTER>> for FrozenDomainSet<'a, SPLITTER> {
+    fn from(set: DomainPatternSet<'a, SPLITTER>) -> Self {
+        set.freeze()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{DomainMap, DomainPattern, DomainPatternSet};
+
+    #[test]
+    pub fn test_capacity() {
+        let patterns: Vec<DomainPattern> = (0..1000).map(|n| DomainPattern::parse(&format!("+.a{}.example.com", n)).expect("failed to parse").into_owned()).collect();
+
+        let mut set = DomainPatternSet::with_capacity(2000);
+        set.extend(patterns.iter().cloned());
+        let reserved = set.memory_usage();
+
+        let mut removed = set.clone();
+        (0..500).for_each(|idx| { removed.remove(idx); });
+
+        let frozen = set.freeze();
+        assert!(frozen.memory_usage().total() < reserved.total());
+        assert!(frozen.memory_usage().nodes < reserved.nodes);
+        assert_eq!(frozen.len(), 1000);
+        assert!(frozen.contains_match("www.a999.example.com"));
+        assert_eq!(frozen.best_match("www.a1.example.com"), Some(1));
+        assert_eq!(frozen.matches_all("www.a2.example.com").collect::<Vec<_>>(), vec![2]);
+
+        let mut set = frozen.into_inner();
+        set.insert("other.com".try_into().expect("failed to parse"));
+        assert!(set.contains_match("other.com"));
+
+        removed.shrink_to_fit();
+        assert!(removed.memory_usage().total() < reserved.total());
+        assert!(removed.contains_match("www.a999.example.com"));
+        assert!(!removed.contains_match("www.a1.example.com"));
+
+        let mut map = DomainMap::with_capacity(10);
+        map.reserve(100);
+        map.insert(patterns[0].clone(), 1);
+        map.shrink_to_fit();
+        assert_eq!(map.get("www.a0.example.com"), Some(&1));
+    }
+}
@@ -0,0 +1,159 @@
+//! Static labels of the trie stored once, nodes refer to them by symbol
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::hash::BuildHasherDefault;
+use crate::binary::{Reader, Writer};
+use crate::{memory, DecodeError};
+use crate::set::label_hash::{HashedLabel, KnownHash, Key, LabelQuery};
+
+pub(crate) type Symbol = u32;
+
+// children of a node by the symbol of their label
+pub(crate) type Symbols = HashMap<Symbol, usize, BuildHasherDefault<KnownHash>>;
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Interner<'a> {
+    symbols: HashMap<HashedLabel<'a>, Symbol, BuildHasherDefault<KnownHash>>,
+    // how many edges use every symbol, 0 once nothing does
+    uses: Vec<usize>,
+    // symbols nothing uses anymore, which can be handed out again
+    free: Vec<Symbol>,
+}
+
+impl<'a> Interner<'a> {
+    /// The symbol of the label, counting one more use of it
+    // the Cow is only cloned for labels that weren't interned yet, borrowed ones are kept borrowed
+    #[allow(clippy::ptr_arg)]
+    pub(crate) fn intern(&mut self, label: &Cow<'a, str>) -> Symbol {
+        if let Some(symbol) = self.get(&LabelQuery::new(label)) {
+            self.uses[symbol as usize] += 1;
+            return symbol;
+        }
+
+        let symbol = match self.free.pop() {
+            Some(symbol) => {
+                self.uses[symbol as usize] = 1;
+                symbol
+            }
+
+            None => {
+                self.uses.push(1);
+                (self.uses.len() - 1) as Symbol
+            }
+        };
+
+        self.symbols.insert(HashedLabel::new(label.clone()), symbol);
+        symbol
+    }
+
+    pub(crate) fn get(&self, label: &LabelQuery) -> Option<Symbol> {
+        self.symbols.get(label as &dyn Key).copied()
+    }
+
+    /// Counts one use of the label less, forgetting it when nothing uses it anymore
+    pub(crate) fn release(&mut self, label: &str) {
+        let query = LabelQuery::new(label);
+        let symbol = self.get(&query).expect("released labels are interned");
+        self.uses[symbol as usize] -= 1;
+        if self.uses[symbol as usize] == 0 {
+            self.symbols.remove(&query as &dyn Key);
+            self.free.push(symbol);
+        }
+    }
+
+    /// The label of every symbol, in no particular order
+    pub(crate) fn labels(&self) -> impl Iterator<Item=(Symbol, &str)> {
+        self.symbols.iter().map(|(label, symbol)| (*symbol, label.label()))
+    }
+
+    /// Amount of different labels
+    // whether the symbol stands for a label, and isn't free
+    pub(crate) fn is_live(&self, symbol: Symbol) -> bool {
+        self.uses.get(symbol as usize).is_some_and(|uses| *uses > 0)
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.symbols.len()
+    }
+
+    // every symbol with how often it's used and its label, and the symbols that are free
+    pub(crate) fn encode(&self, writer: &mut Writer) {
+        let mut labels = vec![""; self.uses.len()];
+        for (symbol, label) in self.labels() {
+            labels[symbol as usize] = label;
+        }
+
+        writer.usize(self.uses.len());
+        for (uses, label) in self.uses.iter().zip(labels) {
+            writer.usize(*uses);
+            writer.str(label);
+        }
+
+        writer.usize(self.free.len());
+        self.free.iter().for_each(|symbol| writer.u32(*symbol));
+    }
+
+    pub(crate) fn decode(reader: &mut Reader) -> Result<Interner<'static>, DecodeError> {
+        let mut interner = Interner::default();
+        for symbol in 0..reader.len(16)? {
+            let uses = reader.usize()?;
+            let label = reader.str()?;
+            interner.uses.push(uses);
+            if uses > 0 && interner.symbols.insert(HashedLabel::new(Cow::Owned(label.to_string())), symbol as Symbol).is_some() {
+                return Err(DecodeError::Invalid);
+            }
+        }
+
+        for _ in 0..reader.len(4)? {
+            match reader.u32()? {
+                symbol if interner.uses.get(symbol as usize) == Some(&0) => interner.free.push(symbol),
+                _ => return Err(DecodeError::Invalid),
+            }
+        }
+
+        Ok(interner)
+    }
+
+    pub(crate) fn shrink_to_fit(&mut self) {
+        self.symbols.shrink_to_fit();
+        self.uses.shrink_to_fit();
+        self.free.shrink_to_fit();
+    }
+
+    pub(crate) fn memory_usage(&self) -> usize {
+        memory::map(&self.symbols)
+            + self.symbols.keys().map(HashedLabel::memory_usage).sum::<usize>()
+            + memory::vec(&self.uses, self.uses.capacity())
+            + memory::vec(&self.free, self.free.capacity())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+    use crate::set::interner::Interner;
+    use crate::set::label_hash::LabelQuery;
+
+    #[test]
+    pub fn test_interner() {
+        let mut interner = Interner::default();
+        let com = interner.intern(&Cow::Borrowed("com"));
+        assert_eq!(interner.intern(&Cow::Owned("com".to_string())), com);
+        let net = interner.intern(&Cow::Borrowed("net"));
+        assert_ne!(com, net);
+        assert_eq!(interner.len(), 2);
+        assert_eq!(interner.get(&LabelQuery::new("com")), Some(com));
+        assert_eq!(interner.labels().find(|(symbol, _)| *symbol == net).map(|(_, label)| label), Some("net"));
+
+        interner.release("com");
+        assert_eq!(interner.get(&LabelQuery::new("com")), Some(com));
+        interner.release("com");
+        assert_eq!(interner.get(&LabelQuery::new("com")), None);
+        assert_eq!(interner.len(), 1);
+
+        // symbols nothing uses are handed out again
+        assert_eq!(interner.intern(&Cow::Borrowed("org")), com);
+        assert_eq!(interner.get(&LabelQuery::new("org")), Some(com));
+    }
+}
@@ -0,0 +1,147 @@
+//! Static labels with their hash computed up front, so a label of the domain is hashed once, and not again for
+//! every time it's looked up
+
+use std::borrow::{Borrow, Cow};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use crate::memory;
+
+pub(crate) fn hash(label: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    label.hash(&mut hasher);
+    hasher.finish()
+}
+
+// what the map compares, the hash first and the label only when the hashes are the same
+pub(crate) trait Key {
+    fn key(&self) -> (u64, &str);
+}
+
+/// A static label of a pattern, hashed once when it's inserted
+#[derive(Clone, Debug)]
+pub(crate) struct HashedLabel<'a> {
+    hash: u64,
+    label: Cow<'a, str>,
+}
+
+impl<'a> HashedLabel<'a> {
+    pub(crate) fn new(label: Cow<'a, str>) -> Self {
+        HashedLabel { hash: hash(&label), label }
+    }
+
+    pub(crate) fn label(&self) -> &str {
+        &self.label
+    }
+
+    pub(crate) fn memory_usage(&self) -> usize {
+        memory::cow(&self.label)
+    }
+}
+
+/// A label of the domain, hashed once before walking the trie
+#[derive(Copy, Clone)]
+pub(crate) struct LabelQuery<'l> {
+    hash: u64,
+    label: &'l str,
+}
+
+impl<'l> LabelQuery<'l> {
+    pub(crate) fn new(label: &'l str) -> Self {
+        LabelQuery { hash: hash(label), label }
+    }
+}
+
+impl Key for HashedLabel<'_> {
+    fn key(&self) -> (u64, &str) {
+        (self.hash, &self.label)
+    }
+}
+
+impl Key for LabelQuery<'_> {
+    fn key(&self) -> (u64, &str) {
+        (self.hash, self.label)
+    }
+}
+
+impl<'a: 'b, 'b> Borrow<dyn Key + 'b> for HashedLabel<'a> {
+    fn borrow(&self) -> &(dyn Key + 'b) {
+        self
+    }
+}
+
+impl Hash for dyn Key + '_ {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.key().0);
+    }
+}
+
+impl PartialEq for dyn Key + '_ {
+    fn eq(&self, other: &Self) -> bool {
+        let ((hash, label), (other_hash, other_label)) = (self.key(), other.key());
+        hash == other_hash && label.len() == other_label.len() && label == other_label
+    }
+}
+
+impl Eq for dyn Key + '_ {}
+
+impl Hash for HashedLabel<'_> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (self as &dyn Key).hash(state)
+    }
+}
+
+impl PartialEq for HashedLabel<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        (self as &dyn Key) == (other as &dyn Key)
+    }
+}
+
+impl Eq for HashedLabel<'_> {}
+
+// the labels are hashed already, so the map uses their hash as is
+#[derive(Default)]
+pub(crate) struct KnownHash(u64);
+
+impl Hasher for KnownHash {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = self.0.rotate_left(8) ^ *byte as u64;
+        }
+    }
+
+    fn write_u64(&mut self, hash: u64) {
+        self.0 = hash;
+    }
+
+    // symbols are small numbers, spread them over all bits
+    fn write_u32(&mut self, symbol: u32) {
+        self.0 = (symbol as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+    use std::collections::HashMap;
+    use std::hash::BuildHasherDefault;
+    use crate::set::label_hash::{HashedLabel, Key, KnownHash, LabelQuery};
+
+    #[test]
+    pub fn test_hashed_labels() {
+        let mut labels: HashMap<HashedLabel, usize, BuildHasherDefault<KnownHash>> = HashMap::default();
+        labels.insert(HashedLabel::new(Cow::Borrowed("com")), 1);
+        labels.insert(HashedLabel::new(Cow::Owned("net".to_string())), 2);
+
+        let domain = String::from("example.com");
+        let query = LabelQuery::new(&domain[8..]);
+        assert_eq!(labels.get(&query as &dyn Key), Some(&1));
+        assert_eq!(labels.get(&LabelQuery::new("net") as &dyn Key), Some(&2));
+        assert_eq!(labels.get(&LabelQuery::new("org") as &dyn Key), None);
+        assert_eq!(labels.remove(&LabelQuery::new("com") as &dyn Key), Some(1));
+        assert_eq!(labels.len(), 1);
+    }
+}
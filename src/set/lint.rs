@@ -0,0 +1,135 @@
+//! Finding patterns that don't change what a set matches
+
+use std::fmt::{Display, Formatter};
+use crate::{DomainPattern, DomainPatternSet};
+
+/// A pattern that can be removed from a [`DomainPatternSet`] without changing which domains it matches,
+/// see [`DomainPatternSet::lint`]
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Lint {
+    /// The pattern matches the same domains as an earlier pattern
+    Duplicate { idx: usize, of: usize },
+    /// Every domain the pattern matches is also matched by another pattern, or excluded by an exception
+    Shadowed { idx: usize, by: usize },
+    /// The pattern never matches anything, like patterns with an empty label
+    NeverMatches { idx: usize },
+    /// The exception doesn't overlap any pattern that isn't an exception, so it never excludes a domain
+    UnusedException { idx: usize },
+}
+
+impl Lint {
+    /// Index of the pattern that can be removed
+    pub fn idx(&self) -> usize {
+        match self {
+            Lint::Duplicate { idx, .. } | Lint::Shadowed { idx, .. } | Lint::NeverMatches { idx } | Lint::UnusedException { idx } => *idx,
+        }
+    }
+}
+
+impl Display for Lint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Lint::Duplicate { idx, of } => write!(f, "pattern #{} is a duplicate of pattern #{}", idx, of),
+            Lint::Shadowed { idx, by } => write!(f, "pattern #{} is shadowed by pattern #{}", idx, by),
+            Lint::NeverMatches { idx } => write!(f, "pattern #{} never matches", idx),
+            Lint::UnusedException { idx } => write!(f, "exception #{} doesn't exclude anything", idx),
+        }
+    }
+}
+
+impl<'a, const SPLITTER: char> DomainPatternSet<'a, SPLITTER> {
+    /// Finds patterns that can be removed without changing which domains the set matches, in the order of their index
+    ///
+    /// Patterns are compared with [`DomainPattern::covers`] after applying the options of the set, so globs and regexes
+    /// are only found redundant when written the same way. Every pattern is compared with every other pattern, so this
+    /// is meant for checking rule files, not for running on every update. Removing a pattern may make others
+    /// redundant, like an exception that only excluded domains of the removed pattern, so it's worth running again.
+    pub fn lint(&self) -> Vec<Lint> {
+        let any: DomainPattern<'_, SPLITTER> = DomainPattern::parse("**").expect("valid pattern");
+        let patterns: Vec<(usize, DomainPattern<'a, SPLITTER>)> = self.iter()
+            .map(|(idx, pattern)| (idx, self.indexed(pattern).into_owned()))
+            .collect();
+
+        let (never, patterns): (Vec<_>, Vec<_>) = patterns.into_iter().partition(|(_, pattern)| !pattern.overlaps(&any));
+        let mut lints: Vec<Lint> = never.into_iter().map(|(idx, _)| Lint::NeverMatches { idx }).collect();
+
+        for (idx, pattern) in &patterns {
+            let others = || patterns.iter().filter(|(other_idx, _)| other_idx != idx);
+
+            let redundant = others().find_map(|(other_idx, other)| {
+                // an exception never makes another exception redundant
+                if (!other.negated && pattern.negated) || !other.covers(pattern) {
+                    return None;
+                }
+
+                match other.negated == pattern.negated && pattern.covers(other) {
+                    true if other_idx < idx => Some(Lint::Duplicate { idx: *idx, of: *other_idx }),
+                    // the later of the two is the duplicate
+                    true => None,
+                    false => Some(Lint::Shadowed { idx: *idx, by: *other_idx }),
+                }
+            });
+
+            if let Some(lint) = redundant {
+                lints.push(lint);
+            } else if pattern.negated && !others().any(|(_, other)| !other.negated && other.overlaps(pattern)) {
+                lints.push(Lint::UnusedException { idx: *idx });
+            }
+        }
+
+        lints.sort_by_key(Lint::idx);
+        lints
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{DomainPattern, DomainPatternSet, Lint, MatchOptions};
+
+    fn set<'a>(patterns: &[&'a str], options: MatchOptions) -> DomainPatternSet<'a> {
+        let mut set = DomainPatternSet::with_options(options);
+        for pattern in patterns {
+            set.insert(DomainPattern::parse(pattern).expect("failed to parse"));
+        }
+
+        set
+    }
+
+    #[test]
+    pub fn test_lint() {
+        let set = set(&[
+            "**.example.com",
+            "api.example.com",
+            "+.**.org",
+            "**+.org",
+            "a..b",
+            "!internal.example.com",
+            "!*.test",
+            "{www,cdn}.other.com",
+            "{cdn,www}.other.com",
+            "!**.other.com",
+        ], MatchOptions::new());
+
+        assert_eq!(set.lint(), vec![
+            Lint::Shadowed { idx: 1, by: 0 },
+            Lint::Duplicate { idx: 3, of: 2 },
+            Lint::NeverMatches { idx: 4 },
+            Lint::UnusedException { idx: 6 },
+            Lint::Shadowed { idx: 7, by: 9 },
+            Lint::Duplicate { idx: 8, of: 7 },
+        ]);
+
+        assert_eq!(Lint::Shadowed { idx: 42, by: 7 }.to_string(), "pattern #42 is shadowed by pattern #7");
+    }
+
+    #[test]
+    pub fn test_lint_options() {
+        let patterns = ["api.example.com", "API.example.com"];
+        assert_eq!(set(&patterns, MatchOptions::new()).lint(), vec![]);
+        assert_eq!(set(&patterns, MatchOptions::new().case_insensitive(true)).lint(), vec![Lint::Duplicate { idx: 1, of: 0 }]);
+
+        let mut set = set(&["**.example.com", "api.example.com"], MatchOptions::new());
+        set.remove(0);
+        assert_eq!(set.lint(), vec![]);
+    }
+}
@@ -0,0 +1,40 @@
+//! Matching a lot of domains at once on all cores
+
+use rayon::prelude::*;
+use crate::{DomainPatternSet, MatchScratch};
+
+impl<const SPLITTER: char> DomainPatternSet<'_, SPLITTER> {
+    /// Whether any pattern matches, for every domain, like [`DomainPatternSet::contains_match`]
+    ///
+    /// The domains are split over rayon's thread pool, every thread keeps its own [`MatchScratch`].
+    pub fn par_matches(&self, domains: &[&str]) -> Vec<bool> {
+        domains.par_iter()
+            .map_init(MatchScratch::new, |scratch, domain| self.contains_match_in(scratch, domain))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{DomainPattern, DomainPatternSet};
+
+    #[test]
+    pub fn test_par_matches() {
+        let set: DomainPatternSet = ["**.example.com", "api.*.org", "!blocked.example.com"]
+            .into_iter()
+            .map(|pattern| DomainPattern::parse(pattern).expect("failed to parse"))
+            .collect();
+
+        let domains: Vec<String> = (0..1000).map(|n| match n % 4 {
+            0 => format!("host{}.example.com", n),
+            1 => format!("api.host{}.org", n),
+            2 => format!("host{}.example.org", n),
+            _ => "blocked.example.com".to_string(),
+        }).collect();
+
+        let domains: Vec<&str> = domains.iter().map(String::as_str).collect();
+        let expected: Vec<bool> = domains.iter().map(|domain| set.contains_match(domain)).collect();
+        assert_eq!(set.par_matches(&domains), expected);
+        assert_eq!(expected.iter().filter(|matched| **matched).count(), 500);
+    }
+}
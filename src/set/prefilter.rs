@@ -0,0 +1,116 @@
+//! Bloom filter over the last labels of all patterns, used to reject most domains before touching the trie
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use crate::{memory, DomainPattern, DomainPatternPart};
+
+const HASHES: u64 = 3;
+
+#[derive(Clone, Debug)]
+pub(crate) struct Prefilter {
+    // counting, so removing patterns works, counters that saturated are never decremented again
+    counters: Vec<u16>,
+    // patterns ending in a wildcard, as long as there's any of these nothing can be rejected
+    unfilterable: usize,
+}
+
+enum Key<'k> {
+    Last(&'k str),
+    LastTwo(&'k str, &'k str),
+}
+
+impl<'k> Key<'k> {
+    fn of<const SPLITTER: char>(pattern: &'k DomainPattern<'_, SPLITTER>) -> Option<Self> {
+        let mut parts = pattern.steps.iter().rev();
+        let DomainPatternPart::Static(last) = parts.next()? else {
+            return None;
+        };
+
+        Some(match parts.next() {
+            Some(DomainPatternPart::Static(second)) => Key::LastTwo(second, last),
+            _ => Key::Last(last),
+        })
+    }
+
+    fn hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        match self {
+            Key::Last(last) => (1u8, last).hash(&mut hasher),
+            Key::LastTwo(second, last) => (2u8, second, last).hash(&mut hasher),
+        }
+
+        hasher.finish()
+    }
+}
+
+impl Prefilter {
+    /// Amount of counters, see [`Prefilter::new`]
+    pub(crate) fn size(&self) -> usize {
+        self.counters.len()
+    }
+
+    pub(crate) fn memory_usage(&self) -> usize {
+        memory::vec(&self.counters, self.counters.capacity())
+    }
+
+    pub(crate) fn new(size: usize) -> Self {
+        Prefilter {
+            counters: vec![0; size.max(1)],
+            unfilterable: 0,
+        }
+    }
+
+    fn positions(&self, key: &Key) -> impl Iterator<Item=usize> {
+        let hash = key.hash();
+        let len = self.counters.len() as u64;
+        let (h1, h2) = (hash & 0xFFFF_FFFF, (hash >> 32) | 1);
+        (0..HASHES).map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % len) as usize)
+    }
+
+    pub(crate) fn insert<const SPLITTER: char>(&mut self, pattern: &DomainPattern<'_, SPLITTER>) {
+        let Some(key) = Key::of(pattern) else {
+            self.unfilterable += 1;
+            return;
+        };
+
+        for pos in self.positions(&key).collect::<Vec<_>>() {
+            self.counters[pos] = self.counters[pos].saturating_add(1);
+        }
+    }
+
+    pub(crate) fn remove<const SPLITTER: char>(&mut self, pattern: &DomainPattern<'_, SPLITTER>) {
+        let Some(key) = Key::of(pattern) else {
+            self.unfilterable -= 1;
+            return;
+        };
+
+        for pos in self.positions(&key).collect::<Vec<_>>() {
+            if self.counters[pos] != u16::MAX {
+                self.counters[pos] -= 1;
+            }
+        }
+    }
+
+    fn contains(&self, key: &Key) -> bool {
+        self.positions(key).all(|pos| self.counters[pos] > 0)
+    }
+
+    /// Returns false if no pattern can match the domain, true if one may
+    pub(crate) fn may_match<const SPLITTER: char>(&self, domain: &str) -> bool {
+        if self.unfilterable > 0 {
+            return true;
+        }
+
+        let mut labels = domain.rsplit(SPLITTER).filter(|label| !label.is_empty());
+        let Some(last) = labels.next() else {
+            // only patterns consisting of optional wildcards match nothing, and those are unfilterable
+            return false;
+        };
+
+        if self.contains(&Key::Last(last)) {
+            return true;
+        }
+
+        labels.next().is_some_and(|second| self.contains(&Key::LastTwo(second, last)))
+    }
+}
@@ -0,0 +1,175 @@
+use std::cmp::Ordering;
+use std::iter;
+use crate::{DomainPattern, DomainPatternPart, DomainPatternWildcard};
+
+// ranks of the separate parts, a higher rank is more specific
+const RANK_STATIC: u8 = 7;
+const RANK_ONE_OF: u8 = 6;
+const RANK_GLOB: u8 = 5;
+const RANK_ONE: u8 = 4;
+// running out of parts beats anything that can match nothing
+const RANK_END: u8 = 3;
+const RANK_OPTIONAL: u8 = 2;
+const RANK_AT_LEAST_ONE: u8 = 1;
+const RANK_ANY: u8 = 0;
+// ranks that fit in a u32 as base 8 digits
+const SCORE_DIGITS: usize = 10;
+
+fn rank(part: &DomainPatternPart) -> u8 {
+    match part {
+        DomainPatternPart::Static(_) => RANK_STATIC,
+        DomainPatternPart::OneOf(_) => RANK_ONE_OF,
+        DomainPatternPart::Glob(_) | DomainPatternPart::Numeric => RANK_GLOB,
+        #[cfg(feature = "regex")]
+        DomainPatternPart::Regex(_) => RANK_GLOB,
+        DomainPatternPart::Wildcard(DomainPatternWildcard { multi: false, optional: false }) | DomainPatternPart::Named(_) => RANK_ONE,
+        DomainPatternPart::Wildcard(DomainPatternWildcard { multi: false, optional: true }) => RANK_OPTIONAL,
+        DomainPatternPart::Wildcard(DomainPatternWildcard { multi: true, optional: false }) => RANK_AT_LEAST_ONE,
+        DomainPatternPart::Wildcard(DomainPatternWildcard { multi: true, optional: true }) => RANK_ANY,
+    }
+}
+
+/// How specific a pattern is, a greater value is a more specific pattern
+///
+/// The parts of both patterns are ranked (static labels > `{a,b}` > globs like `api-*`, `#` and regexes > `+` > `*` > `**+` > `**`), sorted from most to least
+/// specific and then compared one by one, so `api.example.com` > `+.example.com` > `*.example.com` > `**.example.com`.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub(crate) struct Specificity {
+    // how many parts have each rank, which is all sorting them needs
+    counts: [u32; 8],
+}
+
+impl Specificity {
+    pub(crate) fn of<const SPLITTER: char>(pattern: &DomainPattern<'_, SPLITTER>) -> Self {
+        let mut counts = [0; 8];
+        for part in pattern.steps.iter() {
+            counts[rank(part) as usize] += 1;
+        }
+
+        Specificity { counts }
+    }
+
+    // the ranks from most to least specific, followed by RANK_END
+    fn ranks(&self) -> impl Iterator<Item=u8> + '_ {
+        (0..8u8).rev()
+            .flat_map(|rank| iter::repeat_n(rank, self.counts[rank as usize] as usize))
+            .chain(iter::once(RANK_END))
+    }
+
+    // the first ranks as base 8 digits, padded with zeroes after RANK_END, so a more specific pattern never scores lower
+    pub(crate) fn score(&self) -> u32 {
+        let mut ranks = self.ranks();
+        (0..SCORE_DIGITS).fold(0, |score, _| score * 8 + ranks.next().unwrap_or(0) as u32)
+    }
+}
+
+// no part has RANK_END, so comparing the ranks one by one is enough
+impl Ord for Specificity {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.ranks().cmp(other.ranks())
+    }
+}
+
+impl PartialOrd for Specificity {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<const SPLITTER: char> DomainPattern<'_, SPLITTER> {
+    /// How specific the pattern is, a higher score is more specific, see [`DomainPatternSet::best_match`](crate::DomainPatternSet::best_match)
+    ///
+    /// The parts are ranked static labels > `{a,b}` > globs, `#` and regexes > `+` > `*` > `**+` > `**`, sorted from
+    /// most to least specific, and compared one by one, with running out of parts beating a part that can match nothing.
+    /// Only the first 10 parts count, patterns that only differ after that score the same.
+    ///
+    /// The score is stable, the ranks are 7 for static labels down to 0 for `**`, with 3 for running out of parts,
+    /// and the first 10 ranks make up the score as base 8 digits, padded with zeroes. So `a.example.com` scores
+    /// `0o7773000000`, and scores can be stored, or compared against scores of other versions of this crate.
+    /// A pattern scoring higher than another never sorts lower by [`Ord`], but patterns may score the same and still
+    /// have an order.
+    pub fn specificity(&self) -> u32 {
+        Specificity::of(self).score()
+    }
+}
+
+/// Orders patterns by how specific they are, the most specific pattern is the greatest, then negated patterns first,
+/// then by their parts as written, one by one
+impl<const SPLITTER: char> Ord for DomainPattern<'_, SPLITTER> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        Specificity::of(self).cmp(&Specificity::of(other))
+            .then_with(|| other.negated.cmp(&self.negated))
+            .then_with(|| self.steps.iter().map(DomainPatternPart::text).cmp(other.steps.iter().map(DomainPatternPart::text)))
+            // parts written the same, like `a` and `{a}`, can still be different parts
+            .then_with(|| self.steps.iter().map(rank).cmp(other.steps.iter().map(rank)))
+    }
+}
+
+impl<const SPLITTER: char> PartialOrd for DomainPattern<'_, SPLITTER> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use crate::{DomainPattern, DomainPatternPart};
+
+    fn parse(pattern: &str) -> DomainPattern<'_> {
+        pattern.try_into().expect("failed to parse")
+    }
+
+    #[test]
+    // see test_hash in lib.rs
+    #[allow(clippy::mutable_key_type)]
+    pub fn test_ord() {
+        let mut patterns: Vec<_> = ["**.example.com", "b.example.com", "*.example.com", "+.example.com", "a.example.com", "*.*.example.com", "**+.example.com"]
+            .into_iter()
+            .map(parse)
+            .collect();
+
+        patterns.sort();
+        let sorted: Vec<_> = patterns.iter().map(|pattern| pattern.to_string()).collect();
+        assert_eq!(sorted, vec!["**.example.com", "**+.example.com", "*.*.example.com", "*.example.com", "+.example.com", "a.example.com", "b.example.com"]);
+
+        assert!(parse("example.com") > parse("*.example.com"));
+        assert_ne!(parse("!a.com").cmp(&parse("a.com")), std::cmp::Ordering::Equal);
+        assert_eq!(parse("**.**.a").cmp(&parse("**.a")), std::cmp::Ordering::Equal);
+
+        // written the same, but the static label and the group of one label are in other places
+        let static_first = DomainPattern::<'_, '.'>::try_from(vec![DomainPatternPart::Static("a".into()), DomainPatternPart::OneOf(vec!["b".into()])]).expect("valid parts");
+        let group_first = DomainPattern::<'_, '.'>::try_from(vec![DomainPatternPart::OneOf(vec!["a".into()]), DomainPatternPart::Static("b".into())]).expect("valid parts");
+        assert_eq!(static_first.to_string(), group_first.to_string());
+        assert_ne!(static_first.cmp(&group_first), std::cmp::Ordering::Equal);
+        assert!(parse("!b.com") < parse("a.com"));
+
+        let map: BTreeMap<DomainPattern, usize> = [(parse("*.a"), 1), (parse("b.a"), 2)].into_iter().collect();
+        assert_eq!(map.keys().last(), Some(&parse("b.a")));
+    }
+
+    #[test]
+    pub fn test_specificity() {
+        let scores: Vec<_> = ["**.example.com", "**+.example.com", "*.*.example.com", "*.example.com", "+.example.com", "api-*.example.com", "{a,b}.example.com", "a.example.com"]
+            .into_iter()
+            .map(|pattern| parse(pattern).specificity())
+            .collect();
+
+        assert!(scores.windows(2).all(|pair| pair[0] < pair[1]), "{:?}", scores);
+        assert!(parse("example.com").specificity() > parse("*.example.com").specificity());
+        assert!(parse("a.b.c").specificity() > parse("a.b").specificity());
+        assert_eq!(parse("a.a.a.a.a.a.a.a.a.a").specificity(), parse("a.a.a.a.a.a.a.a.a.a.a").specificity());
+
+        // scores are stable, these shouldn't change
+        assert_eq!(parse("a.example.com").specificity(), 0o7773000000);
+        assert_eq!(parse("+.example.com").specificity(), 0o7743000000);
+        assert_eq!(parse("{a,b}.api-*.#.com").specificity(), 0o7655300000);
+        assert_eq!(parse("**.**+.*").specificity(), 0o1300000000);
+        assert_eq!(parse("*.**").specificity(), 0o2030000000);
+        assert_eq!(parse("").specificity(), 0o7300000000);
+
+        let mut patterns: Vec<_> = ["**", "*.com", "a.*.com", "+.+", "a.b.c.d", "{a,b}.**", "x-*.*.com", "+.**+.com"].into_iter().map(parse).collect();
+        patterns.sort();
+        assert!(patterns.windows(2).all(|pair| pair[0].specificity() <= pair[1].specificity()), "{:?}", patterns);
+    }
+}
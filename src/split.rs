@@ -0,0 +1,98 @@
+//! Splitting domains into labels, with `memchr` when the splitter is a single byte
+
+// where the splitter first occurs in the haystack
+pub(crate) fn find(haystack: &str, splitter: char) -> Option<usize> {
+    #[cfg(feature = "memchr")]
+    if splitter.is_ascii() {
+        return memchr::memchr(splitter as u8, haystack.as_bytes());
+    }
+
+    haystack.find(splitter)
+}
+
+// where the splitter last occurs in the haystack
+pub(crate) fn rfind(haystack: &str, splitter: char) -> Option<usize> {
+    #[cfg(feature = "memchr")]
+    if splitter.is_ascii() {
+        return memchr::memrchr(splitter as u8, haystack.as_bytes());
+    }
+
+    haystack.rfind(splitter)
+}
+
+// the labels of a domain, like `domain.split(SPLITTER)`, including empty ones
+pub(crate) struct Labels<'d, const SPLITTER: char> {
+    rest: &'d str,
+    done: bool,
+}
+
+pub(crate) fn labels<const SPLITTER: char>(domain: &str) -> Labels<'_, SPLITTER> {
+    Labels { rest: domain, done: false }
+}
+
+impl<'d, const SPLITTER: char> Iterator for Labels<'d, SPLITTER> {
+    type Item = &'d str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        match find(self.rest, SPLITTER) {
+            Some(idx) => {
+                let label = &self.rest[..idx];
+                self.rest = &self.rest[idx + SPLITTER.len_utf8()..];
+                Some(label)
+            }
+
+            None => {
+                self.done = true;
+                Some(self.rest)
+            }
+        }
+    }
+}
+
+impl<const SPLITTER: char> DoubleEndedIterator for Labels<'_, SPLITTER> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        match rfind(self.rest, SPLITTER) {
+            Some(idx) => {
+                let label = &self.rest[idx + SPLITTER.len_utf8()..];
+                self.rest = &self.rest[..idx];
+                Some(label)
+            }
+
+            None => {
+                self.done = true;
+                Some(self.rest)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::split::labels;
+
+    #[test]
+    pub fn test_labels() {
+        for domain in ["", ".", "a", "a.b.c", ".a..b.", "www.bücher.example", "..."] {
+            assert_eq!(labels::<'.'>(domain).collect::<Vec<_>>(), domain.split('.').collect::<Vec<_>>(), "{:?} splits differently", domain);
+            assert_eq!(labels::<'.'>(domain).rev().collect::<Vec<_>>(), domain.rsplit('.').collect::<Vec<_>>(), "{:?} splits differently", domain);
+        }
+
+        let mut both = labels::<'.'>("a.b.c.d");
+        assert_eq!(both.next(), Some("a"));
+        assert_eq!(both.next_back(), Some("d"));
+        assert_eq!(both.next(), Some("b"));
+        assert_eq!(both.next_back(), Some("c"));
+        assert_eq!(both.next(), None);
+        assert_eq!(both.next_back(), None);
+
+        assert_eq!(labels::<'ü'>("aüb").collect::<Vec<_>>(), ["a", "b"]);
+    }
+}
@@ -0,0 +1,75 @@
+//! Patterns serialized as their parts, for tooling that wants to look inside without parsing
+//!
+//! Use it with `#[serde(with = "eater_domainmatcher::structured")]`, `!*.example.com` becomes
+//! `{"negated":true,"parts":[{"wildcard":{"multi":false,"optional":true}},{"static":"example"},{"static":"com"}]}`.
+//! Parts are checked and wildcards folded when deserializing, like [`DomainPattern::try_from`] does.
+
+use std::borrow::Cow;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use crate::{DomainPattern, DomainPatternPart};
+
+#[derive(Serialize)]
+struct Parts<'p, 'a> {
+    negated: bool,
+    parts: Vec<&'p DomainPatternPart<'a>>,
+}
+
+#[derive(Deserialize)]
+struct OwnedParts<'a> {
+    #[serde(default)]
+    negated: bool,
+    #[serde(borrow)]
+    parts: Vec<DomainPatternPart<'a>>,
+}
+
+pub fn serialize<S: Serializer, const SPLITTER: char>(pattern: &DomainPattern<'_, SPLITTER>, serializer: S) -> Result<S::Ok, S::Error> {
+    Parts {
+        negated: pattern.negated,
+        parts: pattern.steps.iter().collect(),
+    }.serialize(serializer)
+}
+
+pub fn deserialize<'de: 'a, 'a, D: Deserializer<'de>, const SPLITTER: char>(deserializer: D) -> Result<DomainPattern<'a, SPLITTER>, D::Error> {
+    let OwnedParts { negated, parts } = OwnedParts::deserialize(deserializer)?;
+    let mut pattern = DomainPattern::try_from(parts).map_err(D::Error::custom)?;
+    pattern.negated = negated;
+    Ok(pattern)
+}
+
+// labels are borrowed from the input when they can be
+pub(crate) fn borrow_cow<'de: 'a, 'a, D: Deserializer<'de>>(deserializer: D) -> Result<Cow<'a, str>, D::Error> {
+    <Cow<'de, str>>::deserialize(deserializer)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+    use crate::DomainPattern;
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Rule<'a> {
+        #[serde(with = "crate::structured", borrow)]
+        pattern: DomainPattern<'a>,
+    }
+
+    #[test]
+    pub fn test_structured() {
+        let rule = Rule { pattern: "!*.{tenant}.{a,b}.#.api-*.com".try_into().expect("failed to parse") };
+        let json = serde_json::to_string(&rule).expect("serializes");
+        assert_eq!(json, concat!(r#"{"pattern":{"negated":true,"parts":[{"wildcard":{"multi":false,"optional":true}},{"named":"tenant"},"#,
+            r#"{"one_of":["a","b"]},"numeric",{"glob":"api-*"},{"static":"com"}]}}"#));
+        let parsed: Rule = serde_json::from_str(&json).expect("deserializes");
+        assert_eq!(parsed.pattern.to_string(), rule.pattern.to_string());
+        assert!(parsed.pattern.is_negated());
+
+        // wildcards are folded, and negated is optional
+        let rule: Rule = serde_json::from_str(r#"{"pattern":{"parts":[{"wildcard":{"multi":false,"optional":false}},{"wildcard":{"multi":true,"optional":true}},{"static":"com"}]}}"#)
+            .expect("deserializes");
+        assert_eq!(rule.pattern.to_string(), "**+.com");
+        assert!(!rule.pattern.is_negated());
+
+        let err = serde_json::from_str::<Rule>(r#"{"pattern":{"parts":[{"static":"a.b"}]}}"#).expect_err("invalid label");
+        assert!(err.to_string().contains("splitter"), "{}", err);
+    }
+}
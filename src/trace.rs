@@ -0,0 +1,292 @@
+//! Watching what the matcher does, for figuring out why a pattern does or doesn't match, or how expensive it is
+
+use std::convert::Infallible;
+use std::fmt::{Display, Formatter};
+use crate::{DomainPattern, MatchOptions};
+
+// gets told about every decision the matcher makes, returning an error stops matching
+pub(crate) trait Observer {
+    type Break;
+
+    // whether the observer cares about the decisions, if not the matcher may take shortcuts that don't make any
+    const WATCHES: bool = true;
+
+    fn decision(&mut self, label: Option<(usize, &str)>, part: usize, decision: Decision) -> Result<(), Self::Break>;
+
+    // a path tries the part against the next label
+    fn visit(&mut self, _part: usize) -> Result<(), Self::Break> {
+        Ok(())
+    }
+
+    // size of the stack of paths after every label, duplicates included
+    fn stack(&mut self, _size: usize) {}
+}
+
+impl Observer for () {
+    type Break = Infallible;
+
+    const WATCHES: bool = false;
+
+    #[inline(always)]
+    fn decision(&mut self, _label: Option<(usize, &str)>, _part: usize, _decision: Decision) -> Result<(), Self::Break> {
+        Ok(())
+    }
+}
+
+/// What the matcher decided when it tried a part of the pattern
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Decision {
+    /// The part took the label, and the next part gets the next label
+    Accepted,
+    /// A multi wildcard took the label, it both stays around for the next label and hands it to the next part
+    Forked,
+    /// An optional wildcard was skipped without taking a label
+    Skipped,
+    /// The part took the label and was the last part, a match if the label was also the last one
+    Completed,
+    /// The part didn't match the label, this path stops here
+    DeadEnd,
+}
+
+/// A single decision in a [`MatchTrace`]
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct TraceStep<'d> {
+    /// Index and text of the label being matched, None for skips before the first label
+    pub label: Option<(usize, &'d str)>,
+    /// Index of the part in the (optimized) pattern
+    pub part_index: usize,
+    pub decision: Decision,
+}
+
+/// Every decision made while matching a domain, see [`DomainPattern::explain`]
+///
+/// The [`Display`] implementation prints one decision per line.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct MatchTrace<'d> {
+    steps: Vec<TraceStep<'d>>,
+    matched: bool,
+}
+
+impl<'d> MatchTrace<'d> {
+    /// Whether the pattern matched the domain, same as [`DomainPattern::matches`]
+    pub fn matched(&self) -> bool {
+        self.matched
+    }
+
+    pub fn steps(&self) -> &[TraceStep<'d>] {
+        &self.steps
+    }
+}
+
+impl Display for MatchTrace<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for step in &self.steps {
+            match step.label {
+                Some((idx, label)) => write!(f, "label {} {:?}: ", idx, label)?,
+                None => write!(f, "start: ")?,
+            }
+
+            let decision = match step.decision {
+                Decision::Accepted => "accepted",
+                Decision::Forked => "forked",
+                Decision::Skipped => "skipped",
+                Decision::Completed => "completed",
+                Decision::DeadEnd => "dead end",
+            };
+
+            writeln!(f, "part {} {}", step.part_index, decision)?;
+        }
+
+        write!(f, "{}", if self.matched { "matched" } else { "no match" })
+    }
+}
+
+/// How much work matching a domain took, see [`DomainPattern::matches_with_stats`]
+#[derive(Copy, Clone, Eq, PartialEq, Default, Debug)]
+pub struct MatchStats {
+    /// Amount of times a part was tried against a label
+    pub states_visited: usize,
+    /// Amount of extra paths split off, by multi wildcards staying around or optional wildcards being skipped
+    pub forks: usize,
+    /// Largest amount of paths alive at once
+    pub peak_stack: usize,
+}
+
+impl Observer for MatchStats {
+    type Break = Infallible;
+
+    fn decision(&mut self, _label: Option<(usize, &str)>, _part: usize, decision: Decision) -> Result<(), Self::Break> {
+        if matches!(decision, Decision::Forked | Decision::Skipped) {
+            self.forks += 1;
+        }
+
+        Ok(())
+    }
+
+    fn visit(&mut self, _part: usize) -> Result<(), Self::Break> {
+        self.states_visited += 1;
+        Ok(())
+    }
+
+    fn stack(&mut self, size: usize) {
+        self.peak_stack = self.peak_stack.max(size);
+    }
+}
+
+/// Returned by [`DomainPattern::matches_bounded`] when matching needed more steps than allowed
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct BudgetExceeded {
+    pub max_steps: usize,
+}
+
+impl Display for BudgetExceeded {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Matching took more than {} steps", self.max_steps)
+    }
+}
+
+impl std::error::Error for BudgetExceeded {}
+
+struct Budget {
+    max_steps: usize,
+    steps: usize,
+}
+
+impl Observer for Budget {
+    type Break = BudgetExceeded;
+
+    fn decision(&mut self, _label: Option<(usize, &str)>, _part: usize, _decision: Decision) -> Result<(), Self::Break> {
+        Ok(())
+    }
+
+    fn visit(&mut self, _part: usize) -> Result<(), Self::Break> {
+        self.steps += 1;
+        if self.steps > self.max_steps {
+            return Err(BudgetExceeded { max_steps: self.max_steps });
+        }
+
+        Ok(())
+    }
+}
+
+// the trace borrows the labels from the domain, the observer doesn't know about that lifetime
+struct Recorder<'d> {
+    labels: Vec<&'d str>,
+    steps: Vec<TraceStep<'d>>,
+}
+
+impl Observer for Recorder<'_> {
+    type Break = Infallible;
+
+    fn decision(&mut self, label: Option<(usize, &str)>, part: usize, decision: Decision) -> Result<(), Self::Break> {
+        self.steps.push(TraceStep {
+            label: label.map(|(idx, _)| (idx, self.labels[idx])),
+            part_index: part,
+            decision,
+        });
+
+        Ok(())
+    }
+}
+
+impl<const SPLITTER: char> DomainPattern<'_, SPLITTER> {
+    /// Matches the domain like [`DomainPattern::matches`], but records every decision made along the way
+    ///
+    /// Printing the trace shows why e.g. `nice.**+.nice` doesn't match `nice.nice`.
+    pub fn explain<'d>(&self, domain: &'d str) -> MatchTrace<'d> {
+        let mut recorder = Recorder {
+            labels: domain.split(SPLITTER).filter(|label| !label.is_empty()).collect(),
+            steps: vec![],
+        };
+
+        let matched = match self.run(domain, &MatchOptions::new(), &mut recorder) {
+            Ok(matched) => matched,
+            Err(never) => match never {},
+        };
+
+        MatchTrace {
+            steps: recorder.steps,
+            matched,
+        }
+    }
+
+    /// Matches the domain like [`DomainPattern::matches`], but also counts how much work it took, to find expensive patterns
+    pub fn matches_with_stats(&self, domain: &str) -> (bool, MatchStats) {
+        let mut stats = MatchStats::default();
+        let matched = match self.run(domain, &MatchOptions::new(), &mut stats) {
+            Ok(matched) => matched,
+            Err(never) => match never {},
+        };
+
+        (matched, stats)
+    }
+
+    /// Matches the domain like [`DomainPattern::matches`], but gives up after trying more than `max_steps` parts against labels,
+    /// so untrusted patterns can't take too long, see [`MatchStats::states_visited`]
+    pub fn matches_bounded(&self, domain: &str, max_steps: usize) -> Result<bool, BudgetExceeded> {
+        self.run(domain, &MatchOptions::new(), &mut Budget { max_steps, steps: 0 })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{BudgetExceeded, Decision, DomainPattern, MatchStats};
+
+    #[test]
+    pub fn test_explain() {
+        let pattern: DomainPattern = "nice.**+.nice".try_into().expect("failed to parse");
+        let trace = pattern.explain("nice.nice");
+        assert!(!trace.matched());
+
+        let decisions: Vec<_> = trace.steps().iter().map(|step| (step.label.map(|(idx, _)| idx), step.part_index, step.decision)).collect();
+        assert_eq!(decisions, vec![
+            (Some(0), 0, Decision::Accepted),
+            (Some(1), 1, Decision::Forked),
+        ]);
+
+        assert_eq!(trace.to_string(), "label 0 \"nice\": part 0 accepted\nlabel 1 \"nice\": part 1 forked\nno match");
+
+        let trace = pattern.explain("nice.wow.nice");
+        assert!(trace.matched());
+        assert_eq!(trace.steps().last().map(|step| (step.label, step.decision)), Some((Some((2, "nice")), Decision::Completed)));
+
+        let trace = pattern.explain("nice.wow.wow");
+        assert!(!trace.matched());
+        assert_eq!(trace.steps().last().map(|step| (step.part_index, step.decision)), Some((2, Decision::DeadEnd)));
+    }
+
+    #[test]
+    pub fn test_explain_skips() {
+        let pattern: DomainPattern = "*.domain.tld".try_into().expect("failed to parse");
+        let trace = pattern.explain("domain.tld");
+        assert!(trace.matched());
+        assert_eq!(trace.steps()[0].label, None);
+        assert_eq!(trace.steps()[0].decision, Decision::Skipped);
+
+        for domain in ["", "tld", "domain.tld", "sub.domain.tld", "a.sub.domain.tld"] {
+            assert_eq!(pattern.explain(domain).matched(), pattern.matches(domain), "{:?}", domain);
+        }
+    }
+
+    #[test]
+    pub fn test_stats() {
+        let pattern: DomainPattern = "nice.**+.nice".try_into().expect("failed to parse");
+        let (matched, stats) = pattern.matches_with_stats("nice.wow.nice");
+        assert!(matched);
+        assert_eq!(stats, MatchStats { states_visited: 4, forks: 2, peak_stack: 2 });
+
+        let simple: DomainPattern = "a.b.c".try_into().expect("failed to parse");
+        let nested: DomainPattern = "**.**+.*.a".try_into().expect("failed to parse");
+        let domain = "a.a.a.a.a.a";
+        assert!(simple.matches_with_stats(domain).1.peak_stack < nested.matches_with_stats(domain).1.peak_stack);
+        assert_eq!(nested.matches_with_stats(domain).0, nested.matches(domain));
+    }
+
+    #[test]
+    pub fn test_bounded() {
+        let pattern: DomainPattern = "nice.**+.nice".try_into().expect("failed to parse");
+        assert_eq!(pattern.matches_bounded("nice.wow.nice", 4), Ok(true));
+        assert_eq!(pattern.matches_bounded("nice.wow.nice", 3), Err(BudgetExceeded { max_steps: 3 }));
+        assert_eq!(pattern.matches_bounded("nope.wow.nice", 1), Ok(false));
+    }
+}
@@ -0,0 +1,525 @@
+//! Translating path style globs and simple regexes into patterns
+
+use std::borrow::Cow;
+use std::fmt::{Display, Formatter};
+use crate::{glob, push_part, DomainPattern, DomainPatternPart, DomainPatternWildcard, StepVec};
+
+/// Why a glob or regex can't be translated into a pattern, with the byte offset of the problem
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum TranslateError {
+    /// A label is empty, like in `a..b`
+    EmptyLabel(usize),
+    /// A character class is malformed, or could take the splitter
+    InvalidClass(usize),
+    /// Something patterns can't express, like an alternation, or a `.` that isn't escaped within a label of a regex
+    Unsupported(usize),
+    /// The regex doesn't start with `^` and end with `$`
+    Unanchored,
+}
+
+impl Display for TranslateError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TranslateError::EmptyLabel(position) => write!(f, "Empty label at {}", position),
+            TranslateError::InvalidClass(position) => write!(f, "Invalid character class at {}", position),
+            TranslateError::Unsupported(position) => write!(f, "Can't be translated into a pattern at {}", position),
+            TranslateError::Unanchored => write!(f, "Regex isn't anchored with ^ and $"),
+        }
+    }
+}
+
+impl std::error::Error for TranslateError {}
+
+fn wildcard(optional: bool, multi: bool) -> DomainPatternPart<'static> {
+    DomainPatternPart::Wildcard(DomainPatternWildcard { multi, optional })
+}
+
+// `+`, `{` and `}` keep the parser from reading the glob back as a glob, within a class `!` and `^` negate it when
+// they come first, and `-` makes a range
+fn glob_escape(c: char, in_class: bool, out: &mut String) {
+    if matches!(c, '*' | '?' | '[' | ']' | '\\' | '+' | '{' | '}') || (in_class && matches!(c, '!' | '^' | '-')) {
+        out.push('\\');
+    }
+
+    out.push(c);
+}
+
+// a label of a glob, at the given offset
+fn glob_label<const SPLITTER: char>(label: &str, position: usize) -> Result<DomainPatternPart<'static>, TranslateError> {
+    match label {
+        "" => return Err(TranslateError::EmptyLabel(position)),
+        "**" => return Ok(wildcard(true, true)),
+        "*" => return Ok(wildcard(false, false)),
+        _ => {}
+    }
+
+    if let Some(options) = label.strip_prefix('{').and_then(|label| label.strip_suffix('}')) {
+        if options.contains(['{', '}', '*', '?', '[', ']', '\\']) || options.split(',').any(str::is_empty) {
+            return Err(TranslateError::Unsupported(position));
+        }
+
+        return Ok(DomainPatternPart::OneOf(options.split(',').map(|option| Cow::Owned(option.to_string())).collect()));
+    }
+
+    if let Some(offset) = label.find(['{', '}']) {
+        return Err(TranslateError::Unsupported(position + offset));
+    }
+
+    if !label.contains(['*', '?', '[']) {
+        let mut unescaped = String::with_capacity(label.len());
+        let mut chars = label.chars();
+        while let Some(c) = chars.next() {
+            unescaped.push(match c {
+                '\\' => chars.next().unwrap_or('\\'),
+                c => c,
+            });
+        }
+
+        return Ok(DomainPatternPart::Static(Cow::Owned(unescaped)));
+    }
+
+    // `**` within a label is the same as `*`
+    let mut folded = label.to_string();
+    while folded.contains("**") {
+        folded = folded.replace("**", "*");
+    }
+
+    match glob::is_valid(&folded) {
+        // `***` and the like take a single label, like `*`
+        true if folded == "*" => Ok(wildcard(false, false)),
+        true => Ok(DomainPatternPart::Glob(Cow::Owned(folded))),
+        false => Err(TranslateError::InvalidClass(position + label.find(['[', ']']).unwrap_or(0))),
+    }
+}
+
+// a piece of a label in a regex
+enum Atom {
+    Char(char),
+    // `[^.]*`
+    AnyChars,
+    // `[^.]`
+    AnyChar,
+    // `[^.]+`
+    AnyLabel,
+    // `\d+` or `[0-9]+`
+    Digits,
+    // a class in glob syntax
+    Class(String),
+}
+
+// where the regex parser is relative to the labels
+#[derive(Copy, Clone, Eq, PartialEq)]
+enum At {
+    // where a label starts, the start of the regex or after a splitter
+    LabelStart,
+    // within a label, or after wildcards that took a label
+    LabelEnd,
+}
+
+struct RegexParser<'r, const SPLITTER: char> {
+    regex: &'r str,
+    pos: usize,
+    steps: StepVec<'static>,
+    atoms: Vec<Atom>,
+    // where the current label started
+    label_start: usize,
+}
+
+impl<'r, const SPLITTER: char> RegexParser<'r, SPLITTER> {
+    fn rest(&self) -> &'r str {
+        &self.regex[self.pos..]
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        match self.rest().starts_with(token) {
+            true => {
+                self.pos += token.len();
+                true
+            }
+            false => false,
+        }
+    }
+
+    // `\.`, or the splitter as is if it isn't special in a regex
+    fn eat_splitter(&mut self) -> bool {
+        let mut escaped = [0; 4];
+        let splitter: &str = SPLITTER.encode_utf8(&mut escaped);
+        self.eat(&format!("\\{}", splitter)) || (SPLITTER.is_alphanumeric() && self.eat(splitter))
+    }
+
+    // `[^.]` or `[^\.]`
+    fn eat_not_splitter(&mut self) -> bool {
+        let start = self.pos;
+        let mut splitter = [0; 4];
+        let splitter: &str = SPLITTER.encode_utf8(&mut splitter);
+        if self.eat("[^") && (self.eat_splitter() || self.eat(splitter)) && self.eat("]") {
+            return true;
+        }
+
+        self.pos = start;
+        false
+    }
+
+    // `.*` or `.+`, anything including splitters
+    fn eat_anything(&mut self) -> bool {
+        self.eat(".*") || self.eat(".+")
+    }
+
+    fn push(&mut self, part: DomainPatternPart<'static>) {
+        push_part(&mut self.steps, part);
+    }
+
+    // a group like `(?:[^.]+\.)?`, taking its own splitter in front or behind
+    fn group(&mut self, at: At) -> Result<At, TranslateError> {
+        let start = self.pos;
+        if !self.eat("(?:") {
+            self.eat("(");
+        }
+
+        let fail = Err(TranslateError::Unsupported(start));
+        let suffix = self.eat_splitter();
+        let labels = match (self.eat_not_splitter() && self.eat("+"), self.eat_anything()) {
+            (true, _) => false,
+            (_, true) => true,
+            _ => return fail,
+        };
+
+        if (!suffix && !self.eat_splitter()) || !self.eat(")") {
+            return fail;
+        }
+
+        // prefix groups take the splitter after their labels, so go where a label starts
+        match (suffix, at) {
+            (true, At::LabelStart) | (false, At::LabelEnd) => return fail,
+            _ => {}
+        }
+
+        let part = match (self.rest().chars().next(), labels) {
+            (Some('?'), false) => wildcard(true, false),
+            (Some('?' | '*'), _) => wildcard(true, true),
+            (Some('+'), _) => wildcard(false, true),
+            _ => return fail,
+        };
+
+        self.pos += 1;
+        self.push(part);
+        Ok(at)
+    }
+
+    // the atoms collected so far as a part
+    fn flush(&mut self) -> Result<(), TranslateError> {
+        let atoms = std::mem::take(&mut self.atoms);
+        let part = match &atoms[..] {
+            [] => return Err(TranslateError::EmptyLabel(self.label_start)),
+            [Atom::AnyLabel] => wildcard(false, false),
+            [Atom::Digits] => DomainPatternPart::Numeric,
+            atoms if atoms.iter().all(|atom| matches!(atom, Atom::Char(_))) => DomainPatternPart::Static(Cow::Owned(atoms.iter().map(|atom| match atom {
+                Atom::Char(c) => *c,
+                _ => unreachable!("checked all atoms are characters"),
+            }).collect())),
+            atoms => {
+                let mut glob = String::new();
+                for atom in atoms {
+                    match atom {
+                        Atom::Char(c) => glob_escape(*c, false, &mut glob),
+                        Atom::AnyChars => glob.push('*'),
+                        Atom::AnyChar => glob.push('?'),
+                        Atom::AnyLabel => glob.push_str("?*"),
+                        Atom::Digits => return Err(TranslateError::Unsupported(self.label_start)),
+                        Atom::Class(class) => glob.push_str(class),
+                    }
+                }
+
+                if !glob::is_valid(&glob) {
+                    return Err(TranslateError::InvalidClass(self.label_start));
+                }
+
+                DomainPatternPart::Glob(Cow::Owned(glob))
+            }
+        };
+
+        self.push(part);
+        Ok(())
+    }
+
+    // a character class like `[a-z]`, as a glob class
+    fn class(&mut self) -> Result<String, TranslateError> {
+        let start = self.pos;
+        self.pos += 1;
+        let mut class = String::from("[");
+        if self.eat("^") {
+            class.push('!');
+        }
+
+        let negated = class.starts_with("[!");
+        // a negated class could take the splitter, unless it's excluded
+        let mut excluded = false;
+        let mut empty = true;
+        loop {
+            let Some(c) = self.rest().chars().next() else {
+                return Err(TranslateError::InvalidClass(start));
+            };
+
+            self.pos += c.len_utf8();
+            match c {
+                ']' if !empty => break,
+                '\\' => match self.rest().chars().next() {
+                    Some('d') => {
+                        self.pos += 1;
+                        class.push_str("0-9");
+                    }
+                    Some(escaped) if !escaped.is_alphanumeric() => {
+                        self.pos += escaped.len_utf8();
+                        match escaped == SPLITTER {
+                            true if negated => excluded = true,
+                            true => return Err(TranslateError::InvalidClass(start)),
+                            false => glob_escape(escaped, true, &mut class),
+                        }
+                    }
+                    _ => return Err(TranslateError::Unsupported(self.pos - 1)),
+                },
+                '[' => return Err(TranslateError::Unsupported(self.pos - 1)),
+                c if c == SPLITTER && negated => excluded = true,
+                c if c == SPLITTER => return Err(TranslateError::InvalidClass(start)),
+                ']' => glob_escape(c, true, &mut class),
+                '!' | '^' if empty => glob_escape(c, true, &mut class),
+                c => class.push(c),
+            }
+
+            empty = false;
+        }
+
+        if negated && !excluded {
+            return Err(TranslateError::InvalidClass(start));
+        }
+
+        class.push(']');
+        Ok(class)
+    }
+
+    fn parse(mut self) -> Result<DomainPattern<'static, SPLITTER>, TranslateError> {
+        let mut at = At::LabelStart;
+        while !self.rest().is_empty() {
+            if at == At::LabelStart && self.atoms.is_empty() {
+                self.label_start = self.pos;
+            }
+
+            if self.rest().starts_with('(') {
+                if !self.atoms.is_empty() {
+                    // a suffix group ends the label before it
+                    self.flush()?;
+                }
+
+                at = self.group(at)?;
+                continue;
+            }
+
+            let start = self.pos;
+            if self.eat_splitter() {
+                if at == At::LabelEnd && self.atoms.is_empty() {
+                    at = At::LabelStart;
+                    continue;
+                }
+
+                self.flush()?;
+                at = At::LabelStart;
+                continue;
+            }
+
+            // `.*\.` in front of a label, or `\..*` at the end, any labels
+            if at == At::LabelStart && self.atoms.is_empty() && self.eat_anything() {
+                self.push(wildcard(false, true));
+                at = match self.rest().is_empty() || self.rest().starts_with('(') {
+                    true => At::LabelEnd,
+                    false if self.eat_splitter() => At::LabelStart,
+                    false => return Err(TranslateError::Unsupported(start)),
+                };
+
+                continue;
+            }
+
+            let atom = if self.eat_not_splitter() {
+                match self.rest().chars().next() {
+                    Some('*') => {
+                        self.pos += 1;
+                        Atom::AnyChars
+                    }
+                    Some('+') => {
+                        self.pos += 1;
+                        Atom::AnyLabel
+                    }
+                    _ => Atom::AnyChar,
+                }
+            } else if self.eat("\\d+") || self.eat("[0-9]+") {
+                Atom::Digits
+            } else {
+                match self.rest().chars().next().expect("rest isn't empty") {
+                    '[' => Atom::Class(self.class()?),
+                    '\\' => match self.rest()[1..].chars().next() {
+                        Some(escaped) if !escaped.is_alphanumeric() => {
+                            self.pos += 1 + escaped.len_utf8();
+                            Atom::Char(escaped)
+                        }
+                        _ => return Err(TranslateError::Unsupported(start)),
+                    },
+                    c if c.is_alphanumeric() || c == '-' || c == '_' => {
+                        self.pos += c.len_utf8();
+                        Atom::Char(c)
+                    }
+                    _ => return Err(TranslateError::Unsupported(start)),
+                }
+            };
+
+            // quantifiers only work on the classes above
+            if self.rest().starts_with(['*', '+', '?', '{']) {
+                return Err(TranslateError::Unsupported(self.pos));
+            }
+
+            self.atoms.push(atom);
+            at = At::LabelEnd;
+        }
+
+        match (at, self.atoms.is_empty()) {
+            (At::LabelEnd, true) => {}
+            _ => self.flush()?,
+        }
+
+        Ok(DomainPattern {
+            steps: self.steps,
+            negated: false,
+        })
+    }
+}
+
+impl<const SPLITTER: char> DomainPattern<'static, SPLITTER> {
+    /// Translates a path style glob, where the splitter separates labels like `/` separates directories
+    ///
+    /// A label of `**` takes any amount of labels, a label of `*` takes a single label, and labels with `*`, `?`
+    /// or a class like `[0-9]` become globs matching a single label, so `**.api-*.example.com` matches like the pattern.
+    /// A label of `{a,b}` takes either label, and a `\` escapes the next character.
+    pub fn from_glob(glob: &str) -> Result<Self, TranslateError> {
+        let mut steps: StepVec = Default::default();
+        let mut position = 0;
+        for label in glob.split(SPLITTER) {
+            push_part(&mut steps, glob_label::<SPLITTER>(label, position)?);
+            position += label.len() + SPLITTER.len_utf8();
+        }
+
+        Ok(DomainPattern {
+            steps,
+            negated: false,
+        })
+    }
+
+    /// Translates an anchored regex without alternations, like `^(?:[^.]+\.)?example\.com$`
+    ///
+    /// Labels are separated by `\.`, a label of `[^.]+` takes any label, and `\d+` a label of digits. Groups like
+    /// `(?:[^.]+\.)?`, `(?:[^.]+\.)*` and `(?:[^.]+\.)+` take a label, any amount of labels, or at least one, and so do
+    /// `(\.[^.]+)?` and the like at the end. `.*\.` and `(.*\.)?` take any labels too. Within a label, characters,
+    /// `[^.]`, `[^.]*` and classes become a glob. Anything else, like flags, alternations, or other quantifiers, is
+    /// unsupported, as the pattern wouldn't match the same domains.
+    pub fn from_simple_regex(regex: &str) -> Result<Self, TranslateError> {
+        let inner = regex.strip_prefix('^')
+            .and_then(|regex| regex.strip_suffix('$'))
+            .ok_or(TranslateError::Unanchored)?;
+
+        let parser = RegexParser::<SPLITTER> {
+            regex: inner,
+            pos: 0,
+            steps: Default::default(),
+            atoms: vec![],
+            label_start: 0,
+        };
+
+        parser.parse().map_err(|err| match err {
+            // positions are relative to the whole regex
+            TranslateError::EmptyLabel(position) => TranslateError::EmptyLabel(position + 1),
+            TranslateError::InvalidClass(position) => TranslateError::InvalidClass(position + 1),
+            TranslateError::Unsupported(position) => TranslateError::Unsupported(position + 1),
+            err => err,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{DomainPattern, TranslateError};
+
+    fn glob(glob: &str) -> Result<String, TranslateError> {
+        DomainPattern::<'_, '.'>::from_glob(glob).map(|pattern| pattern.to_string())
+    }
+
+    fn regex(regex: &str) -> Result<String, TranslateError> {
+        DomainPattern::<'_, '.'>::from_simple_regex(regex).map(|pattern| pattern.to_string())
+    }
+
+    #[test]
+    pub fn test_from_glob() {
+        assert_eq!(glob("**.example.com"), Ok("**.example.com".to_string()));
+        assert_eq!(glob("*.example.com"), Ok("+.example.com".to_string()));
+        assert_eq!(glob("api-*.node[0-9]?.com"), Ok("api-*.node[0-9]?.com".to_string()));
+        assert_eq!(glob("a**b.com"), Ok("a*b.com".to_string()));
+        assert_eq!(glob("{api,www}.com"), Ok("{api,www}.com".to_string()));
+        assert_eq!(glob("tag\\*1.com"), Ok("tag\\*1.com".to_string()));
+        assert_eq!(glob("**.**.com"), Ok("**.com".to_string()));
+        assert_eq!(glob("***.com"), Ok("+.com".to_string()));
+
+        assert_eq!(glob("a..b"), Err(TranslateError::EmptyLabel(2)));
+        assert_eq!(glob("example.com."), Err(TranslateError::EmptyLabel(12)));
+        assert_eq!(glob("a.node[0-9"), Err(TranslateError::InvalidClass(6)));
+        assert_eq!(glob("a.x{a,b}"), Err(TranslateError::Unsupported(3)));
+        assert_eq!(glob("{a,}.com"), Err(TranslateError::Unsupported(0)));
+
+        let pattern: DomainPattern = DomainPattern::from_glob("**.api-*.example.com").expect("glob translates");
+        assert!(pattern.matches("a.b.api-v1.example.com"));
+        assert!(!pattern.matches("api.example.com"));
+    }
+
+    #[test]
+    pub fn test_from_simple_regex() {
+        assert_eq!(regex("^example\\.com$"), Ok("example.com".to_string()));
+        assert_eq!(regex("^(?:[^.]+\\.)?example\\.com$"), Ok("*.example.com".to_string()));
+        assert_eq!(regex("^(?:[^.]+\\.)*example\\.com$"), Ok("**.example.com".to_string()));
+        assert_eq!(regex("^([^.]+\\.)+example\\.com$"), Ok("**+.example.com".to_string()));
+        assert_eq!(regex("^(.*\\.)?example\\.com$"), Ok("**.example.com".to_string()));
+        assert_eq!(regex("^.*\\.example\\.com$"), Ok("**+.example.com".to_string()));
+        assert_eq!(regex("^[^.]+\\.example\\.com$"), Ok("+.example.com".to_string()));
+        assert_eq!(regex("^example\\.(?:\\.[^.]+)?$"), Err(TranslateError::Unsupported(10)));
+        assert_eq!(regex("^example(?:\\.[^.]+)?$"), Ok("example.*".to_string()));
+        assert_eq!(regex("^example\\..*$"), Ok("example.**+".to_string()));
+        assert_eq!(regex("^api-[^.]*\\.node[0-9][^.]\\.com$"), Ok("api-*.node[0-9]?.com".to_string()));
+        assert_eq!(regex("^\\d+\\.in-addr\\.arpa$"), Ok("#.in-addr.arpa".to_string()));
+        assert_eq!(regex("^tag\\+1\\.com$"), Ok("tag\\+1.com".to_string()));
+        assert_eq!(regex("^tag\\+[0-9]\\.com$"), Ok("tag\\+[0-9].com".to_string()));
+
+        assert_eq!(regex("example\\.com$"), Err(TranslateError::Unanchored));
+        assert_eq!(regex("^example\\.com"), Err(TranslateError::Unanchored));
+        assert_eq!(regex("^(api|www)\\.com$"), Err(TranslateError::Unsupported(1)));
+        assert_eq!(regex("^www.example\\.com$"), Err(TranslateError::Unsupported(4)));
+        assert_eq!(regex("^a+\\.com$"), Err(TranslateError::Unsupported(2)));
+        assert_eq!(regex("^(?i)example\\.com$"), Err(TranslateError::Unsupported(1)));
+        assert_eq!(regex("^a\\.\\.b$"), Err(TranslateError::EmptyLabel(4)));
+        assert_eq!(regex("^[^a]\\.com$"), Err(TranslateError::InvalidClass(1)));
+        assert_eq!(regex("^[a.]\\.com$"), Err(TranslateError::InvalidClass(1)));
+        assert_eq!(regex("^$"), Err(TranslateError::EmptyLabel(1)));
+
+        // classes starting with `!` or with an escaped `-` are taken literally, not negated or as a range
+        for (regex, matching, other) in [("^[!a]\\.com$", "!.com", "b.com"), ("^[a\\-z]\\.com$", "-.com", "b.com")] {
+            let translated: DomainPattern = DomainPattern::from_simple_regex(regex).expect("regex translates");
+            let reparsed: DomainPattern = translated.to_string().parse().expect("translated pattern parses");
+            for pattern in [translated, reparsed] {
+                assert!(pattern.matches(matching), "{} should match {}", pattern, matching);
+                assert!(pattern.matches("a.com"), "{} should match a.com", pattern);
+                assert!(!pattern.matches(other), "{} shouldn't match {}", pattern, other);
+            }
+        }
+
+        // translating back and forth keeps the pattern
+        for pattern in ["*.example.com", "**.example.com", "**+.example.com", "example.**", "+.com", "api-*.node[!0-9].com", "#.com"] {
+            let parsed: DomainPattern = pattern.try_into().expect("failed to parse");
+            let regex = parsed.to_regex_string().expect("pattern can be translated");
+            let translated: DomainPattern = DomainPattern::from_simple_regex(&regex).unwrap_or_else(|err| panic!("{} doesn't translate: {}", regex, err));
+            assert!(translated.equivalent(&parsed), "{} translated to {}", pattern, translated);
+        }
+    }
+}
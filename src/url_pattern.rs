@@ -0,0 +1,205 @@
+//! Matching URLs by their host and path, both as patterns, like `**.example.com/api/**`
+
+use std::borrow::Cow;
+use std::fmt::{Display, Formatter};
+use crate::origin::is_scheme;
+use crate::{DomainPattern, InvalidToken, MatchOptions};
+
+/// Why a URL pattern didn't parse, see [`UrlPattern::parse`]
+#[derive(Debug)]
+pub enum InvalidUrlPattern<'a> {
+    /// The scheme before `://` has a character a scheme can't have
+    Scheme,
+    /// The host has a `:port`, ports aren't part of URL patterns
+    Port,
+    Host(InvalidToken<'a>),
+    Path(InvalidToken<'a>),
+}
+
+impl Display for InvalidUrlPattern<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InvalidUrlPattern::Scheme => write!(f, "Invalid scheme in URL pattern"),
+            InvalidUrlPattern::Port => write!(f, "URL pattern has a port"),
+            InvalidUrlPattern::Host(err) => write!(f, "Invalid host in URL pattern: {}", err),
+            InvalidUrlPattern::Path(err) => write!(f, "Invalid path in URL pattern: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for InvalidUrlPattern<'_> {}
+
+/// A pattern for URLs, a [`DomainPattern`] for the host and one split on `/` for the path
+///
+/// `https://**.example.com/api/*/users/**` takes `https` URLs on `example.com` and its subdomains, with paths like
+/// `/api/v1/users/42`. Without a scheme any scheme is taken, and without a path any path. Paths are matched segment by
+/// segment like domains are label by label, so empty segments are skipped and `/api/` is the same as `/api`. The host is
+/// compared ignoring ASCII case, the path as written without decoding, and the port, query and fragment are ignored.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub struct UrlPattern<'a> {
+    scheme: Option<Cow<'a, str>>,
+    host: DomainPattern<'a>,
+    path: Option<DomainPattern<'a, '/'>>,
+}
+
+impl<'a> UrlPattern<'a> {
+    pub fn parse(pattern: &'a str) -> Result<Self, InvalidUrlPattern<'a>> {
+        let (scheme, rest) = match pattern.split_once("://") {
+            Some(("*", rest)) => (None, rest),
+            Some((scheme, _)) if !is_scheme(scheme) => return Err(InvalidUrlPattern::Scheme),
+            Some((scheme, rest)) => (Some(scheme), rest),
+            None => (None, pattern),
+        };
+
+        // the path without the splitters at its ends, which would be empty segments that never match
+        let (host, path) = match rest.split_once('/') {
+            Some((host, path)) => (host, Some(path.trim_end_matches('/'))),
+            None => (rest, None),
+        };
+
+        if host.contains(':') {
+            return Err(InvalidUrlPattern::Port);
+        }
+
+        let scheme = scheme.map(|scheme| match scheme.bytes().any(|b| b.is_ascii_uppercase()) {
+            true => Cow::Owned(scheme.to_ascii_lowercase()),
+            false => Cow::Borrowed(scheme),
+        });
+
+        Ok(UrlPattern {
+            scheme,
+            host: DomainPattern::parse(host).map_err(InvalidUrlPattern::Host)?,
+            path: path.map(|path| match path.is_empty() {
+                // only the root path, which has no segments
+                true => Ok(DomainPattern { steps: Default::default(), negated: false }),
+                false => DomainPattern::parse(path),
+            }).transpose().map_err(InvalidUrlPattern::Path)?,
+        })
+    }
+
+    pub fn scheme(&self) -> Option<&str> {
+        self.scheme.as_deref()
+    }
+
+    pub fn host(&self) -> &DomainPattern<'a> {
+        &self.host
+    }
+
+    /// The pattern for the path, None if any path matches
+    pub fn path(&self) -> Option<&DomainPattern<'a, '/'>> {
+        self.path.as_ref()
+    }
+
+    fn matches_parts(&self, scheme: &str, host: &str, path: &str) -> bool {
+        if self.scheme.as_ref().is_some_and(|expected| !scheme.eq_ignore_ascii_case(expected)) {
+            return false;
+        }
+
+        if host.is_empty() || host.starts_with('[') || !self.host.matches_with(host, &MatchOptions::new().case_insensitive(true)) {
+            return false;
+        }
+
+        self.path.as_ref().is_none_or(|pattern| pattern.matches(path))
+    }
+
+    /// Matches a URL like `https://user@www.example.com:8443/api/v1?x=1`, anything without `scheme://` never matches
+    pub fn matches_str(&self, url: &str) -> bool {
+        let Some((scheme, rest)) = url.split_once("://") else {
+            return false;
+        };
+
+        if !is_scheme(scheme) {
+            return false;
+        }
+
+        let rest = rest.split(['?', '#']).next().unwrap_or(rest);
+        let (authority, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+        let authority = authority.rsplit_once('@').map_or(authority, |(_, host)| host);
+        let host = match authority.starts_with('[') {
+            true => authority,
+            false => authority.split(':').next().unwrap_or(authority),
+        };
+
+        self.matches_parts(scheme, host, path)
+    }
+
+    /// Matches a parsed URL, URLs without a host like `mailto:` never match
+    #[cfg(feature = "url")]
+    pub fn matches(&self, url: &url::Url) -> bool {
+        match url.host_str() {
+            Some(host) => self.matches_parts(url.scheme(), host, url.path()),
+            None => false,
+        }
+    }
+
+    pub fn into_owned(self) -> UrlPattern<'static> {
+        UrlPattern {
+            scheme: self.scheme.map(|scheme| Cow::Owned(scheme.into_owned())),
+            host: self.host.into_owned(),
+            path: self.path.map(DomainPattern::into_owned),
+        }
+    }
+}
+
+impl<'a> TryFrom<&'a str> for UrlPattern<'a> {
+    type Error = InvalidUrlPattern<'a>;
+
+    fn try_from(pattern: &'a str) -> Result<Self, Self::Error> {
+        UrlPattern::parse(pattern)
+    }
+}
+
+impl Display for UrlPattern<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}://{}", self.scheme.as_deref().unwrap_or("*"), self.host)?;
+        match &self.path {
+            Some(path) => write!(f, "/{}", path),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{InvalidUrlPattern, UrlPattern};
+
+    #[test]
+    pub fn test_url_pattern() {
+        let pattern = UrlPattern::parse("HTTPS://**.example.com/api/*/users/**").expect("valid");
+        assert_eq!(pattern.scheme(), Some("https"));
+        assert_eq!(pattern.to_string(), "https://**.example.com/api/*/users/**");
+        for (url, matches) in [("https://example.com/api/v1/users", true), ("https://www.Example.com/api/v1/users/42?x=1#top", true),
+            ("https://user@example.com:8443/api/v1/users/", true), ("https://example.com//api/v1/users", true),
+            ("https://example.com/api/v1/v2/v3/users/42", false), ("https://example.com/API/v1/users", false),
+            ("http://example.com/api/v1/users", false), ("https://example.org/api/v1/users", false), ("example.com/api/v1/users", false),
+            ("https://[::1]/api/v1/users", false)] {
+            assert_eq!(pattern.matches_str(url), matches, "{:?}", url);
+        }
+
+        let pattern = UrlPattern::parse("example.com").expect("valid");
+        assert_eq!(pattern.path(), None);
+        assert!(pattern.matches_str("ftp://example.com/any/path"));
+        assert!(pattern.matches_str("http://example.com"));
+        assert!(pattern.into_owned().matches_str("http://example.com:80?q"));
+
+        let pattern = UrlPattern::parse("*.example.com/").expect("valid");
+        assert!(pattern.matches_str("https://www.example.com"));
+        assert!(pattern.matches_str("https://www.example.com/"));
+        assert!(!pattern.matches_str("https://www.example.com/x"));
+
+        assert!(matches!(UrlPattern::parse("1://example.com"), Err(InvalidUrlPattern::Scheme)));
+        assert!(matches!(UrlPattern::parse("example.com:8080/x"), Err(InvalidUrlPattern::Port)));
+        assert!(matches!(UrlPattern::parse("{x/y"), Err(InvalidUrlPattern::Host(_))));
+        assert!(matches!(UrlPattern::parse("example.com/{x"), Err(InvalidUrlPattern::Path(_))));
+    }
+
+    #[cfg(feature = "url")]
+    #[test]
+    pub fn test_url_pattern_matches() {
+        let pattern = UrlPattern::parse("https://**.example.com/static/**").expect("valid");
+        for (url, matches) in [("https://cdn.EXAMPLE.com/static/app.js", true), ("https://example.com/static", true),
+            ("https://example.com/other", false), ("mailto:alice@example.com", false)] {
+            assert_eq!(pattern.matches(&url::Url::parse(url).expect("valid url")), matches, "{:?}", url);
+        }
+    }
+}
@@ -0,0 +1,186 @@
+//! Wildcards the way authoritative DNS servers answer them, following RFC 4592
+
+use std::collections::HashSet;
+
+/// What a [`DnsZone`] answers for a name, see [`DnsZone::lookup`]
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum ZoneMatch {
+    /// The name is in the zone, so no wildcard applies to it
+    Exact,
+    /// The name isn't in the zone, but names below it are, like `b.example.com` with `a.b.example.com` in the zone
+    ///
+    /// These exist without records, so wildcards don't apply to them either.
+    EmptyNonTerminal,
+    /// The name is answered by the wildcard `source`, the wildcard right below the closest encloser
+    Wildcard { closest_encloser: String, source: String },
+    /// The name doesn't exist, and there's no wildcard below its closest encloser
+    NxDomain { closest_encloser: String },
+    /// The name isn't below the origin of the zone
+    OutOfZone,
+}
+
+/// The names of a zone, answering like an authoritative server does for names that aren't in it
+///
+/// Unlike a pattern like `**+.example.com`, the wildcard `*.example.com` only answers for names whose closest
+/// encloser, the closest ancestor that exists in the zone, is `example.com`. So it takes `a.example.com` and
+/// `a.b.example.com`, but not `example.com` itself, and with `b.example.com` in the zone neither `b.example.com` nor
+/// anything below it. Names are compared ignoring ASCII case, and a trailing dot is ignored.
+#[derive(Clone, Debug)]
+pub struct DnsZone {
+    origin: String,
+    // names with records, as added
+    names: HashSet<String>,
+    // names with records and all of their ancestors up to the origin, which exist even without records
+    nodes: HashSet<String>,
+}
+
+fn normalize(name: &str) -> String {
+    name.strip_suffix('.').unwrap_or(name).to_ascii_lowercase()
+}
+
+// the name without its leftmost label, None for the root
+fn parent(name: &str) -> Option<&str> {
+    match name.split_once('.') {
+        Some((_, parent)) => Some(parent),
+        None if name.is_empty() => None,
+        None => Some(""),
+    }
+}
+
+impl DnsZone {
+    /// An empty zone for the origin, an empty origin or `.` is the root zone
+    pub fn new(origin: &str) -> Self {
+        let origin = normalize(origin);
+        DnsZone {
+            nodes: HashSet::from([origin.clone()]),
+            origin,
+            names: HashSet::new(),
+        }
+    }
+
+    pub fn origin(&self) -> &str {
+        &self.origin
+    }
+
+    /// Whether the name is the origin or below it
+    pub fn in_zone(&self, name: &str) -> bool {
+        let name = normalize(name);
+        self.is_below_origin(&name)
+    }
+
+    fn is_below_origin(&self, name: &str) -> bool {
+        self.origin.is_empty() || name == self.origin || name.strip_suffix(self.origin.as_str()).is_some_and(|prefix| prefix.ends_with('.'))
+    }
+
+    /// Adds a name with records, which can be a wildcard like `*.example.com`, false if it's not in the zone
+    pub fn insert(&mut self, name: &str) -> bool {
+        let name = normalize(name);
+        if !self.is_below_origin(&name) {
+            return false;
+        }
+
+        let mut node = Some(name.as_str());
+        while let Some(current) = node.filter(|current| current.len() > self.origin.len()) {
+            self.nodes.insert(current.to_string());
+            node = parent(current);
+        }
+
+        self.names.insert(name);
+        true
+    }
+
+    /// Whether the name has records, wildcards only if asked for literally, like `*.example.com`
+    pub fn contains(&self, name: &str) -> bool {
+        self.names.contains(&normalize(name))
+    }
+
+    /// The closest ancestor of the name that exists in the zone, the name itself if it does, None if it's not in the zone
+    pub fn closest_encloser(&self, name: &str) -> Option<String> {
+        let name = normalize(name);
+        if !self.is_below_origin(&name) {
+            return None;
+        }
+
+        let mut node = name.as_str();
+        while !self.nodes.contains(node) {
+            node = parent(node)?;
+        }
+
+        Some(node.to_string())
+    }
+
+    pub fn lookup(&self, name: &str) -> ZoneMatch {
+        let name = normalize(name);
+        let Some(closest_encloser) = self.closest_encloser(&name) else {
+            return ZoneMatch::OutOfZone;
+        };
+
+        if closest_encloser == name {
+            return match self.names.contains(&name) {
+                true => ZoneMatch::Exact,
+                false => ZoneMatch::EmptyNonTerminal,
+            };
+        }
+
+        let source = match closest_encloser.is_empty() {
+            true => "*".to_string(),
+            false => format!("*.{}", closest_encloser),
+        };
+
+        match self.names.contains(&source) {
+            true => ZoneMatch::Wildcard { closest_encloser, source },
+            false => ZoneMatch::NxDomain { closest_encloser },
+        }
+    }
+}
+
+impl<'n> Extend<&'n str> for DnsZone {
+    /// Adds every name, skipping those that aren't in the zone
+    fn extend<T: IntoIterator<Item=&'n str>>(&mut self, names: T) {
+        for name in names {
+            self.insert(name);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{DnsZone, ZoneMatch};
+
+    fn wildcard(closest_encloser: &str, source: &str) -> ZoneMatch {
+        ZoneMatch::Wildcard { closest_encloser: closest_encloser.to_string(), source: source.to_string() }
+    }
+
+    fn nxdomain(closest_encloser: &str) -> ZoneMatch {
+        ZoneMatch::NxDomain { closest_encloser: closest_encloser.to_string() }
+    }
+
+    #[test]
+    pub fn test_lookup() {
+        // the example zone of RFC 4592, section 2.2.1
+        let mut zone = DnsZone::new("example.");
+        zone.extend(["example.", "*.example.", "host1.example.", "sub.*.example.", "_ssh._tcp.host1.example.", "_ssh._tcp.host2.example.",
+            "subdel.example.", "outside.org."]);
+        assert!(!zone.contains("outside.org"));
+        assert!(!zone.insert("www.example.com"));
+
+        for (name, expected) in [("host3.example.", wildcard("example", "*.example")), ("_telnet._tcp.host1.example.", nxdomain("_tcp.host1.example")),
+            ("_dns._udp.host2.example.", nxdomain("host2.example")), ("_telnet._tcp.host3.example.", wildcard("example", "*.example")),
+            ("_chat._udp.host3.example.", wildcard("example", "*.example")), ("foo.bar.example.", wildcard("example", "*.example")),
+            ("host1.example.", ZoneMatch::Exact), ("HOST1.Example", ZoneMatch::Exact), ("example.", ZoneMatch::Exact),
+            ("_tcp.host2.example.", ZoneMatch::EmptyNonTerminal), ("host2.example.", ZoneMatch::EmptyNonTerminal),
+            ("ghost.*.example.", nxdomain("*.example")), ("*.example.", ZoneMatch::Exact), ("example.org.", ZoneMatch::OutOfZone)] {
+            assert_eq!(zone.lookup(name), expected, "{:?}", name);
+        }
+
+        assert_eq!(zone.closest_encloser("a.b.host1.example").as_deref(), Some("host1.example"));
+        assert_eq!(zone.closest_encloser("example.com"), None);
+        assert!(zone.in_zone("a.example."));
+
+        let mut root = DnsZone::new(".");
+        root.extend(["*", "com", "a.com"]);
+        assert_eq!(root.lookup("example.org"), wildcard("", "*"));
+        assert_eq!(root.lookup("b.com"), nxdomain("com"));
+        assert_eq!(root.lookup(""), ZoneMatch::EmptyNonTerminal);
+    }
+}